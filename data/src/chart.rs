@@ -1,6 +1,7 @@
 pub mod heatmap;
 pub mod indicator;
 pub mod kline;
+pub mod liquidation;
 
 use exchange::Timeframe;
 use serde::{Deserialize, Serialize};
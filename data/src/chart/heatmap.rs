@@ -126,6 +126,16 @@ impl OrderRun {
         }
     }
 
+    /// Rebuild a run from its persisted bounds
+    pub fn with_until_time(start_time: u64, until_time: u64, qty: f32, is_bid: bool) -> Self {
+        OrderRun {
+            start_time,
+            until_time,
+            qty,
+            is_bid,
+        }
+    }
+
     pub fn qty(&self) -> f32 {
         self.qty
     }
@@ -145,6 +155,8 @@ pub struct HistoricalDepth {
     aggr_time: u64,
     tick_size: PriceStep,
     min_order_qty: f32,
+    /// Runs ending before this time were already handed out for persistence
+    persisted_until: u64,
 }
 
 impl HistoricalDepth {
@@ -157,6 +169,7 @@ impl HistoricalDepth {
             },
             tick_size,
             min_order_qty,
+            persisted_until: 0,
         }
     }
 
@@ -261,6 +274,82 @@ impl HistoricalDepth {
             })
     }
 
+    /// Merge previously persisted runs, e.g. loaded from the database
+    ///
+    /// Prices are re-binned to the current tick size and runs already present
+    /// at a level with the same start time are skipped. Merged runs are not
+    /// returned again by `take_completed_runs`.
+    pub fn insert_order_runs(&mut self, runs: impl IntoIterator<Item = (Price, OrderRun)>) {
+        let step = self.tick_size;
+
+        for (price, run) in runs {
+            let rounded_price = price.round_to_side_step(run.is_bid, step);
+            let price_level = self.price_levels.entry(rounded_price).or_default();
+
+            if price_level.iter().any(|r| r.start_time == run.start_time) {
+                continue;
+            }
+
+            let idx = price_level.partition_point(|r| r.start_time < run.start_time);
+            price_level.insert(idx, run);
+
+            self.persisted_until = self.persisted_until.max(run.until_time + 1);
+        }
+    }
+
+    /// Runs of a sequence of full book snapshots, e.g. imported from an archive
+    ///
+    /// Snapshots are usually further apart than the aggregation interval, so each
+    /// level is held until the next snapshot instead of leaving gaps in between.
+    pub fn snapshot_runs(&self, snapshots: &[(u64, Depth)]) -> Vec<(Price, OrderRun)> {
+        let mut depth = HistoricalDepth {
+            price_levels: BTreeMap::new(),
+            aggr_time: self.aggr_time,
+            tick_size: self.tick_size,
+            min_order_qty: self.min_order_qty,
+            persisted_until: 0,
+        };
+
+        for (i, (time, snapshot)) in snapshots.iter().enumerate() {
+            let next_time = snapshots.get(i + 1).map_or(*time, |(next, _)| *next);
+
+            depth.aggr_time = next_time.saturating_sub(*time).max(self.aggr_time);
+            depth.insert_latest_depth(snapshot, *time);
+        }
+
+        depth
+            .price_levels
+            .into_iter()
+            .flat_map(|(price, runs)| runs.into_iter().map(move |run| (price, run)))
+            .collect()
+    }
+
+    /// Collect runs that can no longer be extended and weren't collected before
+    ///
+    /// A run is complete once `latest_time` is past its grace period, after that
+    /// neither `update_price_level` extends nor truncates it.
+    pub fn take_completed_runs(&mut self, latest_time: u64) -> Vec<(Price, OrderRun)> {
+        let cutoff = latest_time.saturating_sub(GRACE_PERIOD_MS);
+        let from = self.persisted_until;
+
+        if cutoff <= from {
+            return vec![];
+        }
+
+        let completed = self
+            .price_levels
+            .iter()
+            .flat_map(|(price, runs)| {
+                runs.iter()
+                    .filter(|run| run.until_time >= from && run.until_time < cutoff)
+                    .map(|run| (*price, *run))
+            })
+            .collect();
+
+        self.persisted_until = cutoff;
+        completed
+    }
+
     pub fn cleanup_old_price_levels(&mut self, oldest_time: u64) {
         self.price_levels.iter_mut().for_each(|(_, runs)| {
             runs.retain(|run| run.until_time >= oldest_time);
@@ -15,6 +15,7 @@ pub enum KlineIndicator {
     Volume,
     OpenInterest,
     MovingAverage,
+    FundingRate,
 }
 
 impl Indicator for KlineIndicator {
@@ -32,7 +33,7 @@ impl KlineIndicator {
     /// Indicators that can be used with spot market tickers
     const FOR_SPOT: [KlineIndicator; 2] = [KlineIndicator::Volume, KlineIndicator::MovingAverage];
     /// Indicators that can be used with perpetual swap market tickers
-    const FOR_PERPS: [KlineIndicator; 3] = [KlineIndicator::Volume, KlineIndicator::OpenInterest, KlineIndicator::MovingAverage];
+    const FOR_PERPS: [KlineIndicator; 4] = [KlineIndicator::Volume, KlineIndicator::OpenInterest, KlineIndicator::MovingAverage, KlineIndicator::FundingRate];
 }
 
 impl Display for KlineIndicator {
@@ -41,6 +42,7 @@ impl Display for KlineIndicator {
             KlineIndicator::Volume => write!(f, "Volume"),
             KlineIndicator::OpenInterest => write!(f, "Open Interest"),
             KlineIndicator::MovingAverage => write!(f, "Moving Average"),
+            KlineIndicator::FundingRate => write!(f, "Funding Rate"),
         }
     }
 }
@@ -0,0 +1,49 @@
+use exchange::Liquidation;
+use std::collections::BTreeMap;
+
+/// Liquidations of a single ticker, drawn as markers over the chart data
+#[derive(Debug, Default)]
+pub struct LiquidationSeries {
+    by_time: BTreeMap<u64, Vec<Liquidation>>,
+}
+
+impl LiquidationSeries {
+    /// Adds a liquidation unless it's already present, live and stored ones can overlap
+    pub fn insert(&mut self, liquidation: Liquidation) -> bool {
+        let entry = self.by_time.entry(liquidation.time).or_default();
+
+        if entry.contains(&liquidation) {
+            false
+        } else {
+            entry.push(liquidation);
+            true
+        }
+    }
+
+    pub fn extend(&mut self, liquidations: impl IntoIterator<Item = Liquidation>) {
+        for liquidation in liquidations {
+            self.insert(liquidation);
+        }
+    }
+
+    /// Liquidations with `earliest <= time < until`
+    pub fn range(&self, earliest: u64, until: u64) -> impl Iterator<Item = &Liquidation> {
+        self.by_time
+            .range(earliest..until.max(earliest))
+            .flat_map(|(_, liquidations)| liquidations.iter())
+    }
+
+    pub fn max_qty_in_range(&self, earliest: u64, until: u64) -> f32 {
+        self.range(earliest, until)
+            .map(|liquidation| liquidation.qty)
+            .fold(0.0, f32::max)
+    }
+
+    pub fn remove_older_than(&mut self, time: u64) {
+        self.by_time = self.by_time.split_off(&time);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_time.is_empty()
+    }
+}
@@ -70,4 +70,6 @@ pub enum Menu {
     Audio,
     ThemeEditor,
     Database,
+    ConnectionHealth,
+    Replay,
 }
@@ -1,9 +1,13 @@
 use super::ScaleFactor;
 use super::sidebar::Sidebar;
 use super::timezone::UserTimezone;
+use crate::db::RetentionPolicy;
 use crate::layout::WindowSpec;
 use crate::{AudioStream, Layout, Theme};
 
+use exchange::adapter::ExchangeInclusive;
+use exchange::proxy::Proxy;
+use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize, Default)]
@@ -24,7 +28,12 @@ pub struct State {
     pub scale_factor: ScaleFactor,
     pub audio_cfg: AudioStream,
     pub trade_fetch_enabled: bool,
+    pub frame_capture_enabled: bool,
     pub size_in_quote_currency: bool,
+    /// Without passwords, which are re-entered each session
+    pub proxies: FxHashMap<ExchangeInclusive, Proxy>,
+    pub retention: RetentionPolicy,
+    pub retry_policy: exchange::RetryPolicy,
 }
 
 impl State {
@@ -38,6 +47,7 @@ impl State {
         scale_factor: ScaleFactor,
         audio_cfg: AudioStream,
         preferred_currency: exchange::PreferredCurrency,
+        retention: RetentionPolicy,
     ) -> Self {
         State {
             layout_manager,
@@ -49,7 +59,11 @@ impl State {
             scale_factor,
             audio_cfg,
             trade_fetch_enabled: exchange::fetcher::is_trade_fetch_enabled(),
+            frame_capture_enabled: exchange::capture::is_enabled(),
             size_in_quote_currency: preferred_currency == exchange::PreferredCurrency::Quote,
+            proxies: exchange::proxy::proxies(),
+            retention,
+            retry_policy: exchange::retry_policy(),
         }
     }
 }
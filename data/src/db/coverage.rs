@@ -0,0 +1,630 @@
+//! Which days each ticker has data for, per data type
+//!
+//! Days are UTC calendar days. A run of consecutive days holding at least one row
+//! makes a [`CoveredRange`], and the days between two runs or after the last one are a gap.
+//!
+//! Rows per day are kept on the [`DatabaseManager`] between queries, days before the latest
+//! stored one are taken as settled and only counted again once the table's row count says
+//! they changed.
+
+use super::helpers::{find_ticker_id, id_to_exchange};
+use super::retention::RetainedData;
+use super::{DatabaseError, DatabaseManager, Result};
+
+use duckdb::{Connection, ToSql};
+use exchange::Ticker;
+use std::collections::{BTreeMap, HashMap};
+
+pub const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Days from `first` to `last`, both given as the start of the day in ms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayRange {
+    pub first: u64,
+    pub last: u64,
+}
+
+impl DayRange {
+    pub fn days(&self) -> u64 {
+        (self.last - self.first) / DAY_MS + 1
+    }
+
+    /// Start and exclusive end of the range in ms
+    pub fn bounds(&self) -> (u64, u64) {
+        (self.first, self.last + DAY_MS)
+    }
+}
+
+impl std::fmt::Display for DayRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let date = |time: u64| {
+            chrono::DateTime::from_timestamp_millis(time as i64)
+                .map(|dt| dt.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        };
+
+        if self.first == self.last {
+            write!(f, "{}", date(self.first))
+        } else {
+            write!(f, "{} to {}", date(self.first), date(self.last))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoveredRange {
+    pub days: DayRange,
+    pub rows: u64,
+}
+
+/// Stored days of one data type of a ticker
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    pub ticker: Ticker,
+    pub kind: RetainedData,
+    pub ranges: Vec<CoveredRange>,
+    /// Approximate, the table's size split by row count
+    pub bytes: u64,
+}
+
+impl Coverage {
+    pub fn rows(&self) -> u64 {
+        self.ranges.iter().map(|range| range.rows).sum()
+    }
+
+    /// Days missing between stored days and from the last one up to today, in order
+    pub fn gaps(&self) -> Vec<DayRange> {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        self.gaps_until(now / DAY_MS * DAY_MS)
+    }
+
+    fn gaps_until(&self, today: u64) -> Vec<DayRange> {
+        let mut gaps = self
+            .ranges
+            .windows(2)
+            .map(|pair| DayRange {
+                first: pair[0].days.last + DAY_MS,
+                last: pair[1].days.first - DAY_MS,
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(range) = self.ranges.last()
+            && range.days.last < today
+        {
+            gaps.push(DayRange {
+                first: range.days.last + DAY_MS,
+                last: today,
+            });
+        }
+
+        gaps
+    }
+}
+
+/// Rows per ticker and day of one data type, as of the last [`query`]
+#[derive(Debug, Default)]
+pub(super) struct StoredDays {
+    /// Rows by ticker id and day
+    rows: BTreeMap<(i32, u64), u64>,
+    /// Latest stored day, rows from it on are counted again on refresh
+    latest_day: Option<u64>,
+}
+
+impl StoredDays {
+    /// Count the rows from the latest stored day on again, or every row if older days changed
+    fn refresh(&mut self, conn: &Connection, kind: RetainedData) -> Result<()> {
+        let (table, time, filter) = source(kind);
+
+        let since = self.latest_day.unwrap_or(0);
+        let recent = stored_days(conn, table, time, &filter, since)?;
+
+        if let Some(latest_day) = self.latest_day {
+            let settled = self
+                .rows
+                .iter()
+                .filter(|&(&(_, day), _)| day < latest_day)
+                .map(|(_, rows)| rows)
+                .sum::<u64>();
+            let recent_rows = recent.iter().map(|&(_, _, rows)| rows).sum::<u64>();
+
+            // deleted, backfilled or imported rows
+            if settled + recent_rows != count_rows(conn, table, &filter)? {
+                *self = Self::default();
+                return self.refresh(conn, kind);
+            }
+        }
+
+        self.rows.retain(|&(_, day), _| day < since);
+        for (ticker_id, day, rows) in recent {
+            self.rows.insert((ticker_id, day), rows);
+            self.latest_day = self.latest_day.max(Some(day));
+        }
+
+        Ok(())
+    }
+
+    /// `(day, rows)` of a ticker, ordered by day
+    fn of_ticker(&self, ticker_id: i32) -> Vec<(u64, u64)> {
+        self.rows
+            .range((ticker_id, 0)..=(ticker_id, u64::MAX))
+            .map(|(&(_, day), &rows)| (day, rows))
+            .collect()
+    }
+}
+
+/// Table holding a data type, the expression of its row times in ms and a filter
+pub(super) fn source(kind: RetainedData) -> (&'static str, &'static str, String) {
+    match kind {
+        RetainedData::Trades => ("trades", "timestamp", "TRUE".to_string()),
+        RetainedData::Klines(timeframe) => (
+            "klines",
+            "candle_time",
+            format!("timeframe = '{timeframe}'"),
+        ),
+        RetainedData::DepthSnapshots => ("depth_snapshots", "timestamp", "TRUE".to_string()),
+        RetainedData::OrderRuns => ("order_runs", "epoch_ms(start_time)", "TRUE".to_string()),
+        RetainedData::OpenInterest => (
+            "open_interest",
+            "timestamp",
+            "open_interest IS NOT NULL".to_string(),
+        ),
+        RetainedData::FundingRates => (
+            "open_interest",
+            "timestamp",
+            "funding_rate IS NOT NULL".to_string(),
+        ),
+        RetainedData::Liquidations => ("liquidations", "timestamp", "TRUE".to_string()),
+    }
+}
+
+/// Delete the rows of `kind` matching `condition`
+///
+/// Open interest and funding rates share rows, so only their column is cleared
+/// and a row goes once both are.
+pub(super) fn delete_rows(
+    conn: &Connection,
+    kind: RetainedData,
+    condition: &str,
+    params: &[&dyn ToSql],
+) -> Result<usize> {
+    let (table, _, _) = source(kind);
+
+    let column = match kind {
+        RetainedData::OpenInterest => "open_interest",
+        RetainedData::FundingRates => "funding_rate",
+        _ => {
+            return conn
+                .execute(&format!("DELETE FROM {table} WHERE {condition}"), params)
+                .map_err(|e| DatabaseError::Query(format!("Failed to delete from {table}: {e}")));
+        }
+    };
+
+    let cleared = conn
+        .execute(
+            &format!("UPDATE {table} SET {column} = NULL WHERE {condition}"),
+            params,
+        )
+        .map_err(|e| DatabaseError::Query(format!("Failed to clear {column}: {e}")))?;
+
+    conn.execute(
+        &format!("DELETE FROM {table} WHERE open_interest IS NULL AND funding_rate IS NULL"),
+        [],
+    )
+    .map_err(|e| DatabaseError::Query(format!("Failed to delete empty rows: {e}")))?;
+
+    Ok(cleared)
+}
+
+/// Coverage of every stored ticker, by exchange and symbol, then in [`RetainedData::all`] order
+///
+/// The connection is let go between data types, so writers only wait for one refresh.
+pub fn query(db: &DatabaseManager) -> Result<Vec<Coverage>> {
+    let tickers = db.with_conn(|conn| stored_tickers(conn))?;
+    let mut stored = db.coverage.lock().map_err(|_| DatabaseError::LockError)?;
+
+    let mut coverages = vec![];
+    let mut table_rows: HashMap<&str, u64> = HashMap::new();
+
+    for kind in RetainedData::all() {
+        let (table, _, _) = source(kind);
+
+        let days = stored.entry(kind).or_default();
+        db.with_conn(|conn| days.refresh(conn, kind))?;
+
+        for (ticker_id, ticker) in &tickers {
+            let days = days.of_ticker(*ticker_id);
+            if days.is_empty() {
+                continue;
+            }
+
+            let coverage = Coverage {
+                ticker: *ticker,
+                kind,
+                ranges: merge_days(&days),
+                bytes: 0,
+            };
+            *table_rows.entry(table).or_default() += coverage.rows();
+            coverages.push(coverage);
+        }
+    }
+    drop(stored);
+
+    let table_bytes = db.with_conn(|conn| {
+        table_rows
+            .keys()
+            .map(|table| table_size(conn, table).map(|bytes| (*table, bytes)))
+            .collect::<Result<HashMap<_, _>>>()
+    })?;
+
+    for coverage in &mut coverages {
+        let (table, _, _) = source(coverage.kind);
+        let rows = table_rows.get(table).copied().unwrap_or_default();
+
+        if rows > 0 {
+            let bytes = table_bytes.get(table).copied().unwrap_or_default();
+            coverage.bytes =
+                (u128::from(bytes) * u128::from(coverage.rows()) / u128::from(rows)) as u64;
+        }
+    }
+
+    Ok(coverages)
+}
+
+/// Delete what `ticker` has stored of `kind` within `range`, then checkpoint if anything went
+pub fn delete(
+    db: &DatabaseManager,
+    ticker: &Ticker,
+    kind: RetainedData,
+    range: DayRange,
+) -> Result<usize> {
+    let deleted = db.with_conn(|conn| {
+        let Some(ticker_id) = find_ticker_id(conn, ticker)? else {
+            return Ok(0);
+        };
+
+        let (_, time, filter) = source(kind);
+        let (start, end) = range.bounds();
+
+        delete_rows(
+            conn,
+            kind,
+            &format!("ticker_id = ? AND {time} >= ? AND {time} < ? AND {filter}"),
+            duckdb::params![ticker_id, start as i64, end as i64],
+        )
+    })?;
+
+    if deleted > 0 {
+        db.clear_cache();
+        if let Ok(mut stored) = db.coverage.lock() {
+            stored.remove(&kind);
+        }
+        db.checkpoint()?;
+    }
+
+    Ok(deleted)
+}
+
+/// Tickers by id, ordered by exchange and symbol
+fn stored_tickers(conn: &Connection) -> Result<Vec<(i32, Ticker)>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.ticker_id, t.exchange_id, t.symbol, d.display_symbol
+             FROM tickers t
+             LEFT JOIN ticker_display_symbols d ON d.ticker_id = t.ticker_id
+             ORDER BY t.exchange_id, t.symbol",
+        )
+        .map_err(|e| DatabaseError::Query(format!("Failed to prepare query: {e}")))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, i8>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })
+        .map_err(|e| DatabaseError::Query(format!("Failed to query tickers: {e}")))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| DatabaseError::Query(format!("Failed to read tickers: {e}")))?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(ticker_id, exchange_id, symbol, display_symbol)| {
+            let exchange = id_to_exchange(exchange_id)?;
+            Some((
+                ticker_id,
+                Ticker::new_with_display(&symbol, exchange, display_symbol.as_deref()),
+            ))
+        })
+        .collect())
+}
+
+/// Rows per ticker and day from the day starting at `since` on, ordered by ticker and day
+fn stored_days(
+    conn: &Connection,
+    table: &str,
+    time: &str,
+    filter: &str,
+    since: u64,
+) -> Result<Vec<(i32, u64, u64)>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT ticker_id, {time} // {DAY_MS} AS day, COUNT(*)
+             FROM {table}
+             WHERE {filter} AND {time} >= {since}
+             GROUP BY ticker_id, day
+             ORDER BY ticker_id, day"
+        ))
+        .map_err(|e| DatabaseError::Query(format!("Failed to prepare query: {e}")))?;
+
+    stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i32>(0)?,
+            row.get::<_, i64>(1)? as u64 * DAY_MS,
+            row.get::<_, i64>(2)? as u64,
+        ))
+    })
+    .map_err(|e| DatabaseError::Query(format!("Failed to query {table}: {e}")))?
+    .collect::<std::result::Result<Vec<_>, _>>()
+    .map_err(|e| DatabaseError::Query(format!("Failed to read {table}: {e}")))
+}
+
+fn count_rows(conn: &Connection, table: &str, filter: &str) -> Result<u64> {
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM {table} WHERE {filter}"),
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|rows| rows as u64)
+    .map_err(|e| DatabaseError::Query(format!("Failed to count {table}: {e}")))
+}
+
+/// Join consecutive `(day, rows)` into ranges, `days` being sorted
+fn merge_days(days: &[(u64, u64)]) -> Vec<CoveredRange> {
+    let mut ranges: Vec<CoveredRange> = vec![];
+
+    for &(day, rows) in days {
+        match ranges.last_mut() {
+            Some(range) if range.days.last + DAY_MS == day => {
+                range.days.last = day;
+                range.rows += rows;
+            }
+            _ => ranges.push(CoveredRange {
+                days: DayRange {
+                    first: day,
+                    last: day,
+                },
+                rows,
+            }),
+        }
+    }
+
+    ranges
+}
+
+/// Bytes of the blocks a table occupies, leaving out rows not yet checkpointed
+fn table_size(conn: &Connection, table: &str) -> Result<u64> {
+    let block_size: i64 = conn
+        .query_row("SELECT block_size FROM pragma_database_size()", [], |row| {
+            row.get(0)
+        })
+        .unwrap_or(0);
+
+    let blocks: i64 = conn
+        .query_row(
+            &format!(
+                "SELECT COUNT(DISTINCT block_id) FROM pragma_storage_info('{table}')
+                 WHERE block_id >= 0"
+            ),
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| DatabaseError::Query(format!("Failed to query size of {table}: {e}")))?;
+
+    Ok((blocks * block_size).max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{KlinesCRUD, TradesCRUD};
+    use exchange::adapter::Exchange;
+    use exchange::util::Price;
+    use exchange::{Kline, TickerInfo, Timeframe, Trade};
+    use tempfile::tempdir;
+
+    fn ticker_info() -> TickerInfo {
+        TickerInfo::new(
+            Ticker::new("BTCUSDT", Exchange::BinanceLinear),
+            0.1,
+            0.001,
+            None,
+        )
+    }
+
+    /// Two trades on each of the given days
+    fn trades_on(days: &[u64]) -> Vec<Trade> {
+        days.iter()
+            .flat_map(|day| [day * DAY_MS + 1_000, day * DAY_MS + 2_000])
+            .map(|time| Trade {
+                time,
+                price: Price::from_f32(100.0),
+                qty: 1.0,
+                is_sell: false,
+            })
+            .collect()
+    }
+
+    fn day(n: u64) -> DayRange {
+        DayRange {
+            first: n * DAY_MS,
+            last: n * DAY_MS,
+        }
+    }
+
+    #[test]
+    fn splits_days_into_ranges_and_gaps() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        db.insert_trades(&ticker_info(), &trades_on(&[10, 11, 12, 15, 20, 21]))
+            .unwrap();
+
+        let coverages = query(&db).unwrap();
+        assert_eq!(coverages.len(), 1);
+
+        let trades = &coverages[0];
+        assert_eq!(trades.kind, RetainedData::Trades);
+        assert_eq!(trades.rows(), 12);
+        assert_eq!(
+            trades
+                .ranges
+                .iter()
+                .map(|range| (range.days.days(), range.rows))
+                .collect::<Vec<_>>(),
+            vec![(3, 6), (1, 2), (2, 4)]
+        );
+        assert_eq!(
+            trades.gaps_until(21 * DAY_MS),
+            vec![
+                DayRange {
+                    first: 13 * DAY_MS,
+                    last: 14 * DAY_MS,
+                },
+                DayRange {
+                    first: 16 * DAY_MS,
+                    last: 19 * DAY_MS,
+                },
+            ]
+        );
+
+        // days after the last stored one are missing too
+        assert_eq!(
+            trades.gaps_until(24 * DAY_MS).last(),
+            Some(&DayRange {
+                first: 22 * DAY_MS,
+                last: 24 * DAY_MS,
+            })
+        );
+    }
+
+    #[test]
+    fn refreshes_latest_days_and_rescans_changed_ones() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+        let ticker_info = ticker_info();
+
+        let ranges = |db: &DatabaseManager| {
+            query(db).unwrap()[0]
+                .ranges
+                .iter()
+                .map(|range| (range.days, range.rows))
+                .collect::<Vec<_>>()
+        };
+
+        db.insert_trades(&ticker_info, &trades_on(&[10, 11]))
+            .unwrap();
+        assert_eq!(ranges(&db).len(), 1);
+
+        // the latest day grows and a later one starts
+        let mut later = trades_on(&[12]);
+        later.push(Trade {
+            time: 11 * DAY_MS + 3_000,
+            price: Price::from_f32(100.0),
+            qty: 1.0,
+            is_sell: false,
+        });
+        db.insert_trades(&ticker_info, &later).unwrap();
+        assert_eq!(
+            ranges(&db),
+            vec![(
+                DayRange {
+                    first: 10 * DAY_MS,
+                    last: 12 * DAY_MS,
+                },
+                7
+            )]
+        );
+
+        // a backfilled day before the latest one
+        db.insert_trades(&ticker_info, &trades_on(&[5])).unwrap();
+        assert_eq!(ranges(&db)[0], (day(5), 2));
+    }
+
+    #[test]
+    fn klines_are_covered_per_timeframe() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        let price = Price::from_f32(100.0);
+        let kline = |time| Kline {
+            time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: (1.0, 1.0),
+        };
+
+        db.insert_klines(&ticker_info(), Timeframe::M1, &[kline(DAY_MS)])
+            .unwrap();
+        db.insert_klines(&ticker_info(), Timeframe::M5, &[kline(3 * DAY_MS)])
+            .unwrap();
+
+        let kinds = query(&db)
+            .unwrap()
+            .into_iter()
+            .map(|coverage| (coverage.kind, coverage.ranges[0].days))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (RetainedData::Klines(Timeframe::M1), day(1)),
+                (RetainedData::Klines(Timeframe::M5), day(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn deletes_a_range() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+        let ticker_info = ticker_info();
+
+        db.insert_trades(&ticker_info, &trades_on(&[1, 2, 3]))
+            .unwrap();
+
+        let range = DayRange {
+            first: DAY_MS,
+            last: 2 * DAY_MS,
+        };
+        assert_eq!(
+            delete(&db, &ticker_info.ticker, RetainedData::Trades, range).unwrap(),
+            4
+        );
+
+        let coverages = query(&db).unwrap();
+        assert_eq!(coverages[0].ranges.len(), 1);
+        assert_eq!(coverages[0].ranges[0].days, day(3));
+    }
+
+    #[test]
+    fn keeps_display_symbols() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        let ticker_info = TickerInfo::new(
+            Ticker::new_with_display("@107", Exchange::HyperliquidSpot, Some("HYPEUSDC")),
+            0.001,
+            0.01,
+            None,
+        );
+        db.insert_trades(&ticker_info, &trades_on(&[1])).unwrap();
+
+        let coverages = query(&db).unwrap();
+        assert_eq!(coverages[0].ticker, ticker_info.ticker);
+        assert_eq!(coverages[0].ticker.display_symbol(), Some("HYPEUSDC"));
+    }
+}
@@ -8,7 +8,7 @@ use crate::db::DatabaseManager;
 use crate::db::crud::{FootprintCRUD, KlinesCRUD};
 use crate::aggr::time::TimeSeries;
 use crate::chart::kline::{GroupedTrades, KlineDataPoint, KlineTrades};
-use exchange::util::Price;
+use exchange::util::{Price, PriceStep};
 use exchange::{TickerInfo, Timeframe};
 use rustc_hash::FxHashMap;
 use std::collections::BTreeMap;
@@ -250,6 +250,102 @@ impl FootprintCRUD for DatabaseManager {
         Ok(timeseries)
     }
 
+    /// Rebuild footprints per kline from stored trades
+    ///
+    /// Same aggregation as `query_trades_aggregated` but grouped by candle as well,
+    /// so clusters can be rebuilt for exchanges without a REST trade backfill.
+    /// Raw price levels are re-binned to `step` to match the chart's tick size
+    fn rebuild_footprints_from_trades(
+        &self,
+        ticker_info: &TickerInfo,
+        timeframe: Timeframe,
+        step: PriceStep,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<BTreeMap<u64, KlineTrades>> {
+        let interval = timeframe.to_milliseconds() as i64;
+
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "SELECT
+                         timestamp - (timestamp % ?) as candle_time,
+                         price,
+                         SUM(CASE WHEN is_buyer_maker = true THEN quantity ELSE 0 END) as buy_volume,
+                         SUM(CASE WHEN is_buyer_maker = false THEN quantity ELSE 0 END) as sell_volume,
+                         COUNT(CASE WHEN is_buyer_maker = true THEN 1 END) as buy_count,
+                         COUNT(CASE WHEN is_buyer_maker = false THEN 1 END) as sell_count,
+                         MIN(timestamp) as first_time,
+                         MAX(timestamp) as last_time
+                     FROM trades
+                     WHERE ticker_id = ? AND timestamp >= ? AND timestamp <= ?
+                     GROUP BY candle_time, price
+                     ORDER BY candle_time, price",
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to prepare aggregation query: {}", e)))?;
+
+            let levels_iter = stmt
+                .query_map(
+                    duckdb::params![interval, ticker_id, start_time as i64, end_time as i64],
+                    |row| {
+                        let candle_time: i64 = row.get(0)?;
+                        let price: f64 = row.get(1)?;
+                        let buy_volume: f64 = row.get(2)?;
+                        let sell_volume: f64 = row.get(3)?;
+                        let buy_count: i64 = row.get(4)?;
+                        let sell_count: i64 = row.get(5)?;
+                        let first_time: i64 = row.get(6)?;
+                        let last_time: i64 = row.get(7)?;
+
+                        Ok((
+                            candle_time as u64,
+                            decimal_to_price(price),
+                            GroupedTrades {
+                                buy_qty: buy_volume as f32,
+                                sell_qty: sell_volume as f32,
+                                first_time: first_time as u64,
+                                last_time: last_time as u64,
+                                buy_count: buy_count as usize,
+                                sell_count: sell_count as usize,
+                            },
+                        ))
+                    },
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to query aggregated trades: {}", e)))?;
+
+            let mut footprints: BTreeMap<u64, KlineTrades> = BTreeMap::new();
+
+            for level_result in levels_iter {
+                let (candle_time, price, grouped) = level_result.map_err(|e| {
+                    DatabaseError::Query(format!("Failed to map aggregated trade: {}", e))
+                })?;
+
+                footprints
+                    .entry(candle_time)
+                    .or_default()
+                    .trades
+                    .entry(price.round_to_step(step))
+                    .and_modify(|existing| {
+                        existing.buy_qty += grouped.buy_qty;
+                        existing.sell_qty += grouped.sell_qty;
+                        existing.buy_count += grouped.buy_count;
+                        existing.sell_count += grouped.sell_count;
+                        existing.first_time = existing.first_time.min(grouped.first_time);
+                        existing.last_time = existing.last_time.max(grouped.last_time);
+                    })
+                    .or_insert(grouped);
+            }
+
+            for kline_trades in footprints.values_mut() {
+                kline_trades.calculate_poc();
+            }
+
+            Ok(footprints)
+        })
+    }
+
     /// Delete footprint data older than cutoff
     fn delete_footprints_older_than(&self, cutoff_time: u64) -> Result<usize> {
         self.with_conn(|conn| {
@@ -466,4 +562,57 @@ mod tests {
 
         assert_eq!(queried.trades.len(), 20);
     }
+
+    #[test]
+    fn test_rebuild_footprints_from_trades() {
+        use crate::db::crud::TradesCRUD;
+        use exchange::Trade;
+
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+        let timeframe = Timeframe::M1;
+
+        let trade = |time: u64, price: f32, qty: f32, is_sell: bool| Trade {
+            time,
+            is_sell,
+            price: Price::from_f32(price),
+            qty,
+        };
+
+        let trades = vec![
+            // first candle: two raw prices that share a 1.0 bin
+            trade(60_000, 100.01, 1.0, false),
+            trade(60_010, 100.02, 2.0, true),
+            trade(60_020, 101.00, 3.0, false),
+            // second candle
+            trade(120_500, 102.00, 4.0, true),
+        ];
+        db.insert_trades(&ticker_info, &trades).unwrap();
+
+        let footprints = db
+            .rebuild_footprints_from_trades(
+                &ticker_info,
+                timeframe,
+                PriceStep::from_f32(1.0),
+                0,
+                u64::MAX / 2,
+            )
+            .unwrap();
+
+        assert_eq!(footprints.len(), 2);
+
+        let first = &footprints[&60_000];
+        assert_eq!(first.trades.len(), 2);
+        let bin = &first.trades[&Price::from_f32(100.0)];
+        assert_eq!(bin.buy_qty, 1.0);
+        assert_eq!(bin.sell_qty, 2.0);
+        assert_eq!(bin.buy_count, 1);
+        assert_eq!(bin.sell_count, 1);
+        assert_eq!(bin.first_time, 60_000);
+        assert_eq!(bin.last_time, 60_010);
+        assert!(first.poc.is_some());
+
+        let second = &footprints[&120_000];
+        assert_eq!(second.trades[&Price::from_f32(102.0)].sell_qty, 4.0);
+    }
 }
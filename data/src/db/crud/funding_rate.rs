@@ -0,0 +1,241 @@
+//! Funding rate CRUD operations
+//!
+//! Funding rates live in the `funding_rate` column of the `open_interest` table,
+//! so a row may exist with only one of the two columns populated.
+
+use crate::db::DatabaseManager;
+use crate::db::crud::FundingRateCRUD;
+use crate::db::error::{DatabaseError, Result};
+use crate::db::helpers::{generate_open_interest_id, get_or_create_ticker_id};
+use exchange::{FundingRate, TickerInfo};
+
+impl FundingRateCRUD for DatabaseManager {
+    /// Insert or update settled funding rates
+    ///
+    /// The UNIQUE constraint on (ticker_id, timestamp) keeps a single row per
+    /// timestamp; an existing open interest value on that row is preserved
+    fn insert_funding_rates(
+        &self,
+        ticker_info: &TickerInfo,
+        data: &[FundingRate],
+    ) -> Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "INSERT INTO open_interest (oi_id, ticker_id, timestamp, funding_rate)
+                     VALUES (?, ?, ?, ?)
+                     ON CONFLICT (ticker_id, timestamp) DO UPDATE SET
+                         funding_rate = EXCLUDED.funding_rate",
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to prepare insert: {}", e)))?;
+
+            for funding in data {
+                stmt.execute(duckdb::params![
+                    generate_open_interest_id(ticker_id, funding.time),
+                    ticker_id,
+                    funding.time as i64,
+                    funding.rate as f64,
+                ])
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to insert funding rate: {}", e))
+                })?;
+            }
+
+            Ok(data.len())
+        })
+    }
+
+    /// Query funding rates by time range
+    fn query_funding_rates(
+        &self,
+        ticker_info: &TickerInfo,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<Vec<FundingRate>> {
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "SELECT timestamp, funding_rate
+                     FROM open_interest
+                     WHERE ticker_id = ? AND timestamp >= ? AND timestamp <= ?
+                       AND funding_rate IS NOT NULL
+                     ORDER BY timestamp ASC",
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to prepare query: {}", e)))?;
+
+            let funding_iter = stmt
+                .query_map(
+                    duckdb::params![ticker_id, start_time as i64, end_time as i64],
+                    |row| {
+                        let timestamp: i64 = row.get(0)?;
+                        let rate: f64 = row.get(1)?;
+
+                        Ok(FundingRate {
+                            time: timestamp as u64,
+                            rate: rate as f32,
+                        })
+                    },
+                )
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to query funding rates: {}", e))
+                })?;
+
+            let mut data = Vec::new();
+            for funding_result in funding_iter {
+                data.push(funding_result.map_err(|e| {
+                    DatabaseError::Query(format!("Failed to map funding rate: {}", e))
+                })?);
+            }
+
+            Ok(data)
+        })
+    }
+
+    /// Check database coverage for funding rate data
+    ///
+    /// Returns earliest and latest timestamps, or None if no data exists
+    fn query_funding_rate_coverage(&self, ticker_info: &TickerInfo) -> Result<Option<(u64, u64)>> {
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let (min_time, max_time): (Option<i64>, Option<i64>) = conn
+                .query_row(
+                    "SELECT MIN(timestamp), MAX(timestamp)
+                     FROM open_interest
+                     WHERE ticker_id = ? AND funding_rate IS NOT NULL",
+                    [ticker_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to query funding rate coverage: {}", e))
+                })?;
+
+            Ok(min_time
+                .zip(max_time)
+                .map(|(min, max)| (min as u64, max as u64)))
+        })
+    }
+
+    /// Delete funding rates older than cutoff timestamp
+    ///
+    /// Open interest on the same rows is kept, only rows left empty are removed
+    fn delete_funding_rates_older_than(&self, cutoff_time: u64) -> Result<usize> {
+        self.with_conn(|conn| {
+            let cleared = conn
+                .execute(
+                    "UPDATE open_interest SET funding_rate = NULL
+                     WHERE timestamp < ? AND funding_rate IS NOT NULL",
+                    [cutoff_time as i64],
+                )
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to delete funding rates: {}", e))
+                })?;
+
+            conn.execute(
+                "DELETE FROM open_interest
+                 WHERE timestamp < ? AND open_interest IS NULL AND funding_rate IS NULL",
+                [cutoff_time as i64],
+            )
+            .map_err(|e| DatabaseError::Query(format!("Failed to delete empty rows: {}", e)))?;
+
+            Ok(cleared)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::OpenInterestCRUD;
+    use exchange::adapter::Exchange;
+    use exchange::{OpenInterest, Ticker};
+    use tempfile::tempdir;
+
+    fn create_test_db() -> (DatabaseManager, tempfile::TempDir) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = DatabaseManager::new(db_path).unwrap();
+        (db, dir)
+    }
+
+    fn create_test_ticker_info() -> TickerInfo {
+        let ticker = Ticker::new("BTCUSDT", Exchange::BinanceLinear);
+        TickerInfo::new(ticker, 0.01, 0.001, None)
+    }
+
+    fn create_test_funding(count: usize) -> Vec<FundingRate> {
+        (0..count)
+            .map(|i| FundingRate {
+                time: 28800000 + (i as u64 * 28800000),
+                rate: 0.0001 * (i as f32 - 2.0),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_and_query_funding_rates() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let data = create_test_funding(10);
+        let inserted = db.insert_funding_rates(&ticker_info, &data).unwrap();
+        assert_eq!(inserted, 10);
+
+        let queried = db.query_funding_rates(&ticker_info, 0, 1000000000).unwrap();
+        assert_eq!(queried.len(), 10);
+        assert_eq!(queried[0].time, data[0].time);
+        assert!((queried[0].rate - data[0].rate).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_funding_rates_share_rows_with_open_interest() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let funding = create_test_funding(3);
+        let oi: Vec<OpenInterest> = funding
+            .iter()
+            .map(|f| OpenInterest {
+                time: f.time,
+                value: 80000.0,
+            })
+            .collect();
+
+        db.insert_open_interest(&ticker_info, &oi).unwrap();
+        db.insert_funding_rates(&ticker_info, &funding).unwrap();
+
+        // Neither upsert overwrites the other column
+        assert_eq!(
+            db.query_open_interest(&ticker_info, 0, 1000000000).unwrap(),
+            oi
+        );
+        assert_eq!(
+            db.query_funding_rates(&ticker_info, 0, 1000000000)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // Deleting funding keeps the open interest on those rows
+        let cleared = db.delete_funding_rates_older_than(funding[2].time).unwrap();
+        assert_eq!(cleared, 2);
+        assert_eq!(
+            db.query_open_interest(&ticker_info, 0, 1000000000)
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            db.query_funding_rate_coverage(&ticker_info).unwrap(),
+            Some((funding[2].time, funding[2].time))
+        );
+    }
+}
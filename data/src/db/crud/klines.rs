@@ -212,6 +212,51 @@ impl KlinesCRUD for DatabaseManager {
     }
 }
 
+/// Compute the candle ranges missing from `stored_times` within `[start, end]`
+///
+/// `stored_times` must be sorted ascending candle open times. Each returned range
+/// is inclusive of the first and last missing candle open time, so it can be passed
+/// straight to `exchange::adapter::fetch_klines`.
+pub fn missing_kline_ranges(
+    stored_times: &[u64],
+    start: u64,
+    end: u64,
+    timeframe: Timeframe,
+) -> Vec<(u64, u64)> {
+    let interval = timeframe.to_milliseconds();
+    if interval == 0 || start > end {
+        return vec![];
+    }
+
+    let first = start.div_ceil(interval) * interval;
+    let last = (end / interval) * interval;
+
+    let mut ranges = Vec::new();
+    let mut gap_start: Option<u64> = None;
+    let mut stored = stored_times.iter().copied().peekable();
+
+    let mut t = first;
+    while t <= last {
+        while stored.next_if(|&s| s < t).is_some() {}
+
+        if stored.next_if_eq(&t).is_some() {
+            if let Some(from) = gap_start.take() {
+                ranges.push((from, t - interval));
+            }
+        } else if gap_start.is_none() {
+            gap_start = Some(t);
+        }
+
+        t += interval;
+    }
+
+    if let Some(from) = gap_start {
+        ranges.push((from, last));
+    }
+
+    ranges
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -372,4 +417,37 @@ mod tests {
             assert_eq!(klines.len(), 10);
         }
     }
+
+    #[test]
+    fn test_missing_kline_ranges() {
+        let timeframe = Timeframe::M1;
+        let interval = timeframe.to_milliseconds();
+
+        // Nothing stored: the whole window is missing
+        assert_eq!(
+            missing_kline_ranges(&[], 0, 9 * interval, timeframe),
+            vec![(0, 9 * interval)]
+        );
+
+        // Fully covered: nothing to fetch
+        let stored: Vec<u64> = (0..10).map(|i| i * interval).collect();
+        assert!(missing_kline_ranges(&stored, 0, 9 * interval, timeframe).is_empty());
+
+        // Leading, middle and trailing gaps
+        let stored = vec![2 * interval, 3 * interval, 6 * interval, 7 * interval];
+        assert_eq!(
+            missing_kline_ranges(&stored, 0, 9 * interval, timeframe),
+            vec![
+                (0, interval),
+                (4 * interval, 5 * interval),
+                (8 * interval, 9 * interval),
+            ]
+        );
+
+        // Unaligned bounds snap to candle open times
+        assert_eq!(
+            missing_kline_ranges(&[interval], interval / 2, 2 * interval + 1, timeframe),
+            vec![(2 * interval, 2 * interval)]
+        );
+    }
 }
@@ -0,0 +1,190 @@
+//! Liquidation CRUD operations
+//!
+//! Exchanges don't serve liquidation history, so the only record of them is
+//! what was received over the market streams and stored here.
+
+use crate::db::DatabaseManager;
+use crate::db::crud::LiquidationsCRUD;
+use crate::db::error::{DatabaseError, Result};
+use crate::db::helpers::{
+    decimal_to_price, generate_liquidation_id, get_or_create_ticker_id, price_to_decimal,
+};
+use exchange::{Liquidation, TickerInfo};
+
+impl LiquidationsCRUD for DatabaseManager {
+    /// Insert liquidations with ON CONFLICT DO NOTHING
+    ///
+    /// The same forced order can be received again after a reconnect
+    fn insert_liquidations(&self, ticker_info: &TickerInfo, data: &[Liquidation]) -> Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "INSERT INTO liquidations (liquidation_id, ticker_id, timestamp, price, quantity, is_sell)
+                     VALUES (?, ?, ?, ?, ?, ?)
+                     ON CONFLICT (liquidation_id) DO NOTHING",
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to prepare insert: {}", e)))?;
+
+            let mut inserted = 0;
+            for liquidation in data {
+                inserted += stmt
+                    .execute(duckdb::params![
+                        generate_liquidation_id(ticker_id, liquidation),
+                        ticker_id,
+                        liquidation.time as i64,
+                        price_to_decimal(liquidation.price),
+                        liquidation.qty as f64,
+                        liquidation.is_sell,
+                    ])
+                    .map_err(|e| {
+                        DatabaseError::Query(format!("Failed to insert liquidation: {}", e))
+                    })?;
+            }
+
+            Ok(inserted)
+        })
+    }
+
+    /// Query liquidations by time range
+    fn query_liquidations(
+        &self,
+        ticker_info: &TickerInfo,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<Vec<Liquidation>> {
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "SELECT timestamp, price, quantity, is_sell
+                     FROM liquidations
+                     WHERE ticker_id = ? AND timestamp >= ? AND timestamp <= ?
+                     ORDER BY timestamp ASC",
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to prepare query: {}", e)))?;
+
+            let liquidation_iter = stmt
+                .query_map(
+                    duckdb::params![ticker_id, start_time as i64, end_time as i64],
+                    |row| {
+                        let timestamp: i64 = row.get(0)?;
+                        let price: f64 = row.get(1)?;
+                        let qty: f64 = row.get(2)?;
+                        let is_sell: bool = row.get(3)?;
+
+                        Ok(Liquidation {
+                            time: timestamp as u64,
+                            is_sell,
+                            price: decimal_to_price(price),
+                            qty: qty as f32,
+                        })
+                    },
+                )
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to query liquidations: {}", e))
+                })?;
+
+            let mut data = Vec::new();
+            for liquidation_result in liquidation_iter {
+                data.push(liquidation_result.map_err(|e| {
+                    DatabaseError::Query(format!("Failed to map liquidation: {}", e))
+                })?);
+            }
+
+            Ok(data)
+        })
+    }
+
+    /// Delete liquidations older than cutoff timestamp
+    fn delete_liquidations_older_than(&self, cutoff_time: u64) -> Result<usize> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM liquidations WHERE timestamp < ?",
+                [cutoff_time as i64],
+            )
+            .map_err(|e| DatabaseError::Query(format!("Failed to delete liquidations: {}", e)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exchange::Ticker;
+    use exchange::adapter::Exchange;
+    use exchange::util::Price;
+    use tempfile::tempdir;
+
+    fn create_test_db() -> (DatabaseManager, tempfile::TempDir) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = DatabaseManager::new(db_path).unwrap();
+        (db, dir)
+    }
+
+    fn create_test_ticker_info() -> TickerInfo {
+        let ticker = Ticker::new("BTCUSDT", Exchange::BinanceLinear);
+        TickerInfo::new(ticker, 0.01, 0.001, None)
+    }
+
+    fn create_test_liquidations(count: usize) -> Vec<Liquidation> {
+        (0..count)
+            .map(|i| Liquidation {
+                time: 1000000 + (i as u64 * 1000),
+                is_sell: i % 2 == 0,
+                price: Price::from_f32(50000.0 + i as f32),
+                qty: 0.5 + i as f32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_and_query_liquidations() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let data = create_test_liquidations(10);
+        let inserted = db.insert_liquidations(&ticker_info, &data).unwrap();
+        assert_eq!(inserted, 10);
+
+        let queried = db.query_liquidations(&ticker_info, 0, 2000000).unwrap();
+        assert_eq!(queried, data);
+    }
+
+    #[test]
+    fn test_insert_liquidations_ignores_duplicates() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let data = create_test_liquidations(5);
+        db.insert_liquidations(&ticker_info, &data).unwrap();
+
+        let inserted = db.insert_liquidations(&ticker_info, &data[3..]).unwrap();
+        assert_eq!(inserted, 0);
+
+        let queried = db.query_liquidations(&ticker_info, 0, 2000000).unwrap();
+        assert_eq!(queried.len(), 5);
+    }
+
+    #[test]
+    fn test_delete_liquidations_older_than() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let data = create_test_liquidations(10);
+        db.insert_liquidations(&ticker_info, &data).unwrap();
+
+        let deleted = db.delete_liquidations_older_than(data[4].time).unwrap();
+        assert_eq!(deleted, 4);
+
+        let remaining = db.query_liquidations(&ticker_info, 0, 2000000).unwrap();
+        assert_eq!(remaining.len(), 6);
+    }
+}
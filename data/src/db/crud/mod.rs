@@ -5,9 +5,11 @@
 //! - Klines: OHLCV candlestick data across multiple timeframes
 //! - Depth: Orderbook snapshots for heatmap reconstruction
 //! - Footprint: Price-level aggregations within klines
+//! - Open interest: Derivatives open interest history
+//! - Liquidations: Forced orders from the live market streams
 
 use super::error::Result;
-use exchange::{Kline, TickerInfo, Timeframe, Trade};
+use exchange::{FundingRate, Kline, Liquidation, OpenInterest, TickerInfo, Timeframe, Trade};
 use exchange::depth::Depth;
 use crate::chart::kline::KlineTrades;
 use crate::aggr::time::TimeSeries;
@@ -31,6 +33,8 @@ pub trait TradesCRUD {
     fn query_trades_aggregated(&self, ticker_info: &TickerInfo, start_time: u64, end_time: u64) -> Result<Vec<(exchange::util::Price, f32, f32, usize, usize)>>;
 
     /// Check database coverage for trade data
+    ///
+    /// Only the first and last trade, [`crate::db::coverage`] has the stored days and gaps
     fn query_trades_coverage(&self, ticker_info: &TickerInfo) -> Result<Option<(u64, u64)>>;
 }
 
@@ -81,13 +85,61 @@ pub trait FootprintCRUD {
     /// Load TimeSeries with both klines and footprints
     fn load_timeseries_with_footprints(&self, ticker_info: &TickerInfo, timeframe: Timeframe, start_time: u64, end_time: u64) -> Result<TimeSeries<KlineDataPoint>>;
 
+    /// Rebuild footprints per kline from stored trades, binned to the given price step
+    fn rebuild_footprints_from_trades(&self, ticker_info: &TickerInfo, timeframe: Timeframe, step: exchange::util::PriceStep, start_time: u64, end_time: u64) -> Result<std::collections::BTreeMap<u64, KlineTrades>>;
+
     /// Delete footprint data older than cutoff
     fn delete_footprints_older_than(&self, cutoff_time: u64) -> Result<usize>;
 }
 
+/// Trait for Open Interest CRUD operations
+pub trait OpenInterestCRUD {
+    /// Insert or update open interest values
+    fn insert_open_interest(&self, ticker_info: &TickerInfo, data: &[OpenInterest]) -> Result<usize>;
+
+    /// Query open interest by time range
+    fn query_open_interest(&self, ticker_info: &TickerInfo, start_time: u64, end_time: u64) -> Result<Vec<OpenInterest>>;
+
+    /// Check database coverage for open interest data
+    fn query_open_interest_coverage(&self, ticker_info: &TickerInfo) -> Result<Option<(u64, u64)>>;
+
+    /// Delete open interest older than cutoff timestamp
+    fn delete_open_interest_older_than(&self, cutoff_time: u64) -> Result<usize>;
+}
+
+/// Trait for Funding Rate CRUD operations
+pub trait FundingRateCRUD {
+    /// Insert or update settled funding rates
+    fn insert_funding_rates(&self, ticker_info: &TickerInfo, data: &[FundingRate]) -> Result<usize>;
+
+    /// Query funding rates by time range
+    fn query_funding_rates(&self, ticker_info: &TickerInfo, start_time: u64, end_time: u64) -> Result<Vec<FundingRate>>;
+
+    /// Check database coverage for funding rate data
+    fn query_funding_rate_coverage(&self, ticker_info: &TickerInfo) -> Result<Option<(u64, u64)>>;
+
+    /// Delete funding rates older than cutoff timestamp
+    fn delete_funding_rates_older_than(&self, cutoff_time: u64) -> Result<usize>;
+}
+
+/// Trait for Liquidation CRUD operations
+pub trait LiquidationsCRUD {
+    /// Insert liquidations, ignoring ones already stored
+    fn insert_liquidations(&self, ticker_info: &TickerInfo, data: &[Liquidation]) -> Result<usize>;
+
+    /// Query liquidations by time range
+    fn query_liquidations(&self, ticker_info: &TickerInfo, start_time: u64, end_time: u64) -> Result<Vec<Liquidation>>;
+
+    /// Delete liquidations older than cutoff timestamp
+    fn delete_liquidations_older_than(&self, cutoff_time: u64) -> Result<usize>;
+}
+
 // Import implementations
 pub mod trades;
 pub mod klines;
 pub mod depth;
 pub mod footprint;
 pub mod order_runs;
+pub mod open_interest;
+pub mod funding_rate;
+pub mod liquidations;
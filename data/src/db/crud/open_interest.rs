@@ -0,0 +1,244 @@
+//! Open interest CRUD operations
+//!
+//! Rows share the `open_interest` table with funding rates, so a row may exist
+//! with only one of the two columns populated.
+
+use crate::db::DatabaseManager;
+use crate::db::crud::OpenInterestCRUD;
+use crate::db::error::{DatabaseError, Result};
+use crate::db::helpers::{generate_open_interest_id, get_or_create_ticker_id};
+use exchange::{OpenInterest, TickerInfo};
+
+impl OpenInterestCRUD for DatabaseManager {
+    /// Insert or update open interest values
+    ///
+    /// The UNIQUE constraint on (ticker_id, timestamp) keeps a single row per
+    /// timestamp; an existing funding rate on that row is preserved
+    fn insert_open_interest(
+        &self,
+        ticker_info: &TickerInfo,
+        data: &[OpenInterest],
+    ) -> Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "INSERT INTO open_interest (oi_id, ticker_id, timestamp, open_interest)
+                     VALUES (?, ?, ?, ?)
+                     ON CONFLICT (ticker_id, timestamp) DO UPDATE SET
+                         open_interest = EXCLUDED.open_interest",
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to prepare insert: {}", e)))?;
+
+            for oi in data {
+                stmt.execute(duckdb::params![
+                    generate_open_interest_id(ticker_id, oi.time),
+                    ticker_id,
+                    oi.time as i64,
+                    oi.value as f64,
+                ])
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to insert open interest: {}", e))
+                })?;
+            }
+
+            Ok(data.len())
+        })
+    }
+
+    /// Query open interest by time range
+    fn query_open_interest(
+        &self,
+        ticker_info: &TickerInfo,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<Vec<OpenInterest>> {
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "SELECT timestamp, open_interest
+                     FROM open_interest
+                     WHERE ticker_id = ? AND timestamp >= ? AND timestamp <= ?
+                       AND open_interest IS NOT NULL
+                     ORDER BY timestamp ASC",
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to prepare query: {}", e)))?;
+
+            let oi_iter = stmt
+                .query_map(
+                    duckdb::params![ticker_id, start_time as i64, end_time as i64],
+                    |row| {
+                        let timestamp: i64 = row.get(0)?;
+                        let value: f64 = row.get(1)?;
+
+                        Ok(OpenInterest {
+                            time: timestamp as u64,
+                            value: value as f32,
+                        })
+                    },
+                )
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to query open interest: {}", e))
+                })?;
+
+            let mut data = Vec::new();
+            for oi_result in oi_iter {
+                data.push(oi_result.map_err(|e| {
+                    DatabaseError::Query(format!("Failed to map open interest: {}", e))
+                })?);
+            }
+
+            Ok(data)
+        })
+    }
+
+    /// Check database coverage for open interest data
+    ///
+    /// Returns earliest and latest timestamps, or None if no data exists
+    fn query_open_interest_coverage(&self, ticker_info: &TickerInfo) -> Result<Option<(u64, u64)>> {
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let (min_time, max_time): (Option<i64>, Option<i64>) = conn
+                .query_row(
+                    "SELECT MIN(timestamp), MAX(timestamp)
+                     FROM open_interest
+                     WHERE ticker_id = ? AND open_interest IS NOT NULL",
+                    [ticker_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to query open interest coverage: {}", e))
+                })?;
+
+            Ok(min_time
+                .zip(max_time)
+                .map(|(min, max)| (min as u64, max as u64)))
+        })
+    }
+
+    /// Delete open interest older than cutoff timestamp
+    ///
+    /// Funding rates on the same rows are kept, only rows left empty are removed
+    fn delete_open_interest_older_than(&self, cutoff_time: u64) -> Result<usize> {
+        self.with_conn(|conn| {
+            let cleared = conn
+                .execute(
+                    "UPDATE open_interest SET open_interest = NULL
+                     WHERE timestamp < ? AND open_interest IS NOT NULL",
+                    [cutoff_time as i64],
+                )
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to delete open interest: {}", e))
+                })?;
+
+            conn.execute(
+                "DELETE FROM open_interest
+                 WHERE timestamp < ? AND open_interest IS NULL AND funding_rate IS NULL",
+                [cutoff_time as i64],
+            )
+            .map_err(|e| DatabaseError::Query(format!("Failed to delete empty rows: {}", e)))?;
+
+            Ok(cleared)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exchange::Ticker;
+    use exchange::adapter::Exchange;
+    use tempfile::tempdir;
+
+    fn create_test_db() -> (DatabaseManager, tempfile::TempDir) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = DatabaseManager::new(db_path).unwrap();
+        (db, dir)
+    }
+
+    fn create_test_ticker_info() -> TickerInfo {
+        let ticker = Ticker::new("BTCUSDT", Exchange::BinanceLinear);
+        TickerInfo::new(ticker, 0.01, 0.001, None)
+    }
+
+    fn create_test_oi(count: usize) -> Vec<OpenInterest> {
+        (0..count)
+            .map(|i| OpenInterest {
+                time: 1000000 + (i as u64 * 300000),
+                value: 80000.0 + i as f32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_and_query_open_interest() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let data = create_test_oi(20);
+        let inserted = db.insert_open_interest(&ticker_info, &data).unwrap();
+        assert_eq!(inserted, 20);
+
+        let queried = db.query_open_interest(&ticker_info, 0, 100000000).unwrap();
+        assert_eq!(queried.len(), 20);
+        assert_eq!(queried[0], data[0]);
+    }
+
+    #[test]
+    fn test_insert_open_interest_upsert() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let mut data = create_test_oi(5);
+        db.insert_open_interest(&ticker_info, &data).unwrap();
+
+        data[0].value = 12345.0;
+        db.insert_open_interest(&ticker_info, &data).unwrap();
+
+        let queried = db.query_open_interest(&ticker_info, 0, 100000000).unwrap();
+        assert_eq!(queried.len(), 5);
+        assert_eq!(queried[0].value, 12345.0);
+    }
+
+    #[test]
+    fn test_open_interest_coverage() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        assert!(
+            db.query_open_interest_coverage(&ticker_info)
+                .unwrap()
+                .is_none()
+        );
+
+        let data = create_test_oi(10);
+        db.insert_open_interest(&ticker_info, &data).unwrap();
+
+        let coverage = db.query_open_interest_coverage(&ticker_info).unwrap();
+        assert_eq!(coverage, Some((data[0].time, data[9].time)));
+    }
+
+    #[test]
+    fn test_delete_open_interest_older_than() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let data = create_test_oi(10);
+        db.insert_open_interest(&ticker_info, &data).unwrap();
+
+        let deleted = db.delete_open_interest_older_than(data[5].time).unwrap();
+        assert_eq!(deleted, 5);
+
+        let remaining = db.query_open_interest(&ticker_info, 0, 100000000).unwrap();
+        assert_eq!(remaining.len(), 5);
+    }
+}
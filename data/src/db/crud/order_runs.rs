@@ -6,14 +6,16 @@ use crate::db::error::{DatabaseError, Result};
 use crate::db::helpers::{decimal_to_price, get_or_create_ticker_id, price_to_decimal};
 use crate::db::DatabaseManager;
 use crate::chart::heatmap::{HistoricalDepth, OrderRun};
+use crate::chart::Basis;
 use exchange::util::{Price, PriceStep};
 use exchange::TickerInfo;
-use std::collections::BTreeMap;
 
 impl DatabaseManager {
     /// Insert order runs in bulk for historical depth tracking
     ///
-    /// Used to persist order book changes for heatmap reconstruction
+    /// Used to persist order book changes for heatmap reconstruction. A run is
+    /// keyed by ticker, start time and price, so re-inserting it updates its
+    /// end time and volume instead of duplicating it
     pub fn insert_order_runs(
         &self,
         ticker_info: &TickerInfo,
@@ -30,7 +32,10 @@ impl DatabaseManager {
                 .prepare(
                     "INSERT INTO order_runs
                      (run_id, ticker_id, start_time, end_time, price_level, total_volume, num_orders, is_buy)
-                     VALUES (?, ?, epoch_ms(?), epoch_ms(?), ?, ?, ?, ?)",
+                     VALUES (?, ?, epoch_ms(?), epoch_ms(?), ?, ?, ?, ?)
+                     ON CONFLICT (run_id) DO UPDATE SET
+                         end_time = EXCLUDED.end_time,
+                         total_volume = EXCLUDED.total_volume",
                 )
                 .map_err(|e| DatabaseError::Query(format!("Failed to prepare insert: {}", e)))?;
 
@@ -92,23 +97,49 @@ impl DatabaseManager {
                         price_to_decimal(lowest),
                         price_to_decimal(highest),
                     ],
-                    |row| {
-                        let price_level: f64 = row.get(0)?;
-                        let start_time: i64 = row.get(1)?;
-                        let end_time: i64 = row.get(2)?;
-                        let total_volume: f64 = row.get(3)?;
-                        let is_buy: bool = row.get(4)?;
-
-                        let price = decimal_to_price(price_level);
-                        let run = OrderRun::new(
-                            start_time as u64,
-                            end_time as u64,
-                            total_volume as f32,
-                            is_buy,
-                        );
-
-                        Ok((price, run))
-                    },
+                    map_order_run_row,
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to query order runs: {}", e)))?;
+
+            let mut runs = Vec::new();
+            for run_result in runs_iter {
+                runs.push(
+                    run_result
+                        .map_err(|e| DatabaseError::Query(format!("Failed to map order run: {}", e)))?,
+                );
+            }
+
+            Ok(runs)
+        })
+    }
+
+    /// Query order runs at every price level overlapping a time window
+    ///
+    /// Used to seed a heatmap pane with the liquidity history of its ticker
+    pub fn query_order_runs_in_window(
+        &self,
+        ticker_info: &TickerInfo,
+        earliest: u64,
+        latest: u64,
+    ) -> Result<Vec<(Price, OrderRun)>> {
+        self.with_conn(|conn| {
+            let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "SELECT price_level, epoch_ms(start_time), epoch_ms(end_time), total_volume, is_buy
+                     FROM order_runs
+                     WHERE ticker_id = ?
+                       AND start_time <= epoch_ms(?)
+                       AND end_time >= epoch_ms(?)
+                     ORDER BY price_level, start_time",
+                )
+                .map_err(|e| DatabaseError::Query(format!("Failed to prepare query: {}", e)))?;
+
+            let runs_iter = stmt
+                .query_map(
+                    duckdb::params![ticker_id, latest as i64, earliest as i64],
+                    map_order_run_row,
                 )
                 .map_err(|e| DatabaseError::Query(format!("Failed to query order runs: {}", e)))?;
 
@@ -126,31 +157,20 @@ impl DatabaseManager {
 
     /// Load HistoricalDepth from database for heatmap rendering
     ///
-    /// Reconstructs the complete HistoricalDepth structure with price_levels BTreeMap
+    /// Reconstructs the price levels of every run overlapping the time window
     pub fn load_historical_depth(
         &self,
         ticker_info: &TickerInfo,
         earliest: u64,
         latest: u64,
-        lowest: Price,
-        highest: Price,
         tick_size: PriceStep,
         min_order_qty: f32,
+        basis: Basis,
     ) -> Result<HistoricalDepth> {
-        let runs = self.query_order_runs(ticker_info, earliest, latest, lowest, highest)?;
-
-        // Group runs by price level
-        let mut price_levels: BTreeMap<Price, Vec<OrderRun>> = BTreeMap::new();
-
-        for (price, run) in runs {
-            price_levels.entry(price).or_insert_with(Vec::new).push(run);
-        }
-
-        // Create HistoricalDepth with reconstructed data
-        let depth = HistoricalDepth::new(min_order_qty, tick_size, crate::chart::Basis::Time(exchange::Timeframe::M1));
+        let runs = self.query_order_runs_in_window(ticker_info, earliest, latest)?;
 
-        // Inject the price_levels directly (would need to expose setter or use a constructor)
-        // For now, return a basic depth and note this needs integration with HistoricalDepth API
+        let mut depth = HistoricalDepth::new(min_order_qty, tick_size, basis);
+        depth.insert_order_runs(runs);
 
         Ok(depth)
     }
@@ -195,6 +215,24 @@ impl DatabaseManager {
     }
 }
 
+fn map_order_run_row(row: &duckdb::Row) -> duckdb::Result<(Price, OrderRun)> {
+    let price_level: f64 = row.get(0)?;
+    let start_time: i64 = row.get(1)?;
+    let end_time: i64 = row.get(2)?;
+    let total_volume: f64 = row.get(3)?;
+    let is_buy: bool = row.get(4)?;
+
+    let price = decimal_to_price(price_level);
+    let run = OrderRun::with_until_time(
+        start_time as u64,
+        end_time as u64,
+        total_volume as f32,
+        is_buy,
+    );
+
+    Ok((price, run))
+}
+
 /// Generate a unique ID for order run
 fn generate_run_id(ticker_id: i32, start_time: u64, price: Price) -> i64 {
     // Combine ticker_id, time, and price into unique ID
@@ -352,4 +390,69 @@ mod tests {
 
         assert_eq!(queried.len(), 0);
     }
+
+    #[test]
+    fn test_insert_order_runs_upsert() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let price = Price::from_f32(50000.0);
+        let run = OrderRun::with_until_time(1000000, 1001000, 5.0, true);
+        db.insert_order_runs(&ticker_info, &[(price, run)]).unwrap();
+
+        // Same run flushed again after it was extended
+        let extended = OrderRun::with_until_time(1000000, 1005000, 5.0, true);
+        db.insert_order_runs(&ticker_info, &[(price, extended)]).unwrap();
+
+        let queried = db
+            .query_order_runs_in_window(&ticker_info, 1000000, 2000000)
+            .unwrap();
+        assert_eq!(queried, vec![(price, extended)]);
+    }
+
+    #[test]
+    fn test_load_historical_depth() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+        let step = PriceStep::from_f32(0.01);
+        let basis = Basis::Time(exchange::Timeframe::MS100);
+
+        let mut live = HistoricalDepth::new(0.0, step, basis);
+        let mut bids = std::collections::BTreeMap::new();
+        bids.insert(Price::from_f32(50000.0), 2.0);
+        let depth = exchange::depth::Depth {
+            bids,
+            asks: std::collections::BTreeMap::new(),
+        };
+        for time in (1000000..1002000).step_by(100) {
+            live.insert_latest_depth(&depth, time);
+        }
+
+        // Nothing is complete while the level keeps updating
+        assert!(live.take_completed_runs(1002000).is_empty());
+
+        let completed = live.take_completed_runs(1010000);
+        assert_eq!(completed.len(), 1);
+        assert!(live.take_completed_runs(1010000).is_empty());
+
+        db.insert_order_runs(&ticker_info, &completed).unwrap();
+
+        let mut loaded = db
+            .load_historical_depth(&ticker_info, 1000000, 1010000, step, 0.0, basis)
+            .unwrap();
+
+        let levels: Vec<_> = loaded
+            .iter_time_filtered(
+                1000000,
+                1010000,
+                Price::from_f32(50100.0),
+                Price::from_f32(49900.0),
+            )
+            .map(|(price, runs)| (*price, runs.clone()))
+            .collect();
+        assert_eq!(levels, vec![(completed[0].0, vec![completed[0].1])]);
+
+        // Seeded runs are not handed out for persistence again
+        assert!(loaded.take_completed_runs(1010000).is_empty());
+    }
 }
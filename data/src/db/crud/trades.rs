@@ -8,12 +8,14 @@ use crate::db::DatabaseManager;
 use crate::db::crud::TradesCRUD;
 use exchange::{TickerInfo, Trade};
 use exchange::util::Price;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 impl TradesCRUD for DatabaseManager {
-    /// Insert trades using prepared statement with ON CONFLICT DO NOTHING
+    /// Insert trades in bulk through the Appender, skipping the ones already stored
     ///
-    /// Handles duplicate trades gracefully by ignoring conflicts on trade_id.
-    /// This is necessary because exchanges may send the same trade multiple times.
+    /// Exchanges may send the same trade multiple times, so trade ids are deterministic.
+    /// The Appender can't ignore conflicts on trade_id, so the ids stored within the
+    /// batch's time span are looked up first.
     fn insert_trades(&self, ticker_info: &TickerInfo, trades: &[Trade]) -> Result<usize> {
         if trades.is_empty() {
             return Ok(0);
@@ -22,21 +24,49 @@ impl TradesCRUD for DatabaseManager {
         self.with_conn(|conn| {
             let ticker_id = get_or_create_ticker_id(conn, ticker_info)?;
 
-            // Use prepared statement with ON CONFLICT DO NOTHING to handle duplicates
-            let mut stmt = conn
-                .prepare(
-                    "INSERT INTO trades (trade_id, ticker_id, timestamp, price, quantity, is_buyer_maker)
-                     VALUES (?, ?, ?, ?, ?, ?)
-                     ON CONFLICT (trade_id) DO NOTHING"
+            let (start_time, end_time) = trades
+                .iter()
+                .fold((u64::MAX, u64::MIN), |(start, end), trade| {
+                    (start.min(trade.time), end.max(trade.time))
+                });
+
+            let mut stored_ids = {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT trade_id FROM trades
+                         WHERE ticker_id = ? AND timestamp >= ? AND timestamp <= ?",
+                    )
+                    .map_err(|e| DatabaseError::Query(format!("Failed to prepare query: {}", e)))?;
+
+                stmt.query_map(
+                    duckdb::params![ticker_id, start_time as i64, end_time as i64],
+                    |row| row.get::<_, i64>(0),
                 )
-                .map_err(|e| DatabaseError::Query(format!("Failed to prepare insert: {}", e)))?;
+                .and_then(|ids| ids.collect::<duckdb::Result<FxHashSet<i64>>>())
+                .map_err(|e| DatabaseError::Query(format!("Failed to query trade ids: {}", e)))?
+            };
+
+            let mut appender = conn
+                .appender("trades")
+                .map_err(|e| DatabaseError::Insert(format!("Failed to create appender: {}", e)))?;
+
+            // same time, price and qty, told apart by their order in the batch
+            let mut seqs: FxHashMap<(u64, i64, u32), u32> = FxHashMap::default();
 
             let mut inserted = 0;
             for trade in trades {
-                let trade_id = generate_trade_id(ticker_id, trade.time, trade.price, trade.qty);
-
-                let rows = stmt
-                    .execute(duckdb::params![
+                let seq = seqs
+                    .entry((trade.time, trade.price.units, trade.qty.to_bits()))
+                    .or_default();
+                let trade_id = generate_trade_id(ticker_id, trade, *seq);
+                *seq += 1;
+
+                if !stored_ids.insert(trade_id) {
+                    continue;
+                }
+
+                appender
+                    .append_row(duckdb::params![
                         trade_id,
                         ticker_id,
                         trade.time as i64,
@@ -44,11 +74,15 @@ impl TradesCRUD for DatabaseManager {
                         trade.qty as f64,
                         !trade.is_sell,
                     ])
-                    .map_err(|e| DatabaseError::Query(format!("Failed to insert trade: {}", e)))?;
+                    .map_err(|e| DatabaseError::Insert(format!("Failed to append trade: {}", e)))?;
 
-                inserted += rows;
+                inserted += 1;
             }
 
+            appender
+                .flush()
+                .map_err(|e| DatabaseError::Insert(format!("Failed to flush trades: {}", e)))?;
+
             Ok(inserted)
         })
     }
@@ -146,6 +180,8 @@ impl TradesCRUD for DatabaseManager {
     ///
     /// Pre-aggregates buy_qty, sell_qty, buy_count, sell_count at each price level
     /// This is much faster than loading individual trades and aggregating in memory
+    ///
+    /// Note: `is_buyer_maker` is stored as `!trade.is_sell`, see `insert_trades`
     fn query_trades_aggregated(
         &self,
         ticker_info: &TickerInfo,
@@ -159,10 +195,10 @@ impl TradesCRUD for DatabaseManager {
                 .prepare(
                     "SELECT
                          price,
-                         SUM(CASE WHEN is_buyer_maker = false THEN quantity ELSE 0 END) as buy_volume,
-                         SUM(CASE WHEN is_buyer_maker = true THEN quantity ELSE 0 END) as sell_volume,
-                         COUNT(CASE WHEN is_buyer_maker = false THEN 1 END) as buy_count,
-                         COUNT(CASE WHEN is_buyer_maker = true THEN 1 END) as sell_count
+                         SUM(CASE WHEN is_buyer_maker = true THEN quantity ELSE 0 END) as buy_volume,
+                         SUM(CASE WHEN is_buyer_maker = false THEN quantity ELSE 0 END) as sell_volume,
+                         COUNT(CASE WHEN is_buyer_maker = true THEN 1 END) as buy_count,
+                         COUNT(CASE WHEN is_buyer_maker = false THEN 1 END) as sell_count
                      FROM trades
                      WHERE ticker_id = ? AND timestamp >= ? AND timestamp <= ?
                      GROUP BY price
@@ -318,6 +354,38 @@ mod tests {
         assert!(remaining < 100);
     }
 
+    #[test]
+    fn test_query_trades_aggregated_sides() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let trades = vec![
+            Trade {
+                time: 1000000,
+                price: Price::from_f32(50000.0),
+                qty: 2.0,
+                is_sell: false,
+            },
+            Trade {
+                time: 1000001,
+                price: Price::from_f32(50000.0),
+                qty: 3.0,
+                is_sell: true,
+            },
+        ];
+        db.insert_trades(&ticker_info, &trades).unwrap();
+
+        let aggregated = db
+            .query_trades_aggregated(&ticker_info, 1000000, 2000000)
+            .unwrap();
+
+        assert_eq!(aggregated.len(), 1);
+        let (_, buy_qty, sell_qty, buy_count, sell_count) = aggregated[0];
+        assert!((buy_qty - 2.0).abs() < 0.001);
+        assert!((sell_qty - 3.0).abs() < 0.001);
+        assert_eq!((buy_count, sell_count), (1, 1));
+    }
+
     #[test]
     fn test_empty_insert() {
         let (db, _dir) = create_test_db();
@@ -327,6 +395,32 @@ mod tests {
         assert_eq!(inserted, 0);
     }
 
+    #[test]
+    fn test_same_millisecond_fills_are_kept() {
+        let (db, _dir) = create_test_db();
+        let ticker_info = create_test_ticker_info();
+
+        let fill = Trade {
+            time: 1000000,
+            price: Price::from_f32(50000.0),
+            qty: 1.0,
+            is_sell: false,
+        };
+        let trades = vec![
+            fill,
+            fill,
+            Trade {
+                is_sell: true,
+                ..fill
+            },
+        ];
+
+        assert_eq!(db.insert_trades(&ticker_info, &trades).unwrap(), 3);
+        // the same batch sent again is stored once
+        assert_eq!(db.insert_trades(&ticker_info, &trades).unwrap(), 0);
+        assert_eq!(db.query_trades_count(&ticker_info, 0, 2000000).unwrap(), 3);
+    }
+
     #[test]
     fn test_price_precision() {
         let (db, _dir) = create_test_db();
@@ -10,7 +10,7 @@ use super::error::{DatabaseError, Result};
 use duckdb::{Connection, OptionalExt};
 use exchange::adapter::Exchange;
 use exchange::util::Price;
-use exchange::TickerInfo;
+use exchange::{Liquidation, Ticker, TickerInfo, Trade};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Mutex;
@@ -96,6 +96,16 @@ pub fn get_or_create_ticker_id(conn: &Connection, ticker_info: &TickerInfo) -> R
         next_id
     };
 
+    // Tickers stored before display symbols were kept get theirs on first use
+    if let Some(display_symbol) = ticker_info.ticker.display_symbol() {
+        conn.execute(
+            "INSERT INTO ticker_display_symbols (ticker_id, display_symbol) VALUES (?, ?)
+             ON CONFLICT (ticker_id) DO NOTHING",
+            duckdb::params![ticker_id, display_symbol],
+        )
+        .map_err(|e| DatabaseError::Query(format!("Failed to insert display symbol: {}", e)))?;
+    }
+
     // Update cache
     {
         let mut cache_guard = TICKER_CACHE.lock().unwrap();
@@ -134,6 +144,19 @@ pub fn get_ticker_id(conn: &Connection, ticker_info: &TickerInfo) -> Result<Opti
     Ok(ticker_id)
 }
 
+/// Get ticker ID of a ticker, `None` when neither it nor its exchange got stored yet
+pub fn find_ticker_id(conn: &Connection, ticker: &Ticker) -> Result<Option<i32>> {
+    let (symbol, _) = ticker.to_full_symbol_and_type();
+
+    conn.query_row(
+        "SELECT ticker_id FROM tickers WHERE exchange_id = ? AND symbol = ?",
+        duckdb::params![exchange_to_id(ticker.exchange), symbol],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| DatabaseError::Query(format!("Failed to query ticker: {}", e)))
+}
+
 /// Get or create exchange ID
 ///
 /// Exchange IDs are stored as TINYINT (1-12) corresponding to the Exchange enum variants
@@ -184,7 +207,7 @@ pub fn get_exchange_id(conn: &Connection, exchange: Exchange) -> Result<i8> {
     }
 }
 
-/// Map Exchange enum to database ID (1-12)
+/// Map Exchange enum to database ID (1-14)
 fn exchange_to_id(exchange: Exchange) -> i8 {
     match exchange {
         Exchange::BinanceLinear => 1,
@@ -199,9 +222,18 @@ fn exchange_to_id(exchange: Exchange) -> i8 {
         Exchange::OkexInverse => 10,
         Exchange::OkexSpot => 11,
         Exchange::AsterLinear => 12,
+        Exchange::DeribitLinear => 13,
+        Exchange::DeribitInverse => 14,
     }
 }
 
+/// Exchange stored under a database ID, the inverse of [`exchange_to_id`]
+pub fn id_to_exchange(exchange_id: i8) -> Option<Exchange> {
+    Exchange::ALL
+        .into_iter()
+        .find(|exchange| exchange_to_id(*exchange) == exchange_id)
+}
+
 /// Convert Price to f64 for DECIMAL(18,8) storage
 ///
 /// Preserves 8 decimal places as required by schema
@@ -237,16 +269,24 @@ pub fn ns_to_timestamp(ns: i64) -> u64 {
 ///
 /// Uses hash(ticker_id, timestamp, price, qty) to create a unique identifier.
 /// This approach prevents duplicate key violations across app restarts.
-pub fn generate_trade_id(ticker_id: i32, timestamp: u64, price: Price, qty: f32) -> i64 {
+///
+/// Trades carry no exchange id, so distinct fills with the same time, price and qty are
+/// told apart by `seq`, their order among such trades of a batch. The first one keeps
+/// the id trades were always stored with.
+pub fn generate_trade_id(ticker_id: i32, trade: &Trade, seq: u32) -> i64 {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
 
     let mut hasher = DefaultHasher::new();
     ticker_id.hash(&mut hasher);  // Critical: differentiate by ticker/exchange
-    timestamp.hash(&mut hasher);
-    price.units.hash(&mut hasher);
+    trade.time.hash(&mut hasher);
+    trade.price.units.hash(&mut hasher);
     // Convert qty to bits for hashing
-    (qty.to_bits() as u64).hash(&mut hasher);
+    (trade.qty.to_bits() as u64).hash(&mut hasher);
+    if seq > 0 {
+        trade.is_sell.hash(&mut hasher);
+        seq.hash(&mut hasher);
+    }
 
     hasher.finish() as i64
 }
@@ -281,6 +321,37 @@ pub fn generate_snapshot_id(ticker_id: i32, snapshot_time: u64) -> i64 {
     hasher.finish() as i64
 }
 
+/// Generate deterministic open interest ID from ticker and timestamp
+pub fn generate_open_interest_id(ticker_id: i32, timestamp: u64) -> i64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    ticker_id.hash(&mut hasher);
+    "open_interest".hash(&mut hasher);
+    timestamp.hash(&mut hasher);
+
+    hasher.finish() as i64
+}
+
+/// Generate deterministic liquidation ID from the forced order
+///
+/// The same liquidation may be received again after a reconnect
+pub fn generate_liquidation_id(ticker_id: i32, liquidation: &Liquidation) -> i64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    ticker_id.hash(&mut hasher);
+    "liquidation".hash(&mut hasher);
+    liquidation.time.hash(&mut hasher);
+    liquidation.price.units.hash(&mut hasher);
+    liquidation.qty.to_bits().hash(&mut hasher);
+    liquidation.is_sell.hash(&mut hasher);
+
+    hasher.finish() as i64
+}
+
 /// Generate unique footprint ID
 pub fn generate_footprint_id(ticker_id: i32, timeframe: &str, candle_time: u64, price: Price) -> i64 {
     use std::collections::hash_map::DefaultHasher;
@@ -332,11 +403,16 @@ mod tests {
 
     #[test]
     fn test_trade_id_generation() {
-        let id1 = generate_trade_id();
-        let id2 = generate_trade_id();
-
-        assert!(id2 > id1);
-        assert_eq!(id2, id1 + 1);
+        let trade = Trade {
+            time: 1000000,
+            is_sell: false,
+            price: Price::from_f32(50000.0),
+            qty: 1.0,
+        };
+
+        assert_eq!(generate_trade_id(1, &trade, 0), generate_trade_id(1, &trade, 0));
+        assert_ne!(generate_trade_id(1, &trade, 0), generate_trade_id(2, &trade, 0));
+        assert_ne!(generate_trade_id(1, &trade, 0), generate_trade_id(1, &trade, 1));
     }
 
     #[test]
@@ -393,11 +469,14 @@ mod tests {
             Exchange::OkexInverse,
             Exchange::OkexSpot,
             Exchange::AsterLinear,
+            Exchange::DeribitLinear,
+            Exchange::DeribitInverse,
         ];
 
         for (i, exchange) in exchanges.iter().enumerate() {
             let id = exchange_to_id(*exchange);
             assert_eq!(id, (i + 1) as i8);
+            assert_eq!(id_to_exchange(id), Some(*exchange));
         }
     }
 }
@@ -218,7 +218,8 @@ impl ArchiveMigrator {
             let db_trade_id = generate_trade_id(ticker_id, timestamp as u64, trade_counter);
             trade_counter += 1;
 
-            batch.push((db_trade_id, ticker_id, timestamp, price, quantity, is_sell));
+            // stored as `!is_sell`, the same as `TradesCRUD::insert_trades`
+            batch.push((db_trade_id, ticker_id, timestamp, price, quantity, !is_sell));
 
             if batch.len() >= self.config.batch_size {
                 self.insert_trade_batch(&tx, &batch)?;
@@ -248,7 +249,7 @@ impl ArchiveMigrator {
         let mut stmt = conn
             .prepare(
                 "INSERT OR REPLACE INTO trades
-                 (trade_id, ticker_id, trade_time, price, quantity, is_sell)
+                 (trade_id, ticker_id, timestamp, price, quantity, is_buyer_maker)
                  VALUES (?, ?, ?, ?, ?, ?)",
             )
             .map_err(|e| DatabaseError::Query(format!("Failed to prepare statement: {}", e)))?;
@@ -49,90 +49,18 @@ pub fn generate_run_id(ticker_id: i64, price_level: i64, start_time: u64) -> i64
 }
 
 /// Get exchange_id from exchange name, creating if needed
+///
+/// Same ids as live inserts, see [`crate::db::helpers::get_or_create_exchange_id`]
 pub fn get_or_create_exchange_id(conn: &mut Connection, exchange: Exchange) -> Result<i64> {
-    let exchange_name = format!("{:?}", exchange);
-
-    // Try to get existing exchange_id
-    let maybe_id: Option<i64> = conn
-        .query_row(
-            "SELECT exchange_id FROM exchanges WHERE name = ?",
-            [&exchange_name],
-            |row| row.get(0),
-        )
-        .ok();
-
-    if let Some(id) = maybe_id {
-        return Ok(id);
-    }
-
-    // Generate exchange_id from exchange enum value (simple hash constrained to TINYINT range)
-    let exchange_id: i64 = (format!("{:?}", exchange)
-        .bytes()
-        .fold(0i64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as i64))
-        .abs() % 127) + 1; // Keep in range 1-127 for TINYINT
-
-    // Create new exchange record with explicit ID
-    conn.execute(
-        "INSERT OR IGNORE INTO exchanges (exchange_id, name) VALUES (?, ?)",
-        duckdb::params![exchange_id, exchange_name],
-    )
-    .map_err(|e| DatabaseError::Insert(format!("Failed to insert exchange: {}", e)))?;
-
-    Ok(exchange_id)
+    crate::db::helpers::get_or_create_exchange_id(conn, exchange).map(i64::from)
 }
 
 /// Resolve TickerInfo to ticker_id, creating ticker record if needed
 ///
-/// Handles exchange_id lookup/creation as well
+/// Migrated rows land on the ticker live inserts and queries use, see
+/// [`crate::db::helpers::get_or_create_ticker_id`]
 pub fn get_or_create_ticker_id(conn: &mut Connection, ticker_info: &TickerInfo) -> Result<i64> {
-    let symbol = format!("{}", ticker_info.ticker);
-    let exchange = ticker_info.exchange();
-
-    // Get or create exchange_id
-    let exchange_id = get_or_create_exchange_id(conn, exchange)?;
-
-    // Try to get existing ticker_id
-    let maybe_id: Option<i64> = conn
-        .query_row(
-            "SELECT ticker_id FROM tickers WHERE exchange_id = ? AND symbol = ?",
-            duckdb::params![exchange_id, symbol],
-            |row| row.get(0),
-        )
-        .ok();
-
-    if let Some(id) = maybe_id {
-        return Ok(id);
-    }
-
-    // Create new ticker record
-    // Power10 has a power field, convert to actual float value
-    let min_tick = 10.0_f32.powi(ticker_info.min_ticksize.power as i32);
-    let min_qty = 10.0_f32.powi(ticker_info.min_qty.power as i32);
-    let contract_size = ticker_info.contract_size.map(|c| 10.0_f32.powi(c.power as i32));
-
-    conn.execute(
-        "INSERT INTO tickers (exchange_id, symbol, tick_size, min_quantity, contract_size)
-         VALUES (?, ?, ?, ?, ?)",
-        duckdb::params![
-            exchange_id,
-            symbol,
-            min_tick,
-            min_qty,
-            contract_size
-        ],
-    )
-    .map_err(|e| DatabaseError::Insert(format!("Failed to insert ticker: {}", e)))?;
-
-    // Get the auto-generated ID
-    let id: i64 = conn
-        .query_row(
-            "SELECT ticker_id FROM tickers WHERE exchange_id = ? AND symbol = ?",
-            duckdb::params![exchange_id, symbol],
-            |row| row.get(0),
-        )
-        .map_err(|e| DatabaseError::Query(format!("Failed to get ticker_id: {}", e)))?;
-
-    Ok(id)
+    crate::db::helpers::get_or_create_ticker_id(conn, ticker_info).map(i64::from)
 }
 
 /// Look up existing ticker_id without creating new record
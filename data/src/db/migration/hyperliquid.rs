@@ -0,0 +1,391 @@
+//! Importer for Hyperliquid's S3 archive files
+//!
+//! Hyperliquid publishes `node_trades` fills and hourly `l2Book` snapshots as
+//! LZ4 compressed JSON lines, see `DOCS/HISTORICAL_DATA_API_RESEARCH.md`. Files
+//! downloaded from `s3://hl-mainnet-node-data/node_trades/` and
+//! `s3://hyperliquid-archive/market_data/` are read into the `trades` and
+//! `depth_snapshots` tables, where the footprint and heatmap load paths find them.
+
+use super::{MigrationConfig, MigrationStats, ProgressTracker};
+use crate::db::crud::{DepthCRUD, TradesCRUD};
+use crate::db::{DatabaseError, DatabaseManager, Result};
+use exchange::depth::Depth;
+use exchange::util::Price;
+use exchange::{SIZE_IN_QUOTE_CURRENCY, TickerInfo, Trade};
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Leading bytes of an LZ4 frame, archives unpacked with `unlz4` lack them
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4D, 0x18];
+
+/// A line of either archive, told apart by its fields
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ArchiveLine {
+    Book(BookLine),
+    Trade(NodeTrade),
+}
+
+/// `{"time": "...", "ver_num": 1, "raw": {"channel": "l2Book", "data": {...}}}`
+#[derive(Deserialize)]
+struct BookLine {
+    raw: RawBook,
+}
+
+#[derive(Deserialize)]
+struct RawBook {
+    data: BookSnapshot,
+}
+
+#[derive(Deserialize)]
+struct BookSnapshot {
+    coin: String,
+    time: u64,
+    /// Bids, then asks
+    levels: Vec<Vec<BookLevel>>,
+}
+
+#[derive(Deserialize)]
+struct BookLevel {
+    px: String,
+    sz: String,
+}
+
+#[derive(Deserialize)]
+struct NodeTrade {
+    coin: String,
+    /// `B` when the taker bought, `A` when it sold
+    side: String,
+    /// UTC, without an offset, e.g. `2025-03-22T10:00:00.123456789`
+    time: String,
+    px: String,
+    sz: String,
+}
+
+impl NodeTrade {
+    fn to_trade(&self, ticker_info: &TickerInfo, size_in_quote: bool) -> Option<Trade> {
+        let time = chrono::NaiveDateTime::parse_from_str(&self.time, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()?
+            .and_utc()
+            .timestamp_millis();
+        let price = self.px.parse::<f32>().ok()?;
+        let qty = self.sz.parse::<f32>().ok()?;
+
+        Some(Trade {
+            time: u64::try_from(time).ok()?,
+            is_sell: self.side == "A",
+            price: Price::from_f32(price).round_to_min_tick(ticker_info.min_ticksize),
+            qty: if size_in_quote {
+                (qty * price).round()
+            } else {
+                qty
+            },
+        })
+    }
+}
+
+impl BookSnapshot {
+    fn to_depth(&self, ticker_info: &TickerInfo, size_in_quote: bool) -> Option<Depth> {
+        let side = |levels: &[BookLevel]| -> Option<BTreeMap<Price, f32>> {
+            levels
+                .iter()
+                .map(|level| {
+                    let price = level.px.parse::<f32>().ok()?;
+                    let qty = level.sz.parse::<f32>().ok()?;
+
+                    Some((
+                        Price::from_f32(price).round_to_min_tick(ticker_info.min_ticksize),
+                        if size_in_quote {
+                            (qty * price).round()
+                        } else {
+                            qty
+                        },
+                    ))
+                })
+                .collect()
+        };
+
+        let [bids, asks] = self.levels.as_slice() else {
+            return None;
+        };
+
+        Some(Depth {
+            bids: side(bids)?,
+            asks: side(asks)?,
+        })
+    }
+}
+
+/// Reads Hyperliquid trade and order book archives into the database
+pub struct HyperliquidArchiveImporter {
+    config: MigrationConfig,
+    /// Listed markets by the coin name the archives use, e.g. `BTC` or `@107`
+    tickers: FxHashMap<String, TickerInfo>,
+}
+
+impl HyperliquidArchiveImporter {
+    /// Lines of coins missing from `tickers` are skipped, they carry no tick size to round to
+    pub fn new(config: MigrationConfig, tickers: impl IntoIterator<Item = TickerInfo>) -> Self {
+        let tickers = tickers
+            .into_iter()
+            .map(|ticker_info| (ticker_info.ticker.to_full_symbol_and_type().0, ticker_info))
+            .collect();
+
+        Self { config, tickers }
+    }
+
+    /// Import every archive file found under `dir`
+    pub fn import_dir(&self, db: &DatabaseManager, dir: &Path) -> Result<MigrationStats> {
+        let mut all_stats = MigrationStats::new();
+
+        if !dir.is_dir() {
+            all_stats.add_error(format!(
+                "Archive directory does not exist: {}",
+                dir.display()
+            ));
+            return Ok(all_stats);
+        }
+
+        let mut files = Vec::new();
+        find_archive_files(dir, &mut files)?;
+        files.sort();
+
+        log::info!(
+            "Found {} Hyperliquid archive files in {}",
+            files.len(),
+            dir.display()
+        );
+
+        let mut tracker = ProgressTracker::new(files.len(), "Hyperliquid Import");
+
+        for path in &files {
+            match self.import_file(db, path) {
+                Ok(stats) => {
+                    all_stats.merge(&stats);
+                    all_stats.files_processed += 1;
+                }
+                Err(e) => {
+                    let err_msg = format!("{}: {}", path.display(), e);
+                    log::error!("Failed to import archive: {}", err_msg);
+                    all_stats.add_error(err_msg);
+                }
+            }
+
+            tracker.update(1);
+        }
+
+        tracker.finish();
+        log::info!(
+            "Hyperliquid import complete: {} files, {} trades, {} depth snapshots",
+            all_stats.files_processed,
+            all_stats.trades_migrated,
+            all_stats.snapshots_migrated
+        );
+
+        Ok(all_stats)
+    }
+
+    /// Import a single `node_trades` or `l2Book` file, compressed or not
+    pub fn import_file(&self, db: &DatabaseManager, path: &Path) -> Result<MigrationStats> {
+        let mut stats = MigrationStats::new();
+
+        if self.config.dry_run {
+            log::info!("Dry run mode - skipping archive: {}", path.display());
+            return Ok(stats);
+        }
+
+        let size_in_quote = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+
+        let mut pending: FxHashMap<String, Vec<Trade>> = FxHashMap::default();
+        let mut unlisted = BTreeSet::new();
+        let mut unreadable = 0;
+
+        for line in open_archive(path)?.lines() {
+            let line = line
+                .map_err(|e| DatabaseError::Migration(format!("Failed to read archive: {}", e)))?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let Ok(parsed) = serde_json::from_str::<ArchiveLine>(&line) else {
+                unreadable += 1;
+                continue;
+            };
+
+            match parsed {
+                ArchiveLine::Book(book) => {
+                    let snapshot = book.raw.data;
+                    let Some(ticker_info) = self.tickers.get(&snapshot.coin) else {
+                        unlisted.insert(snapshot.coin);
+                        continue;
+                    };
+                    let Some(depth) = snapshot.to_depth(ticker_info, size_in_quote) else {
+                        unreadable += 1;
+                        continue;
+                    };
+
+                    db.insert_depth_snapshot(ticker_info, snapshot.time, &depth)?;
+                    stats.snapshots_migrated += 1;
+                }
+                ArchiveLine::Trade(node_trade) => {
+                    let Some(ticker_info) = self.tickers.get(&node_trade.coin) else {
+                        unlisted.insert(node_trade.coin);
+                        continue;
+                    };
+                    let Some(trade) = node_trade.to_trade(ticker_info, size_in_quote) else {
+                        unreadable += 1;
+                        continue;
+                    };
+
+                    let batch = pending.entry(node_trade.coin).or_default();
+                    batch.push(trade);
+
+                    if batch.len() >= self.config.batch_size {
+                        stats.trades_migrated += db.insert_trades(ticker_info, batch)?;
+                        batch.clear();
+                    }
+                }
+            }
+        }
+
+        for (coin, batch) in pending {
+            if let Some(ticker_info) = self.tickers.get(&coin) {
+                stats.trades_migrated += db.insert_trades(ticker_info, &batch)?;
+            }
+        }
+
+        if unreadable > 0 {
+            stats.add_error(format!(
+                "{}: skipped {} unreadable lines",
+                path.display(),
+                unreadable
+            ));
+        }
+        if !unlisted.is_empty() {
+            stats.add_error(format!(
+                "{}: skipped coins that aren't listed: {}",
+                path.display(),
+                unlisted.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Archive files keep S3's layout, `<coin>.lz4` or extensionless hourly files
+fn find_archive_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| DatabaseError::Migration(format!("Failed to read directory: {}", e)))?;
+
+    for entry in entries {
+        let path = entry
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to read directory entry: {}", e))
+            })?
+            .path();
+
+        if path.is_dir() {
+            find_archive_files(&path, files)?;
+        } else if matches!(
+            path.extension().and_then(|s| s.to_str()),
+            Some("lz4") | None
+        ) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Lines of an archive file, decompressed if it's still an LZ4 frame
+fn open_archive(path: &Path) -> Result<Box<dyn BufRead>> {
+    let file = fs::File::open(path)
+        .map_err(|e| DatabaseError::Migration(format!("Failed to open archive: {}", e)))?;
+    let mut reader = BufReader::new(file);
+
+    let is_lz4 = reader
+        .fill_buf()
+        .map_err(|e| DatabaseError::Migration(format!("Failed to read archive: {}", e)))?
+        .starts_with(&LZ4_MAGIC);
+
+    if is_lz4 {
+        Ok(Box::new(BufReader::new(
+            lz4_flex::frame::FrameDecoder::new(reader),
+        )))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exchange::Ticker;
+    use exchange::adapter::Exchange;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const L2_BOOK: &str = r#"{"time":"2025-03-22T10:00:00.127539934","ver_num":1,"raw":{"channel":"l2Book","data":{"coin":"BTC","time":1742637600127,"levels":[[{"px":"84000.0","sz":"1.5","n":3},{"px":"83999.0","sz":"0.2","n":1}],[{"px":"84001.0","sz":"0.7","n":2}]]}}}"#;
+    const NODE_TRADES: &str = r#"{"coin":"BTC","side":"A","time":"2025-03-22T10:00:01.250000000","px":"84000.0","sz":"0.1","hash":"0x00","trade_dir_override":"Na","side_info":[]}
+{"coin":"BTC","side":"B","time":"2025-03-22T10:00:02.500000000","px":"84001.0","sz":"0.3","hash":"0x00","trade_dir_override":"Na","side_info":[]}
+{"coin":"DELISTED","side":"B","time":"2025-03-22T10:00:03.000000000","px":"1.0","sz":"1.0","hash":"0x00","trade_dir_override":"Na","side_info":[]}"#;
+
+    fn btc() -> TickerInfo {
+        TickerInfo::new(
+            Ticker::new("BTC", Exchange::HyperliquidLinear),
+            1.0,
+            0.001,
+            None,
+        )
+    }
+
+    #[test]
+    fn imports_compressed_and_unpacked_archives() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        let archives = dir.path().join("archives");
+        fs::create_dir_all(archives.join("l2Book")).unwrap();
+        fs::create_dir_all(archives.join("node_trades")).unwrap();
+
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(
+            fs::File::create(archives.join("l2Book/BTC.lz4")).unwrap(),
+        );
+        encoder.write_all(L2_BOOK.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        fs::write(archives.join("node_trades/10"), NODE_TRADES).unwrap();
+
+        let importer = HyperliquidArchiveImporter::new(MigrationConfig::default(), [btc()]);
+        let stats = importer.import_dir(&db, &archives).unwrap();
+
+        assert_eq!(stats.files_processed, 2);
+        assert_eq!(stats.snapshots_migrated, 1);
+        assert_eq!(stats.trades_migrated, 2);
+        assert_eq!(stats.errors.len(), 1, "{:?}", stats.errors);
+
+        let trades = db
+            .query_trades(&btc(), 1_742_637_600_000, 1_742_637_610_000)
+            .unwrap();
+        assert_eq!(
+            trades
+                .iter()
+                .map(|t| (t.time, t.is_sell))
+                .collect::<Vec<_>>(),
+            vec![(1_742_637_601_250, true), (1_742_637_602_500, false)]
+        );
+
+        let depth = db
+            .query_depth_snapshot(&btc(), 1_742_637_600_127)
+            .unwrap()
+            .unwrap();
+        assert_eq!(depth.bids.len(), 2);
+        assert_eq!(depth.asks.get(&Price::from_f32(84001.0)), Some(&0.7));
+    }
+}
@@ -6,6 +6,7 @@
 //! 1. In-memory TimeSeries<KlineDataPoint> structures containing klines and footprint data
 //! 2. HistoricalDepth structures with order book run data
 //! 3. Binance ZIP archives (up to 4 days retention) containing aggTrades CSV files
+//! 4. Hyperliquid S3 archives of `node_trades` fills and `l2Book` snapshots
 //!
 //! The migration system prioritizes data safety through:
 //! - Mandatory backup creation before migration
@@ -17,6 +18,7 @@ pub mod archive;
 pub mod backup;
 pub mod depth;
 pub mod helpers;
+pub mod hyperliquid;
 pub mod progress;
 pub mod timeseries;
 pub mod verification;
@@ -25,6 +27,7 @@ pub use archive::ArchiveMigrator;
 pub use backup::{BackupManager, BackupMetadata};
 pub use depth::DepthMigrator;
 pub use helpers::*;
+pub use hyperliquid::HyperliquidArchiveImporter;
 pub use progress::ProgressTracker;
 pub use timeseries::TimeSeriesMigrator;
 pub use verification::{HealthCheck, HealthCheckStatus, MigrationGuard};
@@ -44,6 +47,8 @@ pub struct MigrationStats {
     pub footprints_migrated: usize,
     /// Total number of order runs migrated
     pub runs_migrated: usize,
+    /// Total number of depth snapshots migrated
+    pub snapshots_migrated: usize,
     /// List of errors encountered during migration
     pub errors: Vec<String>,
 }
@@ -63,6 +68,7 @@ impl MigrationStats {
         self.klines_migrated += other.klines_migrated;
         self.footprints_migrated += other.footprints_migrated;
         self.runs_migrated += other.runs_migrated;
+        self.snapshots_migrated += other.snapshots_migrated;
         self.errors.extend(other.errors.clone());
     }
 
@@ -79,6 +85,7 @@ impl fmt::Display for MigrationStats {
         writeln!(f, "  Klines migrated: {}", self.klines_migrated)?;
         writeln!(f, "  Footprints migrated: {}", self.footprints_migrated)?;
         writeln!(f, "  Order runs migrated: {}", self.runs_migrated)?;
+        writeln!(f, "  Depth snapshots migrated: {}", self.snapshots_migrated)?;
         if self.has_errors() {
             writeln!(f, "  Errors encountered: {}", self.errors.len())?;
             for (i, error) in self.errors.iter().enumerate() {
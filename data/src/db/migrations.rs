@@ -215,23 +215,44 @@ impl MigrationManager {
 
 /// Get the list of all available migrations
 ///
-/// Initial implementation returns empty Vec since Task 1 only creates initial schema.
-/// Future tasks will add migrations here as schema evolves.
+/// Version 1 is the initial schema from schema.sql, later versions are applied
+/// on top of it when a database is opened.
 pub fn get_migrations() -> Vec<Migration> {
     vec![
-        // Future migrations will be added here
-        // Example:
-        // Migration::new(
-        //     2,
-        //     "Add index on trades timestamp",
-        //     "CREATE INDEX idx_trades_time ON trades(timestamp);"
-        // ),
+        Migration::with_rollback(
+            2,
+            "Add liquidations table",
+            "CREATE TABLE IF NOT EXISTS liquidations (
+            liquidation_id BIGINT PRIMARY KEY,
+            ticker_id INTEGER NOT NULL,
+            timestamp BIGINT NOT NULL,
+            price DECIMAL(18, 8) NOT NULL,
+            quantity DECIMAL(18, 8) NOT NULL,
+            is_sell BOOLEAN NOT NULL,
+            FOREIGN KEY (ticker_id) REFERENCES tickers(ticker_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_liquidations_ticker_time ON liquidations(ticker_id, timestamp);",
+            "DROP INDEX IF EXISTS idx_liquidations_ticker_time;
+        DROP TABLE IF EXISTS liquidations;",
+        ),
+        // A table of its own, as DuckDB can't alter `tickers` while other tables reference it
+        Migration::with_rollback(
+            3,
+            "Add ticker display symbols",
+            "CREATE TABLE IF NOT EXISTS ticker_display_symbols (
+            ticker_id INTEGER PRIMARY KEY,
+            display_symbol VARCHAR NOT NULL,
+            FOREIGN KEY (ticker_id) REFERENCES tickers(ticker_id)
+        );",
+            "DROP TABLE IF EXISTS ticker_display_symbols;",
+        ),
     ]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::SCHEMA_VERSION;
     use tempfile::tempdir;
 
     fn create_test_db() -> (DatabaseManager, tempfile::TempDir) {
@@ -272,7 +293,7 @@ mod tests {
 
         let migrations = vec![
             Migration::new(
-                2,
+                SCHEMA_VERSION + 1,
                 "Add test table",
                 "CREATE TABLE test_migration (id INTEGER PRIMARY KEY, value VARCHAR);",
             ),
@@ -307,7 +328,7 @@ mod tests {
 
         let migrations = vec![
             Migration::with_rollback(
-                2,
+                SCHEMA_VERSION + 1,
                 "Add test table with rollback",
                 "CREATE TABLE test_rollback (id INTEGER PRIMARY KEY);",
                 "DROP TABLE test_rollback;",
@@ -8,9 +8,12 @@
 //! - Rich error types for debugging
 
 use duckdb::Connection;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
 
+pub mod coverage;
 pub mod crud;
 pub mod error;
 pub mod health;
@@ -19,28 +22,47 @@ pub mod metrics;
 pub mod migration;
 pub mod migrations;
 pub mod query_cache;
+pub mod retention;
+pub mod transfer;
+pub mod writer;
 
-pub use crud::{TradesCRUD, KlinesCRUD, DepthCRUD, FootprintCRUD};
+pub use coverage::{Coverage, CoveredRange, DayRange};
+pub use crud::{TradesCRUD, KlinesCRUD, DepthCRUD, FootprintCRUD, OpenInterestCRUD, FundingRateCRUD, LiquidationsCRUD};
 pub use error::{DatabaseError, Result};
 pub use health::{DbHealthMonitor, HealthReport};
 pub use metrics::{PerformanceMetrics, MetricsSnapshot, MetricTimer};
 pub use migration::{
     ArchiveMigrator, BackupManager, BackupMetadata, DepthMigrator, HealthCheck, HealthCheckStatus,
-    MigrationConfig, MigrationGuard, MigrationStats, ProgressTracker, TimeSeriesMigrator,
+    HyperliquidArchiveImporter, MigrationConfig, MigrationGuard, MigrationStats, ProgressTracker,
+    TimeSeriesMigrator,
 };
 pub use migrations::{Migration, MigrationManager};
 pub use query_cache::{QueryCache, CacheStats};
+pub use retention::{RetainedData, RetentionPolicy, RetentionSummary, RetentionWindows};
+pub use transfer::{FileFormat, StoredTable};
+pub use writer::TradeWriter;
 
 /// Default memory limit for DuckDB in gigabytes
 /// Set to 8GB as recommended for trading workloads with multiple tickers
 const DEFAULT_MEMORY_LIMIT_GB: usize = 8;
 
 /// Current schema version - incremented with each schema change
-const SCHEMA_VERSION: i32 = 1;
+const SCHEMA_VERSION: i32 = 3;
 
 /// Embedded schema SQL - loaded at compile time
 const SCHEMA_SQL: &str = include_str!("schema.sql");
 
+static PERSISTENCE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Mark whether a database is attached, so charts can request stored data from it
+pub fn set_persistence_enabled(value: bool) {
+    PERSISTENCE_ENABLED.store(value, Ordering::Relaxed);
+}
+
+pub fn is_persistence_enabled() -> bool {
+    PERSISTENCE_ENABLED.load(Ordering::Relaxed)
+}
+
 /// Configuration options for DatabaseManager initialization
 #[derive(Debug, Clone)]
 pub struct DatabaseConfig {
@@ -115,6 +137,8 @@ pub struct DatabaseManager {
     db_path: PathBuf,
     /// Query cache for avoiding repeated database hits
     query_cache: QueryCache,
+    /// Stored days per data type, refreshed by coverage queries
+    coverage: Arc<Mutex<HashMap<retention::RetainedData, coverage::StoredDays>>>,
     /// Performance metrics tracking
     metrics: PerformanceMetrics,
 }
@@ -178,6 +202,7 @@ impl DatabaseManager {
             conn: Arc::new(Mutex::new(conn)),
             db_path,
             query_cache: QueryCache::new(),
+            coverage: Arc::default(),
             metrics: PerformanceMetrics::new(),
         };
 
@@ -186,6 +211,7 @@ impl DatabaseManager {
 
         // Initialize schema
         manager.initialize_schema()?;
+        MigrationManager::new(migrations::get_migrations()).apply_pending(&manager)?;
 
         log::info!(
             "Database initialized at {} (schema version {})",
@@ -325,6 +351,17 @@ impl DatabaseManager {
         })
     }
 
+    /// Write the WAL into the database file, freeing the blocks of deleted rows
+    ///
+    /// DuckDB never shrinks the file, freed blocks get reused by later inserts.
+    pub fn checkpoint(&self) -> Result<()> {
+        self.with_conn(|conn| {
+            conn.execute_batch("FORCE CHECKPOINT;").map_err(|e| {
+                DatabaseError::Query(format!("Failed to checkpoint database: {}", e))
+            })
+        })
+    }
+
     /// Get database statistics
     pub fn get_stats(&self) -> Result<DbStats> {
         self.with_conn(|conn| {
@@ -407,6 +444,7 @@ impl Clone for DatabaseManager {
             conn: Arc::clone(&self.conn),
             db_path: self.db_path.clone(),
             query_cache: self.query_cache.clone(),
+            coverage: Arc::clone(&self.coverage),
             metrics: self.metrics.clone(),
         }
     }
@@ -455,6 +493,7 @@ mod tests {
             "footprint_data",
             "order_runs",
             "volume_profiles",
+            "liquidations",
         ];
 
         db.with_conn(|conn| {
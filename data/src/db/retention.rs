@@ -0,0 +1,511 @@
+//! How long each kind of stored data is kept, and the deletion enforcing it
+//!
+//! A [`RetentionPolicy`] holds a window per data type for every ticker, and
+//! another for favorited tickers that can only ever keep data longer.
+//! Footprints are kept as long as the klines of their timeframe.
+
+use super::coverage::{delete_rows, source};
+use super::helpers::find_ticker_id;
+use super::{DatabaseError, DatabaseManager, Result};
+
+use duckdb::Connection;
+use exchange::{Ticker, Timeframe};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How often the app enforces the policy in the background
+pub const ENFORCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Data types a retention window can be set for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetainedData {
+    Trades,
+    Klines(Timeframe),
+    DepthSnapshots,
+    OrderRuns,
+    OpenInterest,
+    FundingRates,
+    Liquidations,
+}
+
+impl std::fmt::Display for RetainedData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetainedData::Trades => write!(f, "Trades"),
+            RetainedData::Klines(timeframe) => write!(f, "Klines {timeframe}"),
+            RetainedData::DepthSnapshots => write!(f, "Depth snapshots"),
+            RetainedData::OrderRuns => write!(f, "Order runs"),
+            RetainedData::OpenInterest => write!(f, "Open interest"),
+            RetainedData::FundingRates => write!(f, "Funding rates"),
+            RetainedData::Liquidations => write!(f, "Liquidations"),
+        }
+    }
+}
+
+impl RetainedData {
+    /// Every data type, klines once per kline timeframe
+    pub fn all() -> Vec<RetainedData> {
+        let mut kinds = vec![RetainedData::Trades];
+        kinds.extend(Timeframe::KLINE.map(RetainedData::Klines));
+        kinds.extend([
+            RetainedData::DepthSnapshots,
+            RetainedData::OrderRuns,
+            RetainedData::OpenInterest,
+            RetainedData::FundingRates,
+            RetainedData::Liquidations,
+        ]);
+        kinds
+    }
+}
+
+/// Days of data kept per type, `None` keeps everything
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionWindows {
+    pub trades: Option<u32>,
+    /// Timeframes not listed are kept, footprints of a timeframe go with its klines
+    pub klines: BTreeMap<Timeframe, u32>,
+    pub depth_snapshots: Option<u32>,
+    pub order_runs: Option<u32>,
+    pub open_interest: Option<u32>,
+    pub funding_rates: Option<u32>,
+    pub liquidations: Option<u32>,
+}
+
+impl Default for RetentionWindows {
+    fn default() -> Self {
+        Self {
+            trades: Some(4),
+            klines: [
+                (Timeframe::M1, 30),
+                (Timeframe::M3, 30),
+                (Timeframe::M5, 30),
+                (Timeframe::M15, 90),
+                (Timeframe::M30, 90),
+            ]
+            .into_iter()
+            .collect(),
+            depth_snapshots: Some(4),
+            order_runs: Some(4),
+            open_interest: Some(90),
+            funding_rates: Some(365),
+            liquidations: Some(30),
+        }
+    }
+}
+
+impl RetentionWindows {
+    pub fn days(&self, kind: RetainedData) -> Option<u32> {
+        match kind {
+            RetainedData::Trades => self.trades,
+            RetainedData::Klines(timeframe) => self.klines.get(&timeframe).copied(),
+            RetainedData::DepthSnapshots => self.depth_snapshots,
+            RetainedData::OrderRuns => self.order_runs,
+            RetainedData::OpenInterest => self.open_interest,
+            RetainedData::FundingRates => self.funding_rates,
+            RetainedData::Liquidations => self.liquidations,
+        }
+    }
+
+    pub fn set_days(&mut self, kind: RetainedData, days: Option<u32>) {
+        match kind {
+            RetainedData::Trades => self.trades = days,
+            RetainedData::Klines(timeframe) => match days {
+                Some(days) => {
+                    self.klines.insert(timeframe, days);
+                }
+                None => {
+                    self.klines.remove(&timeframe);
+                }
+            },
+            RetainedData::DepthSnapshots => self.depth_snapshots = days,
+            RetainedData::OrderRuns => self.order_runs = days,
+            RetainedData::OpenInterest => self.open_interest = days,
+            RetainedData::FundingRates => self.funding_rates = days,
+            RetainedData::Liquidations => self.liquidations = days,
+        }
+    }
+
+    /// The longer of both windows for every type
+    fn longest(&self, other: &RetentionWindows) -> RetentionWindows {
+        let mut windows = self.clone();
+
+        for kind in RetainedData::all() {
+            let days = match (self.days(kind), other.days(kind)) {
+                (Some(ours), Some(theirs)) => Some(ours.max(theirs)),
+                _ => None,
+            };
+            windows.set_days(kind, days);
+        }
+
+        windows
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub all: RetentionWindows,
+    /// Windows of favorited tickers, any shorter than in `all` is ignored
+    pub favorites: RetentionWindows,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            all: RetentionWindows::default(),
+            favorites: RetentionWindows {
+                trades: Some(30),
+                klines: BTreeMap::new(),
+                depth_snapshots: Some(14),
+                order_runs: Some(14),
+                open_interest: None,
+                funding_rates: None,
+                liquidations: Some(90),
+            },
+        }
+    }
+}
+
+/// Rows deleted by [`enforce`], per data type
+#[derive(Debug, Clone, Default)]
+pub struct RetentionSummary {
+    pub trades: usize,
+    pub klines: usize,
+    pub footprints: usize,
+    pub depth_snapshots: usize,
+    pub order_runs: usize,
+    pub open_interest: usize,
+    pub funding_rates: usize,
+    pub liquidations: usize,
+    /// Space the checkpoint freed for reuse, DuckDB doesn't shrink the file itself
+    pub reclaimed_bytes: u64,
+}
+
+impl RetentionSummary {
+    pub fn total_rows(&self) -> usize {
+        self.trades
+            + self.klines
+            + self.footprints
+            + self.depth_snapshots
+            + self.order_runs
+            + self.open_interest
+            + self.funding_rates
+            + self.liquidations
+    }
+
+    fn rows_mut(&mut self, kind: RetainedData) -> &mut usize {
+        match kind {
+            RetainedData::Trades => &mut self.trades,
+            RetainedData::Klines(_) => &mut self.klines,
+            RetainedData::DepthSnapshots => &mut self.depth_snapshots,
+            RetainedData::OrderRuns => &mut self.order_runs,
+            RetainedData::OpenInterest => &mut self.open_interest,
+            RetainedData::FundingRates => &mut self.funding_rates,
+            RetainedData::Liquidations => &mut self.liquidations,
+        }
+    }
+}
+
+impl std::fmt::Display for RetentionSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.total_rows() == 0 {
+            return write!(f, "Nothing was past its retention window");
+        }
+
+        let deleted = [
+            ("trades", self.trades),
+            ("klines", self.klines),
+            ("footprints", self.footprints),
+            ("depth snapshots", self.depth_snapshots),
+            ("order runs", self.order_runs),
+            ("open interest", self.open_interest),
+            ("funding rates", self.funding_rates),
+            ("liquidations", self.liquidations),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(name, count)| format!("{count} {name}"))
+        .collect::<Vec<_>>();
+
+        write!(
+            f,
+            "Deleted {}, freed {:.1} MB",
+            deleted.join(", "),
+            self.reclaimed_bytes as f64 / 1024.0 / 1024.0
+        )
+    }
+}
+
+/// Delete every row past its window as of `now`, then checkpoint if anything went
+pub fn enforce(
+    db: &DatabaseManager,
+    policy: &RetentionPolicy,
+    favorites: &[Ticker],
+    now: u64,
+) -> Result<RetentionSummary> {
+    let used_before = db.with_conn(used_bytes)?;
+    let favorite_windows = policy.favorites.longest(&policy.all);
+
+    let mut summary = db.with_conn(|conn| {
+        let favorite_ids = ticker_ids(conn, favorites)?;
+
+        let mut summary = RetentionSummary::default();
+
+        if favorite_ids.is_empty() {
+            delete_past_windows(conn, &policy.all, "TRUE", now, &mut summary)?;
+        } else {
+            let ids = favorite_ids
+                .iter()
+                .map(i32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let others = format!("ticker_id NOT IN ({ids})");
+            delete_past_windows(conn, &policy.all, &others, now, &mut summary)?;
+
+            let favorited = format!("ticker_id IN ({ids})");
+            delete_past_windows(conn, &favorite_windows, &favorited, now, &mut summary)?;
+        }
+
+        Ok(summary)
+    })?;
+
+    if summary.total_rows() > 0 {
+        db.clear_cache();
+        db.checkpoint()?;
+
+        summary.reclaimed_bytes = used_before.saturating_sub(db.with_conn(used_bytes)?);
+    }
+
+    Ok(summary)
+}
+
+/// Delete the rows of tickers matching `scope` that are older than `windows` allow
+fn delete_past_windows(
+    conn: &Connection,
+    windows: &RetentionWindows,
+    scope: &str,
+    now: u64,
+    summary: &mut RetentionSummary,
+) -> Result<()> {
+    for kind in RetainedData::all() {
+        let Some(days) = windows.days(kind) else {
+            continue;
+        };
+        let cutoff = now.saturating_sub(u64::from(days) * DAY_MS) as i64;
+
+        let (_, time, filter) = source(kind);
+        let condition = format!("{time} < ? AND {filter} AND {scope}");
+        *summary.rows_mut(kind) += delete_rows(conn, kind, &condition, duckdb::params![cutoff])?;
+
+        if let RetainedData::Klines(timeframe) = kind {
+            summary.footprints += conn
+                .execute(
+                    &format!(
+                        "DELETE FROM footprint_data
+                         WHERE candle_time < ? AND timeframe = '{timeframe}' AND {scope}"
+                    ),
+                    [cutoff],
+                )
+                .map_err(|e| {
+                    DatabaseError::Query(format!("Failed to delete from footprint_data: {e}"))
+                })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Bytes of the blocks in use, deleted rows only free theirs once checkpointed
+fn used_bytes(conn: &Connection) -> Result<u64> {
+    conn.query_row(
+        "SELECT block_size, used_blocks FROM pragma_database_size()",
+        [],
+        |row| Ok(row.get::<_, i64>(0)? * row.get::<_, i64>(1)?),
+    )
+    .map(|bytes| bytes.max(0) as u64)
+    .map_err(|e| DatabaseError::Query(format!("Failed to query database size: {e}")))
+}
+
+/// Ids of the given tickers that have anything stored
+fn ticker_ids(conn: &Connection, tickers: &[Ticker]) -> Result<Vec<i32>> {
+    tickers
+        .iter()
+        .filter_map(|ticker| find_ticker_id(conn, ticker).transpose())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{FundingRateCRUD, KlinesCRUD, OpenInterestCRUD, TradesCRUD};
+    use exchange::adapter::Exchange;
+    use exchange::util::Price;
+    use exchange::{FundingRate, Kline, OpenInterest, TickerInfo, Trade};
+    use tempfile::tempdir;
+
+    const NOW: u64 = 100 * DAY_MS;
+
+    fn trades_at(days_ago: &[u64]) -> Vec<Trade> {
+        days_ago
+            .iter()
+            .map(|days| Trade {
+                time: NOW - days * DAY_MS,
+                price: Price::from_f32(100.0),
+                qty: 1.0,
+                is_sell: false,
+            })
+            .collect()
+    }
+
+    fn kline_at(days_ago: u64) -> Kline {
+        let price = Price::from_f32(100.0);
+
+        Kline {
+            time: NOW - days_ago * DAY_MS,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: (1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn favorites_keep_data_longer() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        let favorite = TickerInfo::new(
+            Ticker::new("BTCUSDT", Exchange::BybitLinear),
+            0.1,
+            0.001,
+            None,
+        );
+        let other = TickerInfo::new(
+            Ticker::new("ETHUSDT", Exchange::BybitLinear),
+            0.01,
+            0.01,
+            None,
+        );
+
+        for ticker_info in [&favorite, &other] {
+            db.insert_trades(ticker_info, &trades_at(&[1, 10, 40]))
+                .unwrap();
+        }
+
+        let policy = RetentionPolicy {
+            all: RetentionWindows {
+                trades: Some(5),
+                ..RetentionWindows::default()
+            },
+            favorites: RetentionWindows {
+                trades: Some(20),
+                ..RetentionWindows::default()
+            },
+        };
+
+        let summary = enforce(&db, &policy, &[favorite.ticker], NOW).unwrap();
+        assert_eq!(summary.trades, 3);
+
+        assert_eq!(db.query_trades_count(&favorite, 0, NOW).unwrap(), 2);
+        assert_eq!(db.query_trades_count(&other, 0, NOW).unwrap(), 1);
+    }
+
+    #[test]
+    fn klines_follow_their_timeframe() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        let ticker_info = TickerInfo::new(
+            Ticker::new("BTCUSDT", Exchange::BinanceLinear),
+            0.1,
+            0.001,
+            None,
+        );
+        let klines = [kline_at(2), kline_at(50)];
+        db.insert_klines(&ticker_info, Timeframe::M1, &klines)
+            .unwrap();
+        db.insert_klines(&ticker_info, Timeframe::H1, &klines)
+            .unwrap();
+
+        let mut policy = RetentionPolicy::default();
+        policy
+            .all
+            .set_days(RetainedData::Klines(Timeframe::M1), Some(30));
+        policy
+            .all
+            .set_days(RetainedData::Klines(Timeframe::H1), None);
+
+        let summary = enforce(&db, &policy, &[], NOW).unwrap();
+        assert_eq!(summary.klines, 1);
+    }
+
+    #[test]
+    fn funding_rates_outlive_open_interest_on_shared_rows() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        let ticker_info = TickerInfo::new(
+            Ticker::new("BTCUSDT", Exchange::BybitLinear),
+            0.1,
+            0.001,
+            None,
+        );
+        let times = [NOW - 50 * DAY_MS, NOW - 2 * DAY_MS];
+        db.insert_open_interest(
+            &ticker_info,
+            &times.map(|time| OpenInterest { time, value: 1.0 }),
+        )
+        .unwrap();
+        db.insert_funding_rates(
+            &ticker_info,
+            &times.map(|time| FundingRate { time, rate: 0.01 }),
+        )
+        .unwrap();
+
+        let policy = RetentionPolicy {
+            all: RetentionWindows {
+                open_interest: Some(30),
+                funding_rates: Some(365),
+                ..RetentionWindows::default()
+            },
+            ..RetentionPolicy::default()
+        };
+
+        let summary = enforce(&db, &policy, &[], NOW).unwrap();
+        assert_eq!(summary.open_interest, 1);
+        assert_eq!(summary.funding_rates, 0);
+
+        assert_eq!(
+            db.query_open_interest(&ticker_info, 0, NOW).unwrap().len(),
+            1
+        );
+        assert_eq!(
+            db.query_funding_rates(&ticker_info, 0, NOW).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn favorite_windows_never_shorten() {
+        let all = RetentionWindows {
+            trades: Some(10),
+            order_runs: None,
+            ..RetentionWindows::default()
+        };
+        let favorites = RetentionWindows {
+            trades: Some(3),
+            order_runs: Some(30),
+            ..RetentionWindows::default()
+        };
+
+        let windows = favorites.longest(&all);
+        assert_eq!(windows.trades, Some(10));
+        assert_eq!(windows.order_runs, None);
+    }
+}
@@ -0,0 +1,546 @@
+//! Export of stored market data to Parquet or CSV files, and import back from them
+//!
+//! Files have one row per record with plain columns, times in epoch milliseconds and
+//! prices and quantities as doubles, so notebooks can read them as they are. Imports
+//! look the columns up by name, so files from other tools work once they use the same:
+//!
+//! | Table        | Columns                                                              |
+//! |--------------|----------------------------------------------------------------------|
+//! | `trades`     | `timestamp`, `price`, `quantity`, `is_sell`                          |
+//! | `klines`     | `timeframe`, `candle_time`, `open`, `high`, `low`, `close`, `volume` |
+//! | `footprints` | `timeframe`, `candle_time`, `price_level`, `buy_volume`, `sell_volume`, `num_trades` |
+//! | `order_runs` | `start_time`, `end_time`, `price_level`, `total_volume`, `is_buy`    |
+//!
+//! Exports also carry `exchange` and `symbol` columns, which imports ignore in favor of
+//! the ticker they're given. Timeframes are written as in the app, e.g. `1m` or `4h`.
+
+use super::crud::{FootprintCRUD, KlinesCRUD, TradesCRUD};
+use super::helpers::{decimal_to_price, find_ticker_id};
+use super::{DatabaseError, DatabaseManager, Result};
+use crate::chart::heatmap::OrderRun;
+use crate::chart::kline::{GroupedTrades, KlineTrades};
+
+use duckdb::Connection;
+use exchange::{Kline, TickerInfo, Timeframe, Trade};
+use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoredTable {
+    Trades,
+    Klines,
+    Footprints,
+    OrderRuns,
+}
+
+impl StoredTable {
+    pub const ALL: [StoredTable; 4] = [
+        StoredTable::Trades,
+        StoredTable::Klines,
+        StoredTable::Footprints,
+        StoredTable::OrderRuns,
+    ];
+
+    /// Name used in file names, and the table's name in the header above
+    pub fn name(self) -> &'static str {
+        match self {
+            StoredTable::Trades => "trades",
+            StoredTable::Klines => "klines",
+            StoredTable::Footprints => "footprints",
+            StoredTable::OrderRuns => "order_runs",
+        }
+    }
+
+    /// Columns of an export, after `exchange` and `symbol`
+    fn export_columns(self) -> &'static str {
+        match self {
+            StoredTable::Trades => {
+                "timestamp, CAST(price AS DOUBLE) AS price, CAST(quantity AS DOUBLE) AS quantity, \
+                 NOT is_buyer_maker AS is_sell"
+            }
+            StoredTable::Klines => {
+                "timeframe, candle_time, CAST(open_price AS DOUBLE) AS open, \
+                 CAST(high_price AS DOUBLE) AS high, CAST(low_price AS DOUBLE) AS low, \
+                 CAST(close_price AS DOUBLE) AS close, CAST(volume AS DOUBLE) AS volume"
+            }
+            StoredTable::Footprints => {
+                "timeframe, candle_time, CAST(price_level AS DOUBLE) AS price_level, \
+                 CAST(buy_volume AS DOUBLE) AS buy_volume, CAST(sell_volume AS DOUBLE) AS sell_volume, \
+                 num_trades"
+            }
+            StoredTable::OrderRuns => {
+                "epoch_ms(start_time) AS start_time, epoch_ms(end_time) AS end_time, \
+                 CAST(price_level AS DOUBLE) AS price_level, \
+                 CAST(total_volume AS DOUBLE) AS total_volume, is_buy"
+            }
+        }
+    }
+
+    fn sql_table(self) -> &'static str {
+        match self {
+            StoredTable::Footprints => "footprint_data",
+            _ => self.name(),
+        }
+    }
+
+    /// Condition keeping the rows within `[start, end]`
+    fn time_condition(self, start: u64, end: u64) -> String {
+        match self {
+            StoredTable::Trades => format!("timestamp BETWEEN {start} AND {end}"),
+            StoredTable::Klines | StoredTable::Footprints => {
+                format!("candle_time BETWEEN {start} AND {end}")
+            }
+            StoredTable::OrderRuns => {
+                format!("start_time BETWEEN epoch_ms({start}) AND epoch_ms({end})")
+            }
+        }
+    }
+
+    fn order_by(self) -> &'static str {
+        match self {
+            StoredTable::Trades => "timestamp",
+            StoredTable::Klines | StoredTable::Footprints => "timeframe, candle_time",
+            StoredTable::OrderRuns => "start_time",
+        }
+    }
+}
+
+impl std::fmt::Display for StoredTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoredTable::Trades => write!(f, "Trades"),
+            StoredTable::Klines => write!(f, "Klines"),
+            StoredTable::Footprints => write!(f, "Footprints"),
+            StoredTable::OrderRuns => write!(f, "Order runs"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Parquet,
+    Csv,
+}
+
+impl FileFormat {
+    pub const ALL: [FileFormat; 2] = [FileFormat::Parquet, FileFormat::Csv];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Parquet => "parquet",
+            FileFormat::Csv => "csv",
+        }
+    }
+
+    /// Format of a file by its extension
+    pub fn of_path(path: &Path) -> Option<FileFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        FileFormat::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+
+    fn copy_options(self) -> &'static str {
+        match self {
+            FileFormat::Parquet => "FORMAT PARQUET, COMPRESSION ZSTD",
+            FileFormat::Csv => "FORMAT CSV, HEADER",
+        }
+    }
+
+    fn reader(self, path: &str) -> String {
+        match self {
+            FileFormat::Parquet => format!("read_parquet({path})"),
+            FileFormat::Csv => format!("read_csv({path}, header = true)"),
+        }
+    }
+}
+
+impl std::fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileFormat::Parquet => write!(f, "Parquet"),
+            FileFormat::Csv => write!(f, "CSV"),
+        }
+    }
+}
+
+/// Write the rows of `table` for a ticker within `[start, end]` to `path`, returning how many
+pub fn export(
+    db: &DatabaseManager,
+    table: StoredTable,
+    ticker_info: &TickerInfo,
+    (start, end): (u64, u64),
+    format: FileFormat,
+    path: &Path,
+) -> Result<usize> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    db.with_conn(|conn| {
+        let ticker_id = find_ticker_id(conn, &ticker_info.ticker)?.ok_or_else(|| {
+            DatabaseError::NotFound(format!("nothing stored for {}", ticker_info.ticker))
+        })?;
+
+        let (symbol, _) = ticker_info.ticker.to_full_symbol_and_type();
+        let select = format!(
+            "SELECT {exchange} AS exchange, {symbol} AS symbol, {columns}
+             FROM {table}
+             WHERE ticker_id = {ticker_id} AND {time}
+             ORDER BY {order}",
+            exchange = sql_string(&ticker_info.exchange().to_string()),
+            symbol = sql_string(&symbol),
+            columns = table.export_columns(),
+            table = table.sql_table(),
+            time = table.time_condition(start, end),
+            order = table.order_by(),
+        );
+
+        let rows: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM ({select})"), [], |row| {
+                row.get(0)
+            })
+            .map_err(|e| DatabaseError::Query(format!("Failed to count rows: {e}")))?;
+
+        conn.execute(
+            &format!(
+                "COPY ({select}) TO {path} ({options})",
+                path = sql_string(&path.to_string_lossy()),
+                options = format.copy_options(),
+            ),
+            [],
+        )
+        .map_err(|e| DatabaseError::Query(format!("Failed to export {}: {e}", table.name())))?;
+
+        Ok(rows as usize)
+    })
+}
+
+/// Read the rows of `table` from a Parquet or CSV file into the given ticker, returning how many
+/// were written
+///
+/// Rows already stored are updated or skipped as with live data, skipped ones aren't counted.
+pub fn import(
+    db: &DatabaseManager,
+    table: StoredTable,
+    ticker_info: &TickerInfo,
+    path: &Path,
+) -> Result<usize> {
+    let format = FileFormat::of_path(path).ok_or_else(|| {
+        DatabaseError::Migration(format!("{} isn't a .parquet or .csv file", path.display()))
+    })?;
+    let source = format.reader(&sql_string(&path.to_string_lossy()));
+
+    match table {
+        StoredTable::Trades => {
+            let trades = db.with_conn(|conn| read_trades(conn, &source))?;
+            db.insert_trades(ticker_info, &trades)
+        }
+        StoredTable::Klines => {
+            let klines = db.with_conn(|conn| read_klines(conn, &source))?;
+
+            klines
+                .iter()
+                .map(|(timeframe, klines)| db.insert_klines(ticker_info, *timeframe, klines))
+                .sum()
+        }
+        StoredTable::Footprints => {
+            let footprints = db.with_conn(|conn| read_footprints(conn, &source))?;
+
+            let mut levels = 0;
+            for ((timeframe, candle_time), footprint) in &footprints {
+                levels += db.insert_footprint(ticker_info, *timeframe, *candle_time, footprint)?;
+            }
+            Ok(levels)
+        }
+        StoredTable::OrderRuns => {
+            let runs = db.with_conn(|conn| read_order_runs(conn, &source))?;
+            db.insert_order_runs(ticker_info, &runs)
+        }
+    }
+}
+
+fn read_trades(conn: &Connection, source: &str) -> Result<Vec<Trade>> {
+    read_rows(
+        conn,
+        &format!(
+            "SELECT CAST(timestamp AS BIGINT), CAST(price AS DOUBLE), CAST(quantity AS DOUBLE),
+                    CAST(is_sell AS BOOLEAN)
+             FROM {source}"
+        ),
+        |row| {
+            let timestamp: i64 = row.get(0)?;
+            let price: f64 = row.get(1)?;
+            let quantity: f64 = row.get(2)?;
+
+            Ok(Trade {
+                time: timestamp as u64,
+                price: decimal_to_price(price),
+                qty: quantity as f32,
+                is_sell: row.get(3)?,
+            })
+        },
+    )
+}
+
+fn read_klines(conn: &Connection, source: &str) -> Result<BTreeMap<Timeframe, Vec<Kline>>> {
+    let rows = read_rows(
+        conn,
+        &format!(
+            "SELECT CAST(timeframe AS VARCHAR), CAST(candle_time AS BIGINT), CAST(open AS DOUBLE),
+                    CAST(high AS DOUBLE), CAST(low AS DOUBLE), CAST(close AS DOUBLE),
+                    CAST(volume AS DOUBLE)
+             FROM {source}"
+        ),
+        |row| {
+            let timeframe: String = row.get(0)?;
+            let candle_time: i64 = row.get(1)?;
+            let volume: f64 = row.get(6)?;
+
+            Ok((
+                timeframe,
+                Kline {
+                    time: candle_time as u64,
+                    open: decimal_to_price(row.get(2)?),
+                    high: decimal_to_price(row.get(3)?),
+                    low: decimal_to_price(row.get(4)?),
+                    close: decimal_to_price(row.get(5)?),
+                    volume: (volume as f32 / 2.0, volume as f32 / 2.0),
+                },
+            ))
+        },
+    )?;
+
+    let mut klines: BTreeMap<Timeframe, Vec<Kline>> = BTreeMap::new();
+    for (timeframe, kline) in rows {
+        klines
+            .entry(parse_timeframe(&timeframe)?)
+            .or_default()
+            .push(kline);
+    }
+
+    Ok(klines)
+}
+
+fn read_footprints(
+    conn: &Connection,
+    source: &str,
+) -> Result<BTreeMap<(Timeframe, u64), KlineTrades>> {
+    let rows = read_rows(
+        conn,
+        &format!(
+            "SELECT CAST(timeframe AS VARCHAR), CAST(candle_time AS BIGINT),
+                    CAST(price_level AS DOUBLE), CAST(buy_volume AS DOUBLE),
+                    CAST(sell_volume AS DOUBLE), CAST(num_trades AS BIGINT)
+             FROM {source}"
+        ),
+        |row| {
+            let timeframe: String = row.get(0)?;
+            let candle_time: i64 = row.get(1)?;
+            let price_level: f64 = row.get(2)?;
+            let buy_volume: f64 = row.get(3)?;
+            let sell_volume: f64 = row.get(4)?;
+            let num_trades: i64 = row.get(5)?;
+
+            Ok((
+                timeframe,
+                candle_time as u64,
+                price_level,
+                buy_volume,
+                sell_volume,
+                num_trades as usize,
+            ))
+        },
+    )?;
+
+    let mut footprints: BTreeMap<(Timeframe, u64), KlineTrades> = BTreeMap::new();
+    for (timeframe, candle_time, price_level, buy_volume, sell_volume, num_trades) in rows {
+        let footprint = footprints
+            .entry((parse_timeframe(&timeframe)?, candle_time))
+            .or_insert_with(|| KlineTrades {
+                trades: FxHashMap::default(),
+                poc: None,
+            });
+
+        footprint.trades.insert(
+            decimal_to_price(price_level),
+            GroupedTrades {
+                buy_qty: buy_volume as f32,
+                sell_qty: sell_volume as f32,
+                first_time: candle_time,
+                last_time: candle_time,
+                buy_count: num_trades / 2,
+                sell_count: num_trades - num_trades / 2,
+            },
+        );
+    }
+
+    for footprint in footprints.values_mut() {
+        footprint.calculate_poc();
+    }
+
+    Ok(footprints)
+}
+
+fn read_order_runs(
+    conn: &Connection,
+    source: &str,
+) -> Result<Vec<(exchange::util::Price, OrderRun)>> {
+    read_rows(
+        conn,
+        &format!(
+            "SELECT CAST(start_time AS BIGINT), CAST(end_time AS BIGINT),
+                    CAST(price_level AS DOUBLE), CAST(total_volume AS DOUBLE),
+                    CAST(is_buy AS BOOLEAN)
+             FROM {source}"
+        ),
+        |row| {
+            let start_time: i64 = row.get(0)?;
+            let end_time: i64 = row.get(1)?;
+            let price_level: f64 = row.get(2)?;
+            let total_volume: f64 = row.get(3)?;
+
+            Ok((
+                decimal_to_price(price_level),
+                OrderRun::with_until_time(
+                    start_time as u64,
+                    end_time as u64,
+                    total_volume as f32,
+                    row.get(4)?,
+                ),
+            ))
+        },
+    )
+}
+
+fn read_rows<T>(
+    conn: &Connection,
+    sql: &str,
+    map: impl FnMut(&duckdb::Row<'_>) -> duckdb::Result<T>,
+) -> Result<Vec<T>> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| DatabaseError::Migration(format!("Failed to read file: {e}")))?;
+
+    stmt.query_map([], map)
+        .map_err(|e| DatabaseError::Migration(format!("Failed to read file: {e}")))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| DatabaseError::Migration(format!("Failed to read row: {e}")))
+}
+
+fn parse_timeframe(value: &str) -> Result<Timeframe> {
+    Timeframe::KLINE
+        .into_iter()
+        .find(|timeframe| timeframe.to_string() == value)
+        .ok_or_else(|| DatabaseError::Migration(format!("Unknown timeframe {value:?}")))
+}
+
+/// Quote a string as an SQL literal
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exchange::Ticker;
+    use exchange::adapter::Exchange;
+    use exchange::util::Price;
+    use tempfile::tempdir;
+
+    fn ticker_info() -> TickerInfo {
+        TickerInfo::new(
+            Ticker::new("BTCUSDT", Exchange::BinanceLinear),
+            0.1,
+            0.001,
+            None,
+        )
+    }
+
+    fn trades() -> Vec<Trade> {
+        (0..5)
+            .map(|i| Trade {
+                time: 1_000 + i * 100,
+                price: Price::from_f32(50_000.0 + i as f32),
+                qty: 0.5,
+                is_sell: i % 2 == 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn trades_round_trip_through_both_formats() {
+        let dir = tempdir().unwrap();
+        let source = DatabaseManager::new(dir.path().join("source.db")).unwrap();
+        let ticker_info = ticker_info();
+        source.insert_trades(&ticker_info, &trades()).unwrap();
+
+        for format in FileFormat::ALL {
+            let path = dir.path().join(format!("trades.{}", format.extension()));
+
+            let exported = export(
+                &source,
+                StoredTable::Trades,
+                &ticker_info,
+                (1_100, 1_300),
+                format,
+                &path,
+            )
+            .unwrap();
+            assert_eq!(exported, 3);
+
+            let target = DatabaseManager::new(dir.path().join(format!("{format}.db"))).unwrap();
+            let imported = import(&target, StoredTable::Trades, &ticker_info, &path).unwrap();
+            assert_eq!(imported, 3);
+
+            // stored trades are skipped, so importing the file again writes nothing
+            let reimported = import(&target, StoredTable::Trades, &ticker_info, &path).unwrap();
+            assert_eq!(reimported, 0);
+
+            let fields = |trades: &[Trade]| {
+                trades
+                    .iter()
+                    .map(|trade| (trade.time, trade.price, trade.qty, trade.is_sell))
+                    .collect::<Vec<_>>()
+            };
+            let stored = target.query_trades(&ticker_info, 0, u64::MAX / 2).unwrap();
+            assert_eq!(fields(&stored), fields(&trades()[1..4]));
+        }
+    }
+
+    #[test]
+    fn export_needs_stored_ticker() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        let result = export(
+            &db,
+            StoredTable::Klines,
+            &ticker_info(),
+            (0, 1_000),
+            FileFormat::Parquet,
+            &dir.path().join("klines.parquet"),
+        );
+        assert!(matches!(result, Err(DatabaseError::NotFound(_))));
+    }
+
+    #[test]
+    fn rejects_unknown_file_types() {
+        let dir = tempdir().unwrap();
+        let db = DatabaseManager::new(dir.path().join("test.db")).unwrap();
+
+        let result = import(
+            &db,
+            StoredTable::Trades,
+            &ticker_info(),
+            &dir.path().join("trades.json"),
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            FileFormat::of_path(Path::new("a/b.PARQUET")),
+            Some(FileFormat::Parquet)
+        );
+    }
+}
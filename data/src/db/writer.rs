@@ -0,0 +1,298 @@
+//! Background writer for live trade persistence
+//!
+//! Live trades arrive on the UI thread in small buffers with every depth update.
+//! Writing them synchronously would stall the iced update loop, so they are sent
+//! over a channel to a dedicated thread which batches them per ticker and flushes
+//! through `TradesCRUD::insert_trades` once a size or time threshold is reached.
+//! Liquidations of the same streams take the same path, flushed on the timer only.
+
+use crate::db::{DatabaseManager, LiquidationsCRUD, TradesCRUD};
+use exchange::{Liquidation, TickerInfo, Trade};
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Flush a ticker once this many trades are buffered
+const DEFAULT_MAX_BATCH_SIZE: usize = 5_000;
+
+/// Flush all buffered trades at least this often
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+enum WriterCommand {
+    Trades(TickerInfo, Vec<Trade>),
+    Liquidations(TickerInfo, Vec<Liquidation>),
+    Flush,
+}
+
+#[derive(Default)]
+struct Buffers {
+    trades: FxHashMap<TickerInfo, Vec<Trade>>,
+    liquidations: FxHashMap<TickerInfo, Vec<Liquidation>>,
+}
+
+/// Handle to the background trade writer thread
+///
+/// Dropping the handle flushes any buffered trades and stops the thread
+pub struct TradeWriter {
+    sender: Option<mpsc::Sender<WriterCommand>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TradeWriter {
+    /// Spawn a writer with default batching thresholds
+    pub fn spawn(db_manager: Arc<DatabaseManager>) -> Self {
+        Self::with_thresholds(db_manager, DEFAULT_MAX_BATCH_SIZE, DEFAULT_FLUSH_INTERVAL)
+    }
+
+    /// Spawn a writer with custom batch size and flush interval
+    pub fn with_thresholds(
+        db_manager: Arc<DatabaseManager>,
+        max_batch_size: usize,
+        flush_interval: Duration,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let handle = std::thread::Builder::new()
+            .name("trade-writer".to_string())
+            .spawn(move || run(&db_manager, &receiver, max_batch_size, flush_interval))
+            .map_err(|e| log::error!("Failed to spawn trade writer thread: {}", e))
+            .ok();
+
+        Self {
+            sender: Some(sender),
+            handle,
+        }
+    }
+
+    /// Queue a buffer of live trades, never blocks
+    pub fn push(&self, ticker_info: TickerInfo, trades: &[Trade]) {
+        if trades.is_empty() {
+            return;
+        }
+
+        if let Some(sender) = &self.sender
+            && sender
+                .send(WriterCommand::Trades(ticker_info, trades.to_vec()))
+                .is_err()
+        {
+            log::warn!("Trade writer is not running, dropped {} trades", trades.len());
+        }
+    }
+
+    /// Queue live liquidations, never blocks
+    pub fn push_liquidations(&self, ticker_info: TickerInfo, liquidations: &[Liquidation]) {
+        if liquidations.is_empty() {
+            return;
+        }
+
+        if let Some(sender) = &self.sender
+            && sender
+                .send(WriterCommand::Liquidations(
+                    ticker_info,
+                    liquidations.to_vec(),
+                ))
+                .is_err()
+        {
+            log::warn!(
+                "Trade writer is not running, dropped {} liquidations",
+                liquidations.len()
+            );
+        }
+    }
+
+    /// Ask the writer to flush all buffered trades
+    pub fn flush(&self) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(WriterCommand::Flush);
+        }
+    }
+}
+
+impl Drop for TradeWriter {
+    fn drop(&mut self) {
+        // Closing the channel makes the thread flush and exit
+        self.sender.take();
+
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            log::error!("Trade writer thread panicked");
+        }
+    }
+}
+
+fn run(
+    db_manager: &DatabaseManager,
+    receiver: &mpsc::Receiver<WriterCommand>,
+    max_batch_size: usize,
+    flush_interval: Duration,
+) {
+    let mut buffers = Buffers::default();
+    let mut last_flush = Instant::now();
+
+    loop {
+        let timeout = flush_interval.saturating_sub(last_flush.elapsed());
+
+        match receiver.recv_timeout(timeout) {
+            Ok(WriterCommand::Trades(ticker_info, trades)) => {
+                let buffer = buffers.trades.entry(ticker_info).or_default();
+                buffer.extend(trades);
+
+                if buffer.len() >= max_batch_size {
+                    flush_ticker(db_manager, &ticker_info, buffer);
+                }
+            }
+            Ok(WriterCommand::Liquidations(ticker_info, liquidations)) => {
+                buffers
+                    .liquidations
+                    .entry(ticker_info)
+                    .or_default()
+                    .extend(liquidations);
+            }
+            Ok(WriterCommand::Flush) | Err(RecvTimeoutError::Timeout) => {
+                flush_all(db_manager, &mut buffers);
+                last_flush = Instant::now();
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                flush_all(db_manager, &mut buffers);
+                break;
+            }
+        }
+
+        // A steady stream of trades never hits the receive timeout
+        if last_flush.elapsed() >= flush_interval {
+            flush_all(db_manager, &mut buffers);
+            last_flush = Instant::now();
+        }
+    }
+}
+
+fn flush_all(db_manager: &DatabaseManager, buffers: &mut Buffers) {
+    for (ticker_info, buffer) in buffers.trades.iter_mut() {
+        flush_ticker(db_manager, ticker_info, buffer);
+    }
+    buffers.trades.retain(|_, buffer| !buffer.is_empty());
+
+    for (ticker_info, buffer) in buffers.liquidations.drain() {
+        if let Err(e) = db_manager.insert_liquidations(&ticker_info, &buffer) {
+            log::error!(
+                "Failed to persist {} live liquidations for {:?}: {}",
+                buffer.len(),
+                ticker_info.ticker,
+                e
+            );
+        }
+    }
+}
+
+fn flush_ticker(db_manager: &DatabaseManager, ticker_info: &TickerInfo, buffer: &mut Vec<Trade>) {
+    if buffer.is_empty() {
+        return;
+    }
+
+    match db_manager.insert_trades(ticker_info, buffer) {
+        Ok(count) => {
+            log::debug!(
+                "Persisted {} live trades for {:?}",
+                count,
+                ticker_info.ticker
+            );
+        }
+        Err(e) => {
+            log::error!(
+                "Failed to persist {} live trades for {:?}: {}",
+                buffer.len(),
+                ticker_info.ticker,
+                e
+            );
+        }
+    }
+
+    buffer.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exchange::Ticker;
+    use exchange::adapter::Exchange;
+    use exchange::util::Price;
+    use tempfile::tempdir;
+
+    fn create_test_ticker_info() -> TickerInfo {
+        let ticker = Ticker::new("BTCUSDT", Exchange::BybitLinear);
+        TickerInfo::new(ticker, 0.01, 0.001, None)
+    }
+
+    fn create_test_trades(offset: u64, count: usize) -> Vec<Trade> {
+        (0..count)
+            .map(|i| Trade {
+                time: 1000000 + offset + i as u64,
+                price: Price::from_f32(50000.0 + i as f32),
+                qty: 1.0,
+                is_sell: i % 2 == 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flushes_on_drop() {
+        let dir = tempdir().unwrap();
+        let db = Arc::new(DatabaseManager::new(dir.path().join("test.db")).unwrap());
+        let ticker_info = create_test_ticker_info();
+
+        let writer = TradeWriter::with_thresholds(db.clone(), 1_000, Duration::from_secs(60));
+        writer.push(ticker_info, &create_test_trades(0, 10));
+        writer.push(ticker_info, &create_test_trades(100, 10));
+        drop(writer);
+
+        let count = db.query_trades_count(&ticker_info, 0, 2000000).unwrap();
+        assert_eq!(count, 20);
+    }
+
+    #[test]
+    fn test_flushes_liquidations_on_drop() {
+        let dir = tempdir().unwrap();
+        let db = Arc::new(DatabaseManager::new(dir.path().join("test.db")).unwrap());
+        let ticker_info = create_test_ticker_info();
+
+        let liquidations = (0..3)
+            .map(|i| Liquidation {
+                time: 1000000 + i,
+                is_sell: i % 2 == 0,
+                price: Price::from_f32(50000.0),
+                qty: 0.5,
+            })
+            .collect::<Vec<_>>();
+
+        let writer = TradeWriter::with_thresholds(db.clone(), 1_000, Duration::from_secs(60));
+        writer.push_liquidations(ticker_info, &liquidations);
+        drop(writer);
+
+        let stored = db.query_liquidations(&ticker_info, 0, 2000000).unwrap();
+        assert_eq!(stored.len(), 3);
+    }
+
+    #[test]
+    fn test_flushes_on_batch_size() {
+        let dir = tempdir().unwrap();
+        let db = Arc::new(DatabaseManager::new(dir.path().join("test.db")).unwrap());
+        let ticker_info = create_test_ticker_info();
+
+        let writer = TradeWriter::with_thresholds(db.clone(), 5, Duration::from_secs(60));
+        writer.push(ticker_info, &create_test_trades(0, 10));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut count = 0;
+        while Instant::now() < deadline {
+            count = db.query_trades_count(&ticker_info, 0, 2000000).unwrap();
+            if count == 10 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(count, 10);
+    }
+}
@@ -6,6 +6,7 @@ pub mod db;
 pub mod layout;
 pub mod log;
 pub mod panel;
+pub mod replay;
 pub mod tickers_table;
 pub mod util;
 
@@ -131,8 +132,9 @@ fn cleanup_directory(data_path: &PathBuf) -> usize {
         return 0;
     }
 
-    let re =
-        regex::Regex::new(r".*-(\d{4}-\d{2}-\d{2})\.zip$").expect("Cleanup regex pattern is valid");
+    // Binance Vision zips and Bybit csv.gz archives, both named after their day
+    let re = regex::Regex::new(r"(\d{4}-\d{2}-\d{2})\.(zip|csv\.gz)$")
+        .expect("Cleanup regex pattern is valid");
     let today = chrono::Local::now().date_naive();
     let mut deleted_files = Vec::new();
 
@@ -178,68 +180,22 @@ fn cleanup_directory(data_path: &PathBuf) -> usize {
     deleted_files.len()
 }
 
+/// Removes exchange archive files older than 4 days
+///
+/// Database rows are deleted by the retention policy instead, see [`db::retention`].
 pub fn cleanup_old_market_data() -> usize {
-    let paths = ["um", "cm"].map(|market_type| {
-        data_path(Some(&format!(
-            "market_data/binance/data/futures/{}/daily/aggTrades",
-            market_type
-        )))
-    });
-
-    let mut total_deleted: usize = paths.iter().map(cleanup_directory).sum();
-
-    // Also cleanup database if enabled
-    if let Ok(value) = std::env::var("FLOWSURFACE_USE_DUCKDB")
-        && (value == "1" || value.to_lowercase() == "true")
-    {
-        let db_path = data_path(Some("flowsurface.duckdb"));
-
-        if db_path.exists() {
-            match db::DatabaseManager::new(&db_path) {
-                Ok(db_manager) => {
-                    // Delete trades older than 4 days
-                    let cutoff_time = chrono::Local::now()
-                        .date_naive()
-                        .and_hms_opt(0, 0, 0)
-                        .unwrap()
-                        .and_utc()
-                        .timestamp_millis() as u64
-                        - (4 * 24 * 60 * 60 * 1000); // 4 days in milliseconds
-
-                    use db::{TradesCRUD, KlinesCRUD};
-
-                    match db_manager.delete_trades_older_than(cutoff_time) {
-                        Ok(count) => {
-                            info!("Deleted {} trades from database (older than 4 days)", count);
-                            total_deleted += count;
-                        }
-                        Err(e) => {
-                            error!("Failed to delete old trades from database: {}", e);
-                        }
-                    }
-
-                    match db_manager.delete_klines_older_than(cutoff_time) {
-                        Ok(count) => {
-                            info!("Deleted {} klines from database (older than 4 days)", count);
-                            total_deleted += count;
-                        }
-                        Err(e) => {
-                            error!("Failed to delete old klines from database: {}", e);
-                        }
-                    }
+    let mut paths = ["um", "cm"]
+        .map(|market_type| {
+            data_path(Some(&format!(
+                "market_data/binance/data/futures/{}/daily/aggTrades",
+                market_type
+            )))
+        })
+        .to_vec();
+    paths.push(data_path(Some("market_data/bybit/trading")));
 
-                    // Run vacuum to reclaim disk space
-                    if let Err(e) = db_manager.vacuum() {
-                        error!("Failed to vacuum database: {}", e);
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to open database for cleanup: {}", e);
-                }
-            }
-        }
-    }
+    let total_deleted: usize = paths.iter().map(cleanup_directory).sum();
 
-    info!("Cleanup completed. Deleted {} files/records total", total_deleted);
+    info!("Cleanup completed. Deleted {} files total", total_deleted);
     total_deleted
 }
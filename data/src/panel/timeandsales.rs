@@ -36,6 +36,7 @@ pub struct TradeDisplay {
     pub price: Price,
     pub qty: f32,
     pub is_sell: bool,
+    pub is_liquidation: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -100,7 +101,11 @@ pub struct HistAgg {
 }
 
 impl HistAgg {
+    /// Liquidations are left out, their fills already arrive as regular trades
     pub fn add(&mut self, trade: &TradeDisplay) {
+        if trade.is_liquidation {
+            return;
+        }
         let qty = trade.qty as f64;
 
         if trade.is_sell {
@@ -113,6 +118,9 @@ impl HistAgg {
     }
 
     pub fn remove(&mut self, trade: &TradeDisplay) {
+        if trade.is_liquidation {
+            return;
+        }
         let qty = trade.qty as f64;
 
         if trade.is_sell {
@@ -0,0 +1,602 @@
+//! Replays a stored session of a ticker as the events a live stream would emit
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use exchange::adapter::StreamKind;
+use exchange::depth::Depth;
+use exchange::util::{MinTicksize, Price};
+use exchange::{Event, Kline, Liquidation, TickerInfo, Trade};
+
+use crate::chart::heatmap::OrderRun;
+use crate::db::{DatabaseManager, DepthCRUD, LiquidationsCRUD, Result, TradesCRUD};
+
+/// Playback speeds offered to the user, as multiples of the recorded pace
+pub const SPEEDS: [f32; 9] = [0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0];
+
+pub const MIN_SPEED: f32 = 0.25;
+pub const MAX_SPEED: f32 = 100.0;
+
+/// Market time between two depth updates, the pace of most live depth streams
+const FRAME_INTERVAL_MS: u64 = 100;
+
+/// Above this many frames per advance, frames get spread out to keep up at high speeds
+const MAX_FRAMES_PER_ADVANCE: u64 = 20;
+
+#[derive(Debug, Clone)]
+enum BookEvent {
+    Snapshot(Depth),
+    /// A resting order run started at a price level
+    Open {
+        price: Price,
+        is_bid: bool,
+        qty: f32,
+    },
+    /// The run that started at `since` left its price level
+    Close {
+        price: Price,
+        is_bid: bool,
+        since: u64,
+    },
+}
+
+/// Everything stored about a ticker within a time window, in time order
+pub struct Recording {
+    ticker_info: TickerInfo,
+    range: (u64, u64),
+    trades: Vec<Trade>,
+    liquidations: Vec<Liquidation>,
+    book: Vec<(u64, BookEvent)>,
+}
+
+impl std::fmt::Debug for Recording {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recording")
+            .field("ticker", &self.ticker_info.ticker)
+            .field("range", &self.range)
+            .field("trades", &self.trades.len())
+            .field("liquidations", &self.liquidations.len())
+            .field("book", &self.book.len())
+            .finish()
+    }
+}
+
+impl Recording {
+    /// Load the trades, liquidations and order book history of a window
+    ///
+    /// The book is rebuilt from order runs, stored depth snapshots are only
+    /// used when no runs were recorded for the window
+    pub fn load(
+        db_manager: &DatabaseManager,
+        ticker_info: TickerInfo,
+        range: (u64, u64),
+    ) -> Result<Self> {
+        let (start, end) = range;
+
+        let trades = db_manager.query_trades(&ticker_info, start, end)?;
+        let liquidations = db_manager.query_liquidations(&ticker_info, start, end)?;
+
+        let runs = db_manager.query_order_runs_in_window(&ticker_info, start, end)?;
+        let snapshots = if runs.is_empty() {
+            db_manager.query_depth_snapshots_range(&ticker_info, start, end)?
+        } else {
+            vec![]
+        };
+
+        Ok(Self::new(
+            ticker_info,
+            range,
+            trades,
+            liquidations,
+            runs,
+            snapshots,
+        ))
+    }
+
+    pub fn new(
+        ticker_info: TickerInfo,
+        range: (u64, u64),
+        mut trades: Vec<Trade>,
+        mut liquidations: Vec<Liquidation>,
+        runs: Vec<(Price, OrderRun)>,
+        snapshots: Vec<(u64, Depth)>,
+    ) -> Self {
+        trades.sort_by_key(|trade| trade.time);
+        liquidations.sort_by_key(|liquidation| liquidation.time);
+
+        let mut book = Vec::with_capacity(runs.len() * 2 + snapshots.len());
+
+        for (price, run) in runs {
+            book.push((
+                run.start_time,
+                BookEvent::Open {
+                    price,
+                    is_bid: run.is_bid,
+                    qty: run.qty(),
+                },
+            ));
+            book.push((
+                run.until_time,
+                BookEvent::Close {
+                    price,
+                    is_bid: run.is_bid,
+                    since: run.start_time,
+                },
+            ));
+        }
+        book.extend(
+            snapshots
+                .into_iter()
+                .map(|(time, depth)| (time, BookEvent::Snapshot(depth))),
+        );
+
+        // a run ending as the next one starts at the same level must not clear it
+        book.sort_by_key(|(time, event)| (*time, !matches!(event, BookEvent::Close { .. })));
+
+        Self {
+            ticker_info,
+            range,
+            trades,
+            liquidations,
+            book,
+        }
+    }
+
+    pub fn ticker_info(&self) -> TickerInfo {
+        self.ticker_info
+    }
+
+    pub fn range(&self) -> (u64, u64) {
+        self.range
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty() && self.book.is_empty()
+    }
+}
+
+/// Order book at the replay position
+#[derive(Default)]
+struct Book {
+    depth: Depth,
+    /// Start of the run resting at each level
+    runs: BTreeMap<(Price, bool), u64>,
+}
+
+impl Book {
+    fn apply(&mut self, time: u64, event: &BookEvent) {
+        match event {
+            BookEvent::Snapshot(depth) => {
+                self.depth = depth.clone();
+                self.runs.clear();
+            }
+            BookEvent::Open { price, is_bid, qty } => {
+                self.side(*is_bid).insert(*price, *qty);
+                self.runs.insert((*price, *is_bid), time);
+            }
+            BookEvent::Close {
+                price,
+                is_bid,
+                since,
+            } => {
+                if self.runs.get(&(*price, *is_bid)) == Some(since) {
+                    self.side(*is_bid).remove(price);
+                    self.runs.remove(&(*price, *is_bid));
+                }
+            }
+        }
+    }
+
+    fn side(&mut self, is_bid: bool) -> &mut BTreeMap<Price, f32> {
+        if is_bid {
+            &mut self.depth.bids
+        } else {
+            &mut self.depth.asks
+        }
+    }
+}
+
+/// Plays a [`Recording`] back into the given streams
+///
+/// Starts paused at the beginning of the recording
+pub struct Replay {
+    recording: Arc<Recording>,
+    streams: Vec<StreamKind>,
+    position: u64,
+    speed: f32,
+    playing: bool,
+    trade_cursor: usize,
+    liquidation_cursor: usize,
+    book_cursor: usize,
+    book: Book,
+    /// Candle being built for each kline stream
+    candles: Vec<Option<Kline>>,
+}
+
+impl Replay {
+    pub fn new(recording: Arc<Recording>, streams: Vec<StreamKind>) -> Self {
+        let start = recording.range.0;
+
+        let mut replay = Self {
+            candles: vec![None; streams.len()],
+            recording,
+            streams,
+            position: start,
+            speed: 1.0,
+            playing: false,
+            trade_cursor: 0,
+            liquidation_cursor: 0,
+            book_cursor: 0,
+            book: Book::default(),
+        };
+        replay.seek(start);
+        replay
+    }
+
+    pub fn ticker_info(&self) -> TickerInfo {
+        self.recording.ticker_info
+    }
+
+    pub fn streams(&self) -> &[StreamKind] {
+        &self.streams
+    }
+
+    pub fn range(&self) -> (u64, u64) {
+        self.recording.range
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.recording.range.1
+    }
+
+    pub fn play(&mut self) {
+        if !self.is_finished() {
+            self.playing = true;
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// Jump to a point of the recording, rebuilding the book and candles as they were then
+    pub fn seek(&mut self, time: u64) {
+        let recording = Arc::clone(&self.recording);
+        let (start, end) = recording.range;
+        let time = time.clamp(start, end);
+
+        self.book = Book::default();
+        self.book_cursor = 0;
+        self.apply_book_until(time);
+
+        self.trade_cursor = recording.trades.partition_point(|trade| trade.time <= time);
+        self.liquidation_cursor = recording
+            .liquidations
+            .partition_point(|liquidation| liquidation.time <= time);
+
+        let ticksize = recording.ticker_info.min_ticksize;
+
+        for (stream, candle) in self.streams.iter().zip(self.candles.iter_mut()) {
+            *candle = None;
+
+            if let StreamKind::Kline { timeframe, .. } = stream {
+                let interval = timeframe.to_milliseconds();
+                let bucket = time - time % interval;
+
+                let from = recording
+                    .trades
+                    .partition_point(|trade| trade.time < bucket);
+                for trade in &recording.trades[from..self.trade_cursor] {
+                    update_candle(candle, bucket, trade);
+                }
+                if let Some(kline) = candle {
+                    round_candle(kline, ticksize);
+                }
+            }
+        }
+
+        self.position = time;
+    }
+
+    /// Move the replay forward by `elapsed` wall time, scaled by its speed
+    ///
+    /// Returns the events a live stream would have emitted meanwhile
+    pub fn advance(&mut self, elapsed: Duration) -> Vec<Event> {
+        if !self.playing {
+            return vec![];
+        }
+
+        let end = self.recording.range.1;
+        let market_elapsed = (elapsed.as_millis() as f64 * f64::from(self.speed)) as u64;
+        let target = self.position.saturating_add(market_elapsed).min(end);
+
+        let step = FRAME_INTERVAL_MS.max((target - self.position) / MAX_FRAMES_PER_ADVANCE);
+
+        let mut events = vec![];
+
+        while self.position < target {
+            let time = (self.position + step).min(target);
+            self.emit_frame(time, &mut events);
+        }
+
+        if self.is_finished() {
+            self.playing = false;
+        }
+
+        events
+    }
+
+    fn emit_frame(&mut self, time: u64, events: &mut Vec<Event>) {
+        let recording = Arc::clone(&self.recording);
+
+        self.apply_book_until(time);
+
+        let from = self.trade_cursor;
+        self.trade_cursor += recording.trades[from..].partition_point(|trade| trade.time <= time);
+        let trades = &recording.trades[from..self.trade_cursor];
+
+        let ticksize = recording.ticker_info.min_ticksize;
+
+        for (stream, candle) in self.streams.iter().zip(self.candles.iter_mut()) {
+            match stream {
+                StreamKind::DepthAndTrades { .. } => {
+                    events.push(Event::DepthReceived(
+                        *stream,
+                        time,
+                        self.book.depth.clone(),
+                        trades.into(),
+                    ));
+                }
+                StreamKind::Kline { timeframe, .. } => {
+                    let interval = timeframe.to_milliseconds();
+
+                    for trade in trades {
+                        update_candle(candle, trade.time - trade.time % interval, trade);
+                    }
+
+                    if !trades.is_empty()
+                        && let Some(kline) = candle
+                    {
+                        round_candle(kline, ticksize);
+                        events.push(Event::KlineReceived(*stream, *kline));
+                    }
+                }
+                // emitted below for every stream of the ticker
+                StreamKind::Liquidations { .. } => {}
+            }
+        }
+
+        // panes of the ticker all get marked from a single event, like with a live stream
+        if let Some(stream) = self.streams.first() {
+            let from = self.liquidation_cursor;
+            self.liquidation_cursor += recording.liquidations[from..]
+                .partition_point(|liquidation| liquidation.time <= time);
+
+            events.extend(
+                recording.liquidations[from..self.liquidation_cursor]
+                    .iter()
+                    .map(|liquidation| Event::LiquidationReceived(*stream, *liquidation)),
+            );
+        }
+
+        self.position = time;
+    }
+
+    fn apply_book_until(&mut self, time: u64) {
+        let book = &self.recording.book;
+
+        while let Some((event_time, event)) = book.get(self.book_cursor) {
+            if *event_time > time {
+                break;
+            }
+            self.book.apply(*event_time, event);
+            self.book_cursor += 1;
+        }
+    }
+}
+
+fn update_candle(candle: &mut Option<Kline>, bucket: u64, trade: &Trade) {
+    let (buy, sell) = if trade.is_sell {
+        (0.0, trade.qty)
+    } else {
+        (trade.qty, 0.0)
+    };
+
+    match candle {
+        Some(kline) if kline.time == bucket => {
+            kline.high = kline.high.max(trade.price);
+            kline.low = kline.low.min(trade.price);
+            kline.close = trade.price;
+            kline.volume.0 += buy;
+            kline.volume.1 += sell;
+        }
+        _ => {
+            *candle = Some(Kline {
+                time: bucket,
+                open: trade.price,
+                high: trade.price,
+                low: trade.price,
+                close: trade.price,
+                volume: (buy, sell),
+            });
+        }
+    }
+}
+
+fn round_candle(kline: &mut Kline, ticksize: MinTicksize) {
+    kline.open = kline.open.round_to_min_tick(ticksize);
+    kline.high = kline.high.round_to_min_tick(ticksize);
+    kline.low = kline.low.round_to_min_tick(ticksize);
+    kline.close = kline.close.round_to_min_tick(ticksize);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exchange::adapter::{Exchange, StreamTicksize};
+    use exchange::{PushFrequency, Ticker, Timeframe};
+
+    fn ticker_info() -> TickerInfo {
+        TickerInfo::new(
+            Ticker::new("BTCUSDT", Exchange::BinanceLinear),
+            0.1,
+            0.001,
+            None,
+        )
+    }
+
+    fn trade(time: u64, price: f32, qty: f32, is_sell: bool) -> Trade {
+        Trade {
+            time,
+            is_sell,
+            price: Price::from_f32(price),
+            qty,
+        }
+    }
+
+    fn streams() -> Vec<StreamKind> {
+        vec![
+            StreamKind::DepthAndTrades {
+                ticker_info: ticker_info(),
+                depth_aggr: StreamTicksize::Client,
+                push_freq: PushFrequency::ServerDefault,
+            },
+            StreamKind::Kline {
+                ticker_info: ticker_info(),
+                timeframe: Timeframe::M1,
+            },
+        ]
+    }
+
+    fn recording() -> Arc<Recording> {
+        let trades = vec![
+            trade(60_050, 100.0, 1.0, false),
+            trade(60_250, 101.0, 2.0, true),
+            trade(61_000, 99.0, 0.5, true),
+        ];
+        let liquidations = vec![Liquidation {
+            time: 60_300,
+            is_sell: true,
+            price: Price::from_f32(100.5),
+            qty: 3.0,
+        }];
+        let runs = vec![
+            (
+                Price::from_f32(99.5),
+                OrderRun::with_until_time(59_000, 60_500, 4.0, true),
+            ),
+            (
+                Price::from_f32(99.5),
+                OrderRun::with_until_time(60_500, 62_000, 6.0, true),
+            ),
+        ];
+
+        Arc::new(Recording::new(
+            ticker_info(),
+            (60_000, 62_000),
+            trades,
+            liquidations,
+            runs,
+            vec![],
+        ))
+    }
+
+    #[test]
+    fn emits_trades_once_in_order() {
+        let mut replay = Replay::new(recording(), streams());
+        replay.play();
+
+        let mut received = vec![];
+        while !replay.is_finished() {
+            for event in replay.advance(Duration::from_millis(100)) {
+                if let Event::DepthReceived(_, _, _, trades) = event {
+                    received.extend(trades.iter().map(|trade| trade.time));
+                }
+            }
+        }
+
+        assert_eq!(received, vec![60_050, 60_250, 61_000]);
+        assert!(!replay.is_playing());
+    }
+
+    #[test]
+    fn speed_scales_market_time() {
+        let mut replay = Replay::new(recording(), streams());
+        replay.set_speed(5.0);
+        replay.play();
+        replay.advance(Duration::from_millis(100));
+        assert_eq!(replay.position(), 60_500);
+
+        replay.set_speed(1_000.0);
+        assert_eq!(replay.speed(), MAX_SPEED);
+
+        replay.pause();
+        assert!(replay.advance(Duration::from_secs(1)).is_empty());
+        assert_eq!(replay.position(), 60_500);
+    }
+
+    #[test]
+    fn seek_rebuilds_book_and_candle() {
+        let mut replay = Replay::new(recording(), streams());
+
+        replay.seek(60_400);
+        assert_eq!(
+            replay.book.depth.bids.get(&Price::from_f32(99.5)),
+            Some(&4.0)
+        );
+
+        // the next run at the level must survive the previous one closing
+        replay.seek(61_000);
+        assert_eq!(
+            replay.book.depth.bids.get(&Price::from_f32(99.5)),
+            Some(&6.0)
+        );
+
+        let candle = replay.candles[1].expect("candle of the current minute");
+        assert_eq!(candle.time, 60_000);
+        assert_eq!(candle.high, Price::from_f32(101.0));
+        assert_eq!(candle.close, Price::from_f32(99.0));
+        assert_eq!(candle.volume, (1.0, 2.5));
+
+        replay.play();
+        let events = replay.advance(Duration::from_secs(10));
+        assert!(
+            events
+                .iter()
+                .all(|event| !matches!(event, Event::LiquidationReceived(..)))
+        );
+    }
+
+    #[test]
+    fn emits_liquidations_once() {
+        let mut replay = Replay::new(recording(), streams());
+        replay.set_speed(MAX_SPEED);
+        replay.play();
+
+        let events = replay.advance(Duration::from_secs(1));
+        let liquidations = events
+            .iter()
+            .filter(|event| matches!(event, Event::LiquidationReceived(..)))
+            .count();
+
+        assert_eq!(liquidations, 1);
+        assert!(replay.is_finished());
+    }
+}
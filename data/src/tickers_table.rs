@@ -11,6 +11,13 @@ pub struct Settings {
     pub selected_sort_option: SortOptions,
     pub selected_exchanges: Vec<ExchangeInclusive>,
     pub selected_markets: Vec<MarketKind>,
+    /// Dated futures are filtered on their own, the market filters only cover perpetuals
+    #[serde(default = "default_show_dated_futures")]
+    pub show_dated_futures: bool,
+}
+
+fn default_show_dated_futures() -> bool {
+    true
 }
 
 impl Default for Settings {
@@ -21,6 +28,7 @@ impl Default for Settings {
             selected_sort_option: SortOptions::VolumeDesc,
             selected_exchanges: ExchangeInclusive::ALL.to_vec(),
             selected_markets: MarketKind::ALL.into_iter().collect(),
+            show_dated_futures: default_show_dated_futures(),
         }
     }
 }
@@ -74,7 +82,11 @@ pub fn compute_display_data(
         ticker_str + {
             match market {
                 MarketKind::Spot => "",
-                MarketKind::LinearPerps | MarketKind::InversePerps => "P",
+                MarketKind::LinearPerps | MarketKind::InversePerps => match ticker.expiry() {
+                    // dated futures carry their expiry in the symbol instead
+                    Some(_) => "",
+                    None => "P",
+                },
             }
         }
     };
@@ -2,7 +2,7 @@
 
 use data::db::{
     ArchiveMigrator, BackupManager, DatabaseManager, DepthMigrator, HealthCheckStatus,
-    MigrationConfig, MigrationGuard, TimeSeriesMigrator,
+    MigrationConfig, MigrationGuard, TimeSeriesMigrator, TradesCRUD,
 };
 use exchange::adapter::Exchange;
 use exchange::util::Price;
@@ -172,6 +172,47 @@ fn test_archive_migrator_with_zip_file() {
     .unwrap();
 }
 
+#[test]
+fn test_archive_migrator_keeps_trade_sides() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let zip_path = temp_dir.path().join("BTCUSDT-aggTrades-2024-01-15.zip");
+
+    let file = fs::File::create(&zip_path).unwrap();
+    let mut zip = ZipWriter::new(file);
+    zip.start_file("BTCUSDT-aggTrades-2024-01-15.csv", zip::write::SimpleFileOptions::default())
+        .unwrap();
+
+    // a buyer maker is the passive side of a sell
+    let csv_data = "1,50000.0,0.5,1,1,1705334400000,true\n\
+                    2,50001.0,2.0,2,2,1705334401000,false\n";
+    zip.write_all(csv_data.as_bytes()).unwrap();
+    zip.finish().unwrap();
+
+    let db = DatabaseManager::new(&db_path).unwrap();
+    let migrator = ArchiveMigrator::new(MigrationConfig::default());
+    let ticker_info = migrator.parse_archive_path(&zip_path).unwrap().0;
+
+    db.with_conn(|conn| {
+        migrator.migrate_single_archive(conn, &zip_path).unwrap();
+        Ok(())
+    })
+    .unwrap();
+
+    let aggregated = db
+        .query_trades_aggregated(&ticker_info, 1705334400000, 1705334401000)
+        .unwrap();
+
+    // (price, buy_qty, sell_qty, buy_count, sell_count)
+    assert_eq!(aggregated.len(), 2);
+    assert_eq!(aggregated[0].0, Price::from_f32(50000.0));
+    assert_eq!((aggregated[0].1, aggregated[0].2), (0.0, 0.5));
+    assert_eq!((aggregated[0].3, aggregated[0].4), (0, 1));
+    assert_eq!(aggregated[1].0, Price::from_f32(50001.0));
+    assert_eq!((aggregated[1].1, aggregated[1].2), (2.0, 0.0));
+    assert_eq!((aggregated[1].3, aggregated[1].4), (1, 0));
+}
+
 #[test]
 fn test_timeseries_migrator() {
     let temp_dir = TempDir::new().unwrap();
@@ -1,19 +1,30 @@
 use super::{Ticker, Timeframe};
 use crate::{
-    Kline, OpenInterest, Price, PushFrequency, TickMultiplier, TickerInfo, TickerStats, Trade,
-    depth::Depth,
+    FundingRate, Kline, Liquidation, OpenInterest, Price, PushFrequency, TickMultiplier,
+    TickerInfo, TickerStats, Trade, depth::Depth,
 };
 
 use enum_map::{Enum, EnumMap};
 use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 pub mod aster;
 pub mod binance;
 pub mod bybit;
+pub mod deribit;
 pub mod hyperliquid;
+pub mod integrity;
+pub mod mux;
 pub mod okex;
+pub mod registry;
+pub mod telemetry;
+
+pub use registry::{ExchangeAdapter, adapter_for, adapters};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ResolvedStream {
@@ -87,6 +98,9 @@ impl ResolvedStream {
                         };
                         PersistStreamKind::Kline(persist_kline)
                     }
+                    StreamKind::Liquidations { ticker_info } => {
+                        PersistStreamKind::Liquidations(ticker_info.ticker)
+                    }
                 })
                 .collect(),
         }
@@ -122,8 +136,17 @@ pub enum AdapterError {
     WebsocketError(String),
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
+    #[error("Rate limited by the exchange{}", retry_hint(.retry_after))]
+    RateLimited { retry_after: Option<Duration> },
 }
 
+fn retry_hint(retry_after: &Option<Duration>) -> String {
+    retry_after.map_or(String::new(), |wait| {
+        format!(", retry after {}s", wait.as_secs())
+    })
+}
+
+/// How a market's contracts settle, dated futures are told apart by [`Ticker::expiry`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum MarketKind {
     Spot,
@@ -178,13 +201,16 @@ pub enum StreamKind {
         depth_aggr: StreamTicksize,
         push_freq: PushFrequency,
     },
+    /// Liquidations alone, for a ticker whose panes need no depth stream
+    Liquidations { ticker_info: TickerInfo },
 }
 
 impl StreamKind {
     pub fn ticker_info(&self) -> TickerInfo {
         match self {
             StreamKind::Kline { ticker_info, .. }
-            | StreamKind::DepthAndTrades { ticker_info, .. } => *ticker_info,
+            | StreamKind::DepthAndTrades { ticker_info, .. }
+            | StreamKind::Liquidations { ticker_info } => *ticker_info,
         }
     }
 
@@ -208,6 +234,13 @@ impl StreamKind {
             _ => None,
         }
     }
+
+    pub fn as_liquidation_stream(&self) -> Option<TickerInfo> {
+        match self {
+            StreamKind::Liquidations { ticker_info } => Some(*ticker_info),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -228,9 +261,8 @@ impl UniqueStreams {
     pub fn add(&mut self, stream: StreamKind) {
         let (exchange, ticker_info) = match stream {
             StreamKind::Kline { ticker_info, .. }
-            | StreamKind::DepthAndTrades { ticker_info, .. } => {
-                (ticker_info.exchange(), ticker_info)
-            }
+            | StreamKind::DepthAndTrades { ticker_info, .. }
+            | StreamKind::Liquidations { ticker_info } => (ticker_info.exchange(), ticker_info),
         };
 
         self.streams[exchange]
@@ -251,10 +283,12 @@ impl UniqueStreams {
     fn update_specs_for_exchange(&mut self, exchange: Exchange) {
         let depth_streams = self.depth_streams(Some(exchange));
         let kline_streams = self.kline_streams(Some(exchange));
+        let liquidation_streams = self.liquidation_streams(Some(exchange));
 
         self.specs[exchange] = Some(StreamSpecs {
             depth: depth_streams,
             kline: kline_streams,
+            liquidations: liquidation_streams,
         });
     }
 
@@ -289,6 +323,10 @@ impl UniqueStreams {
         self.streams(exchange_filter, |_, stream| stream.as_kline_stream())
     }
 
+    pub fn liquidation_streams(&self, exchange_filter: Option<Exchange>) -> Vec<TickerInfo> {
+        self.streams(exchange_filter, |_, stream| stream.as_liquidation_stream())
+    }
+
     pub fn combined_used(&self) -> impl Iterator<Item = (Exchange, &StreamSpecs)> {
         self.specs
             .iter()
@@ -304,6 +342,7 @@ impl UniqueStreams {
 pub enum PersistStreamKind {
     Kline(PersistKline),
     DepthAndTrades(PersistDepth),
+    Liquidations(Ticker),
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -340,6 +379,9 @@ impl From<StreamKind> for PersistStreamKind {
                 depth_aggr,
                 push_freq,
             }),
+            StreamKind::Liquidations { ticker_info } => {
+                PersistStreamKind::Liquidations(ticker_info.ticker)
+            }
         }
     }
 }
@@ -365,6 +407,9 @@ impl PersistStreamKind {
                     push_freq: d.push_freq,
                 })
                 .ok_or_else(|| format!("TickerInfo not found for {}", d.ticker)),
+            PersistStreamKind::Liquidations(ticker) => resolver(&ticker)
+                .map(|ti| StreamKind::Liquidations { ticker_info: ti })
+                .ok_or_else(|| format!("TickerInfo not found for {ticker}")),
         }
     }
 }
@@ -388,6 +433,7 @@ fn default_push_freq() -> PushFrequency {
 pub struct StreamSpecs {
     pub depth: Vec<(TickerInfo, StreamTicksize, PushFrequency)>,
     pub kline: Vec<(TickerInfo, Timeframe)>,
+    pub liquidations: Vec<TickerInfo>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -395,15 +441,17 @@ pub enum ExchangeInclusive {
     Aster,
     Bybit,
     Binance,
+    Deribit,
     Hyperliquid,
     Okex,
 }
 
 impl ExchangeInclusive {
-    pub const ALL: [ExchangeInclusive; 5] = [
+    pub const ALL: [ExchangeInclusive; 6] = [
         ExchangeInclusive::Aster,
         ExchangeInclusive::Bybit,
         ExchangeInclusive::Binance,
+        ExchangeInclusive::Deribit,
         ExchangeInclusive::Hyperliquid,
         ExchangeInclusive::Okex,
     ];
@@ -415,12 +463,19 @@ impl ExchangeInclusive {
             Exchange::BinanceLinear | Exchange::BinanceInverse | Exchange::BinanceSpot => {
                 Self::Binance
             }
+            Exchange::DeribitLinear | Exchange::DeribitInverse => Self::Deribit,
             Exchange::HyperliquidLinear | Exchange::HyperliquidSpot => Self::Hyperliquid,
             Exchange::OkexLinear | Exchange::OkexInverse | Exchange::OkexSpot => Self::Okex,
         }
     }
 }
 
+impl std::fmt::Display for ExchangeInclusive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", adapter_for(*self).name())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Enum)]
 pub enum Exchange {
     AsterLinear,
@@ -430,6 +485,8 @@ pub enum Exchange {
     BybitLinear,
     BybitInverse,
     BybitSpot,
+    DeribitLinear,
+    DeribitInverse,
     HyperliquidLinear,
     HyperliquidSpot,
     OkexLinear,
@@ -450,6 +507,8 @@ impl std::fmt::Display for Exchange {
                 Exchange::BybitLinear => "Bybit Linear",
                 Exchange::BybitInverse => "Bybit Inverse",
                 Exchange::BybitSpot => "Bybit Spot",
+                Exchange::DeribitLinear => "Deribit Linear",
+                Exchange::DeribitInverse => "Deribit Inverse",
                 Exchange::HyperliquidLinear => "Hyperliquid Linear",
                 Exchange::HyperliquidSpot => "Hyperliquid Spot",
                 Exchange::OkexLinear => "Okex Linear",
@@ -472,6 +531,8 @@ impl FromStr for Exchange {
             "Bybit Linear" => Ok(Exchange::BybitLinear),
             "Bybit Inverse" => Ok(Exchange::BybitInverse),
             "Bybit Spot" => Ok(Exchange::BybitSpot),
+            "Deribit Linear" => Ok(Exchange::DeribitLinear),
+            "Deribit Inverse" => Ok(Exchange::DeribitInverse),
             "Hyperliquid Linear" => Ok(Exchange::HyperliquidLinear),
             "Hyperliquid Spot" => Ok(Exchange::HyperliquidSpot),
             "Okex Linear" => Ok(Exchange::OkexLinear),
@@ -483,7 +544,7 @@ impl FromStr for Exchange {
 }
 
 impl Exchange {
-    pub const ALL: [Exchange; 12] = [
+    pub const ALL: [Exchange; 14] = [
         Exchange::AsterLinear,
         Exchange::BinanceLinear,
         Exchange::BinanceInverse,
@@ -491,6 +552,8 @@ impl Exchange {
         Exchange::BybitLinear,
         Exchange::BybitInverse,
         Exchange::BybitSpot,
+        Exchange::DeribitLinear,
+        Exchange::DeribitInverse,
         Exchange::HyperliquidLinear,
         Exchange::HyperliquidSpot,
         Exchange::OkexLinear,
@@ -503,11 +566,13 @@ impl Exchange {
             Exchange::AsterLinear
             | Exchange::BinanceLinear
             | Exchange::BybitLinear
+            | Exchange::DeribitLinear
             | Exchange::HyperliquidLinear
             | Exchange::OkexLinear => MarketKind::LinearPerps,
-            Exchange::BinanceInverse | Exchange::BybitInverse | Exchange::OkexInverse => {
-                MarketKind::InversePerps
-            }
+            Exchange::BinanceInverse
+            | Exchange::BybitInverse
+            | Exchange::DeribitInverse
+            | Exchange::OkexInverse => MarketKind::InversePerps,
             Exchange::BinanceSpot
             | Exchange::BybitSpot
             | Exchange::HyperliquidSpot
@@ -515,52 +580,27 @@ impl Exchange {
         }
     }
 
+    pub fn adapter(&self) -> &'static dyn ExchangeAdapter {
+        adapter_for(ExchangeInclusive::of(*self))
+    }
+
     pub fn is_depth_client_aggr(&self) -> bool {
-        matches!(
-            self,
-            Exchange::AsterLinear
-                | Exchange::BinanceLinear
-                | Exchange::BinanceInverse
-                | Exchange::BinanceSpot
-                | Exchange::BybitLinear
-                | Exchange::BybitInverse
-                | Exchange::BybitSpot
-                | Exchange::OkexLinear
-                | Exchange::OkexInverse
-                | Exchange::OkexSpot
-        )
+        self.adapter().is_depth_client_aggr()
     }
 
     pub fn is_custom_push_freq(&self) -> bool {
-        matches!(
-            self,
-            Exchange::BybitLinear | Exchange::BybitInverse | Exchange::BybitSpot
-        )
+        self.allowed_push_freqs()
+            .iter()
+            .any(|freq| matches!(freq, PushFrequency::Custom(_)))
     }
 
-    pub fn allowed_push_freqs(&self) -> &[PushFrequency] {
-        match self {
-            Exchange::BybitLinear | Exchange::BybitInverse => &[
-                PushFrequency::Custom(Timeframe::MS100),
-                PushFrequency::Custom(Timeframe::MS300),
-            ],
-            Exchange::BybitSpot => &[
-                PushFrequency::Custom(Timeframe::MS200),
-                PushFrequency::Custom(Timeframe::MS300),
-            ],
-            _ => &[PushFrequency::ServerDefault],
-        }
+    pub fn allowed_push_freqs(&self) -> &'static [PushFrequency] {
+        self.adapter().allowed_push_freqs(self.market_type())
     }
 
     pub fn supports_heatmap_timeframe(&self, tf: Timeframe) -> bool {
-        match self {
-            Exchange::BybitSpot => tf != Timeframe::MS100,
-            Exchange::BybitLinear | Exchange::BybitInverse => tf != Timeframe::MS200,
-            Exchange::HyperliquidLinear | Exchange::HyperliquidSpot => {
-                tf != Timeframe::MS100 && tf != Timeframe::MS200 && tf != Timeframe::MS300
-            }
-            _ => true,
-        }
+        self.adapter()
+            .supports_heatmap_timeframe(self.market_type(), tf)
     }
 
     pub fn is_perps(&self) -> bool {
@@ -571,11 +611,31 @@ impl Exchange {
                 | Exchange::BinanceInverse
                 | Exchange::BybitLinear
                 | Exchange::BybitInverse
+                | Exchange::DeribitLinear
+                | Exchange::DeribitInverse
                 | Exchange::HyperliquidLinear
                 | Exchange::OkexLinear
                 | Exchange::OkexInverse
         )
     }
+
+    pub fn supports_open_interest(&self) -> bool {
+        self.adapter().supports_open_interest(self.market_type())
+    }
+
+    pub fn supports_funding_rates(&self) -> bool {
+        self.adapter().supports_funding_rates(self.market_type())
+    }
+
+    /// Whether historical trades can be backfilled from the exchange
+    pub fn supports_trade_backfill(&self) -> bool {
+        self.adapter().supports_trade_backfill(self.market_type())
+    }
+
+    /// Whether the market stream also carries public liquidation orders
+    pub fn supports_liquidations(&self) -> bool {
+        self.adapter().supports_liquidations(self.market_type())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -584,6 +644,23 @@ pub enum Event {
     Disconnected(Exchange, String),
     DepthReceived(StreamKind, u64, Depth, Box<[Trade]>),
     KlineReceived(StreamKind, Kline),
+    LiquidationReceived(StreamKind, Liquidation),
+    /// Connection of a stream changed, see [`ConnectionState`]
+    StateChanged(StreamKind, ConnectionState),
+}
+
+/// Where a stream is at in its connection lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    /// Data is flowing and in sync
+    Live,
+    /// Connected, but the local book is being rebuilt from a snapshot
+    Resyncing,
+    /// Waiting to reconnect after a failed attempt
+    Backoff {
+        until: Instant,
+    },
 }
 
 #[derive(Debug, Clone, Hash)]
@@ -614,45 +691,19 @@ impl<I> StreamConfig<I> {
 pub async fn fetch_ticker_info(
     exchange: Exchange,
 ) -> Result<HashMap<Ticker, Option<TickerInfo>>, AdapterError> {
-    let market_type = exchange.market_type();
-
-    match exchange {
-        Exchange::AsterLinear => aster::fetch_ticksize(market_type).await,
-        Exchange::BinanceLinear | Exchange::BinanceInverse | Exchange::BinanceSpot => {
-            binance::fetch_ticksize(market_type).await
-        }
-        Exchange::BybitLinear | Exchange::BybitInverse | Exchange::BybitSpot => {
-            bybit::fetch_ticksize(market_type).await
-        }
-        Exchange::HyperliquidLinear | Exchange::HyperliquidSpot => {
-            hyperliquid::fetch_ticksize(market_type).await
-        }
-        Exchange::OkexLinear | Exchange::OkexInverse | Exchange::OkexSpot => {
-            okex::fetch_ticksize(market_type).await
-        }
-    }
+    exchange
+        .adapter()
+        .fetch_ticker_info(exchange.market_type())
+        .await
 }
 
 pub async fn fetch_ticker_prices(
     exchange: Exchange,
 ) -> Result<HashMap<Ticker, TickerStats>, AdapterError> {
-    let market_type = exchange.market_type();
-
-    match exchange {
-        Exchange::AsterLinear => aster::fetch_ticker_prices(market_type).await,
-        Exchange::BinanceLinear | Exchange::BinanceInverse | Exchange::BinanceSpot => {
-            binance::fetch_ticker_prices(market_type).await
-        }
-        Exchange::BybitLinear | Exchange::BybitInverse | Exchange::BybitSpot => {
-            bybit::fetch_ticker_prices(market_type).await
-        }
-        Exchange::HyperliquidLinear | Exchange::HyperliquidSpot => {
-            hyperliquid::fetch_ticker_prices(market_type).await
-        }
-        Exchange::OkexLinear | Exchange::OkexInverse | Exchange::OkexSpot => {
-            okex::fetch_ticker_prices(market_type).await
-        }
-    }
+    exchange
+        .adapter()
+        .fetch_ticker_prices(exchange.market_type())
+        .await
 }
 
 pub async fn fetch_klines(
@@ -660,21 +711,11 @@ pub async fn fetch_klines(
     timeframe: Timeframe,
     range: Option<(u64, u64)>,
 ) -> Result<Vec<Kline>, AdapterError> {
-    match ticker_info.ticker.exchange {
-        Exchange::AsterLinear => aster::fetch_klines(ticker_info, timeframe, range).await,
-        Exchange::BinanceLinear | Exchange::BinanceInverse | Exchange::BinanceSpot => {
-            binance::fetch_klines(ticker_info, timeframe, range).await
-        }
-        Exchange::BybitLinear | Exchange::BybitInverse | Exchange::BybitSpot => {
-            bybit::fetch_klines(ticker_info, timeframe, range).await
-        }
-        Exchange::HyperliquidLinear | Exchange::HyperliquidSpot => {
-            hyperliquid::fetch_klines(ticker_info, timeframe, range).await
-        }
-        Exchange::OkexLinear | Exchange::OkexInverse | Exchange::OkexSpot => {
-            okex::fetch_klines(ticker_info, timeframe, range).await
-        }
-    }
+    ticker_info
+        .exchange()
+        .adapter()
+        .fetch_klines(ticker_info, timeframe, range)
+        .await
 }
 
 pub async fn fetch_open_interest(
@@ -682,19 +723,48 @@ pub async fn fetch_open_interest(
     timeframe: Timeframe,
     range: Option<(u64, u64)>,
 ) -> Result<Vec<OpenInterest>, AdapterError> {
-    match ticker.exchange {
-        Exchange::AsterLinear => {
-            aster::fetch_historical_oi(ticker, range, timeframe).await
-        }
-        Exchange::BinanceLinear | Exchange::BinanceInverse => {
-            binance::fetch_historical_oi(ticker, range, timeframe).await
-        }
-        Exchange::BybitLinear | Exchange::BybitInverse => {
-            bybit::fetch_historical_oi(ticker, range, timeframe).await
-        }
-        Exchange::OkexLinear | Exchange::OkexInverse => {
-            okex::fetch_historical_oi(ticker, range, timeframe).await
-        }
-        _ => Err(AdapterError::InvalidRequest("Open interest not available for this exchange or market type".to_string())),
+    if !ticker.exchange.supports_open_interest() {
+        return Err(AdapterError::InvalidRequest(
+            "Open interest not available for this exchange or market type".to_string(),
+        ));
+    }
+
+    ticker
+        .exchange
+        .adapter()
+        .fetch_open_interest(ticker, timeframe, range)
+        .await
+}
+
+/// Settled funding rates within `range`, or the most recent ones when `None`
+pub async fn fetch_funding_rates(
+    ticker: Ticker,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<FundingRate>, AdapterError> {
+    if !ticker.supports_funding_rates() {
+        return Err(AdapterError::InvalidRequest(
+            "Funding rates are only available for perpetuals".to_string(),
+        ));
     }
+
+    ticker
+        .exchange
+        .adapter()
+        .fetch_funding_rates(ticker, range)
+        .await
+}
+
+/// Predicted rate for the upcoming funding settlement
+pub async fn fetch_predicted_funding(ticker: Ticker) -> Result<FundingRate, AdapterError> {
+    if !ticker.supports_funding_rates() {
+        return Err(AdapterError::InvalidRequest(
+            "Funding rates are only available for perpetuals".to_string(),
+        ));
+    }
+
+    ticker
+        .exchange
+        .adapter()
+        .fetch_predicted_funding(ticker)
+        .await
 }
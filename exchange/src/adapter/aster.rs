@@ -11,6 +11,8 @@
 //! - ✅ Real-time trades stream
 //! - ✅ Historical klines (candlestick data)
 //! - ✅ Open interest data (perpetuals only)
+//! - ✅ Funding rates, settled and predicted (perpetuals only)
+//! - ✅ Trade backfill over REST aggregate trades (perpetuals only)
 //! - ❌ Inverse perpetuals (not supported by Aster)
 //! - ❌ Server-side depth aggregation (requires client-side via LocalDepthCache)
 //! - ❌ Custom push frequencies (only ServerDefault supported)
@@ -29,39 +31,49 @@
 //! - REST: `https://fapi.asterdex.com`
 //! - WebSocket: `wss://fstream.asterdex.com`
 
-use crate::{Price, PushFrequency};
+use crate::{FundingRate, OpenInterest, Price, PushFrequency, TickMultiplier, endpoint};
 
 use super::{
     super::{
         Exchange, Kline, MarketKind, SIZE_IN_QUOTE_CURRENCY, StreamKind, Ticker, TickerInfo,
         TickerStats, Timeframe, Trade,
-        connect::{State, connect_ws},
+        capture::{self, FrameKind},
+        connect::{self, Backoff, State, connect_ws},
         de_string_to_f32, de_string_to_u64,
         depth::{DeOrder, DepthPayload, DepthUpdate, LocalDepthCache},
         limiter::{self, RateLimiter},
     },
-    AdapterError, Event,
+    AdapterError, ConnectionState, Event, ExchangeInclusive, integrity, mux,
+    registry::{AdapterFuture, EventStream, ExchangeAdapter},
+    telemetry,
 };
 
-use fastwebsockets::{FragmentCollector, Frame, OpCode};
+use fastwebsockets::{FragmentCollector, Frame, OpCode, WebSocketError};
 use hyper::upgrade::Upgraded;
 use hyper_util::rt::TokioIo;
 use iced_futures::{
-    futures::{SinkExt, Stream},
+    futures::{SinkExt, Stream, channel::mpsc},
     stream,
 };
+use rustc_hash::FxHashSet;
 use serde::Deserialize;
 use serde_json::Value;
 
-use std::{sync::LazyLock, time::Duration};
-use std::collections::HashMap;
-use tokio::sync::Mutex;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    pin::Pin,
+    sync::LazyLock,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::sync::{Mutex, oneshot};
 
 /// REST API base URL for Aster DEX
 const API_DOMAIN: &str = "https://fapi.asterdex.com";
 
-/// WebSocket base domain for Aster DEX
-const WS_DOMAIN: &str = "fstream.asterdex.com";
+/// WebSocket base URL for Aster DEX
+const WS_DOMAIN: &str = "wss://fstream.asterdex.com";
 
 /// Rate limit: 2400 weight per minute
 const LIMIT: usize = 2400;
@@ -72,6 +84,12 @@ const REFILL_RATE: Duration = Duration::from_secs(60);
 /// Safety buffer percentage for rate limiter (5%)
 const LIMITER_BUFFER_PCT: f32 = 0.05;
 
+/// Widest time range a single aggTrades request may span: 1 hour
+const AGG_TRADES_MAX_RANGE: u64 = 3_600_000;
+
+/// Most aggregate trades returned by a single aggTrades request
+const AGG_TRADES_LIMIT: usize = 1000;
+
 /// Rate limiter for Aster DEX API
 pub struct AsterLimiter {
     bucket: limiter::FixedWindowBucket,
@@ -101,7 +119,7 @@ impl RateLimiter for AsterLimiter {
         self.bucket.consume_tokens(weight);
     }
 
-    fn should_exit_on_response(&self, response: &reqwest::Response) -> bool {
+    fn is_rate_limited(&self, response: &reqwest::Response) -> bool {
         response.status() == 429 || response.status() == 418
     }
 }
@@ -171,6 +189,24 @@ struct AsterOIData {
     open_interest: f32,
 }
 
+/// Settled funding rate entry
+#[derive(Debug, Deserialize)]
+struct AsterFundingRate {
+    #[serde(rename = "fundingTime")]
+    funding_time: u64,
+    #[serde(rename = "fundingRate", deserialize_with = "de_string_to_f32")]
+    funding_rate: f32,
+}
+
+/// Mark price and upcoming funding
+#[derive(Debug, Deserialize)]
+struct AsterPremiumIndex {
+    #[serde(rename = "lastFundingRate", deserialize_with = "de_string_to_f32")]
+    last_funding_rate: f32,
+    #[serde(rename = "nextFundingTime")]
+    next_funding_time: u64,
+}
+
 /// REST depth snapshot response
 #[derive(Debug, Deserialize)]
 struct AsterDepthSnapshot {
@@ -188,7 +224,7 @@ struct AsterWSMessage {
 }
 
 /// WebSocket depth update with sequence validation fields
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct AsterDepthUpdate {
     #[serde(rename = "U")]
     first_update_id: u64,
@@ -197,6 +233,8 @@ struct AsterDepthUpdate {
     #[serde(rename = "pu")]
     #[allow(dead_code)]
     prev_final_update_id: u64,
+    #[serde(rename = "T")]
+    time: u64,
     #[serde(rename = "b")]
     bids: Vec<Vec<String>>,
     #[serde(rename = "a")]
@@ -216,6 +254,21 @@ struct AsterTrade {
     timestamp: u64,
 }
 
+/// REST aggregate trade, timestamped with a number unlike the stream's
+#[derive(Debug, Deserialize)]
+struct AsterAggTrade {
+    #[serde(rename = "a")]
+    id: u64,
+    #[serde(rename = "p", deserialize_with = "de_string_to_f32")]
+    price: f32,
+    #[serde(rename = "q", deserialize_with = "de_string_to_f32")]
+    qty: f32,
+    #[serde(rename = "m")]
+    is_buyer_maker: bool,
+    #[serde(rename = "T")]
+    timestamp: u64,
+}
+
 /// WebSocket kline/candlestick update
 #[derive(Debug, Deserialize)]
 struct AsterWSKlineWrapper {
@@ -350,7 +403,7 @@ pub async fn fetch_ticksize(
         }
     };
 
-    let endpoint = format!("{}/fapi/v1/exchangeInfo", API_DOMAIN);
+    let endpoint = format!("{}/fapi/v1/exchangeInfo", endpoint::resolve(API_DOMAIN));
 
     let mut limiter = ASTER_LIMITER.lock().await;
     if let Some(wait) = limiter.prepare_request(1) {
@@ -358,7 +411,7 @@ pub async fn fetch_ticksize(
     }
     drop(limiter);
 
-    let response = reqwest::Client::new().get(&endpoint).send().await?;
+    let response = crate::proxy::http_client(&endpoint)?.get(&endpoint).send().await?;
 
     if !response.status().is_success() {
         return Err(AdapterError::InvalidRequest(format!(
@@ -409,7 +462,7 @@ pub async fn fetch_ticker_prices(
         }
     };
 
-    let endpoint = format!("{}/fapi/v1/ticker/24hr", API_DOMAIN);
+    let endpoint = format!("{}/fapi/v1/ticker/24hr", endpoint::resolve(API_DOMAIN));
 
     let mut limiter = ASTER_LIMITER.lock().await;
     if let Some(wait) = limiter.prepare_request(1) {
@@ -417,7 +470,7 @@ pub async fn fetch_ticker_prices(
     }
     drop(limiter);
 
-    let response = reqwest::Client::new().get(&endpoint).send().await?;
+    let response = crate::proxy::http_client(&endpoint)?.get(&endpoint).send().await?;
 
     if !response.status().is_success() {
         return Err(AdapterError::InvalidRequest(format!(
@@ -462,7 +515,7 @@ pub async fn fetch_klines(
                 "Aster DEX spot markets are not supported yet".to_string(),
             ))
         }
-        MarketKind::LinearPerps => format!("{}/fapi/v1/klines", API_DOMAIN),
+        MarketKind::LinearPerps => format!("{}/fapi/v1/klines", endpoint::resolve(API_DOMAIN)),
         _ => {
             return Err(AdapterError::InvalidRequest(
                 "Unsupported market type".to_string(),
@@ -489,7 +542,7 @@ pub async fn fetch_klines(
     }
     drop(limiter);
 
-    let response = reqwest::Client::new().get(&url).send().await?;
+    let response = crate::proxy::http_client(&url)?.get(&url).send().await?;
 
     if !response.status().is_success() {
         return Err(AdapterError::InvalidRequest(format!(
@@ -541,7 +594,7 @@ pub async fn fetch_historical_oi(
 
     let mut url = format!(
         "{}/futures/data/openInterestHist?symbol={}&period={}",
-        API_DOMAIN, symbol_str, period
+        endpoint::resolve(API_DOMAIN), symbol_str, period
     );
 
     if let Some((start, end)) = range {
@@ -556,7 +609,7 @@ pub async fn fetch_historical_oi(
     }
     drop(limiter);
 
-    let response = reqwest::Client::new().get(&url).send().await?;
+    let response = crate::proxy::http_client(&url)?.get(&url).send().await?;
 
     if !response.status().is_success() {
         return Err(AdapterError::InvalidRequest(format!(
@@ -578,15 +631,209 @@ pub async fn fetch_historical_oi(
     Ok(result)
 }
 
+/// Fetch settled funding rates (linear perpetuals only)
+pub async fn fetch_funding_rates(
+    ticker: Ticker,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<crate::FundingRate>, AdapterError> {
+    if ticker.exchange != Exchange::AsterLinear {
+        return Err(AdapterError::InvalidRequest(
+            "Funding rates only available for linear perpetuals".to_string(),
+        ));
+    }
+
+    let (symbol_str, _) = ticker.to_full_symbol_and_type();
+
+    let mut url = format!(
+        "{}/fapi/v1/fundingRate?symbol={}&limit=1000",
+        endpoint::resolve(API_DOMAIN), symbol_str
+    );
+
+    if let Some((start, end)) = range {
+        url.push_str(&format!("&startTime={}&endTime={}", start, end));
+    }
+
+    let mut limiter = ASTER_LIMITER.lock().await;
+    if let Some(wait) = limiter.prepare_request(1) {
+        tokio::time::sleep(wait).await;
+    }
+    drop(limiter);
+
+    let response = crate::proxy::http_client(&url)?.get(&url).send().await?;
+
+    if !response.status().is_success() {
+        return Err(AdapterError::InvalidRequest(format!(
+            "Failed to fetch funding rates: {}",
+            response.status()
+        )));
+    }
+
+    let data: Vec<AsterFundingRate> = response.json().await?;
+
+    Ok(data
+        .into_iter()
+        .map(|f| crate::FundingRate {
+            time: f.funding_time,
+            rate: f.funding_rate,
+        })
+        .collect())
+}
+
+/// Fetch the predicted rate of the upcoming funding (linear perpetuals only)
+pub async fn fetch_predicted_funding(ticker: Ticker) -> Result<crate::FundingRate, AdapterError> {
+    if ticker.exchange != Exchange::AsterLinear {
+        return Err(AdapterError::InvalidRequest(
+            "Funding rates only available for linear perpetuals".to_string(),
+        ));
+    }
+
+    let (symbol_str, _) = ticker.to_full_symbol_and_type();
+    let url = format!(
+        "{}/fapi/v1/premiumIndex?symbol={}",
+        endpoint::resolve(API_DOMAIN),
+        symbol_str
+    );
+
+    let mut limiter = ASTER_LIMITER.lock().await;
+    if let Some(wait) = limiter.prepare_request(1) {
+        tokio::time::sleep(wait).await;
+    }
+    drop(limiter);
+
+    let response = crate::proxy::http_client(&url)?.get(&url).send().await?;
+
+    if !response.status().is_success() {
+        return Err(AdapterError::InvalidRequest(format!(
+            "Failed to fetch premium index: {}",
+            response.status()
+        )));
+    }
+
+    let index: AsterPremiumIndex = response.json().await?;
+
+    Ok(crate::FundingRate {
+        time: index.next_funding_time,
+        rate: index.last_funding_rate,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct AsterServerTime {
+    #[serde(rename = "serverTime")]
+    server_time: u64,
+}
+
+pub async fn fetch_server_time() -> Result<u64, AdapterError> {
+    let url = format!("{}/fapi/v1/time", endpoint::resolve(API_DOMAIN));
+
+    let mut limiter = ASTER_LIMITER.lock().await;
+    if let Some(wait) = limiter.prepare_request(1) {
+        tokio::time::sleep(wait).await;
+    }
+    drop(limiter);
+
+    let response = crate::proxy::http_client(&url)?.get(&url).send().await?;
+
+    if !response.status().is_success() {
+        return Err(AdapterError::InvalidRequest(format!(
+            "Failed to fetch server time: {}",
+            response.status()
+        )));
+    }
+
+    let time: AsterServerTime = response.json().await?;
+    Ok(time.server_time)
+}
+
+/// Fetch a batch of aggregate trades after `from_time` (linear perpetuals only)
+///
+/// Unlike OKX's history, `aggTrades` returns the oldest trades of a time range
+/// first, so batches page forward. Ranges are limited to an hour, empty ones are
+/// skipped until the present is reached.
+pub async fn fetch_trades(
+    ticker_info: TickerInfo,
+    from_time: u64,
+) -> Result<Vec<Trade>, AdapterError> {
+    let (symbol_str, market) = ticker_info.ticker.to_full_symbol_and_type();
+    if market != MarketKind::LinearPerps {
+        return Err(AdapterError::InvalidRequest(
+            "Trade backfill only available for linear perpetuals".to_string(),
+        ));
+    }
+
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let mut start = from_time + 1;
+
+    while start <= now {
+        // both bounds are inclusive
+        let end = (start + AGG_TRADES_MAX_RANGE - 1).min(now);
+
+        let mut data =
+            fetch_agg_trades(&symbol_str, &format!("&startTime={start}&endTime={end}")).await?;
+
+        // the next call starts after the last trade's millisecond, so a full page
+        // has to be completed by id with the trades left in that millisecond
+        let mut is_full = data.len() == AGG_TRADES_LIMIT;
+        while is_full && let Some(last) = data.last() {
+            let boundary_ms = last.timestamp;
+
+            let page = fetch_agg_trades(&symbol_str, &format!("&fromId={}", last.id + 1)).await?;
+            is_full =
+                page.len() == AGG_TRADES_LIMIT && page.iter().all(|t| t.timestamp == boundary_ms);
+
+            data.extend(page.into_iter().filter(|t| t.timestamp == boundary_ms));
+        }
+
+        if !data.is_empty() {
+            return Ok(agg_trades_to_trades(data, ticker_info));
+        }
+
+        start = end + 1;
+    }
+
+    Ok(vec![])
+}
+
+async fn fetch_agg_trades(symbol: &str, query: &str) -> Result<Vec<AsterAggTrade>, AdapterError> {
+    let url = format!(
+        "{}/fapi/v1/aggTrades?symbol={symbol}&limit={AGG_TRADES_LIMIT}{query}",
+        endpoint::resolve(API_DOMAIN),
+    );
+
+    let text = limiter::http_request_with_limiter(&url, &ASTER_LIMITER, 20, None, None).await?;
+
+    serde_json::from_str(&text)
+        .map_err(|e| AdapterError::ParseError(format!("Failed to parse trades: {}", e)))
+}
+
+fn agg_trades_to_trades(data: Vec<AsterAggTrade>, ticker_info: TickerInfo) -> Vec<Trade> {
+    let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+
+    data.into_iter()
+        .map(|t| Trade {
+            time: t.timestamp,
+            is_sell: t.is_buyer_maker, // buyer_maker means the trade was a sell
+            price: Price::from_f32(t.price).round_to_min_tick(ticker_info.min_ticksize),
+            qty: if size_in_quote_currency {
+                (t.qty * t.price).round()
+            } else {
+                t.qty
+            },
+        })
+        .collect()
+}
+
 /// Fetch REST orderbook snapshot for WebSocket synchronization
 async fn fetch_depth_snapshot(
     symbol: &str,
     market: MarketKind,
+    source: &capture::Source,
 ) -> Result<AsterDepthSnapshot, AdapterError> {
+    let domain = endpoint::resolve(API_DOMAIN);
     let endpoint = match market {
-        MarketKind::Spot => format!("{}/api/v3/depth?symbol={}&limit=1000", API_DOMAIN, symbol),
+        MarketKind::Spot => format!("{}/api/v3/depth?symbol={}&limit=1000", domain, symbol),
         MarketKind::LinearPerps => {
-            format!("{}/fapi/v1/depth?symbol={}&limit=1000", API_DOMAIN, symbol)
+            format!("{}/fapi/v1/depth?symbol={}&limit=1000", domain, symbol)
         }
         _ => {
             return Err(AdapterError::InvalidRequest(
@@ -601,7 +848,7 @@ async fn fetch_depth_snapshot(
     }
     drop(limiter);
 
-    let response = reqwest::Client::new().get(&endpoint).send().await?;
+    let response = crate::proxy::http_client(&endpoint)?.get(&endpoint).send().await?;
 
     if !response.status().is_success() {
         return Err(AdapterError::InvalidRequest(format!(
@@ -610,23 +857,31 @@ async fn fetch_depth_snapshot(
         )));
     }
 
-    let snapshot: AsterDepthSnapshot = response.json().await?;
-    Ok(snapshot)
+    let body = response.text().await?;
+    capture::record(source, FrameKind::Rest, &endpoint, body.as_bytes());
+
+    serde_json::from_str(&body)
+        .map_err(|e| AdapterError::ParseError(format!("Failed to parse depth snapshot: {}", e)))
 }
 
 // ============================================================================
 // WebSocket Functions
 // ============================================================================
 
+fn ws_url(path: &str) -> String {
+    format!("{}{}", endpoint::resolve(WS_DOMAIN), path)
+}
+
 async fn connect_websocket(
-    path: &str,
+    url: &str,
 ) -> Result<FragmentCollector<TokioIo<Upgraded>>, AdapterError> {
-    connect_ws(WS_DOMAIN, path)
+    connect_ws(url)
         .await
         .map_err(|e| AdapterError::WebsocketError(e.to_string()))
 }
 
-fn parse_websocket_message(payload: &[u8]) -> Result<StreamData, AdapterError> {
+/// Parses a combined stream message, along with the name of the stream it was sent on
+fn parse_websocket_message(payload: &[u8]) -> Result<(String, StreamData), AdapterError> {
     let msg: AsterWSMessage = serde_json::from_slice(payload)
         .map_err(|e| AdapterError::ParseError(format!("Failed to parse WS message: {}", e)))?;
 
@@ -634,7 +889,7 @@ fn parse_websocket_message(payload: &[u8]) -> Result<StreamData, AdapterError> {
         let update: AsterDepthUpdate = serde_json::from_value(msg.data).map_err(|e| {
             AdapterError::ParseError(format!("Failed to parse depth update: {}", e))
         })?;
-        Ok(StreamData::Depth(update))
+        Ok((msg.stream, StreamData::Depth(update)))
     } else if msg.stream.contains("aggTrade") {
         let trade_data: AsterTrade = serde_json::from_value(msg.data)
             .map_err(|e| AdapterError::ParseError(format!("Failed to parse trade: {}", e)))?;
@@ -646,7 +901,7 @@ fn parse_websocket_message(payload: &[u8]) -> Result<StreamData, AdapterError> {
             qty: trade_data.qty,
         };
 
-        Ok(StreamData::Trade(vec![trade]))
+        Ok((msg.stream, StreamData::Trade(vec![trade])))
     } else if msg.stream.contains("kline") {
         let wrapper: AsterWSKlineWrapper = serde_json::from_value(msg.data)
             .map_err(|e| AdapterError::ParseError(format!("Failed to parse kline: {}", e)))?;
@@ -654,7 +909,7 @@ fn parse_websocket_message(payload: &[u8]) -> Result<StreamData, AdapterError> {
         // Extract symbol from stream name (format: "btcusdt@kline_1m")
         let symbol = msg.stream.split('@').next().unwrap_or("").to_uppercase();
 
-        Ok(StreamData::Kline(symbol, wrapper.k))
+        Ok((msg.stream, StreamData::Kline(symbol, wrapper.k)))
     } else {
         Err(AdapterError::ParseError(format!(
             "Unknown stream type: {}",
@@ -663,182 +918,421 @@ fn parse_websocket_message(payload: &[u8]) -> Result<StreamData, AdapterError> {
     }
 }
 
-/// Connect to combined depth + trades stream with orderbook management
-pub fn connect_market_stream(
+type SnapshotResult = Result<Result<DepthPayload, AdapterError>, oneshot::error::RecvError>;
+
+/// Depth and trades of one ticker carried on a market connection
+struct DepthFeed {
+    stream: StreamKind,
     ticker_info: TickerInfo,
-    _push_freq: PushFrequency,
-) -> impl Stream<Item = Event> {
-    stream::channel(100, async move |mut output| {
-        let (symbol_str, market) = ticker_info.ticker.to_full_symbol_and_type();
+    topics: Vec<String>,
+    orderbook: LocalDepthCache,
+    trades_buffer: Vec<Trade>,
+    /// Snapshot being fetched, diffs wait in `pending_diffs` until it's in
+    snapshot: Option<oneshot::Receiver<Result<DepthPayload, AdapterError>>>,
+    pending_diffs: Vec<AsterDepthUpdate>,
+}
+
+impl DepthFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::DepthAndTrades { ticker_info, .. } = stream else {
+            return None;
+        };
+
+        let (symbol_str, _) = ticker_info.ticker.to_full_symbol_and_type();
         let symbol = symbol_str.to_lowercase();
-        let exchange = ticker_info.ticker.exchange;
 
-        let mut state = State::Disconnected;
-        let mut local_depth: LocalDepthCache = LocalDepthCache::default();
-        let mut trades_buffer: Vec<Trade> = Vec::new();
-        let mut last_update_id = 0u64;
+        Some(Self {
+            stream,
+            ticker_info,
+            topics: vec![format!("{symbol}@depth"), format!("{symbol}@aggTrade")],
+            orderbook: LocalDepthCache::default(),
+            trades_buffer: Vec::new(),
+            snapshot: None,
+            pending_diffs: Vec::new(),
+        })
+    }
 
-        loop {
-            match &mut state {
-                State::Disconnected => {
-                    // Build WebSocket path
-                    let path = format!("/stream?streams={}@depth/{}@aggTrade", symbol, symbol);
+    /// Starts fetching a fresh snapshot on its own task, so the other feeds of the connection keep going
+    async fn resync(&mut self, source: &capture::Source, output: &mut mpsc::Sender<Event>) {
+        let (symbol_str, market) = self.ticker_info.ticker.to_full_symbol_and_type();
+        let min_ticksize = self.ticker_info.min_ticksize;
+        let source = source.clone();
+
+        connect::publish(output, &[self.stream], ConnectionState::Resyncing).await;
+
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let result = fetch_depth_snapshot(&symbol_str, market, &source)
+                .await
+                .map(|snapshot| DepthPayload {
+                    last_update_id: snapshot.last_update_id,
+                    time: chrono::Utc::now().timestamp_millis() as u64,
+                    bids: parse_price_qty_array(snapshot.bids, min_ticksize.into()),
+                    asks: parse_price_qty_array(snapshot.asks, min_ticksize.into()),
+                });
+            let _ = tx.send(result);
+        });
 
-                    // Connect to WebSocket
-                    match connect_websocket(&path).await {
-                        Ok(ws) => {
-                            // Fetch initial depth snapshot
-                            match fetch_depth_snapshot(&symbol_str, market).await {
-                                Ok(snapshot) => {
-                                    last_update_id = snapshot.last_update_id;
-
-                                    // Initialize orderbook (using base min_ticksize for client-side aggregation)
-                                    let bids = parse_price_qty_array(snapshot.bids, ticker_info.min_ticksize.into());
-                                    let asks = parse_price_qty_array(snapshot.asks, ticker_info.min_ticksize.into());
-
-                                    local_depth.update(
-                                        DepthUpdate::Snapshot(DepthPayload {
-                                            last_update_id,
-                                            time: chrono::Utc::now().timestamp_millis() as u64,
-                                            bids,
-                                            asks,
-                                        }),
-                                        ticker_info.min_ticksize,
-                                    );
-
-                                    state = State::Connected(ws);
-                                    let _ = output.send(Event::Connected(exchange)).await;
-                                }
-                                Err(e) => {
-                                    let _ = output
-                                        .send(Event::Disconnected(
-                                            exchange,
-                                            format!("Failed to fetch depth snapshot: {}", e),
-                                        ))
-                                        .await;
-                                    tokio::time::sleep(Duration::from_secs(5)).await;
-                                    continue;
-                                }
-                            }
-                        }
-                        Err(e) => {
+        self.orderbook.last_update_id = 0;
+        self.snapshot = Some(rx);
+        self.pending_diffs.clear();
+    }
+
+    /// Replaces the local book with the fetched snapshot and applies the diffs received meanwhile
+    async fn apply_snapshot(
+        &mut self,
+        result: SnapshotResult,
+        exchange: Exchange,
+        source: &capture::Source,
+        output: &mut mpsc::Sender<Event>,
+    ) {
+        let ticker = self.ticker_info.ticker;
+        self.snapshot = None;
+
+        match result {
+            Ok(Ok(depth)) => {
+                self.orderbook
+                    .update(DepthUpdate::Snapshot(depth), self.ticker_info.min_ticksize);
+
+                connect::publish(output, &[self.stream], ConnectionState::Live).await;
+
+                for diff in std::mem::take(&mut self.pending_diffs) {
+                    self.apply_diff(&diff, source, output).await;
+                }
+            }
+            Ok(Err(e)) => {
+                // retried on the next diff
+                self.pending_diffs.clear();
+
+                let _ = output
+                    .send(Event::Disconnected(
+                        exchange,
+                        format!("Failed to fetch depth snapshot for {ticker}: {e}"),
+                    ))
+                    .await;
+            }
+            Err(e) => {
+                self.pending_diffs.clear();
+
+                log::error!("Failed to receive depth snapshot for {ticker}, error: {e}");
+            }
+        }
+    }
+
+    async fn apply_diff(
+        &mut self,
+        update: &AsterDepthUpdate,
+        source: &capture::Source,
+        output: &mut mpsc::Sender<Event>,
+    ) {
+        if self.snapshot.is_some() {
+            self.pending_diffs.push(update.clone());
+            return;
+        }
+
+        let last_update_id = self.orderbook.last_update_id;
+
+        if last_update_id == 0 {
+            self.resync(source, output).await;
+            self.pending_diffs.push(update.clone());
+            return;
+        }
+        // already in the snapshot
+        if update.final_update_id <= last_update_id {
+            return;
+        }
+
+        if update.first_update_id > last_update_id + 1 {
+            integrity::record(
+                self.stream,
+                integrity::Fault::SequenceGap {
+                    last: last_update_id,
+                    got: update.first_update_id,
+                },
+            );
+            self.resync(source, output).await;
+            return;
+        }
+
+        let min_ticksize = self.ticker_info.min_ticksize;
+
+        self.orderbook.update(
+            DepthUpdate::Diff(DepthPayload {
+                last_update_id: update.final_update_id,
+                time: update.time,
+                bids: parse_price_qty_array(update.bids.clone(), min_ticksize.into()),
+                asks: parse_price_qty_array(update.asks.clone(), min_ticksize.into()),
+            }),
+            min_ticksize,
+        );
+
+        if let Err(fault) = integrity::check_crossed(&self.orderbook.depth) {
+            integrity::record(self.stream, fault);
+            self.resync(source, output).await;
+            return;
+        }
+
+        telemetry::observe(self.stream, update.time);
+
+        let _ = output
+            .send(Event::DepthReceived(
+                self.stream,
+                update.time,
+                self.orderbook.depth.clone(),
+                std::mem::take(&mut self.trades_buffer).into_boxed_slice(),
+            ))
+            .await;
+    }
+
+    async fn handle(
+        &mut self,
+        data: &StreamData,
+        source: &capture::Source,
+        output: &mut mpsc::Sender<Event>,
+    ) {
+        match data {
+            StreamData::Trade(trades) => self.trades_buffer.extend_from_slice(trades),
+            StreamData::Depth(update) => self.apply_diff(update, source, output).await,
+            StreamData::Kline(..) => {}
+        }
+    }
+}
+
+/// Index of the first feed whose snapshot fetch finished, along with its result
+fn poll_snapshots(feeds: &mut [DepthFeed], cx: &mut Context<'_>) -> Poll<(usize, SnapshotResult)> {
+    for (index, feed) in feeds.iter_mut().enumerate() {
+        if let Some(snapshot) = &mut feed.snapshot
+            && let Poll::Ready(result) = Pin::new(snapshot).poll(cx)
+        {
+            return Poll::Ready((index, result));
+        }
+    }
+
+    Poll::Pending
+}
+
+async fn send_subscription(
+    ws: &mut FragmentCollector<TokioIo<Upgraded>>,
+    method: &str,
+    topics: Vec<String>,
+    request_id: &mut u64,
+) -> Result<(), WebSocketError> {
+    if topics.is_empty() {
+        return Ok(());
+    }
+    *request_id += 1;
+
+    let request = serde_json::json!({
+        "method": method,
+        "params": topics,
+        "id": *request_id,
+    });
+
+    ws.write_frame(Frame::text(fastwebsockets::Payload::Owned(
+        request.to_string().into_bytes(),
+    )))
+    .await
+}
+
+/// Subscribes and drops topics on the open socket so it carries exactly `wanted`
+async fn update_subscriptions(
+    ws: &mut FragmentCollector<TokioIo<Upgraded>>,
+    feeds: &mut Vec<DepthFeed>,
+    wanted: &FxHashSet<StreamKind>,
+    request_id: &mut u64,
+    source: &capture::Source,
+    output: &mut mpsc::Sender<Event>,
+) -> Result<(), WebSocketError> {
+    let current_topics = feeds
+        .iter()
+        .flat_map(|feed| feed.topics.iter().cloned())
+        .collect::<FxHashSet<_>>();
+
+    feeds.retain(|feed| wanted.contains(&feed.stream));
+
+    let mut added = wanted
+        .iter()
+        .filter(|stream| !feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| DepthFeed::new(*stream))
+        .collect::<Vec<_>>();
+
+    let wanted_topics = feeds
+        .iter()
+        .chain(&added)
+        .flat_map(|feed| feed.topics.iter().cloned())
+        .collect::<FxHashSet<_>>();
+
+    let (subscribe, unsubscribe) = mux::diff_topics(&current_topics, &wanted_topics);
+
+    send_subscription(ws, "UNSUBSCRIBE", unsubscribe, request_id).await?;
+    send_subscription(ws, "SUBSCRIBE", subscribe, request_id).await?;
+
+    // diffs received meanwhile are held by each feed until its snapshot is in
+    for feed in &mut added {
+        feed.resync(source, output).await;
+    }
+    feeds.extend(added);
+
+    Ok(())
+}
+
+/// Depth and trades of every stream in `wanted` over a single connection
+async fn run_market_streams(
+    exchange: Exchange,
+    mut wanted: mux::WantedStreams,
+    output: &mut mpsc::Sender<Event>,
+) {
+    let url = ws_url("/stream");
+    let source = capture::Source::new(exchange, "market");
+
+    let mut state = State::Disconnected;
+    let mut backoff = Backoff::default();
+    let mut feeds: Vec<DepthFeed> = Vec::new();
+    let mut request_id = 0;
+
+    loop {
+        match &mut state {
+            State::Disconnected => {
+                feeds.clear();
+
+                let streams = wanted.borrow().iter().copied().collect::<Vec<_>>();
+                connect::publish(output, &streams, ConnectionState::Connecting).await;
+
+                match connect_websocket(&url).await {
+                    Ok(ws) => {
+                        state = State::Connected(ws);
+                        backoff.reset();
+                        // a fresh socket has nothing subscribed yet, feeds go live once synced
+                        wanted.mark_changed();
+
+                        let _ = output.send(Event::Connected(exchange)).await;
+                    }
+                    Err(e) => {
+                        let _ = output
+                            .send(Event::Disconnected(
+                                exchange,
+                                format!("WebSocket connection failed: {}", e),
+                            ))
+                            .await;
+                        backoff.wait(&streams, output).await;
+                    }
+                }
+            }
+
+            State::Connected(ws) => {
+                let timeout = tokio::time::sleep(Duration::from_secs(20));
+                tokio::pin!(timeout);
+
+                tokio::select! {
+                    Ok(()) = wanted.changed() => {
+                        let streams = wanted.borrow_and_update().clone();
+
+                        if let Err(e) = update_subscriptions(
+                            ws,
+                            &mut feeds,
+                            &streams,
+                            &mut request_id,
+                            &source,
+                            output,
+                        )
+                        .await
+                        {
                             let _ = output
                                 .send(Event::Disconnected(
                                     exchange,
-                                    format!("WebSocket connection failed: {}", e),
+                                    format!("Failed updating subscriptions: {}", e),
                                 ))
                                 .await;
-                            tokio::time::sleep(Duration::from_secs(5)).await;
-                            continue;
+                            state = State::Disconnected;
                         }
                     }
-                }
-
-                State::Connected(ws) => {
-                    let timeout = tokio::time::sleep(Duration::from_secs(20));
-                    tokio::pin!(timeout);
-
-                    tokio::select! {
-                        frame = ws.read_frame() => {
-                            match frame {
-                                Ok(frame) => {
-                                    match frame.opcode {
-                                        OpCode::Text => {
-                                            match parse_websocket_message(&frame.payload) {
-                                                Ok(StreamData::Depth(update)) => {
-                                                    // Sequence validation
-                                                    if update.first_update_id <= last_update_id + 1 &&
-                                                       update.final_update_id > last_update_id {
-
-                                                        let bids = parse_price_qty_array(update.bids, ticker_info.min_ticksize.into());
-                                                        let asks = parse_price_qty_array(update.asks, ticker_info.min_ticksize.into());
-
-                                                        last_update_id = update.final_update_id;
-
-                                                        local_depth.update(
-                                                            DepthUpdate::Diff(DepthPayload {
-                                                                last_update_id,
-                                                                time: chrono::Utc::now().timestamp_millis() as u64,
-                                                                bids,
-                                                                asks,
-                                                            }),
-                                                            ticker_info.min_ticksize,
-                                                        );
-
-                                                        let depth = local_depth.depth.clone();
-                                                        let trades = std::mem::take(&mut trades_buffer);
-
-                                                        let stream_kind = StreamKind::DepthAndTrades {
-                                                            ticker_info,
-                                                            depth_aggr: super::StreamTicksize::Client,
-                                                            push_freq: PushFrequency::ServerDefault,
-                                                        };
-
-                                                        let _ = output
-                                                            .send(Event::DepthReceived(
-                                                                stream_kind,
-                                                                last_update_id,
-                                                                depth,
-                                                                trades.into_boxed_slice(),
-                                                            ))
-                                                            .await;
-                                                    } else {
-                                                        // Sequence break - reconnect
-                                                        let _ = output
-                                                            .send(Event::Disconnected(
-                                                                exchange,
-                                                                "Sequence break detected".to_string(),
-                                                            ))
-                                                            .await;
-                                                        state = State::Disconnected;
-                                                    }
-                                                }
-                                                Ok(StreamData::Trade(trades)) => {
-                                                    trades_buffer.extend(trades);
-                                                }
-                                                Ok(StreamData::Kline(_, _)) => {
-                                                    // Ignore klines in market stream
-                                                }
-                                                Err(e) => {
-                                                    eprintln!("Parse error: {}", e);
-                                                }
+                    (index, result) = std::future::poll_fn(|cx| poll_snapshots(&mut feeds, cx)) => {
+                        feeds[index].apply_snapshot(result, exchange, &source, output).await;
+                    }
+                    frame = ws.read_frame() => {
+                        match frame {
+                            Ok(frame) => {
+                                match frame.opcode {
+                                    OpCode::Text => {
+                                        capture::record(&source, FrameKind::WebSocket, &url, &frame.payload);
+
+                                        // subscription acks carry no stream
+                                        if let Ok((topic, data)) = parse_websocket_message(&frame.payload) {
+                                            let routed = feeds
+                                                .iter_mut()
+                                                .filter(|feed| feed.topics.contains(&topic));
+
+                                            for feed in routed {
+                                                feed.handle(&data, &source, output).await;
                                             }
                                         }
-                                        OpCode::Ping => {
-                                            let pong = Frame::pong(frame.payload);
-                                            let _ = ws.write_frame(pong).await;
-                                        }
-                                        OpCode::Close => {
-                                            let _ = output
-                                                .send(Event::Disconnected(exchange, "Connection closed".to_string()))
-                                                .await;
-                                            state = State::Disconnected;
-                                        }
-                                        _ => {}
                                     }
-                                }
-                                Err(e) => {
-                                    let _ = output
-                                        .send(Event::Disconnected(
-                                            exchange,
-                                            format!("WebSocket error: {}", e),
-                                        ))
-                                        .await;
-                                    state = State::Disconnected;
+                                    OpCode::Ping => {
+                                        let pong = Frame::pong(frame.payload);
+                                        let _ = ws.write_frame(pong).await;
+                                    }
+                                    OpCode::Close => {
+                                        let _ = output
+                                            .send(Event::Disconnected(exchange, "Connection closed".to_string()))
+                                            .await;
+                                        state = State::Disconnected;
+                                    }
+                                    _ => {}
                                 }
                             }
-                        }
-                        _ = &mut timeout => {
-                            // Timeout - reconnect
-                            let _ = output
-                                .send(Event::Disconnected(exchange, "Connection timeout".to_string()))
-                                .await;
-                            state = State::Disconnected;
+                            Err(e) => {
+                                let _ = output
+                                    .send(Event::Disconnected(
+                                        exchange,
+                                        format!("WebSocket error: {}", e),
+                                    ))
+                                    .await;
+                                state = State::Disconnected;
+                            }
                         }
                     }
+                    // an idle socket without subscriptions is expected
+                    _ = &mut timeout, if !feeds.is_empty() => {
+                        // Timeout - reconnect
+                        let _ = output
+                            .send(Event::Disconnected(exchange, "Connection timeout".to_string()))
+                            .await;
+                        state = State::Disconnected;
+                    }
                 }
             }
         }
+    }
+}
+
+/// Connect to combined depth + trades stream with orderbook management
+pub fn connect_market_stream(
+    ticker_info: TickerInfo,
+    _push_freq: PushFrequency,
+) -> impl Stream<Item = Event> {
+    let stream = StreamKind::DepthAndTrades {
+        ticker_info,
+        depth_aggr: super::StreamTicksize::Client,
+        push_freq: PushFrequency::ServerDefault,
+    };
+
+    stream::channel(100, async move |mut output| {
+        let exchange = ticker_info.ticker.exchange;
+        run_market_streams(exchange, mux::fixed([stream]), &mut output).await;
+    })
+}
+
+/// Depth and trades of all the tickers wanted from a market, over one connection
+pub fn connect_shared_market_stream(market: MarketKind) -> impl Stream<Item = Event> {
+    stream::channel(100, async move |mut output| {
+        // only linear perpetuals are listed
+        if market != MarketKind::LinearPerps {
+            return;
+        }
+
+        let exchange = Exchange::AsterLinear;
+        run_market_streams(exchange, mux::wanted(exchange), &mut output).await;
     })
 }
 
@@ -865,17 +1359,32 @@ pub fn connect_kline_stream(
             })
             .collect();
 
-        let path = format!("/stream?streams={}", stream_names.join("/"));
+        let url = ws_url(&format!("/stream?streams={}", stream_names.join("/")));
+        let source = capture::Source::new(exchange, "klines");
+
+        let kline_streams: Vec<StreamKind> = streams
+            .iter()
+            .map(|(ticker_info, timeframe)| StreamKind::Kline {
+                ticker_info: *ticker_info,
+                timeframe: *timeframe,
+            })
+            .collect();
 
         let mut state = State::Disconnected;
+        let mut backoff = Backoff::default();
 
         loop {
             match &mut state {
                 State::Disconnected => {
-                    match connect_websocket(&path).await {
+                    connect::publish(&mut output, &kline_streams, ConnectionState::Connecting).await;
+
+                    match connect_websocket(&url).await {
                         Ok(ws) => {
                             state = State::Connected(ws);
+                            backoff.reset();
+
                             let _ = output.send(Event::Connected(exchange)).await;
+                            connect::publish(&mut output, &kline_streams, ConnectionState::Live).await;
                         }
                         Err(e) => {
                             let _ = output
@@ -884,7 +1393,7 @@ pub fn connect_kline_stream(
                                     format!("WebSocket connection failed: {}", e),
                                 ))
                                 .await;
-                            tokio::time::sleep(Duration::from_secs(5)).await;
+                            backoff.wait(&kline_streams, &mut output).await;
                             continue;
                         }
                     }
@@ -900,8 +1409,10 @@ pub fn connect_kline_stream(
                                 Ok(frame) => {
                                     match frame.opcode {
                                         OpCode::Text => {
+                                            capture::record(&source, FrameKind::WebSocket, &url, &frame.payload);
+
                                             match parse_websocket_message(&frame.payload) {
-                                                Ok(StreamData::Kline(symbol, kline)) => {
+                                                Ok((_, StreamData::Kline(symbol, kline))) => {
                                                     // Find matching ticker info by symbol
                                                     if let Some((ticker_info, timeframe)) = streams.iter().find(|(info, _)| {
                                                         let (ticker_symbol, _) = info.ticker.to_full_symbol_and_type();
@@ -972,6 +1483,120 @@ pub fn connect_kline_stream(
     })
 }
 
+pub struct Aster;
+
+impl ExchangeAdapter for Aster {
+    fn venue(&self) -> ExchangeInclusive {
+        ExchangeInclusive::Aster
+    }
+
+    fn name(&self) -> &'static str {
+        "Aster DEX"
+    }
+
+    fn id(&self) -> &'static str {
+        "aster"
+    }
+
+    fn markets(&self) -> &'static [Exchange] {
+        &[Exchange::AsterLinear]
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["asterdex.com"]
+    }
+
+    fn supports_open_interest(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn supports_trade_backfill(&self, market: MarketKind) -> bool {
+        market == MarketKind::LinearPerps
+    }
+
+    fn fetch_ticker_info(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, Option<TickerInfo>>> {
+        Box::pin(fetch_ticksize(market))
+    }
+
+    fn fetch_ticker_prices(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, TickerStats>> {
+        Box::pin(fetch_ticker_prices(market))
+    }
+
+    fn fetch_klines(
+        &self,
+        ticker_info: TickerInfo,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<Kline>> {
+        Box::pin(fetch_klines(ticker_info, timeframe, range))
+    }
+
+    fn fetch_open_interest(
+        &self,
+        ticker: Ticker,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<OpenInterest>> {
+        Box::pin(fetch_historical_oi(ticker, range, timeframe))
+    }
+
+    fn fetch_funding_rates(
+        &self,
+        ticker: Ticker,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<FundingRate>> {
+        Box::pin(fetch_funding_rates(ticker, range))
+    }
+
+    fn fetch_predicted_funding(&self, ticker: Ticker) -> AdapterFuture<FundingRate> {
+        Box::pin(fetch_predicted_funding(ticker))
+    }
+
+    fn fetch_server_time(&self) -> AdapterFuture<u64> {
+        Box::pin(fetch_server_time())
+    }
+
+    fn fetch_trades(
+        &self,
+        ticker_info: TickerInfo,
+        from_time: u64,
+        _data_path: PathBuf,
+    ) -> AdapterFuture<Vec<Trade>> {
+        Box::pin(fetch_trades(ticker_info, from_time))
+    }
+
+    fn connect_market_stream(
+        &self,
+        ticker_info: TickerInfo,
+        _tick_multiplier: Option<TickMultiplier>,
+        push_freq: PushFrequency,
+    ) -> EventStream {
+        Box::pin(connect_market_stream(ticker_info, push_freq))
+    }
+
+    fn multiplexes_market_streams(&self) -> bool {
+        true
+    }
+
+    fn connect_shared_market_stream(&self, market: MarketKind) -> EventStream {
+        Box::pin(connect_shared_market_stream(market))
+    }
+
+    fn connect_kline_stream(
+        &self,
+        streams: Vec<(TickerInfo, Timeframe)>,
+        market: MarketKind,
+    ) -> EventStream {
+        Box::pin(connect_kline_stream(streams, market))
+    }
+}
+
 // ============================================================================
 // Unit Tests
 // ============================================================================
@@ -1119,6 +1744,33 @@ mod tests {
         assert!((oi.open_interest - 12345.67).abs() < 0.01);
     }
 
+    #[test]
+    fn test_funding_rate_deserialization() {
+        let json = r#"{
+            "symbol": "BTCUSDT",
+            "fundingTime": 1627776000000,
+            "fundingRate": "-0.00012500",
+            "markPrice": "50000.5"
+        }"#;
+        let funding: AsterFundingRate = serde_json::from_str(json).unwrap();
+        assert_eq!(funding.funding_time, 1627776000000);
+        assert!((funding.funding_rate + 0.000125).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_premium_index_deserialization() {
+        let json = r#"{
+            "symbol": "BTCUSDT",
+            "markPrice": "50000.5",
+            "lastFundingRate": "0.00010000",
+            "nextFundingTime": 1627804800000,
+            "time": 1627776000000
+        }"#;
+        let index: AsterPremiumIndex = serde_json::from_str(json).unwrap();
+        assert_eq!(index.next_funding_time, 1627804800000);
+        assert!((index.last_funding_rate - 0.0001).abs() < 1e-9);
+    }
+
     #[test]
     fn test_trade_deserialization() {
         let json = r#"{
@@ -1133,4 +1785,26 @@ mod tests {
         assert_eq!(trade.is_buyer_maker, false);
         assert_eq!(trade.timestamp, 1627776000000);
     }
+
+    #[test]
+    fn test_agg_trades_deserialization() {
+        let json = r#"[
+            {"a": 26129, "p": "100.5", "q": "1.5", "f": 27781, "l": 27781, "T": 1627776000000, "m": true},
+            {"a": 26130, "p": "100.6", "q": "0.5", "f": 27782, "l": 27783, "T": 1627776000100, "m": false}
+        ]"#;
+        let data: Vec<AsterAggTrade> = serde_json::from_str(json).unwrap();
+        let ticker_info = TickerInfo::new(
+            Ticker::new("BTCUSDT", Exchange::AsterLinear),
+            0.1,
+            0.001,
+            None,
+        );
+
+        let trades = agg_trades_to_trades(data, ticker_info);
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].time, 1627776000000);
+        assert!(trades[0].is_sell);
+        assert!(!trades[1].is_sell);
+        assert!((trades[1].qty - 0.5).abs() < 0.01);
+    }
 }
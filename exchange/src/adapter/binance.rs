@@ -1,33 +1,50 @@
-use crate::{Price, PushFrequency, adapter::StreamTicksize, de_string_to_f32};
+use crate::{
+    Price, PushFrequency, TickMultiplier, adapter::StreamTicksize, de_string_to_f32, endpoint,
+};
 
 use super::{
     super::{
-        Exchange, Kline, MarketKind, OpenInterest, SIZE_IN_QUOTE_CURRENCY, StreamKind, Ticker,
-        TickerInfo, TickerStats, Timeframe, Trade,
-        connect::{State, connect_ws},
+        Exchange, FundingRate, Kline, Liquidation, MarketKind, OpenInterest,
+        SIZE_IN_QUOTE_CURRENCY, StreamKind, Ticker, TickerInfo, TickerStats, Timeframe, Trade,
+        capture::{self, FrameKind},
+        connect::{self, Backoff, State, connect_ws},
         depth::{DeOrder, DepthPayload, DepthUpdate, LocalDepthCache},
         is_symbol_supported,
         limiter::{self, RateLimiter},
         str_f32_parse,
     },
-    AdapterError, Event,
+    AdapterError, ConnectionState, Event, ExchangeInclusive, integrity, mux,
+    registry::{AdapterFuture, EventStream, ExchangeAdapter},
+    telemetry,
 };
 
 use csv::ReaderBuilder;
-use fastwebsockets::OpCode;
+use fastwebsockets::{FragmentCollector, Frame, OpCode, Payload, WebSocketError};
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
 use iced_futures::{
     futures::{SinkExt, Stream, channel::mpsc},
     stream,
 };
+use rustc_hash::FxHashSet;
 use serde::Deserialize;
 use sonic_rs::{FastStr, to_object_iter_unchecked};
-use tokio::sync::Mutex;
-
-use std::{collections::HashMap, io::BufReader, path::PathBuf, sync::LazyLock, time::Duration};
+use tokio::sync::{Mutex, oneshot};
+
+use std::{
+    collections::HashMap,
+    io::BufReader,
+    path::PathBuf,
+    pin::Pin,
+    sync::LazyLock,
+    task::{Context, Poll},
+    time::Duration,
+};
 
 const SPOT_DOMAIN: &str = "https://api.binance.com";
 const LINEAR_PERP_DOMAIN: &str = "https://fapi.binance.com";
 const INVERSE_PERP_DOMAIN: &str = "https://dapi.binance.com";
+const BULK_DATA_DOMAIN: &str = "https://data.binance.vision";
 
 static SPOT_LIMITER: LazyLock<Mutex<BinanceLimiter>> =
     LazyLock::new(|| Mutex::new(BinanceLimiter::new(SPOT_LIMIT, REFILL_RATE)));
@@ -72,10 +89,14 @@ impl RateLimiter for BinanceLimiter {
         }
     }
 
-    fn should_exit_on_response(&self, response: &reqwest::Response) -> bool {
+    fn is_rate_limited(&self, response: &reqwest::Response) -> bool {
         let status = response.status();
         status == 429 || status == 418
     }
+
+    fn retry_after(&self, response: &reqwest::Response) -> Option<Duration> {
+        limiter::retry_after_header(response).or_else(|| self.bucket.exhausted_for())
+    }
 }
 
 fn exchange_from_market_type(market: MarketKind) -> Exchange {
@@ -94,12 +115,12 @@ fn limiter_from_market_type(market: MarketKind) -> &'static Mutex<BinanceLimiter
     }
 }
 
-fn ws_domain_from_market_type(market: MarketKind) -> &'static str {
-    match market {
-        MarketKind::Spot => "stream.binance.com",
-        MarketKind::LinearPerps => "fstream.binance.com",
-        MarketKind::InversePerps => "dstream.binance.com",
-    }
+fn ws_domain_from_market_type(market: MarketKind) -> String {
+    endpoint::resolve(match market {
+        MarketKind::Spot => "wss://stream.binance.com",
+        MarketKind::LinearPerps => "wss://fstream.binance.com",
+        MarketKind::InversePerps => "wss://dstream.binance.com",
+    })
 }
 
 #[derive(Deserialize, Clone)]
@@ -163,12 +184,54 @@ struct SonicTrade {
     #[serde(rename = "m")]
     is_sell: bool,
 }
+
+#[derive(Deserialize, Debug)]
+struct SonicForceOrder {
+    #[serde(rename = "o")]
+    order: ForceOrder,
+}
+
+#[derive(Deserialize, Debug)]
+struct ForceOrder {
+    #[serde(rename = "T")]
+    time: u64,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "ap", deserialize_with = "de_string_to_f32")]
+    avg_price: f32,
+    #[serde(rename = "z", deserialize_with = "de_string_to_f32")]
+    filled_qty: f32,
+}
+
+impl ForceOrder {
+    fn liquidation(&self, ticker_info: &TickerInfo, contract_size: Option<f32>) -> Liquidation {
+        let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+
+        let qty = contract_size.map_or(
+            if size_in_quote_currency {
+                (self.filled_qty * self.avg_price).round()
+            } else {
+                self.filled_qty
+            },
+            |size| self.filled_qty * size,
+        );
+
+        Liquidation {
+            time: self.time,
+            is_sell: self.side == "SELL",
+            price: Price::from_f32(self.avg_price).round_to_min_tick(ticker_info.min_ticksize),
+            qty,
+        }
+    }
+}
+
+#[derive(Clone)]
 enum SonicDepth {
     Spot(SpotDepth),
     Perp(PerpDepth),
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 struct SpotDepth {
     #[serde(rename = "E")]
     time: u64,
@@ -182,7 +245,7 @@ struct SpotDepth {
     asks: Vec<DeOrder>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 struct PerpDepth {
     #[serde(rename = "T")]
     time: u64,
@@ -202,12 +265,14 @@ enum StreamData {
     Trade(SonicTrade),
     Depth(SonicDepth),
     Kline(Ticker, SonicKline),
+    Liquidation(ForceOrder),
 }
 
 enum StreamWrapper {
     Trade,
     Depth,
     Kline,
+    Liquidation,
 }
 
 impl StreamWrapper {
@@ -219,14 +284,17 @@ impl StreamWrapper {
                 s if s.starts_with("de") => Some(StreamWrapper::Depth),
                 s if s.starts_with("ag") => Some(StreamWrapper::Trade),
                 s if s.starts_with("kl") => Some(StreamWrapper::Kline),
+                s if s.starts_with("fo") => Some(StreamWrapper::Liquidation),
                 _ => None,
             })
     }
 }
 
-fn feed_de(slice: &[u8], market: MarketKind) -> Result<StreamData, AdapterError> {
+/// Parses a combined stream message, along with the name of the stream it came from
+fn feed_de(slice: &[u8], market: MarketKind) -> Result<(String, StreamData), AdapterError> {
     let exchange = exchange_from_market_type(market);
 
+    let mut stream_name = String::new();
     let mut stream_type: Option<StreamWrapper> = None;
     let iter: sonic_rs::ObjectJsonIter = unsafe { to_object_iter_unchecked(slice) };
 
@@ -237,42 +305,53 @@ fn feed_de(slice: &[u8], market: MarketKind) -> Result<StreamData, AdapterError>
             if let Some(s) = StreamWrapper::from_stream_type(&v.as_raw_faststr()) {
                 stream_type = Some(s);
             }
+            v.as_str().unwrap_or_default().clone_into(&mut stream_name);
         } else if k == "data" {
             match stream_type {
                 Some(StreamWrapper::Trade) => {
                     let trade: SonicTrade = sonic_rs::from_str(&v.as_raw_faststr())
                         .map_err(|e| AdapterError::ParseError(e.to_string()))?;
 
-                    return Ok(StreamData::Trade(trade));
+                    return Ok((stream_name, StreamData::Trade(trade)));
                 }
                 Some(StreamWrapper::Depth) => match market {
                     MarketKind::Spot => {
                         let depth: SpotDepth = sonic_rs::from_str(&v.as_raw_faststr())
                             .map_err(|e| AdapterError::ParseError(e.to_string()))?;
 
-                        return Ok(StreamData::Depth(SonicDepth::Spot(depth)));
+                        return Ok((stream_name, StreamData::Depth(SonicDepth::Spot(depth))));
                     }
                     MarketKind::LinearPerps | MarketKind::InversePerps => {
                         let depth: PerpDepth = sonic_rs::from_str(&v.as_raw_faststr())
                             .map_err(|e| AdapterError::ParseError(e.to_string()))?;
 
-                        return Ok(StreamData::Depth(SonicDepth::Perp(depth)));
+                        return Ok((stream_name, StreamData::Depth(SonicDepth::Perp(depth))));
                     }
                 },
                 Some(StreamWrapper::Kline) => {
                     let kline_wrap: SonicKlineWrap = sonic_rs::from_str(&v.as_raw_faststr())
                         .map_err(|e| AdapterError::ParseError(e.to_string()))?;
 
-                    return Ok(StreamData::Kline(
-                        Ticker::new(&kline_wrap.symbol, exchange),
-                        kline_wrap.kline,
+                    return Ok((
+                        stream_name,
+                        StreamData::Kline(
+                            Ticker::new(&kline_wrap.symbol, exchange),
+                            kline_wrap.kline,
+                        ),
                     ));
                 }
+                Some(StreamWrapper::Liquidation) => {
+                    let force_order: SonicForceOrder = sonic_rs::from_str(&v.as_raw_faststr())
+                        .map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+                    return Ok((stream_name, StreamData::Liquidation(force_order.order)));
+                }
                 _ => {
                     log::error!("Unknown stream type");
                 }
             }
-        } else {
+        } else if k != "result" && k != "id" {
+            // "result" and "id" only answer our subscribe requests
             log::error!("Unknown data: {:?}", k);
         }
     }
@@ -282,295 +361,446 @@ fn feed_de(slice: &[u8], market: MarketKind) -> Result<StreamData, AdapterError>
     ))
 }
 
-async fn try_resync(
-    exchange: Exchange,
+type SnapshotResult = Result<Result<DepthPayload, AdapterError>, oneshot::error::RecvError>;
+
+/// Depth and trades of one ticker carried on a market connection
+struct DepthFeed {
+    stream: StreamKind,
     ticker_info: TickerInfo,
     contract_size: Option<f32>,
-    orderbook: &mut LocalDepthCache,
-    state: &mut State,
-    output: &mut mpsc::Sender<Event>,
-    already_fetching: &mut bool,
-) {
-    let ticker = ticker_info.ticker;
+    topics: Vec<String>,
+    orderbook: LocalDepthCache,
+    trades_buffer: Vec<Trade>,
+    prev_id: u64,
+    /// Snapshot being fetched, diffs wait in `pending_diffs` until it's in
+    snapshot: Option<oneshot::Receiver<Result<DepthPayload, AdapterError>>>,
+    pending_diffs: Vec<SonicDepth>,
+}
 
-    let (tx, rx) = tokio::sync::oneshot::channel();
-    *already_fetching = true;
+impl DepthFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::DepthAndTrades { ticker_info, .. } = stream else {
+            return None;
+        };
 
-    tokio::spawn(async move {
-        let result = fetch_depth(&ticker, contract_size).await;
-        let _ = tx.send(result);
-    });
+        let (symbol_str, market) = ticker_info.ticker.to_full_symbol_and_type();
+        let symbol = symbol_str.to_lowercase();
 
-    match rx.await {
-        Ok(Ok(depth)) => {
-            orderbook.update(DepthUpdate::Snapshot(depth), ticker_info.min_ticksize);
+        let mut topics = vec![
+            format!("{symbol}@aggTrade"),
+            format!("{symbol}@depth@100ms"),
+        ];
+        if exchange_from_market_type(market).supports_liquidations() {
+            topics.push(format!("{symbol}@forceOrder"));
         }
-        Ok(Err(e)) => {
-            let _ = output
-                .send(Event::Disconnected(
-                    exchange,
-                    format!("Depth fetch failed: {e}"),
-                ))
-                .await;
+
+        Some(Self {
+            stream,
+            ticker_info,
+            contract_size: get_contract_size(&ticker_info.ticker, market),
+            topics,
+            orderbook: LocalDepthCache::default(),
+            trades_buffer: Vec::new(),
+            prev_id: 0,
+            snapshot: None,
+            pending_diffs: Vec::new(),
+        })
+    }
+
+    /// Starts fetching a fresh snapshot, see [`Self::apply_snapshot`]
+    ///
+    /// The fetch runs on its own task so the other feeds of the connection keep going.
+    async fn resync(&mut self, output: &mut mpsc::Sender<Event>) {
+        let ticker = self.ticker_info.ticker;
+        let contract_size = self.contract_size;
+
+        connect::publish(output, &[self.stream], ConnectionState::Resyncing).await;
+
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let result = fetch_depth(&ticker, contract_size).await;
+            let _ = tx.send(result);
+        });
+
+        self.prev_id = 0;
+        self.orderbook.last_update_id = 0;
+        self.snapshot = Some(rx);
+        self.pending_diffs.clear();
+    }
+
+    /// Replaces the local book with the fetched snapshot and applies the diffs received meanwhile
+    async fn apply_snapshot(
+        &mut self,
+        result: SnapshotResult,
+        exchange: Exchange,
+        output: &mut mpsc::Sender<Event>,
+    ) {
+        let ticker = self.ticker_info.ticker;
+        self.snapshot = None;
+
+        match result {
+            Ok(Ok(depth)) => {
+                self.orderbook
+                    .update(DepthUpdate::Snapshot(depth), self.ticker_info.min_ticksize);
+
+                connect::publish(output, &[self.stream], ConnectionState::Live).await;
+
+                for diff in std::mem::take(&mut self.pending_diffs) {
+                    self.apply_diff(&diff, output).await;
+                }
+            }
+            Ok(Err(e)) => {
+                // retried on the next diff
+                self.pending_diffs.clear();
+
+                let _ = output
+                    .send(Event::Disconnected(
+                        exchange,
+                        format!("Depth fetch failed for {ticker}: {e}"),
+                    ))
+                    .await;
+            }
+            Err(e) => {
+                self.pending_diffs.clear();
+
+                log::error!("Failed to receive fetched depth for {ticker}, error: {e}");
+            }
         }
-        Err(e) => {
-            *state = State::Disconnected;
-
-            output
-                .send(Event::Disconnected(
-                    exchange,
-                    format!("Failed to send fetched depth for {ticker}, error: {e}"),
-                ))
-                .await
-                .expect("Trying to send disconnect event...");
+    }
+
+    async fn apply_diff(&mut self, depth: &SonicDepth, output: &mut mpsc::Sender<Event>) {
+        if self.snapshot.is_some() {
+            self.pending_diffs.push(depth.clone());
+            return;
+        }
+
+        let (time, first_id, final_id, continuity) = match depth {
+            SonicDepth::Perp(de) => (
+                de.time,
+                de.first_id,
+                de.final_id,
+                integrity::check_prev(self.prev_id, de.prev_final_id, de.final_id),
+            ),
+            SonicDepth::Spot(de) => (
+                de.time,
+                de.first_id,
+                de.final_id,
+                integrity::check_next(self.prev_id, de.first_id),
+            ),
+        };
+
+        let last_update_id = self.orderbook.last_update_id;
+
+        if last_update_id == 0 {
+            self.resync(output).await;
+            self.pending_diffs.push(depth.clone());
+            return;
+        }
+        if final_id <= last_update_id {
+            return;
+        }
+
+        if self.prev_id == 0 && first_id > last_update_id + 1 {
+            log::warn!("Out of sync at first event. Trying to resync...\n");
+            self.resync(output).await;
+            self.pending_diffs.push(depth.clone());
+            return;
+        }
+
+        if self.prev_id != 0
+            && let Err(fault) = continuity
+        {
+            integrity::record(self.stream, fault);
+            self.resync(output).await;
+            return;
+        }
+
+        self.orderbook.update(
+            DepthUpdate::Diff(new_depth_cache(depth, self.contract_size)),
+            self.ticker_info.min_ticksize,
+        );
+
+        if let Err(fault) = integrity::check_crossed(&self.orderbook.depth) {
+            integrity::record(self.stream, fault);
+            self.resync(output).await;
+            return;
+        }
+
+        telemetry::observe(self.stream, time);
+
+        let _ = output
+            .send(Event::DepthReceived(
+                self.stream,
+                time,
+                self.orderbook.depth.clone(),
+                std::mem::take(&mut self.trades_buffer).into_boxed_slice(),
+            ))
+            .await;
+
+        self.prev_id = final_id;
+    }
+
+    async fn handle(&mut self, data: &StreamData, output: &mut mpsc::Sender<Event>) {
+        let min_ticksize = self.ticker_info.min_ticksize;
+        let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+
+        match data {
+            StreamData::Trade(de_trade) => {
+                let price = Price::from_f32(de_trade.price).round_to_min_tick(min_ticksize);
+                let qty = self.contract_size.map_or(
+                    if size_in_quote_currency {
+                        (de_trade.qty * de_trade.price).round()
+                    } else {
+                        de_trade.qty
+                    },
+                    |size| de_trade.qty * size,
+                );
+
+                self.trades_buffer.push(Trade {
+                    time: de_trade.time,
+                    is_sell: de_trade.is_sell,
+                    price,
+                    qty,
+                });
+            }
+            StreamData::Liquidation(de_order) => {
+                let liquidation = de_order.liquidation(&self.ticker_info, self.contract_size);
+
+                let _ = output
+                    .send(Event::LiquidationReceived(self.stream, liquidation))
+                    .await;
+            }
+            StreamData::Depth(depth) => self.apply_diff(depth, output).await,
+            StreamData::Kline(..) => {}
         }
     }
-    *already_fetching = false;
 }
 
-#[allow(unused_assignments)]
-pub fn connect_market_stream(
+/// Liquidations of a ticker with no depth stream to carry them
+struct LiquidationFeed {
+    stream: StreamKind,
     ticker_info: TickerInfo,
-    push_freq: PushFrequency,
-) -> impl Stream<Item = Event> {
-    stream::channel(100, async move |mut output| {
-        let mut state = State::Disconnected;
+    contract_size: Option<f32>,
+    topic: String,
+}
 
-        let ticker = ticker_info.ticker;
+impl LiquidationFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::Liquidations { ticker_info } = stream else {
+            return None;
+        };
 
-        let (symbol_str, market) = ticker.to_full_symbol_and_type();
-        let exchange = exchange_from_market_type(market);
+        let (symbol_str, market) = ticker_info.ticker.to_full_symbol_and_type();
 
-        let mut orderbook: LocalDepthCache = LocalDepthCache::default();
-        let mut trades_buffer: Vec<Trade> = Vec::new();
-        let mut already_fetching: bool = false;
-        let mut prev_id: u64 = 0;
+        Some(Self {
+            stream,
+            ticker_info,
+            contract_size: get_contract_size(&ticker_info.ticker, market),
+            topic: format!("{}@forceOrder", symbol_str.to_lowercase()),
+        })
+    }
 
-        let contract_size = get_contract_size(&ticker, market);
-        let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+    async fn handle(&self, data: &StreamData, output: &mut mpsc::Sender<Event>) {
+        if let StreamData::Liquidation(de_order) = data {
+            let liquidation = de_order.liquidation(&self.ticker_info, self.contract_size);
 
-        loop {
-            match &mut state {
-                State::Disconnected => {
-                    let stream_1 = format!("{}@aggTrade", symbol_str.to_lowercase());
-                    let stream_2 = format!("{}@depth@100ms", symbol_str.to_lowercase());
+            let _ = output
+                .send(Event::LiquidationReceived(self.stream, liquidation))
+                .await;
+        }
+    }
+}
+
+/// Index of the first feed whose snapshot fetch finished, along with its result
+fn poll_snapshots(feeds: &mut [DepthFeed], cx: &mut Context<'_>) -> Poll<(usize, SnapshotResult)> {
+    for (index, feed) in feeds.iter_mut().enumerate() {
+        if let Some(snapshot) = &mut feed.snapshot
+            && let Poll::Ready(result) = Pin::new(snapshot).poll(cx)
+        {
+            return Poll::Ready((index, result));
+        }
+    }
+
+    Poll::Pending
+}
 
-                    let domain = ws_domain_from_market_type(market);
-                    let streams = format!("{stream_1}/{stream_2}");
-                    let url = format!("wss://{domain}/stream?streams={streams}");
+async fn send_subscription(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    method: &str,
+    topics: Vec<String>,
+    request_id: &mut u64,
+) -> Result<(), WebSocketError> {
+    if topics.is_empty() {
+        return Ok(());
+    }
+    *request_id += 1;
 
-                    if let Ok(websocket) = connect_ws(domain, &url).await {
-                        let (tx, rx) = tokio::sync::oneshot::channel();
+    let request = serde_json::json!({
+        "method": method,
+        "params": topics,
+        "id": *request_id,
+    });
 
-                        tokio::spawn(async move {
-                            let result = fetch_depth(&ticker, contract_size).await;
-                            let _ = tx.send(result);
-                        });
-                        match rx.await {
-                            Ok(Ok(depth)) => {
-                                orderbook
-                                    .update(DepthUpdate::Snapshot(depth), ticker_info.min_ticksize);
-                                prev_id = 0;
+    websocket
+        .write_frame(Frame::text(Payload::Owned(
+            request.to_string().into_bytes(),
+        )))
+        .await
+}
 
-                                state = State::Connected(websocket);
+/// Subscribes and drops topics on the open socket so it carries exactly `wanted`
+async fn update_subscriptions(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    feeds: &mut Vec<DepthFeed>,
+    liquidation_feeds: &mut Vec<LiquidationFeed>,
+    wanted: &FxHashSet<StreamKind>,
+    request_id: &mut u64,
+    output: &mut mpsc::Sender<Event>,
+) -> Result<(), WebSocketError> {
+    let current_topics = feeds
+        .iter()
+        .flat_map(|feed| feed.topics.iter().cloned())
+        .chain(liquidation_feeds.iter().map(|feed| feed.topic.clone()))
+        .collect::<FxHashSet<_>>();
 
-                                let _ = output.send(Event::Connected(exchange)).await;
-                            }
-                            Ok(Err(e)) => {
-                                let _ = output
-                                    .send(Event::Disconnected(
-                                        exchange,
-                                        format!("Depth fetch failed: {e}"),
-                                    ))
-                                    .await;
-                            }
-                            Err(e) => {
-                                let _ = output
-                                    .send(Event::Disconnected(
-                                        exchange,
-                                        format!("Channel error: {e}"),
-                                    ))
-                                    .await;
-                            }
-                        }
-                    } else {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    feeds.retain(|feed| wanted.contains(&feed.stream));
+    liquidation_feeds.retain(|feed| wanted.contains(&feed.stream));
 
-                        let _ = output
-                            .send(Event::Disconnected(
-                                exchange,
-                                "Failed to connect to websocket".to_string(),
-                            ))
-                            .await;
-                    }
+    let mut added = wanted
+        .iter()
+        .filter(|stream| !feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| DepthFeed::new(*stream))
+        .collect::<Vec<_>>();
+    let added_liquidations = wanted
+        .iter()
+        .filter(|stream| !liquidation_feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| LiquidationFeed::new(*stream))
+        .collect::<Vec<_>>();
+
+    let wanted_topics = feeds
+        .iter()
+        .chain(&added)
+        .flat_map(|feed| feed.topics.iter().cloned())
+        .chain(
+            liquidation_feeds
+                .iter()
+                .chain(&added_liquidations)
+                .map(|feed| feed.topic.clone()),
+        )
+        .collect::<FxHashSet<_>>();
+
+    let (subscribe, unsubscribe) = mux::diff_topics(&current_topics, &wanted_topics);
+
+    send_subscription(websocket, "UNSUBSCRIBE", unsubscribe, request_id).await?;
+    send_subscription(websocket, "SUBSCRIBE", subscribe, request_id).await?;
+
+    // diffs received meanwhile are held by each feed until its snapshot is in
+    for feed in &mut added {
+        feed.resync(output).await;
+    }
+    feeds.extend(added);
+
+    let streams = added_liquidations
+        .iter()
+        .map(|feed| feed.stream)
+        .collect::<Vec<_>>();
+    connect::publish(output, &streams, ConnectionState::Live).await;
+    liquidation_feeds.extend(added_liquidations);
+
+    Ok(())
+}
+
+/// Depth and trades of every stream in `wanted` over a single connection
+async fn run_market_streams(
+    market: MarketKind,
+    mut wanted: mux::WantedStreams,
+    output: &mut mpsc::Sender<Event>,
+) {
+    let exchange = exchange_from_market_type(market);
+    let url = format!("{}/stream", ws_domain_from_market_type(market));
+    let source = capture::Source::new(exchange, "market");
+
+    let mut state = State::Disconnected;
+    let mut backoff = Backoff::default();
+    let mut feeds: Vec<DepthFeed> = Vec::new();
+    let mut liquidation_feeds: Vec<LiquidationFeed> = Vec::new();
+    let mut request_id = 0;
+
+    loop {
+        match &mut state {
+            State::Disconnected => {
+                feeds.clear();
+                liquidation_feeds.clear();
+
+                let streams = wanted.borrow().iter().copied().collect::<Vec<_>>();
+                connect::publish(output, &streams, ConnectionState::Connecting).await;
+
+                if let Ok(websocket) = connect_ws(&url).await {
+                    state = State::Connected(websocket);
+                    backoff.reset();
+                    // a fresh socket has nothing subscribed yet, feeds go live once resynced
+                    wanted.mark_changed();
+
+                    let _ = output.send(Event::Connected(exchange)).await;
+                } else {
+                    let _ = output
+                        .send(Event::Disconnected(
+                            exchange,
+                            "Failed to connect to websocket".to_string(),
+                        ))
+                        .await;
+
+                    backoff.wait(&streams, output).await;
                 }
-                State::Connected(ws) => {
-                    match ws.read_frame().await {
+            }
+            State::Connected(ws) => {
+                tokio::select! {
+                    Ok(()) = wanted.changed() => {
+                        let streams = wanted.borrow_and_update().clone();
+
+                        if let Err(e) = update_subscriptions(
+                            ws,
+                            &mut feeds,
+                            &mut liquidation_feeds,
+                            &streams,
+                            &mut request_id,
+                            output,
+                        )
+                        .await
+                        {
+                            state = State::Disconnected;
+                            let _ = output
+                                .send(Event::Disconnected(
+                                    exchange,
+                                    format!("Failed updating subscriptions: {e}"),
+                                ))
+                                .await;
+                        }
+                    }
+                    (index, result) = std::future::poll_fn(|cx| poll_snapshots(&mut feeds, cx)) => {
+                        feeds[index].apply_snapshot(result, exchange, output).await;
+                    }
+                    frame = ws.read_frame() => match frame {
                         Ok(msg) => match msg.opcode {
                             OpCode::Text => {
-                                if let Ok(data) = feed_de(&msg.payload[..], market) {
-                                    match data {
-                                        StreamData::Trade(de_trade) => {
-                                            let price = Price::from_f32(de_trade.price)
-                                                .round_to_min_tick(ticker_info.min_ticksize);
-                                            let qty = contract_size.map_or(
-                                                if size_in_quote_currency {
-                                                    (de_trade.qty * de_trade.price).round()
-                                                } else {
-                                                    de_trade.qty
-                                                },
-                                                |size| de_trade.qty * size,
-                                            );
-
-                                            let trade = Trade {
-                                                time: de_trade.time,
-                                                is_sell: de_trade.is_sell,
-                                                price,
-                                                qty,
-                                            };
-
-                                            trades_buffer.push(trade);
-                                        }
-                                        StreamData::Depth(depth_type) => {
-                                            if already_fetching {
-                                                log::warn!("Already fetching...\n");
-                                                continue;
-                                            }
-
-                                            let last_update_id = orderbook.last_update_id;
-
-                                            match depth_type {
-                                                SonicDepth::Perp(ref de_depth) => {
-                                                    if (de_depth.final_id <= last_update_id)
-                                                        || last_update_id == 0
-                                                    {
-                                                        continue;
-                                                    }
-
-                                                    if prev_id == 0
-                                                        && (de_depth.first_id > last_update_id + 1)
-                                                        || (last_update_id + 1 > de_depth.final_id)
-                                                    {
-                                                        log::warn!(
-                                                            "Out of sync at first event. Trying to resync...\n"
-                                                        );
-
-                                                        try_resync(
-                                                            exchange,
-                                                            ticker_info,
-                                                            contract_size,
-                                                            &mut orderbook,
-                                                            &mut state,
-                                                            &mut output,
-                                                            &mut already_fetching,
-                                                        )
-                                                        .await;
-                                                    }
-
-                                                    if (prev_id == 0)
-                                                        || (prev_id == de_depth.prev_final_id)
-                                                    {
-                                                        orderbook.update(
-                                                            DepthUpdate::Diff(new_depth_cache(
-                                                                &depth_type,
-                                                                contract_size,
-                                                            )),
-                                                            ticker_info.min_ticksize,
-                                                        );
-
-                                                        let _ = output
-                                                            .send(Event::DepthReceived(
-                                                                StreamKind::DepthAndTrades {
-                                                                    ticker_info,
-                                                                    depth_aggr:
-                                                                        StreamTicksize::Client,
-                                                                    push_freq,
-                                                                },
-                                                                de_depth.time,
-                                                                orderbook.depth.clone(),
-                                                                std::mem::take(&mut trades_buffer)
-                                                                    .into_boxed_slice(),
-                                                            ))
-                                                            .await;
-
-                                                        prev_id = de_depth.final_id;
-                                                    } else {
-                                                        state = State::Disconnected;
-                                                        let _ = output.send(
-                                                                Event::Disconnected(
-                                                                    exchange,
-                                                                    format!("Out of sync. Expected update_id: {}, got: {}", de_depth.prev_final_id, prev_id)
-                                                                )
-                                                            ).await;
-                                                    }
-                                                }
-                                                SonicDepth::Spot(ref de_depth) => {
-                                                    if (de_depth.final_id <= last_update_id)
-                                                        || last_update_id == 0
-                                                    {
-                                                        continue;
-                                                    }
-
-                                                    if prev_id == 0
-                                                        && (de_depth.first_id > last_update_id + 1)
-                                                        || (last_update_id + 1 > de_depth.final_id)
-                                                    {
-                                                        log::warn!(
-                                                            "Out of sync at first event. Trying to resync...\n"
-                                                        );
-
-                                                        try_resync(
-                                                            exchange,
-                                                            ticker_info,
-                                                            contract_size,
-                                                            &mut orderbook,
-                                                            &mut state,
-                                                            &mut output,
-                                                            &mut already_fetching,
-                                                        )
-                                                        .await;
-                                                    }
-
-                                                    if (prev_id == 0)
-                                                        || (prev_id == de_depth.first_id - 1)
-                                                    {
-                                                        orderbook.update(
-                                                            DepthUpdate::Diff(new_depth_cache(
-                                                                &depth_type,
-                                                                contract_size,
-                                                            )),
-                                                            ticker_info.min_ticksize,
-                                                        );
-
-                                                        let _ = output
-                                                            .send(Event::DepthReceived(
-                                                                StreamKind::DepthAndTrades {
-                                                                    ticker_info,
-                                                                    depth_aggr:
-                                                                        StreamTicksize::Client,
-                                                                    push_freq,
-                                                                },
-                                                                de_depth.time,
-                                                                orderbook.depth.clone(),
-                                                                std::mem::take(&mut trades_buffer)
-                                                                    .into_boxed_slice(),
-                                                            ))
-                                                            .await;
-
-                                                        prev_id = de_depth.final_id;
-                                                    } else {
-                                                        state = State::Disconnected;
-                                                        let _ = output.send(
-                                                                Event::Disconnected(
-                                                                    exchange,
-                                                                    format!("Out of sync. Expected update_id: {}, got: {}", de_depth.final_id, prev_id)
-                                                                )
-                                                            ).await;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        _ => {}
+                                capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
+                                if let Ok((topic, data)) = feed_de(&msg.payload[..], market) {
+                                    let routed = feeds
+                                        .iter_mut()
+                                        .filter(|feed| feed.topics.contains(&topic));
+
+                                    for feed in routed {
+                                        feed.handle(&data, output).await;
+                                    }
+
+                                    let routed = liquidation_feeds
+                                        .iter()
+                                        .filter(|feed| feed.topic == topic);
+
+                                    for feed in routed {
+                                        feed.handle(&data, output).await;
                                     }
                                 }
                             }
@@ -594,10 +824,34 @@ pub fn connect_market_stream(
                                 ))
                                 .await;
                         }
-                    };
+                    },
                 }
             }
         }
+    }
+}
+
+pub fn connect_market_stream(
+    ticker_info: TickerInfo,
+    push_freq: PushFrequency,
+) -> impl Stream<Item = Event> {
+    let stream = StreamKind::DepthAndTrades {
+        ticker_info,
+        depth_aggr: StreamTicksize::Client,
+        push_freq,
+    };
+
+    stream::channel(100, async move |mut output| {
+        let market = ticker_info.market_type();
+        run_market_streams(market, mux::fixed([stream]), &mut output).await;
+    })
+}
+
+/// Depth and trades of all the tickers wanted from a market, over one connection
+pub fn connect_shared_market_stream(market: MarketKind) -> impl Stream<Item = Event> {
+    stream::channel(100, async move |mut output| {
+        let wanted = mux::wanted(exchange_from_market_type(market));
+        run_market_streams(market, wanted, &mut output).await;
     })
 }
 
@@ -607,6 +861,7 @@ pub fn connect_kline_stream(
 ) -> impl Stream<Item = Event> {
     stream::channel(100, async move |mut output| {
         let mut state = State::Disconnected;
+        let mut backoff = Backoff::default();
         let exchange = exchange_from_market_type(market);
 
         let ticker_info_map = streams
@@ -614,43 +869,62 @@ pub fn connect_kline_stream(
             .map(|(ticker_info, _)| (ticker_info.ticker, *ticker_info))
             .collect::<HashMap<Ticker, TickerInfo>>();
 
+        let kline_streams = streams
+            .iter()
+            .map(|(ticker_info, timeframe)| StreamKind::Kline {
+                ticker_info: *ticker_info,
+                timeframe: *timeframe,
+            })
+            .collect::<Vec<_>>();
+
+        let url = {
+            let stream_str = streams
+                .iter()
+                .map(|(ticker_info, timeframe)| {
+                    let ticker = ticker_info.ticker;
+                    format!(
+                        "{}@kline_{}",
+                        ticker.to_full_symbol_and_type().0.to_lowercase(),
+                        timeframe
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("/");
+
+            let domain = ws_domain_from_market_type(market);
+            format!("{domain}/stream?streams={stream_str}")
+        };
+        let source = capture::Source::new(exchange, "klines");
+
         loop {
             match &mut state {
                 State::Disconnected => {
-                    let stream_str = streams
-                        .iter()
-                        .map(|(ticker_info, timeframe)| {
-                            let ticker = ticker_info.ticker;
-                            format!(
-                                "{}@kline_{}",
-                                ticker.to_full_symbol_and_type().0.to_lowercase(),
-                                timeframe
-                            )
-                        })
-                        .collect::<Vec<String>>()
-                        .join("/");
-
-                    let domain = ws_domain_from_market_type(market);
-                    let url = format!("wss://{domain}/stream?streams={stream_str}");
+                    connect::publish(&mut output, &kline_streams, ConnectionState::Connecting)
+                        .await;
 
-                    if let Ok(websocket) = connect_ws(domain, &url).await {
+                    if let Ok(websocket) = connect_ws(&url).await {
                         state = State::Connected(websocket);
+                        backoff.reset();
+
                         let _ = output.send(Event::Connected(exchange)).await;
+                        connect::publish(&mut output, &kline_streams, ConnectionState::Live).await;
                     } else {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
                         let _ = output
                             .send(Event::Disconnected(
                                 exchange,
                                 "Failed to connect to websocket".to_string(),
                             ))
                             .await;
+
+                        backoff.wait(&kline_streams, &mut output).await;
                     }
                 }
                 State::Connected(ws) => match ws.read_frame().await {
                     Ok(msg) => match msg.opcode {
                         OpCode::Text => {
-                            if let Ok(StreamData::Kline(ticker, de_kline)) =
+                            capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
+                            if let Ok((_, StreamData::Kline(ticker, de_kline))) =
                                 feed_de(&msg.payload[..], market)
                             {
                                 let (buy_volume, sell_volume) = {
@@ -776,9 +1050,9 @@ async fn fetch_depth(
     let (symbol_str, market_type) = ticker.to_full_symbol_and_type();
 
     let base_url = match market_type {
-        MarketKind::Spot => SPOT_DOMAIN.to_string() + "/api/v3/depth",
-        MarketKind::LinearPerps => LINEAR_PERP_DOMAIN.to_string() + "/fapi/v1/depth",
-        MarketKind::InversePerps => INVERSE_PERP_DOMAIN.to_string() + "/dapi/v1/depth",
+        MarketKind::Spot => endpoint::resolve(SPOT_DOMAIN) + "/api/v3/depth",
+        MarketKind::LinearPerps => endpoint::resolve(LINEAR_PERP_DOMAIN) + "/fapi/v1/depth",
+        MarketKind::InversePerps => endpoint::resolve(INVERSE_PERP_DOMAIN) + "/dapi/v1/depth",
     };
 
     let depth_limit = match market_type {
@@ -813,6 +1087,9 @@ async fn fetch_depth(
     let limiter = limiter_from_market_type(market_type);
     let text = crate::limiter::http_request_with_limiter(&url, limiter, weight, None, None).await?;
 
+    let source = capture::Source::new(ticker.exchange, "market");
+    capture::record(&source, FrameKind::Rest, &url, text.as_bytes());
+
     let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
 
     match market_type {
@@ -929,9 +1206,9 @@ pub async fn fetch_klines(
     let timeframe_str = timeframe.to_string();
 
     let base_url = match market_type {
-        MarketKind::Spot => SPOT_DOMAIN.to_string() + "/api/v3/klines",
-        MarketKind::LinearPerps => LINEAR_PERP_DOMAIN.to_string() + "/fapi/v1/klines",
-        MarketKind::InversePerps => INVERSE_PERP_DOMAIN.to_string() + "/dapi/v1/klines",
+        MarketKind::Spot => endpoint::resolve(SPOT_DOMAIN) + "/api/v3/klines",
+        MarketKind::LinearPerps => endpoint::resolve(LINEAR_PERP_DOMAIN) + "/fapi/v1/klines",
+        MarketKind::InversePerps => endpoint::resolve(INVERSE_PERP_DOMAIN) + "/dapi/v1/klines",
     };
 
     let mut url = format!("{base_url}?symbol={symbol_str}&interval={timeframe_str}");
@@ -1022,12 +1299,18 @@ pub async fn fetch_ticksize(
     market: MarketKind,
 ) -> Result<HashMap<Ticker, Option<TickerInfo>>, AdapterError> {
     let (url, _weight) = match market {
-        MarketKind::Spot => (SPOT_DOMAIN.to_string() + "/api/v3/exchangeInfo", 20),
-        MarketKind::LinearPerps => (LINEAR_PERP_DOMAIN.to_string() + "/fapi/v1/exchangeInfo", 1),
-        MarketKind::InversePerps => (INVERSE_PERP_DOMAIN.to_string() + "/dapi/v1/exchangeInfo", 1),
+        MarketKind::Spot => (endpoint::resolve(SPOT_DOMAIN) + "/api/v3/exchangeInfo", 20),
+        MarketKind::LinearPerps => (
+            endpoint::resolve(LINEAR_PERP_DOMAIN) + "/fapi/v1/exchangeInfo",
+            1,
+        ),
+        MarketKind::InversePerps => (
+            endpoint::resolve(INVERSE_PERP_DOMAIN) + "/dapi/v1/exchangeInfo",
+            1,
+        ),
     };
 
-    let response_text = crate::limiter::HTTP_CLIENT
+    let response_text = crate::proxy::http_client(&url)?
         .get(&url)
         .send()
         .await
@@ -1117,9 +1400,15 @@ pub async fn fetch_ticker_prices(
     market: MarketKind,
 ) -> Result<HashMap<Ticker, TickerStats>, AdapterError> {
     let (url, weight) = match market {
-        MarketKind::Spot => (SPOT_DOMAIN.to_string() + "/api/v3/ticker/24hr", 80),
-        MarketKind::LinearPerps => (LINEAR_PERP_DOMAIN.to_string() + "/fapi/v1/ticker/24hr", 40),
-        MarketKind::InversePerps => (INVERSE_PERP_DOMAIN.to_string() + "/dapi/v1/ticker/24hr", 40),
+        MarketKind::Spot => (endpoint::resolve(SPOT_DOMAIN) + "/api/v3/ticker/24hr", 80),
+        MarketKind::LinearPerps => (
+            endpoint::resolve(LINEAR_PERP_DOMAIN) + "/fapi/v1/ticker/24hr",
+            40,
+        ),
+        MarketKind::InversePerps => (
+            endpoint::resolve(INVERSE_PERP_DOMAIN) + "/dapi/v1/ticker/24hr",
+            40,
+        ),
     };
 
     let limiter = limiter_from_market_type(market);
@@ -1215,12 +1504,12 @@ pub async fn fetch_historical_oi(
 
     let (base_url, pair_str, weight) = match market {
         MarketKind::LinearPerps => (
-            LINEAR_PERP_DOMAIN.to_string() + "/futures/data/openInterestHist",
+            endpoint::resolve(LINEAR_PERP_DOMAIN) + "/futures/data/openInterestHist",
             format!("?symbol={ticker_str}",),
             12,
         ),
         MarketKind::InversePerps => (
-            INVERSE_PERP_DOMAIN.to_string() + "/futures/data/openInterestHist",
+            endpoint::resolve(INVERSE_PERP_DOMAIN) + "/futures/data/openInterestHist",
             format!(
                 "?pair={}&contractType=PERPETUAL",
                 ticker_str
@@ -1302,6 +1591,109 @@ pub async fn fetch_historical_oi(
     Ok(open_interest)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeFundingRate {
+    funding_time: u64,
+    #[serde(deserialize_with = "de_string_to_f32")]
+    funding_rate: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DePremiumIndex {
+    #[serde(deserialize_with = "de_string_to_f32")]
+    last_funding_rate: f32,
+    next_funding_time: u64,
+}
+
+fn funding_domain(market: MarketKind) -> Result<(String, &'static str), AdapterError> {
+    match market {
+        MarketKind::LinearPerps => Ok((endpoint::resolve(LINEAR_PERP_DOMAIN), "/fapi/v1")),
+        MarketKind::InversePerps => Ok((endpoint::resolve(INVERSE_PERP_DOMAIN), "/dapi/v1")),
+        MarketKind::Spot => Err(AdapterError::InvalidRequest(
+            "Funding rates are only available for perpetuals".to_string(),
+        )),
+    }
+}
+
+pub async fn fetch_funding_rates(
+    ticker: Ticker,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<FundingRate>, AdapterError> {
+    let (ticker_str, market) = ticker.to_full_symbol_and_type();
+    let (domain, api_path) = funding_domain(market)?;
+
+    let mut url = format!("{domain}{api_path}/fundingRate?symbol={ticker_str}&limit=1000");
+
+    if let Some((start, end)) = range {
+        url.push_str(&format!("&startTime={start}&endTime={end}"));
+    }
+
+    let limiter = limiter_from_market_type(market);
+    let text = crate::limiter::http_request_with_limiter(&url, limiter, 1, None, None).await?;
+
+    let rates: Vec<DeFundingRate> = serde_json::from_str(&text).map_err(|e| {
+        log::error!(
+            "Failed to parse response from {}: {}\nResponse: {}",
+            url,
+            e,
+            text
+        );
+        AdapterError::ParseError(format!("Failed to parse funding rates: {e}"))
+    })?;
+
+    Ok(rates
+        .into_iter()
+        .map(|x| FundingRate {
+            time: x.funding_time,
+            rate: x.funding_rate,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct DeServerTime {
+    #[serde(rename = "serverTime")]
+    server_time: u64,
+}
+
+pub async fn fetch_server_time() -> Result<u64, AdapterError> {
+    let url = endpoint::resolve(LINEAR_PERP_DOMAIN) + "/fapi/v1/time";
+
+    let limiter = limiter_from_market_type(MarketKind::LinearPerps);
+    let text = crate::limiter::http_request_with_limiter(&url, limiter, 1, None, None).await?;
+
+    serde_json::from_str::<DeServerTime>(&text)
+        .map(|time| time.server_time)
+        .map_err(|e| AdapterError::ParseError(format!("Failed to parse server time: {e}")))
+}
+
+pub async fn fetch_predicted_funding(ticker: Ticker) -> Result<FundingRate, AdapterError> {
+    let (ticker_str, market) = ticker.to_full_symbol_and_type();
+    let (domain, api_path) = funding_domain(market)?;
+
+    let url = format!("{domain}{api_path}/premiumIndex?symbol={ticker_str}");
+
+    let limiter = limiter_from_market_type(market);
+    let text = crate::limiter::http_request_with_limiter(&url, limiter, 1, None, None).await?;
+
+    // Coin-M responds with an array even when a single symbol is requested
+    let premium_index = match market {
+        MarketKind::InversePerps => {
+            serde_json::from_str::<Vec<DePremiumIndex>>(&text).map(|list| list.into_iter().next())
+        }
+        _ => serde_json::from_str::<DePremiumIndex>(&text).map(Some),
+    }
+    .map_err(|e| AdapterError::ParseError(format!("Failed to parse premium index: {e}")))?
+    .ok_or_else(|| AdapterError::ParseError(format!("No premium index for {ticker_str}")))?;
+
+    Ok(FundingRate {
+        time: premium_index.next_funding_time,
+        rate: premium_index.last_funding_rate,
+    })
+}
+
 pub async fn fetch_trades(
     ticker_info: TickerInfo,
     from_time: u64,
@@ -1341,9 +1733,15 @@ pub async fn fetch_intraday_trades(
     let (symbol_str, market_type) = ticker.to_full_symbol_and_type();
 
     let (base_url, weight) = match market_type {
-        MarketKind::Spot => (SPOT_DOMAIN.to_string() + "/api/v3/aggTrades", 4),
-        MarketKind::LinearPerps => (LINEAR_PERP_DOMAIN.to_string() + "/fapi/v1/aggTrades", 20),
-        MarketKind::InversePerps => (INVERSE_PERP_DOMAIN.to_string() + "/dapi/v1/aggTrades", 20),
+        MarketKind::Spot => (endpoint::resolve(SPOT_DOMAIN) + "/api/v3/aggTrades", 4),
+        MarketKind::LinearPerps => (
+            endpoint::resolve(LINEAR_PERP_DOMAIN) + "/fapi/v1/aggTrades",
+            20,
+        ),
+        MarketKind::InversePerps => (
+            endpoint::resolve(INVERSE_PERP_DOMAIN) + "/dapi/v1/aggTrades",
+            20,
+        ),
     };
 
     let mut url = format!("{base_url}?symbol={symbol_str}&limit=1000",);
@@ -1411,11 +1809,15 @@ pub async fn get_hist_trades(
     if std::fs::metadata(&base_zip_path).is_ok() {
         log::info!("Using cached {}", zip_path);
     } else {
-        let url = format!("https://data.binance.vision/{zip_path}");
+        let url = format!("{}/{zip_path}", endpoint::resolve(BULK_DATA_DOMAIN));
 
         log::info!("Downloading from {}", url);
 
-        let resp = reqwest::get(&url).await.map_err(AdapterError::FetchError)?;
+        let resp = crate::proxy::http_client(&url)?
+            .get(&url)
+            .send()
+            .await
+            .map_err(AdapterError::FetchError)?;
 
         if !resp.status().is_success() {
             return Err(AdapterError::InvalidRequest(format!(
@@ -1494,3 +1896,125 @@ pub async fn get_hist_trades(
         ))),
     }
 }
+
+pub struct Binance;
+
+impl ExchangeAdapter for Binance {
+    fn venue(&self) -> ExchangeInclusive {
+        ExchangeInclusive::Binance
+    }
+
+    fn name(&self) -> &'static str {
+        "Binance"
+    }
+
+    fn id(&self) -> &'static str {
+        "binance"
+    }
+
+    fn markets(&self) -> &'static [Exchange] {
+        &[
+            Exchange::BinanceLinear,
+            Exchange::BinanceInverse,
+            Exchange::BinanceSpot,
+        ]
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["binance.com", "binance.vision"]
+    }
+
+    fn supports_open_interest(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn supports_trade_backfill(&self, _market: MarketKind) -> bool {
+        true
+    }
+
+    fn supports_liquidations(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn fetch_ticker_info(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, Option<TickerInfo>>> {
+        Box::pin(fetch_ticksize(market))
+    }
+
+    fn fetch_ticker_prices(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, TickerStats>> {
+        Box::pin(fetch_ticker_prices(market))
+    }
+
+    fn fetch_klines(
+        &self,
+        ticker_info: TickerInfo,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<Kline>> {
+        Box::pin(fetch_klines(ticker_info, timeframe, range))
+    }
+
+    fn fetch_open_interest(
+        &self,
+        ticker: Ticker,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<OpenInterest>> {
+        Box::pin(fetch_historical_oi(ticker, range, timeframe))
+    }
+
+    fn fetch_funding_rates(
+        &self,
+        ticker: Ticker,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<FundingRate>> {
+        Box::pin(fetch_funding_rates(ticker, range))
+    }
+
+    fn fetch_predicted_funding(&self, ticker: Ticker) -> AdapterFuture<FundingRate> {
+        Box::pin(fetch_predicted_funding(ticker))
+    }
+
+    fn fetch_server_time(&self) -> AdapterFuture<u64> {
+        Box::pin(fetch_server_time())
+    }
+
+    fn fetch_trades(
+        &self,
+        ticker_info: TickerInfo,
+        from_time: u64,
+        data_path: PathBuf,
+    ) -> AdapterFuture<Vec<Trade>> {
+        Box::pin(fetch_trades(ticker_info, from_time, data_path))
+    }
+
+    fn connect_market_stream(
+        &self,
+        ticker_info: TickerInfo,
+        _tick_multiplier: Option<TickMultiplier>,
+        push_freq: PushFrequency,
+    ) -> EventStream {
+        Box::pin(connect_market_stream(ticker_info, push_freq))
+    }
+
+    fn connect_kline_stream(
+        &self,
+        streams: Vec<(TickerInfo, Timeframe)>,
+        market: MarketKind,
+    ) -> EventStream {
+        Box::pin(connect_kline_stream(streams, market))
+    }
+
+    fn multiplexes_market_streams(&self) -> bool {
+        true
+    }
+
+    fn connect_shared_market_stream(&self, market: MarketKind) -> EventStream {
+        Box::pin(connect_shared_market_stream(market))
+    }
+}
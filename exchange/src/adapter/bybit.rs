@@ -1,31 +1,44 @@
-use crate::{Price, PushFrequency, adapter::StreamTicksize};
+use crate::{Price, PushFrequency, TickMultiplier, adapter::StreamTicksize, endpoint};
 
 use super::{
     super::{
-        Exchange, Kline, MarketKind, OpenInterest, SIZE_IN_QUOTE_CURRENCY, StreamKind, Ticker,
-        TickerInfo, TickerStats, Timeframe, Trade,
-        connect::{State, connect_ws},
+        Exchange, FundingRate, Kline, Liquidation, MarketKind, OpenInterest,
+        SIZE_IN_QUOTE_CURRENCY, StreamKind, Ticker, TickerInfo, TickerStats, Timeframe, Trade,
+        capture::{self, FrameKind},
+        connect::{self, Backoff, State, connect_ws},
         de_string_to_f32, de_string_to_u64,
         depth::{DeOrder, DepthPayload, DepthUpdate, LocalDepthCache},
         is_symbol_supported,
         limiter::{self, http_request_with_limiter},
     },
-    AdapterError, Event,
+    AdapterError, ConnectionState, Event, ExchangeInclusive, integrity, mux,
+    registry::{AdapterFuture, EventStream, ExchangeAdapter},
+    telemetry,
 };
 
-use fastwebsockets::{Frame, OpCode};
+use fastwebsockets::{FragmentCollector, Frame, OpCode, Payload, WebSocketError};
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
 use iced_futures::{
     futures::{SinkExt, Stream, channel::mpsc},
     stream,
 };
+use rustc_hash::FxHashSet;
 use serde_json::{Value, json};
 use sonic_rs::{Deserialize, JsonValueTrait, to_object_iter_unchecked};
 use tokio::sync::Mutex;
 
-use std::{collections::HashMap, sync::LazyLock, time::Duration};
+use std::{
+    collections::HashMap,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
 
-const WS_DOMAIN: &str = "stream.bybit.com";
+const WS_DOMAIN: &str = "wss://stream.bybit.com";
 const FETCH_DOMAIN: &str = "https://api.bybit.com";
+const BULK_DATA_DOMAIN: &str = "https://public.bybit.com";
 
 static BYBIT_LIMITER: LazyLock<Mutex<BybitLimiter>> =
     LazyLock::new(|| Mutex::new(BybitLimiter::new(LIMIT, REFILL_RATE)));
@@ -57,7 +70,7 @@ impl limiter::RateLimiter for BybitLimiter {
         self.bucket.consume_tokens(weight);
     }
 
-    fn should_exit_on_response(&self, response: &reqwest::Response) -> bool {
+    fn is_rate_limited(&self, response: &reqwest::Response) -> bool {
         response.status() == 403
     }
 }
@@ -74,6 +87,8 @@ fn exchange_from_market_type(market: MarketKind) -> Exchange {
 struct SonicDepth {
     #[serde(rename = "u")]
     pub update_id: u64,
+    #[serde(default)]
+    pub seq: u64,
     #[serde(rename = "b")]
     pub bids: Vec<DeOrder>,
     #[serde(rename = "a")]
@@ -92,6 +107,37 @@ struct SonicTrade {
     pub is_sell: String,
 }
 
+#[derive(Deserialize, Debug)]
+struct SonicLiquidation {
+    #[serde(rename = "T")]
+    pub time: u64,
+    #[serde(rename = "p", deserialize_with = "de_string_to_f32")]
+    pub price: f32,
+    #[serde(rename = "v", deserialize_with = "de_string_to_f32")]
+    pub qty: f32,
+    /// Side of the liquidated position, "Buy" for a long
+    #[serde(rename = "S")]
+    pub position_side: String,
+}
+
+impl SonicLiquidation {
+    fn liquidation(&self, ticker_info: &TickerInfo) -> Liquidation {
+        let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true)
+            && ticker_info.market_type() != MarketKind::InversePerps;
+
+        Liquidation {
+            time: self.time,
+            is_sell: self.position_side == "Buy",
+            price: Price::from_f32(self.price).round_to_min_tick(ticker_info.min_ticksize),
+            qty: if size_in_quote_currency {
+                (self.qty * self.price).round()
+            } else {
+                self.qty
+            },
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct SonicKline {
     #[serde(rename = "start")]
@@ -114,6 +160,7 @@ enum StreamData {
     Trade(Vec<SonicTrade>),
     Depth(SonicDepth, String, u64),
     Kline(Ticker, Vec<SonicKline>),
+    Liquidation(Vec<SonicLiquidation>),
 }
 
 #[derive(Debug)]
@@ -121,6 +168,7 @@ enum StreamName {
     Depth(Ticker),
     Trade(Ticker),
     Kline(Ticker),
+    Liquidation(Ticker),
     Unknown,
 }
 
@@ -136,6 +184,7 @@ impl StreamName {
                 Some(&"publicTrade") => StreamName::Trade(ticker),
                 Some(&"orderbook") => StreamName::Depth(ticker),
                 Some(&"kline") => StreamName::Kline(ticker),
+                Some(&"allLiquidation") => StreamName::Liquidation(ticker),
                 _ => StreamName::Unknown,
             }
         } else {
@@ -149,14 +198,17 @@ enum StreamWrapper {
     Trade,
     Depth,
     Kline,
+    Liquidation,
 }
 
 #[allow(unused_assignments)]
+/// Parses a public stream message, along with the topic it was published on
 fn feed_de(
     slice: &[u8],
     ticker: Option<Ticker>,
     market_type: MarketKind,
-) -> Result<StreamData, AdapterError> {
+) -> Result<(String, StreamData), AdapterError> {
+    let mut topic = String::new();
     let mut stream_type: Option<StreamWrapper> = None;
     let mut depth_wrap: Option<SonicDepth> = None;
 
@@ -170,6 +222,8 @@ fn feed_de(
 
         if k == "topic" {
             if let Some(val) = v.as_str() {
+                val.clone_into(&mut topic);
+
                 let mut is_ticker = None;
 
                 if let Some(t) = ticker {
@@ -189,6 +243,10 @@ fn feed_de(
                         stream_type = Some(StreamWrapper::Kline);
                         topic_ticker = Some(t);
                     }
+                    StreamName::Liquidation(t) => {
+                        stream_type = Some(StreamWrapper::Liquidation);
+                        topic_ticker = Some(t);
+                    }
                     _ => {
                         log::error!("Unknown stream name");
                     }
@@ -202,12 +260,13 @@ fn feed_de(
                     let trade_wrap: Vec<SonicTrade> = sonic_rs::from_str(&v.as_raw_faststr())
                         .map_err(|e| AdapterError::ParseError(e.to_string()))?;
 
-                    return Ok(StreamData::Trade(trade_wrap));
+                    return Ok((topic, StreamData::Trade(trade_wrap)));
                 }
                 Some(StreamWrapper::Depth) => {
                     if depth_wrap.is_none() {
                         depth_wrap = Some(SonicDepth {
                             update_id: 0,
+                            seq: 0,
                             bids: Vec::new(),
                             asks: Vec::new(),
                         });
@@ -222,13 +281,20 @@ fn feed_de(
                         .map_err(|e| AdapterError::ParseError(e.to_string()))?;
 
                     if let Some(t) = topic_ticker {
-                        return Ok(StreamData::Kline(t, kline_wrap));
+                        return Ok((topic, StreamData::Kline(t, kline_wrap)));
                     } else {
                         return Err(AdapterError::ParseError(
                             "Missing ticker for kline data".to_string(),
                         ));
                     }
                 }
+                Some(StreamWrapper::Liquidation) => {
+                    let liquidations: Vec<SonicLiquidation> =
+                        sonic_rs::from_str(&v.as_raw_faststr())
+                            .map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+                    return Ok((topic, StreamData::Liquidation(liquidations)));
+                }
                 _ => {
                     log::error!("Unknown stream type");
                 }
@@ -240,16 +306,30 @@ fn feed_de(
                 .as_u64()
                 .ok_or_else(|| AdapterError::ParseError("Failed to parse u64".to_string()))?;
 
-            return Ok(StreamData::Depth(dw, data_type.to_string(), time));
+            return Ok((topic, StreamData::Depth(dw, data_type.to_string(), time)));
         }
     }
 
     Err(AdapterError::ParseError("Unknown data".to_string()))
 }
 
+fn ws_url(market_type: MarketKind) -> String {
+    format!(
+        "{}/v5/public/{}",
+        endpoint::resolve(WS_DOMAIN),
+        match market_type {
+            MarketKind::Spot => "spot",
+            MarketKind::LinearPerps => "linear",
+            MarketKind::InversePerps => "inverse",
+        }
+    )
+}
+
 async fn try_connect(
-    streams: &Value,
+    subscribe_message: &Value,
+    streams: &[StreamKind],
     market_type: MarketKind,
+    backoff: &mut Backoff,
     output: &mut mpsc::Sender<Event>,
 ) -> State {
     let exchange = match market_type {
@@ -257,21 +337,14 @@ async fn try_connect(
         MarketKind::LinearPerps => Exchange::BybitLinear,
         MarketKind::InversePerps => Exchange::BybitInverse,
     };
-    let url = format!(
-        "wss://{}/v5/public/{}",
-        WS_DOMAIN,
-        match market_type {
-            MarketKind::Spot => "spot",
-            MarketKind::LinearPerps => "linear",
-            MarketKind::InversePerps => "inverse",
-        }
-    );
 
-    match connect_ws(WS_DOMAIN, &url).await {
+    connect::publish(output, streams, ConnectionState::Connecting).await;
+
+    match connect_ws(&ws_url(market_type)).await {
         Ok(mut websocket) => {
             if let Err(e) = websocket
                 .write_frame(Frame::text(fastwebsockets::Payload::Borrowed(
-                    streams.to_string().as_bytes(),
+                    subscribe_message.to_string().as_bytes(),
                 )))
                 .await
             {
@@ -281,184 +354,504 @@ async fn try_connect(
                         format!("Failed subscribing: {e}"),
                     ))
                     .await;
+                backoff.wait(streams, output).await;
                 return State::Disconnected;
             }
+            backoff.reset();
 
             let _ = output.send(Event::Connected(exchange)).await;
+            connect::publish(output, streams, ConnectionState::Live).await;
+
             State::Connected(websocket)
         }
         Err(err) => {
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
             let _ = output
                 .send(Event::Disconnected(
                     exchange,
                     format!("Failed to connect: {err}"),
                 ))
                 .await;
+
+            backoff.wait(streams, output).await;
             State::Disconnected
         }
     }
 }
 
-pub fn connect_market_stream(
+/// Orderbook depth level of the topic matching a push frequency
+fn depth_level(market_type: MarketKind, push_freq: PushFrequency) -> &'static str {
+    if let PushFrequency::Custom(tf) = push_freq {
+        match market_type {
+            MarketKind::Spot => match tf {
+                Timeframe::MS100 => "200",
+                Timeframe::MS300 => "1000",
+                _ => "200",
+            },
+            MarketKind::LinearPerps | MarketKind::InversePerps => match tf {
+                Timeframe::MS200 => "200",
+                Timeframe::MS300 => "1000",
+                _ => "200",
+            },
+        }
+    } else {
+        "200"
+    }
+}
+
+/// Depth and trades of one ticker carried on a market connection
+struct DepthFeed {
+    stream: StreamKind,
     ticker_info: TickerInfo,
-    push_freq: PushFrequency,
-) -> impl Stream<Item = Event> {
-    stream::channel(100, async move |mut output| {
-        let mut state: State = State::Disconnected;
+    depth_topic: String,
+    topics: Vec<String>,
+    orderbook: LocalDepthCache,
+    trades_buffer: Vec<Trade>,
+    /// Cross sequence of the last applied update
+    last_seq: u64,
+}
 
-        let ticker = ticker_info.ticker;
+impl DepthFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::DepthAndTrades {
+            ticker_info,
+            push_freq,
+            ..
+        } = stream
+        else {
+            return None;
+        };
 
-        let (symbol_str, market_type) = ticker.to_full_symbol_and_type();
-        let exchange = exchange_from_market_type(market_type);
+        let (symbol_str, market_type) = ticker_info.ticker.to_full_symbol_and_type();
+        let depth_topic = format!(
+            "orderbook.{}.{symbol_str}",
+            depth_level(market_type, push_freq)
+        );
 
-        let mut trades_buffer: Vec<Trade> = Vec::new();
-        let mut orderbook = LocalDepthCache::default();
+        let mut topics = vec![format!("publicTrade.{symbol_str}"), depth_topic.clone()];
+        if exchange_from_market_type(market_type).supports_liquidations() {
+            topics.push(format!("allLiquidation.{symbol_str}"));
+        }
 
-        let size_in_quote_currency =
-            SIZE_IN_QUOTE_CURRENCY.get() == Some(&true) && market_type != MarketKind::InversePerps;
+        Some(Self {
+            stream,
+            ticker_info,
+            depth_topic,
+            topics,
+            orderbook: LocalDepthCache::default(),
+            trades_buffer: Vec::new(),
+            last_seq: 0,
+        })
+    }
 
-        loop {
-            match &mut state {
-                State::Disconnected => {
-                    let depth_level = if let PushFrequency::Custom(tf) = push_freq {
-                        match market_type {
-                            MarketKind::Spot => match tf {
-                                Timeframe::MS100 => "200",
-                                Timeframe::MS300 => "1000",
-                                _ => "200",
-                            },
-                            MarketKind::LinearPerps | MarketKind::InversePerps => match tf {
-                                Timeframe::MS200 => "200",
-                                Timeframe::MS300 => "1000",
-                                _ => "200",
-                            },
-                        }
+    /// Drops the book until the snapshot pushed on resubscribing to its topic
+    async fn invalidate(&mut self, fault: integrity::Fault, output: &mut mpsc::Sender<Event>) {
+        integrity::record(self.stream, fault);
+
+        self.orderbook.last_update_id = 0;
+        connect::publish(output, &[self.stream], ConnectionState::Resyncing).await;
+    }
+
+    /// Applies the data, returns whether the depth topic has to be resubscribed for a new snapshot
+    async fn handle(&mut self, data: &StreamData, output: &mut mpsc::Sender<Event>) -> bool {
+        let min_ticksize = self.ticker_info.min_ticksize;
+        let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true)
+            && self.ticker_info.market_type() != MarketKind::InversePerps;
+
+        match data {
+            StreamData::Trade(de_trade_vec) => {
+                for de_trade in de_trade_vec {
+                    let price = Price::from_f32(de_trade.price).round_to_min_tick(min_ticksize);
+                    let qty = if size_in_quote_currency {
+                        (de_trade.qty * de_trade.price).round()
                     } else {
-                        "200"
+                        de_trade.qty
                     };
 
-                    let stream_1 = format!("publicTrade.{symbol_str}");
-                    let stream_2 = format!("orderbook.{depth_level}.{symbol_str}");
-
-                    let subscribe_message = serde_json::json!({
-                        "op": "subscribe",
-                        "args": [stream_1, stream_2]
+                    self.trades_buffer.push(Trade {
+                        time: de_trade.time,
+                        is_sell: de_trade.is_sell == "Sell",
+                        price,
+                        qty,
                     });
-                    state = try_connect(&subscribe_message, market_type, &mut output).await;
                 }
-                State::Connected(websocket) => match websocket.read_frame().await {
-                    Ok(msg) => match msg.opcode {
-                        OpCode::Text => {
-                            if let Ok(data) = feed_de(&msg.payload[..], Some(ticker), market_type) {
-                                match data {
-                                    StreamData::Trade(de_trade_vec) => {
-                                        for de_trade in &de_trade_vec {
-                                            let price = Price::from_f32(de_trade.price)
-                                                .round_to_min_tick(ticker_info.min_ticksize);
-                                            let qty = if size_in_quote_currency {
-                                                (de_trade.qty * de_trade.price).round()
-                                            } else {
-                                                de_trade.qty
-                                            };
-
-                                            let trade = Trade {
-                                                time: de_trade.time,
-                                                is_sell: de_trade.is_sell == "Sell",
-                                                price,
-                                                qty,
-                                            };
-
-                                            trades_buffer.push(trade);
-                                        }
+            }
+            StreamData::Liquidation(de_liq_vec) => {
+                for de_liq in de_liq_vec {
+                    let liquidation = de_liq.liquidation(&self.ticker_info);
+
+                    let _ = output
+                        .send(Event::LiquidationReceived(self.stream, liquidation))
+                        .await;
+                }
+            }
+            StreamData::Depth(de_depth, data_type, time) => {
+                let to_order = |x: &DeOrder| DeOrder {
+                    price: x.price,
+                    qty: if size_in_quote_currency {
+                        (x.qty * x.price).round()
+                    } else {
+                        x.qty
+                    },
+                };
+
+                let depth = DepthPayload {
+                    last_update_id: de_depth.update_id,
+                    time: *time,
+                    bids: de_depth.bids.iter().map(to_order).collect(),
+                    asks: de_depth.asks.iter().map(to_order).collect(),
+                };
+
+                if (data_type == "snapshot") || (depth.last_update_id == 1) {
+                    let was_synced = self.orderbook.last_update_id != 0;
+
+                    self.orderbook
+                        .update(DepthUpdate::Snapshot(depth), min_ticksize);
+                    self.last_seq = de_depth.seq;
+
+                    if let Err(fault) = integrity::check_crossed(&self.orderbook.depth) {
+                        self.invalidate(fault, output).await;
+                        return true;
+                    }
+
+                    if !was_synced {
+                        connect::publish(output, &[self.stream], ConnectionState::Live).await;
+                    }
+                } else if data_type == "delta" {
+                    // waiting for the snapshot of a resync
+                    if self.orderbook.last_update_id == 0 {
+                        return false;
+                    }
+
+                    let continuity =
+                        integrity::check_next(self.orderbook.last_update_id, de_depth.update_id)
+                            .and(integrity::check_increasing(self.last_seq, de_depth.seq))
+                            .and(integrity::check_monotonic(self.orderbook.time, *time));
+                    if let Err(fault) = continuity {
+                        self.invalidate(fault, output).await;
+                        return true;
+                    }
+
+                    self.orderbook
+                        .update(DepthUpdate::Diff(depth), min_ticksize);
+                    self.last_seq = de_depth.seq;
+
+                    if let Err(fault) = integrity::check_crossed(&self.orderbook.depth) {
+                        self.invalidate(fault, output).await;
+                        return true;
+                    }
+
+                    telemetry::observe(self.stream, *time);
+
+                    let _ = output
+                        .send(Event::DepthReceived(
+                            self.stream,
+                            *time,
+                            self.orderbook.depth.clone(),
+                            std::mem::take(&mut self.trades_buffer).into_boxed_slice(),
+                        ))
+                        .await;
+                }
+            }
+            StreamData::Kline(..) => {
+                log::warn!("Unknown data received");
+            }
+        }
+
+        false
+    }
+}
+
+async fn send_subscription(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    op: &str,
+    topics: &[String],
+) -> Result<(), WebSocketError> {
+    // spot takes at most 10 args per request
+    for args in topics.chunks(10) {
+        let request = json!({
+            "op": op,
+            "args": args,
+        });
+
+        websocket
+            .write_frame(Frame::text(Payload::Owned(
+                request.to_string().into_bytes(),
+            )))
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn resubscribe(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    topic: &str,
+) -> Result<(), WebSocketError> {
+    let topics = [topic.to_string()];
+
+    send_subscription(websocket, "unsubscribe", &topics).await?;
+    send_subscription(websocket, "subscribe", &topics).await
+}
+
+/// Liquidations of a ticker with no depth stream to carry them
+struct LiquidationFeed {
+    stream: StreamKind,
+    ticker_info: TickerInfo,
+    topic: String,
+}
+
+impl LiquidationFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::Liquidations { ticker_info } = stream else {
+            return None;
+        };
+
+        let (symbol_str, _) = ticker_info.ticker.to_full_symbol_and_type();
+
+        Some(Self {
+            stream,
+            ticker_info,
+            topic: format!("allLiquidation.{symbol_str}"),
+        })
+    }
+
+    async fn handle(&self, data: &StreamData, output: &mut mpsc::Sender<Event>) {
+        if let StreamData::Liquidation(de_liq_vec) = data {
+            for de_liq in de_liq_vec {
+                let liquidation = de_liq.liquidation(&self.ticker_info);
+
+                let _ = output
+                    .send(Event::LiquidationReceived(self.stream, liquidation))
+                    .await;
+            }
+        }
+    }
+}
+
+/// Subscribes and drops topics on the open socket so it carries exactly `wanted`
+async fn update_subscriptions(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    feeds: &mut Vec<DepthFeed>,
+    liquidation_feeds: &mut Vec<LiquidationFeed>,
+    wanted: &FxHashSet<StreamKind>,
+    output: &mut mpsc::Sender<Event>,
+) -> Result<(), WebSocketError> {
+    let current_topics = feeds
+        .iter()
+        .flat_map(|feed| feed.topics.iter().cloned())
+        .chain(liquidation_feeds.iter().map(|feed| feed.topic.clone()))
+        .collect::<FxHashSet<_>>();
+
+    feeds.retain(|feed| wanted.contains(&feed.stream));
+    liquidation_feeds.retain(|feed| wanted.contains(&feed.stream));
+
+    let mut added = wanted
+        .iter()
+        .filter(|stream| !feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| DepthFeed::new(*stream))
+        .collect::<Vec<_>>();
+    let added_liquidations = wanted
+        .iter()
+        .filter(|stream| !liquidation_feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| LiquidationFeed::new(*stream))
+        .collect::<Vec<_>>();
+
+    // snapshots are only pushed on subscribing, a feed joining a topic takes the book as is
+    for feed in &mut added {
+        if let Some(existing) = feeds.iter().find(|f| f.depth_topic == feed.depth_topic) {
+            feed.orderbook.last_update_id = existing.orderbook.last_update_id;
+            feed.orderbook.time = existing.orderbook.time;
+            feed.orderbook.depth = existing.orderbook.depth.clone();
+            feed.last_seq = existing.last_seq;
+
+            connect::publish(output, &[feed.stream], ConnectionState::Live).await;
+        }
+    }
+
+    let wanted_topics = feeds
+        .iter()
+        .chain(&added)
+        .flat_map(|feed| feed.topics.iter().cloned())
+        .chain(
+            liquidation_feeds
+                .iter()
+                .chain(&added_liquidations)
+                .map(|feed| feed.topic.clone()),
+        )
+        .collect::<FxHashSet<_>>();
+
+    let (subscribe, unsubscribe) = mux::diff_topics(&current_topics, &wanted_topics);
+
+    send_subscription(websocket, "unsubscribe", &unsubscribe).await?;
+    send_subscription(websocket, "subscribe", &subscribe).await?;
+
+    feeds.extend(added);
+
+    let streams = added_liquidations
+        .iter()
+        .map(|feed| feed.stream)
+        .collect::<Vec<_>>();
+    connect::publish(output, &streams, ConnectionState::Live).await;
+    liquidation_feeds.extend(added_liquidations);
+
+    Ok(())
+}
+
+/// Depth and trades of every stream in `wanted` over a single connection
+async fn run_market_streams(
+    market_type: MarketKind,
+    mut wanted: mux::WantedStreams,
+    output: &mut mpsc::Sender<Event>,
+) {
+    let exchange = exchange_from_market_type(market_type);
+    let url = ws_url(market_type);
+    let source = capture::Source::new(exchange, "market");
+
+    let mut state = State::Disconnected;
+    let mut backoff = Backoff::default();
+    let mut feeds: Vec<DepthFeed> = Vec::new();
+    let mut liquidation_feeds: Vec<LiquidationFeed> = Vec::new();
+
+    loop {
+        match &mut state {
+            State::Disconnected => {
+                feeds.clear();
+                liquidation_feeds.clear();
+
+                let streams = wanted.borrow().iter().copied().collect::<Vec<_>>();
+                connect::publish(output, &streams, ConnectionState::Connecting).await;
+
+                match connect_ws(&url).await {
+                    Ok(websocket) => {
+                        state = State::Connected(websocket);
+                        backoff.reset();
+                        // nothing is subscribed on a fresh socket, feeds go live on their snapshot
+                        wanted.mark_changed();
+
+                        let _ = output.send(Event::Connected(exchange)).await;
+                    }
+                    Err(err) => {
+                        let _ = output
+                            .send(Event::Disconnected(
+                                exchange,
+                                format!("Failed to connect: {err}"),
+                            ))
+                            .await;
+
+                        backoff.wait(&streams, output).await;
+                    }
+                }
+            }
+            State::Connected(websocket) => {
+                tokio::select! {
+                    Ok(()) = wanted.changed() => {
+                        let streams = wanted.borrow_and_update().clone();
+
+                        if let Err(e) = update_subscriptions(
+                            websocket,
+                            &mut feeds,
+                            &mut liquidation_feeds,
+                            &streams,
+                            output,
+                        )
+                        .await
+                        {
+                            state = State::Disconnected;
+                            let _ = output
+                                .send(Event::Disconnected(
+                                    exchange,
+                                    format!("Failed subscribing: {e}"),
+                                ))
+                                .await;
+                        }
+                    }
+                    frame = websocket.read_frame() => match frame {
+                        Ok(msg) => match msg.opcode {
+                            OpCode::Text => {
+                                capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
+                                if let Ok((topic, data)) =
+                                    feed_de(&msg.payload[..], None, market_type)
+                                {
+                                    let routed = feeds
+                                        .iter_mut()
+                                        .filter(|feed| feed.topics.contains(&topic));
+
+                                    let mut stale = false;
+                                    for feed in routed {
+                                        stale |= feed.handle(&data, output).await;
                                     }
-                                    StreamData::Depth(de_depth, data_type, time) => {
-                                        let depth = DepthPayload {
-                                            last_update_id: de_depth.update_id,
-                                            time,
-                                            bids: de_depth
-                                                .bids
-                                                .iter()
-                                                .map(|x| DeOrder {
-                                                    price: x.price,
-                                                    qty: if size_in_quote_currency {
-                                                        (x.qty * x.price).round()
-                                                    } else {
-                                                        x.qty
-                                                    },
-                                                })
-                                                .collect(),
-                                            asks: de_depth
-                                                .asks
-                                                .iter()
-                                                .map(|x| DeOrder {
-                                                    price: x.price,
-                                                    qty: if size_in_quote_currency {
-                                                        (x.qty * x.price).round()
-                                                    } else {
-                                                        x.qty
-                                                    },
-                                                })
-                                                .collect(),
-                                        };
-
-                                        if (data_type == "snapshot") || (depth.last_update_id == 1)
-                                        {
-                                            orderbook.update(
-                                                DepthUpdate::Snapshot(depth),
-                                                ticker_info.min_ticksize,
-                                            );
-                                        } else if data_type == "delta" {
-                                            orderbook.update(
-                                                DepthUpdate::Diff(depth),
-                                                ticker_info.min_ticksize,
-                                            );
 
-                                            let _ = output
-                                                .send(Event::DepthReceived(
-                                                    StreamKind::DepthAndTrades {
-                                                        ticker_info,
-                                                        depth_aggr: StreamTicksize::Client,
-                                                        push_freq,
-                                                    },
-                                                    time,
-                                                    orderbook.depth.clone(),
-                                                    std::mem::take(&mut trades_buffer)
-                                                        .into_boxed_slice(),
-                                                ))
-                                                .await;
-                                        }
+                                    let routed = liquidation_feeds
+                                        .iter()
+                                        .filter(|feed| feed.topic == topic);
+
+                                    for feed in routed {
+                                        feed.handle(&data, output).await;
                                     }
-                                    _ => {
-                                        log::warn!("Unknown data received");
+
+                                    // resubscribing is the only way to get a new snapshot
+                                    if stale
+                                        && let Err(e) = resubscribe(websocket, &topic).await
+                                    {
+                                        state = State::Disconnected;
+                                        let _ = output
+                                            .send(Event::Disconnected(
+                                                exchange,
+                                                format!("Failed resubscribing: {e}"),
+                                            ))
+                                            .await;
                                     }
                                 }
                             }
-                        }
-                        OpCode::Close => {
+                            OpCode::Close => {
+                                state = State::Disconnected;
+                                let _ = output
+                                    .send(Event::Disconnected(
+                                        exchange,
+                                        "Connection closed".to_string(),
+                                    ))
+                                    .await;
+                            }
+                            _ => {}
+                        },
+                        Err(e) => {
                             state = State::Disconnected;
                             let _ = output
                                 .send(Event::Disconnected(
                                     exchange,
-                                    "Connection closed".to_string(),
+                                    "Error reading frame: ".to_string() + &e.to_string(),
                                 ))
                                 .await;
                         }
-                        _ => {}
                     },
-                    Err(e) => {
-                        state = State::Disconnected;
-                        let _ = output
-                            .send(Event::Disconnected(
-                                exchange,
-                                "Error reading frame: ".to_string() + &e.to_string(),
-                            ))
-                            .await;
-                    }
-                },
+                }
             }
         }
+    }
+}
+
+pub fn connect_market_stream(
+    ticker_info: TickerInfo,
+    push_freq: PushFrequency,
+) -> impl Stream<Item = Event> {
+    let stream = StreamKind::DepthAndTrades {
+        ticker_info,
+        depth_aggr: StreamTicksize::Client,
+        push_freq,
+    };
+
+    stream::channel(100, async move |mut output| {
+        let market_type = ticker_info.market_type();
+        run_market_streams(market_type, mux::fixed([stream]), &mut output).await;
+    })
+}
+
+/// Depth and trades of all the tickers wanted from a market, over one connection
+pub fn connect_shared_market_stream(market_type: MarketKind) -> impl Stream<Item = Event> {
+    stream::channel(100, async move |mut output| {
+        let wanted = mux::wanted(exchange_from_market_type(market_type));
+        run_market_streams(market_type, wanted, &mut output).await;
     })
 }
 
@@ -468,6 +861,7 @@ pub fn connect_kline_stream(
 ) -> impl Stream<Item = Event> {
     stream::channel(100, async move |mut output| {
         let mut state = State::Disconnected;
+        let mut backoff = Backoff::default();
 
         let exchange = exchange_from_market_type(market_type);
         let size_in_quote_currency =
@@ -478,6 +872,17 @@ pub fn connect_kline_stream(
             .map(|(ticker_info, _)| (ticker_info.ticker, *ticker_info))
             .collect::<HashMap<Ticker, TickerInfo>>();
 
+        let kline_streams = streams
+            .iter()
+            .map(|(ticker_info, timeframe)| StreamKind::Kline {
+                ticker_info: *ticker_info,
+                timeframe: *timeframe,
+            })
+            .collect::<Vec<_>>();
+
+        let url = ws_url(market_type);
+        let source = capture::Source::new(exchange, "klines");
+
         loop {
             match &mut state {
                 State::Disconnected => {
@@ -503,12 +908,21 @@ pub fn connect_kline_stream(
                         "args": stream_str
                     });
 
-                    state = try_connect(&subscribe_message, market_type, &mut output).await;
+                    state = try_connect(
+                        &subscribe_message,
+                        &kline_streams,
+                        market_type,
+                        &mut backoff,
+                        &mut output,
+                    )
+                    .await;
                 }
                 State::Connected(websocket) => match websocket.read_frame().await {
                     Ok(msg) => match msg.opcode {
                         OpCode::Text => {
-                            if let Ok(StreamData::Kline(ticker, de_kline_vec)) =
+                            capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
+                            if let Ok((_, StreamData::Kline(ticker, de_kline_vec))) =
                                 feed_de(&msg.payload[..], None, market_type)
                             {
                                 for de_kline in &de_kline_vec {
@@ -627,8 +1041,9 @@ pub async fn fetch_historical_oi(
         _ => panic!("Unsupported timeframe for open interest: {period}"),
     };
 
+    let domain = endpoint::resolve(FETCH_DOMAIN);
     let mut url = format!(
-        "{FETCH_DOMAIN}/v5/market/open-interest?category=linear&symbol={ticker_str}&intervalTime={period_str}",
+        "{domain}/v5/market/open-interest?category=linear&symbol={ticker_str}&intervalTime={period_str}",
     );
 
     if let Some((start, end)) = range {
@@ -688,6 +1103,259 @@ pub async fn fetch_historical_oi(
     Ok(open_interest)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeFundingRate {
+    #[serde(deserialize_with = "de_string_to_f32")]
+    funding_rate: f32,
+    #[serde(deserialize_with = "de_string_to_u64")]
+    funding_rate_timestamp: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DePredictedFunding {
+    #[serde(deserialize_with = "de_string_to_f32")]
+    funding_rate: f32,
+    #[serde(deserialize_with = "de_string_to_u64")]
+    next_funding_time: u64,
+}
+
+fn funding_category(market: MarketKind) -> Result<&'static str, AdapterError> {
+    match market {
+        MarketKind::LinearPerps => Ok("linear"),
+        MarketKind::InversePerps => Ok("inverse"),
+        MarketKind::Spot => Err(AdapterError::InvalidRequest(
+            "Funding rates are only available for perpetuals".to_string(),
+        )),
+    }
+}
+
+fn parse_result_list(response_text: &str) -> Result<Value, AdapterError> {
+    let content: Value =
+        sonic_rs::from_str(response_text).map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+    let result_list = content["result"]["list"].as_array().ok_or_else(|| {
+        log::error!("Result list is not an array in response: {}", response_text);
+        AdapterError::ParseError("Result list is not an array".to_string())
+    })?;
+
+    Ok(json!(result_list))
+}
+
+pub async fn fetch_funding_rates(
+    ticker: Ticker,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<FundingRate>, AdapterError> {
+    let (symbol_str, market) = ticker.to_full_symbol_and_type();
+    let category = funding_category(market)?;
+
+    let domain = endpoint::resolve(FETCH_DOMAIN);
+    let mut url = format!(
+        "{domain}/v5/market/funding/history?category={category}&symbol={}&limit=200",
+        symbol_str.to_uppercase(),
+    );
+
+    if let Some((start, end)) = range {
+        url.push_str(&format!("&startTime={start}&endTime={end}"));
+    }
+
+    let response_text = http_request_with_limiter(&url, &BYBIT_LIMITER, 1, None, None).await?;
+
+    let rates: Vec<DeFundingRate> = serde_json::from_value(parse_result_list(&response_text)?)
+        .map_err(|e| AdapterError::ParseError(format!("Failed to parse funding rates: {e}")))?;
+
+    // Listed newest first
+    let mut funding_rates: Vec<FundingRate> = rates
+        .into_iter()
+        .map(|x| FundingRate {
+            time: x.funding_rate_timestamp,
+            rate: x.funding_rate,
+        })
+        .collect();
+    funding_rates.sort_by_key(|f| f.time);
+
+    Ok(funding_rates)
+}
+
+pub async fn fetch_predicted_funding(ticker: Ticker) -> Result<FundingRate, AdapterError> {
+    let (symbol_str, market) = ticker.to_full_symbol_and_type();
+    let category = funding_category(market)?;
+
+    let domain = endpoint::resolve(FETCH_DOMAIN);
+    let url = format!(
+        "{domain}/v5/market/tickers?category={category}&symbol={}",
+        symbol_str.to_uppercase(),
+    );
+
+    let response_text = http_request_with_limiter(&url, &BYBIT_LIMITER, 1, None, None).await?;
+
+    let tickers: Vec<DePredictedFunding> =
+        serde_json::from_value(parse_result_list(&response_text)?).map_err(|e| {
+            AdapterError::ParseError(format!("Failed to parse predicted funding: {e}"))
+        })?;
+
+    tickers
+        .first()
+        .map(|x| FundingRate {
+            time: x.next_funding_time,
+            rate: x.funding_rate,
+        })
+        .ok_or_else(|| AdapterError::ParseError(format!("No ticker found for {symbol_str}")))
+}
+
+pub async fn fetch_server_time() -> Result<u64, AdapterError> {
+    let url = format!("{}/v5/market/time", endpoint::resolve(FETCH_DOMAIN));
+
+    let response_text = http_request_with_limiter(&url, &BYBIT_LIMITER, 1, None, None).await?;
+
+    let content: Value =
+        sonic_rs::from_str(&response_text).map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+    content["time"]
+        .as_u64()
+        .ok_or_else(|| AdapterError::ParseError("Missing server time".to_string()))
+}
+
+/// Trades after `from_time`, from the first daily archive that has any
+///
+/// Archives of a day are published the day after, so trades of today only come
+/// from the live stream.
+pub async fn fetch_trades(
+    ticker_info: TickerInfo,
+    from_time: u64,
+    data_path: PathBuf,
+) -> Result<Vec<Trade>, AdapterError> {
+    if ticker_info.market_type() == MarketKind::Spot {
+        return Err(AdapterError::InvalidRequest(
+            "Trade backfill is only available for Bybit perpetuals".to_string(),
+        ));
+    }
+
+    let today = chrono::Utc::now().date_naive();
+    let mut date = chrono::DateTime::from_timestamp_millis(from_time as i64)
+        .ok_or_else(|| AdapterError::ParseError("Invalid timestamp".into()))?
+        .date_naive();
+
+    while date < today {
+        let Some(trades) = get_hist_trades(ticker_info, date, &data_path).await? else {
+            log::info!("No Bybit trade archive for {date} yet");
+            break;
+        };
+
+        let trades = trades
+            .into_iter()
+            .filter(|trade| trade.time > from_time)
+            .collect::<Vec<_>>();
+
+        if !trades.is_empty() {
+            return Ok(trades);
+        }
+
+        date = date
+            .succ_opt()
+            .ok_or_else(|| AdapterError::ParseError("Date out of range".into()))?;
+    }
+
+    Ok(vec![])
+}
+
+/// Trades of a day from `public.bybit.com`, cached under `base_path`, `None` if not published
+pub async fn get_hist_trades(
+    ticker_info: TickerInfo,
+    date: chrono::NaiveDate,
+    base_path: &Path,
+) -> Result<Option<Vec<Trade>>, AdapterError> {
+    let (symbol, _) = ticker_info.ticker.to_full_symbol_and_type();
+
+    let archive_path = format!(
+        "trading/{symbol}/{symbol}{}.csv.gz",
+        date.format("%Y-%m-%d")
+    );
+    let cached_path = base_path.join(&archive_path);
+
+    if std::fs::metadata(&cached_path).is_ok() {
+        log::info!("Using cached {}", archive_path);
+    } else {
+        let url = format!("{}/{archive_path}", endpoint::resolve(BULK_DATA_DOMAIN));
+
+        log::info!("Downloading from {}", url);
+
+        let resp = crate::proxy::http_client(&url)?
+            .get(&url)
+            .send()
+            .await
+            .map_err(AdapterError::FetchError)?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Err(AdapterError::InvalidRequest(format!(
+                "Failed to fetch from {}: {}",
+                url,
+                resp.status()
+            )));
+        }
+
+        let body = resp.bytes().await.map_err(AdapterError::FetchError)?;
+
+        if let Some(dir) = cached_path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                AdapterError::ParseError(format!("Failed to create directories: {e}"))
+            })?;
+        }
+        std::fs::write(&cached_path, &body).map_err(|e| {
+            AdapterError::ParseError(format!("Failed to write archive: {e}, {cached_path:?}"))
+        })?;
+    }
+
+    let file = std::fs::File::open(&cached_path)
+        .map_err(|e| AdapterError::ParseError(format!("Failed to open compressed file: {e}")))?;
+
+    Ok(Some(parse_hist_trades(
+        flate2::read::GzDecoder::new(BufReader::new(file)),
+        ticker_info,
+    )))
+}
+
+/// Rows of `timestamp,symbol,side,size,price,...`, timestamps in fractional seconds
+fn parse_hist_trades(archive: impl Read, ticker_info: TickerInfo) -> Vec<Trade> {
+    let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true)
+        && ticker_info.market_type() != MarketKind::InversePerps;
+
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(archive);
+
+    let mut trades = csv_reader
+        .records()
+        .filter_map(|record| {
+            let record = record.ok()?;
+
+            let time = (record.get(0)?.parse::<f64>().ok()? * 1000.0).round() as u64;
+            let is_sell = record.get(2)? == "Sell";
+            let qty = record.get(3)?.parse::<f32>().ok()?;
+            let price_f32 = record.get(4)?.parse::<f32>().ok()?;
+
+            Some(Trade {
+                time,
+                is_sell,
+                price: Price::from_f32(price_f32).round_to_min_tick(ticker_info.min_ticksize),
+                qty: if size_in_quote_currency {
+                    (qty * price_f32).round()
+                } else {
+                    qty
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // older archives are newest first
+    trades.sort_by_key(|trade| trade.time);
+    trades
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 struct ApiResponse {
@@ -737,8 +1405,9 @@ pub async fn fetch_klines(
         MarketKind::InversePerps => "inverse",
     };
 
+    let domain = endpoint::resolve(FETCH_DOMAIN);
     let mut url = format!(
-        "{FETCH_DOMAIN}/v5/market/kline?category={}&symbol={}&interval={}",
+        "{domain}/v5/market/kline?category={}&symbol={}&interval={}",
         market,
         symbol_str.to_uppercase(),
         timeframe_str
@@ -808,9 +1477,10 @@ pub async fn fetch_ticksize(
         MarketKind::InversePerps => "inverse",
     };
 
-    let url = format!("{FETCH_DOMAIN}/v5/market/instruments-info?category={market}&limit=1000",);
+    let domain = endpoint::resolve(FETCH_DOMAIN);
+    let url = format!("{domain}/v5/market/instruments-info?category={market}&limit=1000",);
 
-    let response_text = crate::limiter::HTTP_CLIENT
+    let response_text = crate::proxy::http_client(&url)?
         .get(&url)
         .send()
         .await
@@ -891,7 +1561,8 @@ pub async fn fetch_ticker_prices(
         MarketKind::InversePerps => "inverse",
     };
 
-    let url = format!("{FETCH_DOMAIN}/v5/market/tickers?category={market}");
+    let domain = endpoint::resolve(FETCH_DOMAIN);
+    let url = format!("{domain}/v5/market/tickers?category={market}");
 
     let response_text = http_request_with_limiter(&url, &BYBIT_LIMITER, 1, None, None).await?;
 
@@ -950,3 +1621,178 @@ pub async fn fetch_ticker_prices(
 
     Ok(ticker_prices_map)
 }
+
+pub struct Bybit;
+
+impl ExchangeAdapter for Bybit {
+    fn venue(&self) -> ExchangeInclusive {
+        ExchangeInclusive::Bybit
+    }
+
+    fn name(&self) -> &'static str {
+        "Bybit"
+    }
+
+    fn id(&self) -> &'static str {
+        "bybit"
+    }
+
+    fn markets(&self) -> &'static [Exchange] {
+        &[
+            Exchange::BybitLinear,
+            Exchange::BybitInverse,
+            Exchange::BybitSpot,
+        ]
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["bybit.com"]
+    }
+
+    fn supports_open_interest(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn supports_liquidations(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn supports_trade_backfill(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn allowed_push_freqs(&self, market: MarketKind) -> &'static [PushFrequency] {
+        match market {
+            MarketKind::Spot => &[
+                PushFrequency::Custom(Timeframe::MS200),
+                PushFrequency::Custom(Timeframe::MS300),
+            ],
+            MarketKind::LinearPerps | MarketKind::InversePerps => &[
+                PushFrequency::Custom(Timeframe::MS100),
+                PushFrequency::Custom(Timeframe::MS300),
+            ],
+        }
+    }
+
+    fn supports_heatmap_timeframe(&self, market: MarketKind, tf: Timeframe) -> bool {
+        match market {
+            MarketKind::Spot => tf != Timeframe::MS100,
+            MarketKind::LinearPerps | MarketKind::InversePerps => tf != Timeframe::MS200,
+        }
+    }
+
+    fn fetch_ticker_info(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, Option<TickerInfo>>> {
+        Box::pin(fetch_ticksize(market))
+    }
+
+    fn fetch_ticker_prices(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, TickerStats>> {
+        Box::pin(fetch_ticker_prices(market))
+    }
+
+    fn fetch_klines(
+        &self,
+        ticker_info: TickerInfo,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<Kline>> {
+        Box::pin(fetch_klines(ticker_info, timeframe, range))
+    }
+
+    fn fetch_open_interest(
+        &self,
+        ticker: Ticker,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<OpenInterest>> {
+        Box::pin(fetch_historical_oi(ticker, range, timeframe))
+    }
+
+    fn fetch_funding_rates(
+        &self,
+        ticker: Ticker,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<FundingRate>> {
+        Box::pin(fetch_funding_rates(ticker, range))
+    }
+
+    fn fetch_predicted_funding(&self, ticker: Ticker) -> AdapterFuture<FundingRate> {
+        Box::pin(fetch_predicted_funding(ticker))
+    }
+
+    fn fetch_server_time(&self) -> AdapterFuture<u64> {
+        Box::pin(fetch_server_time())
+    }
+
+    fn fetch_trades(
+        &self,
+        ticker_info: TickerInfo,
+        from_time: u64,
+        data_path: PathBuf,
+    ) -> AdapterFuture<Vec<Trade>> {
+        Box::pin(fetch_trades(ticker_info, from_time, data_path))
+    }
+
+    fn connect_market_stream(
+        &self,
+        ticker_info: TickerInfo,
+        _tick_multiplier: Option<TickMultiplier>,
+        push_freq: PushFrequency,
+    ) -> EventStream {
+        Box::pin(connect_market_stream(ticker_info, push_freq))
+    }
+
+    fn connect_kline_stream(
+        &self,
+        streams: Vec<(TickerInfo, Timeframe)>,
+        market: MarketKind,
+    ) -> EventStream {
+        Box::pin(connect_kline_stream(streams, market))
+    }
+
+    fn multiplexes_market_streams(&self) -> bool {
+        true
+    }
+
+    fn connect_shared_market_stream(&self, market: MarketKind) -> EventStream {
+        Box::pin(connect_shared_market_stream(market))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_daily_trade_archive() {
+        let ticker_info = TickerInfo::new(
+            Ticker::new("BTCUSDT", Exchange::BybitLinear),
+            0.1,
+            0.001,
+            None,
+        );
+
+        // newest first, as in older archives
+        let csv = "timestamp,symbol,side,size,price,tickDirection,trdMatchID,grossValue,homeNotional,foreignNotional\n\
+                   1704067200.5,BTCUSDT,Sell,0.25,42000.1,MinusTick,b,1.05e+12,0.25,10500.025\n\
+                   1704067200.0123,BTCUSDT,Buy,0.1,42000.2,PlusTick,a,4.2e+11,0.1,4200.02\n";
+
+        let trades = parse_hist_trades(csv.as_bytes(), ticker_info);
+
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].time, 1704067200012);
+        assert!(!trades[0].is_sell);
+        assert_eq!(trades[0].qty, 0.1);
+        assert_eq!(trades[1].time, 1704067200500);
+        assert!(trades[1].is_sell);
+        assert_eq!(
+            trades[1].price,
+            Price::from_f32(42000.1).round_to_min_tick(ticker_info.min_ticksize)
+        );
+    }
+}
@@ -0,0 +1,1242 @@
+use crate::{
+    FundingRate, OpenInterest, Price, PushFrequency, SIZE_IN_QUOTE_CURRENCY, TickMultiplier,
+    adapter::{StreamKind, StreamTicksize},
+    endpoint,
+    limiter::{self, RateLimiter},
+};
+
+use super::{
+    super::{
+        Exchange, Kline, MarketKind, Ticker, TickerInfo, TickerStats, Timeframe, Trade,
+        capture::{self, FrameKind},
+        connect::{self, Backoff, State, connect_ws},
+        is_symbol_supported,
+    },
+    AdapterError, ConnectionState, Event, ExchangeInclusive, integrity, mux,
+    registry::{AdapterFuture, EventStream, ExchangeAdapter},
+    telemetry,
+};
+
+use super::super::depth::{DeOrder, DepthPayload, DepthUpdate, LocalDepthCache};
+
+use fastwebsockets::{FragmentCollector, Frame, OpCode, WebSocketError};
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
+use iced_futures::{
+    futures::{SinkExt, Stream, channel::mpsc},
+    stream,
+};
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::{collections::HashMap, sync::LazyLock, time::Duration};
+use tokio::sync::Mutex;
+
+const WS_DOMAIN: &str = "wss://www.deribit.com";
+const REST_DOMAIN: &str = "https://www.deribit.com";
+
+/// Candles per kline request when no range is given
+const KLINE_LIMIT: u64 = 200;
+/// Hourly funding rates requested when no range is given
+const FUNDING_HISTORY_HOURS: u64 = 200;
+const HOUR_MS: u64 = 60 * 60 * 1000;
+/// Cap on the candles of a ranged kline request, the latest ones are kept
+const KLINE_MAX_LIMIT: u64 = 1000;
+
+const LIMIT: usize = 20;
+
+const REFILL_RATE: Duration = Duration::from_secs(1);
+const LIMITER_BUFFER_PCT: f32 = 0.05;
+
+static DERIBIT_LIMITER: LazyLock<Mutex<DeribitLimiter>> =
+    LazyLock::new(|| Mutex::new(DeribitLimiter::new(LIMIT, REFILL_RATE)));
+
+pub struct DeribitLimiter {
+    bucket: limiter::FixedWindowBucket,
+}
+
+impl DeribitLimiter {
+    pub fn new(limit: usize, refill_rate: Duration) -> Self {
+        let effective_limit = (limit as f32 * (1.0 - LIMITER_BUFFER_PCT)) as usize;
+        Self {
+            bucket: limiter::FixedWindowBucket::new(effective_limit, refill_rate),
+        }
+    }
+}
+
+impl RateLimiter for DeribitLimiter {
+    fn prepare_request(&mut self, weight: usize) -> Option<Duration> {
+        self.bucket.calculate_wait_time(weight)
+    }
+
+    fn update_from_response(&mut self, _response: &reqwest::Response, weight: usize) {
+        self.bucket.consume_tokens(weight);
+    }
+
+    fn is_rate_limited(&self, response: &reqwest::Response) -> bool {
+        response.status() == 429
+    }
+}
+
+fn exchange_from_market_type(market: MarketKind) -> Result<Exchange, AdapterError> {
+    match market {
+        MarketKind::LinearPerps => Ok(Exchange::DeribitLinear),
+        MarketKind::InversePerps => Ok(Exchange::DeribitInverse),
+        MarketKind::Spot => Err(AdapterError::InvalidRequest(
+            "Deribit spot markets are not supported".to_string(),
+        )),
+    }
+}
+
+/// Linear instruments carry their settlement currency, as in `BTC_USDC-PERPETUAL`
+fn exchange_of(instrument: &str) -> Exchange {
+    match instrument.rsplit_once('-') {
+        Some((pair, _)) if pair.contains('_') => Exchange::DeribitLinear,
+        _ => Exchange::DeribitInverse,
+    }
+}
+
+fn is_perpetual(instrument: &str) -> bool {
+    instrument.ends_with("-PERPETUAL")
+}
+
+/// Settlement of a dated future, named after its day as in `BTC-27DEC24`, at 08:00 UTC
+fn instrument_expiry(instrument: &str) -> Option<u64> {
+    let (_, date) = instrument.rsplit_once('-')?;
+    let day = chrono::NaiveDate::parse_from_str(date, "%d%b%y").ok()?;
+
+    Some(day.and_hms_opt(8, 0, 0)?.and_utc().timestamp_millis() as u64)
+}
+
+/// The instrument, displayed as `BTCUSD` for `BTC-PERPETUAL` and `BTC27DEC24` for `BTC-27DEC24`
+fn instrument_ticker(instrument: &str, exchange: Exchange) -> Ticker {
+    let display = match instrument.rsplit_once('-') {
+        Some((pair, "PERPETUAL")) => {
+            let pair = pair.replace('_', "");
+            if exchange == Exchange::DeribitInverse {
+                format!("{pair}USD")
+            } else {
+                pair
+            }
+        }
+        _ => instrument.replace(['_', '-'], ""),
+    };
+
+    Ticker::new_with_display(instrument, exchange, Some(&display))
+}
+
+/// Inverse amounts are in USD already, linear ones in the base currency
+fn calc_qty(amount: f32, price: f32, size_in_quote_currency: bool, market: MarketKind) -> f32 {
+    match market {
+        MarketKind::InversePerps => amount,
+        _ if size_in_quote_currency => amount * price,
+        _ => amount,
+    }
+}
+
+fn timeframe_to_resolution(tf: Timeframe) -> Option<&'static str> {
+    Some(match tf {
+        Timeframe::M1 => "1",
+        Timeframe::M3 => "3",
+        Timeframe::M5 => "5",
+        Timeframe::M15 => "15",
+        Timeframe::M30 => "30",
+        Timeframe::H1 => "60",
+        Timeframe::H2 => "120",
+        Timeframe::H6 => "360",
+        Timeframe::H12 => "720",
+        Timeframe::D1 => "1D",
+        _ => return None,
+    })
+}
+
+/// The `result` of a JSON-RPC response, or its `error` message
+fn parse_result<T: DeserializeOwned>(response_text: &str) -> Result<T, AdapterError> {
+    let mut doc: Value =
+        serde_json::from_str(response_text).map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+    if let Some(error) = doc.get("error") {
+        return Err(AdapterError::InvalidRequest(
+            error["message"]
+                .as_str()
+                .unwrap_or("Unknown error")
+                .to_string(),
+        ));
+    }
+
+    serde_json::from_value(doc.get_mut("result").map(Value::take).unwrap_or_default())
+        .map_err(|e| AdapterError::ParseError(e.to_string()))
+}
+
+fn rpc_request(method: &str, channels: &[String]) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": { "channels": channels },
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct DeInstrument {
+    instrument_name: String,
+    tick_size: f32,
+    min_trade_amount: f32,
+    contract_size: f32,
+    is_active: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeFundingRate {
+    timestamp: u64,
+    /// Rate of the hour ending at `timestamp`
+    interest_1h: f32,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeBookSummary {
+    instrument_name: String,
+    mark_price: f32,
+    last: Option<f32>,
+    /// 24h change in percent
+    price_change: Option<f32>,
+    volume_usd: Option<f32>,
+    /// In USD for inverse instruments, in the base currency for linear ones
+    open_interest: f32,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct DeChartData {
+    #[serde(default)]
+    ticks: Vec<u64>,
+    #[serde(default)]
+    open: Vec<f32>,
+    #[serde(default)]
+    high: Vec<f32>,
+    #[serde(default)]
+    low: Vec<f32>,
+    #[serde(default)]
+    close: Vec<f32>,
+    /// In the base currency
+    #[serde(default)]
+    volume: Vec<f32>,
+    /// In the quote currency
+    #[serde(default)]
+    cost: Vec<f32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeTrade {
+    timestamp: u64,
+    price: f32,
+    amount: f32,
+    direction: String,
+}
+
+/// `[action, price, amount]`, where the action is `new`, `change` or `delete`
+#[derive(Deserialize, Debug)]
+struct DeLevel(String, f32, f32);
+
+#[derive(Deserialize, Debug)]
+struct DeDepth {
+    #[serde(rename = "type")]
+    kind: String,
+    timestamp: u64,
+    /// Missing on snapshots
+    prev_change_id: Option<u64>,
+    change_id: u64,
+    bids: Vec<DeLevel>,
+    asks: Vec<DeLevel>,
+}
+
+impl DeDepth {
+    fn is_snapshot(&self) -> bool {
+        self.kind == "snapshot"
+    }
+
+    fn payload(&self, size_in_quote_currency: bool, market: MarketKind) -> DepthPayload {
+        let orders = |levels: &[DeLevel]| {
+            levels
+                .iter()
+                .map(|DeLevel(action, price, amount)| DeOrder {
+                    price: *price,
+                    qty: if action == "delete" {
+                        0.0
+                    } else {
+                        calc_qty(*amount, *price, size_in_quote_currency, market)
+                    },
+                })
+                .collect::<Vec<_>>()
+        };
+
+        DepthPayload {
+            last_update_id: self.change_id,
+            time: self.timestamp,
+            bids: orders(&self.bids),
+            asks: orders(&self.asks),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct DeCandle {
+    tick: u64,
+    open: f32,
+    high: f32,
+    low: f32,
+    close: f32,
+    volume: f32,
+    cost: f32,
+}
+
+enum StreamData {
+    Trade(Vec<DeTrade>),
+    Depth(DeDepth),
+    Kline(DeCandle),
+}
+
+/// Parses a subscription message, along with the channel it was sent on
+fn feed_de(slice: &[u8]) -> Result<(String, StreamData), AdapterError> {
+    let mut v: Value =
+        serde_json::from_slice(slice).map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+    if v["method"].as_str() != Some("subscription") {
+        return Err(AdapterError::ParseError("Unknown data".to_string()));
+    }
+
+    let channel = v["params"]["channel"].as_str().unwrap_or("").to_string();
+    let data = v
+        .pointer_mut("/params/data")
+        .map(Value::take)
+        .unwrap_or_default();
+
+    let parse_err = |e: serde_json::Error| AdapterError::ParseError(e.to_string());
+
+    let data = match channel.split('.').next() {
+        Some("book") => StreamData::Depth(serde_json::from_value(data).map_err(parse_err)?),
+        Some("trades") => StreamData::Trade(serde_json::from_value(data).map_err(parse_err)?),
+        Some("chart") => StreamData::Kline(serde_json::from_value(data).map_err(parse_err)?),
+        _ => {
+            return Err(AdapterError::ParseError(format!(
+                "Unknown channel: {channel}"
+            )));
+        }
+    };
+
+    Ok((channel, data))
+}
+
+fn ws_url() -> String {
+    format!("{}/ws/api/v2", endpoint::resolve(WS_DOMAIN))
+}
+
+async fn try_connect(
+    subscribe_message: &Value,
+    streams: &[StreamKind],
+    exchange: Exchange,
+    backoff: &mut Backoff,
+    output: &mut mpsc::Sender<Event>,
+) -> State {
+    connect::publish(output, streams, ConnectionState::Connecting).await;
+
+    match connect_ws(&ws_url()).await {
+        Ok(mut websocket) => {
+            if let Err(e) = websocket
+                .write_frame(Frame::text(fastwebsockets::Payload::Borrowed(
+                    subscribe_message.to_string().as_bytes(),
+                )))
+                .await
+            {
+                let _ = output
+                    .send(Event::Disconnected(
+                        exchange,
+                        format!("Failed subscribing: {e}"),
+                    ))
+                    .await;
+                backoff.wait(streams, output).await;
+                return State::Disconnected;
+            }
+            backoff.reset();
+
+            let _ = output.send(Event::Connected(exchange)).await;
+            connect::publish(output, streams, ConnectionState::Live).await;
+
+            State::Connected(websocket)
+        }
+        Err(err) => {
+            let _ = output
+                .send(Event::Disconnected(
+                    exchange,
+                    format!("Failed to connect: {err}"),
+                ))
+                .await;
+
+            backoff.wait(streams, output).await;
+            State::Disconnected
+        }
+    }
+}
+
+async fn send_subscription(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    method: &str,
+    channels: &[String],
+) -> Result<(), WebSocketError> {
+    if channels.is_empty() {
+        return Ok(());
+    }
+
+    let request = rpc_request(method, channels);
+
+    websocket
+        .write_frame(Frame::text(fastwebsockets::Payload::Owned(
+            request.to_string().into_bytes(),
+        )))
+        .await
+}
+
+/// Drops and requests a channel again, Deribit sends a new book snapshot on subscribing
+async fn resubscribe(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    channel: &str,
+) -> Result<(), WebSocketError> {
+    let channels = [channel.to_string()];
+
+    send_subscription(websocket, "public/unsubscribe", &channels).await?;
+    send_subscription(websocket, "public/subscribe", &channels).await
+}
+
+/// Depth and trades of one ticker carried on a market connection
+struct DepthFeed {
+    stream: StreamKind,
+    ticker_info: TickerInfo,
+    channels: Vec<String>,
+    orderbook: LocalDepthCache,
+    trades_buffer: Vec<Trade>,
+    /// Waiting for the snapshot sent on (re)subscribing to the book
+    resyncing: bool,
+}
+
+impl DepthFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::DepthAndTrades { ticker_info, .. } = stream else {
+            return None;
+        };
+
+        let (symbol_str, _) = ticker_info.ticker.to_full_symbol_and_type();
+
+        Some(Self {
+            stream,
+            ticker_info,
+            channels: vec![
+                format!("trades.{symbol_str}.100ms"),
+                format!("book.{symbol_str}.100ms"),
+            ],
+            orderbook: LocalDepthCache::default(),
+            trades_buffer: Vec::new(),
+            resyncing: true,
+        })
+    }
+
+    /// Applies the data, returns whether the book has to be resubscribed for a new snapshot
+    async fn handle(&mut self, data: &StreamData, output: &mut mpsc::Sender<Event>) -> bool {
+        let size_in_quote = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+        let market_type = self.ticker_info.market_type();
+        let min_ticksize = self.ticker_info.min_ticksize;
+
+        match data {
+            StreamData::Trade(de_trade_vec) => {
+                for de_trade in de_trade_vec {
+                    self.trades_buffer.push(Trade {
+                        time: de_trade.timestamp,
+                        is_sell: de_trade.direction == "sell",
+                        price: Price::from_f32(de_trade.price).round_to_min_tick(min_ticksize),
+                        qty: calc_qty(de_trade.amount, de_trade.price, size_in_quote, market_type),
+                    });
+                }
+            }
+            StreamData::Depth(de_depth) => {
+                let is_snapshot = de_depth.is_snapshot();
+
+                // waiting for the snapshot of a resync
+                if self.resyncing && !is_snapshot {
+                    return false;
+                }
+
+                let continuity = match de_depth.prev_change_id {
+                    Some(prev) if !is_snapshot => integrity::check_prev(
+                        self.orderbook.last_update_id,
+                        prev,
+                        de_depth.change_id,
+                    ),
+                    _ => Ok(()),
+                };
+
+                let checked = continuity.and_then(|()| {
+                    let depth = de_depth.payload(size_in_quote, market_type);
+                    self.orderbook.update(
+                        if is_snapshot {
+                            DepthUpdate::Snapshot(depth)
+                        } else {
+                            DepthUpdate::Diff(depth)
+                        },
+                        min_ticksize,
+                    );
+
+                    integrity::check_crossed(&self.orderbook.depth)
+                });
+
+                match checked {
+                    Ok(()) if is_snapshot => {
+                        if self.resyncing {
+                            self.resyncing = false;
+                            connect::publish(output, &[self.stream], ConnectionState::Live).await;
+                        }
+                    }
+                    Ok(()) => {
+                        telemetry::observe(self.stream, de_depth.timestamp);
+
+                        let _ = output
+                            .send(Event::DepthReceived(
+                                self.stream,
+                                de_depth.timestamp,
+                                self.orderbook.depth.clone(),
+                                std::mem::take(&mut self.trades_buffer).into_boxed_slice(),
+                            ))
+                            .await;
+                    }
+                    Err(fault) => {
+                        integrity::record(self.stream, fault);
+
+                        self.resyncing = true;
+                        connect::publish(output, &[self.stream], ConnectionState::Resyncing).await;
+
+                        return true;
+                    }
+                }
+            }
+            StreamData::Kline(..) => {}
+        }
+
+        false
+    }
+}
+
+/// Subscribes and drops channels on the open socket so it carries exactly `wanted`
+async fn update_subscriptions(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    feeds: &mut Vec<DepthFeed>,
+    wanted: &FxHashSet<StreamKind>,
+) -> Result<(), WebSocketError> {
+    let current_channels = feeds
+        .iter()
+        .flat_map(|feed| feed.channels.iter().cloned())
+        .collect::<FxHashSet<_>>();
+
+    feeds.retain(|feed| wanted.contains(&feed.stream));
+
+    let added = wanted
+        .iter()
+        .filter(|stream| !feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| DepthFeed::new(*stream))
+        .collect::<Vec<_>>();
+
+    let wanted_channels = feeds
+        .iter()
+        .chain(&added)
+        .flat_map(|feed| feed.channels.iter().cloned())
+        .collect::<FxHashSet<_>>();
+
+    let (subscribe, unsubscribe) = mux::diff_topics(&current_channels, &wanted_channels);
+
+    send_subscription(websocket, "public/unsubscribe", &unsubscribe).await?;
+    send_subscription(websocket, "public/subscribe", &subscribe).await?;
+
+    feeds.extend(added);
+
+    Ok(())
+}
+
+/// Depth and trades of every stream in `wanted` over a single connection
+async fn run_market_streams(
+    exchange: Exchange,
+    mut wanted: mux::WantedStreams,
+    output: &mut mpsc::Sender<Event>,
+) {
+    let url = ws_url();
+    let source = capture::Source::new(exchange, "market");
+
+    let mut state = State::Disconnected;
+    let mut backoff = Backoff::default();
+    let mut feeds: Vec<DepthFeed> = Vec::new();
+
+    loop {
+        match &mut state {
+            State::Disconnected => {
+                feeds.clear();
+
+                let streams = wanted.borrow().iter().copied().collect::<Vec<_>>();
+                connect::publish(output, &streams, ConnectionState::Connecting).await;
+
+                match connect_ws(&url).await {
+                    Ok(websocket) => {
+                        state = State::Connected(websocket);
+                        backoff.reset();
+                        // nothing is subscribed on a fresh socket, feeds go live on their snapshot
+                        wanted.mark_changed();
+
+                        let _ = output.send(Event::Connected(exchange)).await;
+                    }
+                    Err(err) => {
+                        let _ = output
+                            .send(Event::Disconnected(
+                                exchange,
+                                format!("Failed to connect: {err}"),
+                            ))
+                            .await;
+
+                        backoff.wait(&streams, output).await;
+                    }
+                }
+            }
+            State::Connected(websocket) => {
+                tokio::select! {
+                    Ok(()) = wanted.changed() => {
+                        let streams = wanted.borrow_and_update().clone();
+
+                        if let Err(e) = update_subscriptions(websocket, &mut feeds, &streams).await {
+                            state = State::Disconnected;
+                            let _ = output
+                                .send(Event::Disconnected(
+                                    exchange,
+                                    format!("Failed subscribing: {e}"),
+                                ))
+                                .await;
+                        }
+                    }
+                    frame = websocket.read_frame() => match frame {
+                        Ok(msg) => match msg.opcode {
+                            OpCode::Text => {
+                                capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
+                                if let Ok((channel, data)) = feed_de(&msg.payload[..]) {
+                                    let routed = feeds
+                                        .iter_mut()
+                                        .filter(|feed| feed.channels.contains(&channel));
+
+                                    let mut stale = false;
+                                    for feed in routed {
+                                        stale |= feed.handle(&data, output).await;
+                                    }
+
+                                    if stale
+                                        && let Err(e) = resubscribe(websocket, &channel).await
+                                    {
+                                        state = State::Disconnected;
+                                        let _ = output
+                                            .send(Event::Disconnected(
+                                                exchange,
+                                                format!("Failed resubscribing: {e}"),
+                                            ))
+                                            .await;
+                                    }
+                                }
+                            }
+                            OpCode::Close => {
+                                state = State::Disconnected;
+                                let _ = output
+                                    .send(Event::Disconnected(
+                                        exchange,
+                                        "Connection closed".to_string(),
+                                    ))
+                                    .await;
+                            }
+                            _ => {}
+                        },
+                        Err(e) => {
+                            state = State::Disconnected;
+                            let _ = output
+                                .send(Event::Disconnected(
+                                    exchange,
+                                    "Error reading frame: ".to_string() + &e.to_string(),
+                                ))
+                                .await;
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
+
+pub fn connect_market_stream(
+    ticker_info: TickerInfo,
+    push_freq: PushFrequency,
+) -> impl Stream<Item = Event> {
+    let stream = StreamKind::DepthAndTrades {
+        ticker_info,
+        depth_aggr: StreamTicksize::Client,
+        push_freq,
+    };
+
+    stream::channel(100, async move |mut output| {
+        run_market_streams(ticker_info.exchange(), mux::fixed([stream]), &mut output).await;
+    })
+}
+
+/// Depth and trades of all the tickers wanted from a market, over one connection
+pub fn connect_shared_market_stream(market_type: MarketKind) -> impl Stream<Item = Event> {
+    stream::channel(100, async move |mut output| {
+        let Ok(exchange) = exchange_from_market_type(market_type) else {
+            return;
+        };
+        run_market_streams(exchange, mux::wanted(exchange), &mut output).await;
+    })
+}
+
+pub fn connect_kline_stream(
+    streams: Vec<(TickerInfo, Timeframe)>,
+    market_type: MarketKind,
+) -> impl Stream<Item = Event> {
+    stream::channel(100, async move |mut output| {
+        let mut state = State::Disconnected;
+        let mut backoff = Backoff::default();
+
+        let kline_streams = streams
+            .iter()
+            .map(|(ticker_info, timeframe)| StreamKind::Kline {
+                ticker_info: *ticker_info,
+                timeframe: *timeframe,
+            })
+            .collect::<Vec<_>>();
+
+        let mut channels = Vec::with_capacity(streams.len());
+        let mut lookup = HashMap::new();
+        for (ticker_info, timeframe) in &streams {
+            if let Some(resolution) = timeframe_to_resolution(*timeframe) {
+                let (symbol, _) = ticker_info.ticker.to_full_symbol_and_type();
+                let channel = format!("chart.trades.{symbol}.{resolution}");
+
+                lookup.insert(channel.clone(), (*ticker_info, *timeframe));
+                channels.push(channel);
+            }
+        }
+
+        let exchange = streams
+            .first()
+            .map(|(t, _)| t.exchange())
+            .unwrap_or(Exchange::DeribitInverse);
+
+        let subscribe_message = rpc_request("public/subscribe", &channels);
+
+        let size_in_quote = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+
+        let url = ws_url();
+        let source = capture::Source::new(exchange, "klines");
+
+        loop {
+            match &mut state {
+                State::Disconnected => {
+                    state = try_connect(
+                        &subscribe_message,
+                        &kline_streams,
+                        exchange,
+                        &mut backoff,
+                        &mut output,
+                    )
+                    .await;
+                }
+                State::Connected(ws) => match ws.read_frame().await {
+                    Ok(msg) => match msg.opcode {
+                        OpCode::Text => {
+                            capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
+                            if let Ok((channel, StreamData::Kline(de_candle))) =
+                                feed_de(&msg.payload[..])
+                                && let Some(&(ticker_info, timeframe)) = lookup.get(&channel)
+                            {
+                                let kline = Kline::new(
+                                    de_candle.tick,
+                                    de_candle.open,
+                                    de_candle.high,
+                                    de_candle.low,
+                                    de_candle.close,
+                                    (
+                                        -1.0,
+                                        candle_volume(
+                                            de_candle.volume,
+                                            de_candle.cost,
+                                            size_in_quote,
+                                            market_type,
+                                        ),
+                                    ),
+                                    ticker_info.min_ticksize,
+                                );
+
+                                let _ = output
+                                    .send(Event::KlineReceived(
+                                        StreamKind::Kline {
+                                            ticker_info,
+                                            timeframe,
+                                        },
+                                        kline,
+                                    ))
+                                    .await;
+                            }
+                        }
+                        OpCode::Close => {
+                            state = State::Disconnected;
+                            let _ = output
+                                .send(Event::Disconnected(
+                                    exchange,
+                                    "Connection closed".to_string(),
+                                ))
+                                .await;
+                        }
+                        _ => {}
+                    },
+                    Err(e) => {
+                        state = State::Disconnected;
+                        let _ = output
+                            .send(Event::Disconnected(
+                                exchange,
+                                "Error reading frame: ".to_string() + &e.to_string(),
+                            ))
+                            .await;
+                    }
+                },
+            }
+        }
+    })
+}
+
+/// Candle volume in the units trades are shown in, see [`calc_qty`]
+fn candle_volume(volume: f32, cost: f32, size_in_quote_currency: bool, market: MarketKind) -> f32 {
+    if market == MarketKind::InversePerps || size_in_quote_currency {
+        cost
+    } else {
+        volume
+    }
+}
+
+pub async fn fetch_ticksize(
+    market_type: MarketKind,
+) -> Result<HashMap<Ticker, Option<TickerInfo>>, AdapterError> {
+    let exchange = exchange_from_market_type(market_type)?;
+
+    let url = format!(
+        "{}/api/v2/public/get_instruments?currency=any&kind=future&expired=false",
+        endpoint::resolve(REST_DOMAIN)
+    );
+
+    let response_text =
+        limiter::http_request_with_limiter(&url, &DERIBIT_LIMITER, 1, None, None).await?;
+
+    let instruments: Vec<DeInstrument> = parse_result(&response_text)?;
+
+    let mut map = HashMap::new();
+
+    for instrument in instruments {
+        let symbol = instrument.instrument_name.as_str();
+
+        if !instrument.is_active || exchange_of(symbol) != exchange {
+            continue;
+        }
+
+        if !is_symbol_supported(symbol, exchange, true) {
+            continue;
+        }
+
+        let ticker = instrument_ticker(symbol, exchange);
+        let info = TickerInfo::new(
+            ticker,
+            instrument.tick_size,
+            instrument.min_trade_amount,
+            Some(instrument.contract_size),
+        );
+
+        map.insert(ticker, Some(info));
+    }
+
+    Ok(map)
+}
+
+pub async fn fetch_ticker_prices(
+    market_type: MarketKind,
+) -> Result<HashMap<Ticker, TickerStats>, AdapterError> {
+    let exchange = exchange_from_market_type(market_type)?;
+
+    let url = format!(
+        "{}/api/v2/public/get_book_summary_by_currency?currency=any&kind=future",
+        endpoint::resolve(REST_DOMAIN)
+    );
+
+    let response_text =
+        limiter::http_request_with_limiter(&url, &DERIBIT_LIMITER, 1, None, None).await?;
+
+    let summaries: Vec<DeBookSummary> = parse_result(&response_text)?;
+
+    let mut map = HashMap::new();
+
+    for summary in summaries {
+        let symbol = summary.instrument_name.as_str();
+
+        if exchange_of(symbol) != exchange || !is_symbol_supported(symbol, exchange, false) {
+            continue;
+        }
+
+        map.insert(
+            instrument_ticker(symbol, exchange),
+            TickerStats {
+                mark_price: summary.last.unwrap_or(summary.mark_price),
+                daily_price_chg: summary.price_change.unwrap_or(0.0),
+                daily_volume: summary.volume_usd.unwrap_or(0.0),
+            },
+        );
+    }
+
+    Ok(map)
+}
+
+pub async fn fetch_klines(
+    ticker_info: TickerInfo,
+    timeframe: Timeframe,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<Kline>, AdapterError> {
+    let (symbol_str, market) = ticker_info.ticker.to_full_symbol_and_type();
+
+    let resolution = timeframe_to_resolution(timeframe).ok_or_else(|| {
+        AdapterError::InvalidRequest(format!("Unsupported timeframe: {timeframe}"))
+    })?;
+
+    let interval = timeframe.to_milliseconds();
+    let (start, end) = match range {
+        Some((start, end)) => (
+            start.max(end.saturating_sub(interval * KLINE_MAX_LIMIT)),
+            end,
+        ),
+        None => {
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            (now.saturating_sub(interval * KLINE_LIMIT), now)
+        }
+    };
+
+    let url = format!(
+        "{}/api/v2/public/get_tradingview_chart_data?instrument_name={symbol_str}\
+         &resolution={resolution}&start_timestamp={start}&end_timestamp={end}",
+        endpoint::resolve(REST_DOMAIN)
+    );
+
+    let response_text =
+        limiter::http_request_with_limiter(&url, &DERIBIT_LIMITER, 1, None, None).await?;
+
+    let chart: DeChartData = parse_result(&response_text)?;
+
+    Ok(chart_klines(chart, ticker_info, market))
+}
+
+fn chart_klines(chart: DeChartData, ticker_info: TickerInfo, market: MarketKind) -> Vec<Kline> {
+    let size_in_quote = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+
+    let mut klines: Vec<Kline> = (0..chart.ticks.len())
+        .filter_map(|i| {
+            Some(Kline::new(
+                chart.ticks[i],
+                *chart.open.get(i)?,
+                *chart.high.get(i)?,
+                *chart.low.get(i)?,
+                *chart.close.get(i)?,
+                (
+                    -1.0,
+                    candle_volume(
+                        *chart.volume.get(i)?,
+                        *chart.cost.get(i)?,
+                        size_in_quote,
+                        market,
+                    ),
+                ),
+                ticker_info.min_ticksize,
+            ))
+        })
+        .collect();
+
+    klines.sort_by_key(|k| k.time);
+    klines
+}
+
+/// The current open interest in the base currency, at the start of the ongoing `timeframe`
+///
+/// Deribit keeps no open interest history, so it is only returned for ranges reaching
+/// into the present and the indicator fills in while the chart stays open.
+pub async fn fetch_open_interest(
+    ticker: Ticker,
+    timeframe: Timeframe,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<OpenInterest>, AdapterError> {
+    let (symbol_str, market) = ticker.to_full_symbol_and_type();
+
+    let interval = timeframe.to_milliseconds();
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let time = now - (now % interval);
+
+    if range.is_some_and(|(_, end)| end < time) {
+        return Ok(vec![]);
+    }
+
+    let url = format!(
+        "{}/api/v2/public/get_book_summary_by_instrument?instrument_name={symbol_str}",
+        endpoint::resolve(REST_DOMAIN)
+    );
+
+    let response_text =
+        limiter::http_request_with_limiter(&url, &DERIBIT_LIMITER, 1, None, None).await?;
+
+    let summaries: Vec<DeBookSummary> = parse_result(&response_text)?;
+
+    Ok(summaries
+        .first()
+        .filter(|summary| summary.mark_price > 0.0)
+        .map(|summary| OpenInterest {
+            time,
+            value: if market == MarketKind::InversePerps {
+                summary.open_interest / summary.mark_price
+            } else {
+                summary.open_interest
+            },
+        })
+        .into_iter()
+        .collect())
+}
+
+/// Hourly funding of a perpetual, dated futures have none
+pub async fn fetch_funding_rates(
+    ticker: Ticker,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<FundingRate>, AdapterError> {
+    let (symbol_str, _) = ticker.to_full_symbol_and_type();
+
+    if !is_perpetual(&symbol_str) {
+        return Err(AdapterError::InvalidRequest(
+            "Funding rates are only available for perpetuals".to_string(),
+        ));
+    }
+
+    let (start, end) = range.unwrap_or_else(|| {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        (now.saturating_sub(FUNDING_HISTORY_HOURS * HOUR_MS), now)
+    });
+
+    let url = format!(
+        "{}/api/v2/public/get_funding_rate_history?instrument_name={symbol_str}\
+         &start_timestamp={start}&end_timestamp={end}",
+        endpoint::resolve(REST_DOMAIN)
+    );
+
+    let response_text =
+        limiter::http_request_with_limiter(&url, &DERIBIT_LIMITER, 1, None, None).await?;
+
+    let rates: Vec<DeFundingRate> = parse_result(&response_text)?;
+
+    Ok(rates
+        .into_iter()
+        .map(|rate| FundingRate {
+            time: rate.timestamp,
+            rate: rate.interest_1h,
+        })
+        .collect())
+}
+
+pub async fn fetch_server_time() -> Result<u64, AdapterError> {
+    let url = format!("{}/api/v2/public/get_time", endpoint::resolve(REST_DOMAIN));
+
+    let response_text =
+        limiter::http_request_with_limiter(&url, &DERIBIT_LIMITER, 1, None, None).await?;
+
+    parse_result(&response_text)
+}
+
+pub struct Deribit;
+
+impl ExchangeAdapter for Deribit {
+    fn venue(&self) -> ExchangeInclusive {
+        ExchangeInclusive::Deribit
+    }
+
+    fn name(&self) -> &'static str {
+        "Deribit"
+    }
+
+    fn id(&self) -> &'static str {
+        "deribit"
+    }
+
+    fn markets(&self) -> &'static [Exchange] {
+        &[Exchange::DeribitLinear, Exchange::DeribitInverse]
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["deribit.com"]
+    }
+
+    fn supports_open_interest(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn expiry(&self, symbol: &str) -> Option<u64> {
+        instrument_expiry(symbol)
+    }
+
+    fn fetch_ticker_info(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, Option<TickerInfo>>> {
+        Box::pin(fetch_ticksize(market))
+    }
+
+    fn fetch_ticker_prices(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, TickerStats>> {
+        Box::pin(fetch_ticker_prices(market))
+    }
+
+    fn fetch_klines(
+        &self,
+        ticker_info: TickerInfo,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<Kline>> {
+        Box::pin(fetch_klines(ticker_info, timeframe, range))
+    }
+
+    fn fetch_open_interest(
+        &self,
+        ticker: Ticker,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<OpenInterest>> {
+        Box::pin(fetch_open_interest(ticker, timeframe, range))
+    }
+
+    fn fetch_funding_rates(
+        &self,
+        ticker: Ticker,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<FundingRate>> {
+        Box::pin(fetch_funding_rates(ticker, range))
+    }
+
+    fn fetch_server_time(&self) -> AdapterFuture<u64> {
+        Box::pin(fetch_server_time())
+    }
+
+    fn connect_market_stream(
+        &self,
+        ticker_info: TickerInfo,
+        _tick_multiplier: Option<TickMultiplier>,
+        push_freq: PushFrequency,
+    ) -> EventStream {
+        Box::pin(connect_market_stream(ticker_info, push_freq))
+    }
+
+    fn multiplexes_market_streams(&self) -> bool {
+        true
+    }
+
+    fn connect_shared_market_stream(&self, market: MarketKind) -> EventStream {
+        Box::pin(connect_shared_market_stream(market))
+    }
+
+    fn connect_kline_stream(
+        &self,
+        streams: Vec<(TickerInfo, Timeframe)>,
+        market: MarketKind,
+    ) -> EventStream {
+        Box::pin(connect_kline_stream(streams, market))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_dated_futures_expiry() {
+        assert_eq!(instrument_expiry("BTC-27DEC24"), Some(1_735_286_400_000));
+        assert_eq!(instrument_expiry("ETH-7MAR25"), Some(1_741_334_400_000));
+        assert_eq!(instrument_expiry("BTC-PERPETUAL"), None);
+        assert_eq!(instrument_expiry("BTC_USDC-PERPETUAL"), None);
+
+        let ticker = instrument_ticker("BTC-27DEC24", Exchange::DeribitInverse);
+        assert_eq!(ticker.display_symbol(), Some("BTC27DEC24"));
+        assert_eq!(ticker.expiry(), Some(1_735_286_400_000));
+        assert!(!ticker.is_perps());
+
+        let ticker = instrument_ticker("BTC-PERPETUAL", Exchange::DeribitInverse);
+        assert_eq!(ticker.expiry(), None);
+        assert!(ticker.is_perps());
+    }
+
+    #[test]
+    fn splits_markets_by_settlement() {
+        assert_eq!(exchange_of("BTC-PERPETUAL"), Exchange::DeribitInverse);
+        assert_eq!(exchange_of("ETH-27DEC24"), Exchange::DeribitInverse);
+        assert_eq!(exchange_of("SOL_USDC-PERPETUAL"), Exchange::DeribitLinear);
+
+        let inverse = instrument_ticker("BTC-PERPETUAL", Exchange::DeribitInverse);
+        let linear = instrument_ticker("SOL_USDC-PERPETUAL", Exchange::DeribitLinear);
+        assert_eq!(inverse.display_symbol(), Some("BTCUSD"));
+        assert_eq!(linear.display_symbol(), Some("SOLUSDC"));
+    }
+
+    #[test]
+    fn applies_book_changes() {
+        let (_, StreamData::Depth(snapshot)) = feed_de(
+            br#"{"jsonrpc":"2.0","method":"subscription","params":{"channel":"book.BTC-PERPETUAL.100ms",
+                "data":{"type":"snapshot","timestamp":1700000000000,"instrument_name":"BTC-PERPETUAL",
+                "change_id":10,"bids":[["new",60000.0,1000.0],["new",59999.5,500.0]],
+                "asks":[["new",60000.5,2000.0]]}}}"#,
+        )
+        .unwrap() else {
+            panic!("expected a book snapshot");
+        };
+        let (_, StreamData::Depth(change)) = feed_de(
+            br#"{"jsonrpc":"2.0","method":"subscription","params":{"channel":"book.BTC-PERPETUAL.100ms",
+                "data":{"type":"change","timestamp":1700000000100,"instrument_name":"BTC-PERPETUAL",
+                "prev_change_id":10,"change_id":12,"bids":[["delete",60000.0,0.0]],
+                "asks":[["change",60000.5,1500.0]]}}}"#,
+        )
+        .unwrap() else {
+            panic!("expected a book change");
+        };
+
+        assert!(snapshot.is_snapshot());
+        assert_eq!(change.prev_change_id, Some(10));
+
+        let min_ticksize = TickerInfo::new(
+            instrument_ticker("BTC-PERPETUAL", Exchange::DeribitInverse),
+            0.5,
+            10.0,
+            Some(10.0),
+        )
+        .min_ticksize;
+
+        let mut orderbook = LocalDepthCache::default();
+        orderbook.update(
+            DepthUpdate::Snapshot(snapshot.payload(false, MarketKind::InversePerps)),
+            min_ticksize,
+        );
+        assert_eq!(
+            integrity::check_prev(orderbook.last_update_id, 10, change.change_id),
+            Ok(())
+        );
+        orderbook.update(
+            DepthUpdate::Diff(change.payload(false, MarketKind::InversePerps)),
+            min_ticksize,
+        );
+
+        assert_eq!(orderbook.depth.bids.len(), 1);
+        assert_eq!(orderbook.depth.asks.values().next(), Some(&1500.0));
+        assert_eq!(orderbook.last_update_id, 12);
+    }
+}
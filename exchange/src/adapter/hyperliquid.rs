@@ -1,33 +1,43 @@
-use crate::{Price, PushFrequency, TickMultiplier};
+use crate::{FundingRate, Price, PushFrequency, TickMultiplier, endpoint};
 
 use super::{
     super::{
         Exchange, Kline, MarketKind, SIZE_IN_QUOTE_CURRENCY, StreamKind, Ticker, TickerInfo,
         TickerStats, Timeframe, Trade,
-        connect::{State, connect_ws},
+        capture::{self, FrameKind},
+        connect::{self, Backoff, State, connect_ws},
         de_string_to_f32,
         depth::{DeOrder, DepthPayload, DepthUpdate, LocalDepthCache},
         limiter::{self, RateLimiter},
     },
-    AdapterError, Event,
+    AdapterError, ConnectionState, Event, ExchangeInclusive, StreamTicksize, integrity, mux,
+    registry::{AdapterFuture, EventStream, ExchangeAdapter},
+    telemetry,
 };
 
-use fastwebsockets::{FragmentCollector, Frame, OpCode};
+use fastwebsockets::{FragmentCollector, Frame, OpCode, WebSocketError};
 use hyper::upgrade::Upgraded;
 use hyper_util::rt::TokioIo;
 use iced_futures::{
-    futures::{SinkExt, Stream},
+    futures::{SinkExt, Stream, channel::mpsc, future},
     stream,
 };
 use reqwest::Method;
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::Deserialize;
 use serde_json::{Value, json};
 
-use std::{collections::HashMap, sync::LazyLock, time::Duration};
-use tokio::sync::Mutex;
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::LazyLock,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::sync::{Mutex, oneshot, watch};
 
 const API_DOMAIN: &str = "https://api.hyperliquid.xyz";
-const WS_DOMAIN: &str = "api.hyperliquid.xyz";
+const WS_DOMAIN: &str = "wss://api.hyperliquid.xyz";
 
 const _MAX_DECIMALS_SPOT: u8 = 8;
 const MAX_DECIMALS_PERP: u8 = 6;
@@ -102,7 +112,7 @@ impl RateLimiter for HyperliquidLimiter {
         self.bucket.consume_tokens(weight);
     }
 
-    fn should_exit_on_response(&self, response: &reqwest::Response) -> bool {
+    fn is_rate_limited(&self, response: &reqwest::Response) -> bool {
         response.status() == 429
     }
 }
@@ -146,6 +156,13 @@ struct HyperliquidAssetContext {
     // open_interest: f32, // Only available for perps
 }
 
+#[derive(Debug, Deserialize)]
+struct HyperliquidFunding {
+    #[serde(rename = "fundingRate", deserialize_with = "de_string_to_f32")]
+    funding_rate: f32,
+    time: u64,
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 struct HyperliquidKline {
@@ -217,7 +234,7 @@ enum StreamData {
 pub async fn fetch_ticksize(
     market: MarketKind,
 ) -> Result<HashMap<Ticker, Option<TickerInfo>>, AdapterError> {
-    let url = format!("{}/info", API_DOMAIN);
+    let url = format!("{}/info", endpoint::resolve(API_DOMAIN));
 
     let (endpoint_type, exchange) = match market {
         MarketKind::LinearPerps => ("metaAndAssetCtxs", Exchange::HyperliquidLinear),
@@ -575,7 +592,7 @@ fn compute_tick_size(price: f32, sz_decimals: u32, market: MarketKind) -> f32 {
 pub async fn fetch_ticker_prices(
     market: MarketKind,
 ) -> Result<HashMap<Ticker, TickerStats>, AdapterError> {
-    let url = format!("{}/info", API_DOMAIN);
+    let url = format!("{}/info", endpoint::resolve(API_DOMAIN));
 
     // Step 1: Get all mid prices (contains both perp and spot)
     let mids = fetch_all_mids(&url).await?;
@@ -787,7 +804,7 @@ pub async fn fetch_klines(
         }
     };
 
-    let url = format!("{}/info", API_DOMAIN);
+    let url = format!("{}/info", endpoint::resolve(API_DOMAIN));
     // Use the internal symbol (e.g., "@107" for spot, "BTC" for perps)
     let (symbol_str, _) = ticker.to_full_symbol_and_type();
 
@@ -858,12 +875,88 @@ pub async fn fetch_klines(
     Ok(klines)
 }
 
-async fn connect_websocket(
-    domain: &str,
-    path: &str,
-) -> Result<FragmentCollector<TokioIo<Upgraded>>, AdapterError> {
-    let url = format!("wss://{}{}", domain, path);
-    connect_ws(domain, &url).await
+/// Funding settles hourly, a single response holds at most 500 entries
+const FUNDING_HISTORY_LIMIT: u64 = 500;
+const FUNDING_INTERVAL_MS: u64 = 60 * 60 * 1000;
+
+pub async fn fetch_funding_rates(
+    ticker: Ticker,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<FundingRate>, AdapterError> {
+    let url = format!("{}/info", endpoint::resolve(API_DOMAIN));
+    let (symbol_str, _) = ticker.to_full_symbol_and_type();
+
+    let (start_time, end_time) = range.unwrap_or_else(|| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        (now - FUNDING_HISTORY_LIMIT * FUNDING_INTERVAL_MS, now)
+    });
+
+    let body = json!({
+        "type": "fundingHistory",
+        "coin": symbol_str,
+        "startTime": start_time,
+        "endTime": end_time
+    });
+
+    let response_text = limiter::http_request_with_limiter(
+        &url,
+        &HYPERLIQUID_LIMITER,
+        1,
+        Some(Method::POST),
+        Some(&body),
+    )
+    .await?;
+
+    let history: Vec<HyperliquidFunding> = serde_json::from_str(&response_text)
+        .map_err(|e| AdapterError::ParseError(format!("Failed to parse funding history: {e}")))?;
+
+    Ok(history
+        .into_iter()
+        .map(|f| FundingRate {
+            time: f.time,
+            rate: f.funding_rate,
+        })
+        .collect())
+}
+
+pub async fn fetch_predicted_funding(ticker: Ticker) -> Result<FundingRate, AdapterError> {
+    let url = format!("{}/info", endpoint::resolve(API_DOMAIN));
+    let (symbol_str, _) = ticker.to_full_symbol_and_type();
+
+    let body = json!({"type": "predictedFundings"});
+
+    let response_text = limiter::http_request_with_limiter(
+        &url,
+        &HYPERLIQUID_LIMITER,
+        1,
+        Some(Method::POST),
+        Some(&body),
+    )
+    .await?;
+
+    // [[coin, [[venue, {fundingRate, nextFundingTime}], ...]], ...]
+    let predictions: Vec<Value> = serde_json::from_str(&response_text)
+        .map_err(|e| AdapterError::ParseError(format!("Failed to parse predicted funding: {e}")))?;
+
+    predictions
+        .iter()
+        .filter(|entry| entry[0].as_str() == Some(symbol_str.as_str()))
+        .filter_map(|entry| entry[1].as_array())
+        .flatten()
+        .find(|venue| venue[0].as_str() == Some("HlPerp"))
+        .and_then(|venue| {
+            let rate = venue[1]["fundingRate"].as_str()?.parse::<f32>().ok()?;
+            let time = venue[1]["nextFundingTime"].as_u64()?;
+            Some(FundingRate { time, rate })
+        })
+        .ok_or_else(|| AdapterError::ParseError(format!("No predicted funding for {symbol_str}")))
+}
+
+fn ws_url() -> String {
+    format!("{}/ws", endpoint::resolve(WS_DOMAIN))
 }
 
 fn parse_websocket_message(payload: &[u8]) -> Result<StreamData, AdapterError> {
@@ -898,209 +991,400 @@ fn parse_websocket_message(payload: &[u8]) -> Result<StreamData, AdapterError> {
     }
 }
 
-pub fn connect_market_stream(
+/// Most tick sizes of one coin carried at once, see [`partition_lanes`]
+const MAX_LANES: usize = MULTS_OVERFLOW.len();
+
+/// Wait before fetching a feed's price again after a failed fetch
+const PRICE_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+type PriceResult = Result<Result<f32, AdapterError>, oneshot::error::RecvError>;
+
+impl StreamData {
+    /// Coin the data is about, what the shared connection routes on
+    fn coin(&self) -> Option<&str> {
+        match self {
+            StreamData::Trade(trades) => trades.first().map(|trade| trade.coin.as_str()),
+            StreamData::Depth(depth) => Some(&depth.coin),
+            StreamData::Kline(_) => None,
+        }
+    }
+}
+
+fn book_subscription(coin: &str, depth_cfg: DepthFeedConfig) -> Value {
+    let mut subscription = json!({
+        "type": "l2Book",
+        "coin": coin,
+    });
+    if let Some(n) = depth_cfg.n_sig_figs {
+        subscription["nSigFigs"] = json!(n);
+    }
+    if let (Some(m), Some(5)) = (depth_cfg.mantissa, depth_cfg.n_sig_figs) {
+        subscription["mantissa"] = json!(m);
+    }
+    subscription
+}
+
+async fn send_subscription(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    method: &str,
+    subscription: &Value,
+) -> Result<(), WebSocketError> {
+    let request = json!({
+        "method": method,
+        "subscription": subscription,
+    });
+
+    log::debug!("Hyperliquid WS {method}: {subscription}");
+
+    websocket
+        .write_frame(Frame::text(fastwebsockets::Payload::Owned(
+            request.to_string().into_bytes(),
+        )))
+        .await
+}
+
+/// Depth and trades of one coin at one tick size, carried on a market connection
+struct DepthFeed {
+    stream: StreamKind,
     ticker_info: TickerInfo,
-    tick_multiplier: Option<TickMultiplier>,
-    push_freq: PushFrequency,
-) -> impl Stream<Item = Event> {
-    stream::channel(100, async move |mut output| {
-        let mut state = State::Disconnected;
+    coin: String,
+    multiplier: u16,
+    /// Price the book precision is picked from, nothing is subscribed until it's in
+    price: Option<oneshot::Receiver<Result<f32, AdapterError>>>,
+    /// Sent once the price is in, unsubscribing has to repeat them
+    subscriptions: Vec<Value>,
+    orderbook: LocalDepthCache,
+    trades_buffer: Vec<Trade>,
+}
 
-        let ticker = ticker_info.ticker;
-        let exchange = ticker.exchange;
+impl DepthFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::DepthAndTrades {
+            ticker_info,
+            depth_aggr,
+            ..
+        } = stream
+        else {
+            return None;
+        };
 
-        let mut local_depth_cache = LocalDepthCache::default();
-        let mut trades_buffer = Vec::new();
+        let multiplier = match depth_aggr {
+            StreamTicksize::ServerSide(multiplier) => multiplier.0,
+            StreamTicksize::Client => 1,
+        };
+        let (coin, _) = ticker_info.ticker.to_full_symbol_and_type();
+
+        let mut feed = Self {
+            stream,
+            ticker_info,
+            coin,
+            multiplier,
+            price: None,
+            subscriptions: Vec::new(),
+            orderbook: LocalDepthCache::default(),
+            trades_buffer: Vec::new(),
+        };
+        feed.fetch_price(Duration::ZERO);
 
-        let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
-        let user_multiplier = tick_multiplier.unwrap_or(TickMultiplier(1)).0;
+        Some(feed)
+    }
 
-        let (symbol_str, _) = ticker.to_full_symbol_and_type();
+    /// Fetches the price after `delay` on its own task, so the other feeds of the connection keep going
+    fn fetch_price(&mut self, delay: Duration) {
+        let coin = self.coin.clone();
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            let result = fetch_orderbook(&coin, None).await.and_then(|depth| {
+                let best_bid = depth.bids.first().map(|order| order.price);
+                best_bid.ok_or_else(|| AdapterError::ParseError(format!("Empty book for {coin}")))
+            });
+            let _ = tx.send(result);
+        });
+
+        self.price = Some(rx);
+    }
+
+    /// Subscribes to the trades, and to the book at the precision the fetched price calls for
+    async fn subscribe(
+        &mut self,
+        result: PriceResult,
+        websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+        output: &mut mpsc::Sender<Event>,
+    ) -> Result<(), WebSocketError> {
+        self.price = None;
+
+        let price = match result {
+            Ok(Ok(price)) => price,
+            Ok(Err(e)) => {
+                log::error!("Failed to fetch orderbook for price: {}", e);
+                self.fetch_price(PRICE_RETRY_DELAY);
+                return Ok(());
+            }
+            Err(e) => {
+                log::error!("Failed to receive price for {}, error: {}", self.coin, e);
+                self.fetch_price(PRICE_RETRY_DELAY);
+                return Ok(());
+            }
+        };
 
         log::debug!(
-            "Connecting market stream for ticker symbol: '{}'",
-            symbol_str
+            "Subscribing Hyperliquid market stream with price {} and multiplier {}",
+            price,
+            self.multiplier
         );
 
-        loop {
-            match &mut state {
-                State::Disconnected => {
-                    let price = match fetch_orderbook(&symbol_str, None).await {
-                        Ok(depth) => depth.bids.first().map(|o| o.price),
-                        Err(e) => {
-                            log::error!("Failed to fetch orderbook for price: {}", e);
-                            None
-                        }
+        let depth_cfg = config_from_multiplier(price, self.multiplier);
+        self.subscriptions = vec![
+            book_subscription(&self.coin, depth_cfg),
+            json!({
+                "type": "trades",
+                "coin": self.coin,
+            }),
+        ];
+
+        for subscription in &self.subscriptions {
+            send_subscription(websocket, "subscribe", subscription).await?;
+        }
+
+        connect::publish(output, &[self.stream], ConnectionState::Live).await;
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, data: &StreamData, output: &mut mpsc::Sender<Event>) {
+        let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+        let min_ticksize = self.ticker_info.min_ticksize;
+
+        match data {
+            StreamData::Trade(trades) => {
+                for hl_trade in trades {
+                    let price = Price::from_f32(hl_trade.px).round_to_min_tick(min_ticksize);
+                    let qty = if size_in_quote_currency {
+                        (hl_trade.sz * hl_trade.px).round()
+                    } else {
+                        hl_trade.sz
                     };
-                    if price.is_none() {
-                        tokio::time::sleep(Duration::from_secs(1)).await;
-                        continue;
-                    }
-                    let price = price.unwrap();
 
-                    log::debug!(
-                        "Connecting to Hyperliquid market stream with price {} and multiplier {}",
+                    self.trades_buffer.push(Trade {
+                        time: hl_trade.time,
+                        is_sell: hl_trade.side == "A", // A for Ask/Sell, B for Bid/Buy
                         price,
-                        user_multiplier
-                    );
+                        qty,
+                    });
+                }
+            }
+            // left over from a precision this feed didn't subscribe to
+            StreamData::Depth(_) if self.subscriptions.is_empty() => {}
+            StreamData::Depth(depth) => {
+                let to_orders = |levels: &[HyperliquidLevel]| -> Vec<DeOrder> {
+                    levels
+                        .iter()
+                        .map(|level| DeOrder {
+                            price: level.px,
+                            qty: if size_in_quote_currency {
+                                (level.sz * level.px).round()
+                            } else {
+                                level.sz
+                            },
+                        })
+                        .collect()
+                };
+
+                self.orderbook.update(
+                    DepthUpdate::Snapshot(DepthPayload {
+                        last_update_id: depth.time,
+                        time: depth.time,
+                        bids: to_orders(&depth.levels[0]),
+                        asks: to_orders(&depth.levels[1]),
+                    }),
+                    min_ticksize,
+                );
 
-                    let depth_cfg = config_from_multiplier(price, user_multiplier);
+                // pushes are full snapshots, the next one replaces it
+                if let Err(fault) = integrity::check_crossed(&self.orderbook.depth) {
+                    integrity::record(self.stream, fault);
+                    return;
+                }
 
-                    match connect_websocket(WS_DOMAIN, "/ws").await {
-                        Ok(mut websocket) => {
-                            let mut depth_subscription = json!({
-                                "method": "subscribe",
-                                "subscription": {
-                                    "type": "l2Book",
-                                    "coin": symbol_str,
-                                }
-                            });
-                            if let Some(n) = depth_cfg.n_sig_figs {
-                                depth_subscription["subscription"]["nSigFigs"] = json!(n);
-                            }
-                            if let (Some(m), Some(5)) = (depth_cfg.mantissa, depth_cfg.n_sig_figs) {
-                                depth_subscription["subscription"]["mantissa"] = json!(m);
-                            }
+                telemetry::observe(self.stream, depth.time);
 
-                            log::debug!(
-                                "Hyperliquid WS Depth Subscription: {}",
-                                serde_json::to_string_pretty(&depth_subscription)
-                                    .unwrap_or_else(|_| "Failed to serialize".to_string())
-                            );
-
-                            if websocket
-                                .write_frame(Frame::text(fastwebsockets::Payload::Borrowed(
-                                    depth_subscription.to_string().as_bytes(),
-                                )))
-                                .await
-                                .is_err()
-                            {
-                                tokio::time::sleep(Duration::from_secs(1)).await;
-                                continue;
-                            }
+                let _ = output
+                    .send(Event::DepthReceived(
+                        self.stream,
+                        depth.time,
+                        self.orderbook.depth.clone(),
+                        std::mem::take(&mut self.trades_buffer).into_boxed_slice(),
+                    ))
+                    .await;
+            }
+            StreamData::Kline(_) => {}
+        }
+    }
+}
 
-                            let trades_subscribe_msg = json!({
-                                "method": "subscribe",
-                                "subscription": {
-                                    "type": "trades",
-                                    "coin": symbol_str
-                                }
-                            });
-
-                            log::debug!(
-                                "Hyperliquid WS Trades Subscription: {}",
-                                serde_json::to_string_pretty(&trades_subscribe_msg)
-                                    .unwrap_or_else(|_| "Failed to serialize".to_string())
-                            );
-
-                            if websocket
-                                .write_frame(Frame::text(fastwebsockets::Payload::Borrowed(
-                                    trades_subscribe_msg.to_string().as_bytes(),
-                                )))
-                                .await
-                                .is_err()
-                            {
-                                tokio::time::sleep(Duration::from_secs(1)).await;
-                                continue;
-                            }
+/// Index of the first feed whose price fetch finished, along with its result
+fn poll_prices(feeds: &mut [DepthFeed], cx: &mut Context<'_>) -> Poll<(usize, PriceResult)> {
+    for (index, feed) in feeds.iter_mut().enumerate() {
+        if let Some(price) = &mut feed.price
+            && let Poll::Ready(result) = Pin::new(price).poll(cx)
+        {
+            return Poll::Ready((index, result));
+        }
+    }
 
-                            state = State::Connected(websocket);
-                            let _ = output.send(Event::Connected(exchange)).await;
+    Poll::Pending
+}
+
+/// Drops the feeds no longer wanted and adds the new ones, which subscribe once their price is in
+async fn update_subscriptions(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    feeds: &mut Vec<DepthFeed>,
+    wanted: &FxHashSet<StreamKind>,
+) -> Result<(), WebSocketError> {
+    let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(feeds)
+        .into_iter()
+        .partition(|feed| wanted.contains(&feed.stream));
+    *feeds = kept;
+
+    for feed in &removed {
+        for subscription in &feed.subscriptions {
+            send_subscription(websocket, "unsubscribe", subscription).await?;
+        }
+    }
+
+    let added = wanted
+        .iter()
+        .filter(|stream| !feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| DepthFeed::new(*stream))
+        .collect::<Vec<_>>();
+    feeds.extend(added);
+
+    Ok(())
+}
+
+/// Splits the wanted streams so that a connection carries at most one tick size of a coin
+///
+/// l2Book pushes don't say which `nSigFigs` they were subscribed with, so a coin shown at
+/// several tick sizes takes a connection per tick size. Lanes left empty stay closed.
+fn partition_lanes(wanted: &FxHashSet<StreamKind>) -> Vec<FxHashSet<StreamKind>> {
+    let mut by_coin = FxHashMap::<Ticker, Vec<StreamKind>>::default();
+    for stream in wanted {
+        by_coin
+            .entry(stream.ticker_info().ticker)
+            .or_default()
+            .push(*stream);
+    }
+
+    let mut lanes = vec![FxHashSet::default(); MAX_LANES];
+    for streams in by_coin.values_mut() {
+        streams.sort_by_key(|stream| match stream.as_depth_stream() {
+            Some((_, StreamTicksize::ServerSide(multiplier), _)) => multiplier.0,
+            _ => 1,
+        });
+
+        for (lane, stream) in lanes.iter_mut().zip(streams.iter()) {
+            lane.insert(*stream);
+        }
+    }
+
+    lanes
+}
+
+/// Depth and trades of every stream in `wanted` over a single connection
+async fn run_market_streams(
+    exchange: Exchange,
+    mut wanted: mux::WantedStreams,
+    output: &mut mpsc::Sender<Event>,
+) {
+    let url = ws_url();
+    let source = capture::Source::new(exchange, "market");
+
+    let mut state = State::Disconnected;
+    let mut backoff = Backoff::default();
+    let mut feeds: Vec<DepthFeed> = Vec::new();
+
+    loop {
+        match &mut state {
+            State::Disconnected => {
+                feeds.clear();
+
+                let streams = wanted.borrow().iter().copied().collect::<Vec<_>>();
+                if streams.is_empty() {
+                    if wanted.changed().await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+
+                connect::publish(output, &streams, ConnectionState::Connecting).await;
+
+                match connect_ws(&url).await {
+                    Ok(websocket) => {
+                        state = State::Connected(websocket);
+                        backoff.reset();
+                        // a fresh socket has nothing subscribed yet, feeds go live once subscribed
+                        wanted.mark_changed();
+
+                        let _ = output.send(Event::Connected(exchange)).await;
+                    }
+                    Err(_) => {
+                        let _ = output
+                            .send(Event::Disconnected(
+                                exchange,
+                                "Failed to connect to websocket".to_string(),
+                            ))
+                            .await;
+
+                        backoff.wait(&streams, output).await;
+                    }
+                }
+            }
+            State::Connected(websocket) => {
+                tokio::select! {
+                    Ok(()) = wanted.changed() => {
+                        let streams = wanted.borrow_and_update().clone();
+
+                        if streams.is_empty() {
+                            // closed until something is wanted again
+                            state = State::Disconnected;
+                        } else if let Err(e) =
+                            update_subscriptions(websocket, &mut feeds, &streams).await
+                        {
+                            state = State::Disconnected;
+                            let _ = output
+                                .send(Event::Disconnected(
+                                    exchange,
+                                    format!("Failed updating subscriptions: {}", e),
+                                ))
+                                .await;
                         }
-                        Err(_) => {
-                            tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                    (index, result) = std::future::poll_fn(|cx| poll_prices(&mut feeds, cx)) => {
+                        if let Err(e) = feeds[index].subscribe(result, websocket, output).await {
+                            state = State::Disconnected;
                             let _ = output
                                 .send(Event::Disconnected(
                                     exchange,
-                                    "Failed to connect to websocket".to_string(),
+                                    format!("Failed subscribing: {}", e),
                                 ))
                                 .await;
                         }
                     }
-                }
-                State::Connected(websocket) => {
-                    match websocket.read_frame().await {
+                    frame = websocket.read_frame() => match frame {
                         Ok(msg) => match msg.opcode {
                             OpCode::Text => {
-                                if let Ok(stream_data) = parse_websocket_message(&msg.payload) {
-                                    match stream_data {
-                                        StreamData::Trade(trades) => {
-                                            for hl_trade in trades {
-                                                let price = Price::from_f32(hl_trade.px)
-                                                    .round_to_min_tick(ticker_info.min_ticksize);
-                                                let qty = if size_in_quote_currency {
-                                                    (hl_trade.sz * hl_trade.px).round()
-                                                } else {
-                                                    hl_trade.sz
-                                                };
-
-                                                let trade = Trade {
-                                                    time: hl_trade.time,
-                                                    is_sell: hl_trade.side == "A", // A for Ask/Sell, B for Bid/Buy
-                                                    price,
-                                                    qty,
-                                                };
-                                                trades_buffer.push(trade);
-                                            }
-                                        }
-                                        StreamData::Depth(depth) => {
-                                            let bids = depth.levels[0]
-                                                .iter()
-                                                .map(|level| DeOrder {
-                                                    price: level.px,
-                                                    qty: if size_in_quote_currency {
-                                                        (level.sz * level.px).round()
-                                                    } else {
-                                                        level.sz
-                                                    },
-                                                })
-                                                .collect();
-                                            let asks = depth.levels[1]
-                                                .iter()
-                                                .map(|level| DeOrder {
-                                                    price: level.px,
-                                                    qty: if size_in_quote_currency {
-                                                        (level.sz * level.px).round()
-                                                    } else {
-                                                        level.sz
-                                                    },
-                                                })
-                                                .collect();
-
-                                            let depth_payload = DepthPayload {
-                                                last_update_id: depth.time,
-                                                time: depth.time,
-                                                bids,
-                                                asks,
-                                            };
-                                            local_depth_cache.update(
-                                                DepthUpdate::Snapshot(depth_payload),
-                                                ticker_info.min_ticksize,
-                                            );
-
-                                            let stream_kind = StreamKind::DepthAndTrades {
-                                                ticker_info,
-                                                depth_aggr: super::StreamTicksize::ServerSide(
-                                                    TickMultiplier(user_multiplier),
-                                                ),
-                                                push_freq,
-                                            };
-                                            let current_depth = local_depth_cache.depth.clone();
-                                            let trades = std::mem::take(&mut trades_buffer)
-                                                .into_boxed_slice();
-
-                                            let _ = output
-                                                .send(Event::DepthReceived(
-                                                    stream_kind,
-                                                    depth.time,
-                                                    current_depth,
-                                                    trades,
-                                                ))
-                                                .await;
-                                        }
-                                        StreamData::Kline(_) => {
-                                            // Handle kline data if needed for depth stream
-                                        }
+                                capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
+                                if let Ok(stream_data) = parse_websocket_message(&msg.payload)
+                                    && let Some(coin) = stream_data.coin()
+                                {
+                                    let routed = feeds.iter_mut().filter(|feed| feed.coin == coin);
+
+                                    for feed in routed {
+                                        feed.handle(&stream_data, output).await;
                                     }
                                 }
                             }
@@ -1127,10 +1411,74 @@ pub fn connect_market_stream(
                                 ))
                                 .await;
                         }
-                    }
+                    },
                 }
             }
         }
+    }
+}
+
+pub fn connect_market_stream(
+    ticker_info: TickerInfo,
+    tick_multiplier: Option<TickMultiplier>,
+    push_freq: PushFrequency,
+) -> impl Stream<Item = Event> {
+    let stream = StreamKind::DepthAndTrades {
+        ticker_info,
+        depth_aggr: StreamTicksize::ServerSide(tick_multiplier.unwrap_or(TickMultiplier(1))),
+        push_freq,
+    };
+
+    stream::channel(100, async move |mut output| {
+        let exchange = ticker_info.ticker.exchange;
+        run_market_streams(exchange, mux::fixed([stream]), &mut output).await;
+    })
+}
+
+/// Depth and trades of all the tickers wanted from a market, see [`partition_lanes`] for
+/// when that takes more than one connection
+pub fn connect_shared_market_stream(market: MarketKind) -> impl Stream<Item = Event> {
+    stream::channel(100, async move |output| {
+        let exchange = match market {
+            MarketKind::Spot => Exchange::HyperliquidSpot,
+            MarketKind::LinearPerps => Exchange::HyperliquidLinear,
+            MarketKind::InversePerps => return,
+        };
+        let mut wanted = mux::wanted(exchange);
+
+        let lanes = (0..MAX_LANES)
+            .map(|_| watch::Sender::new(FxHashSet::default()))
+            .collect::<Vec<_>>();
+
+        let connections = lanes.iter().map(|lane| {
+            let lane_wanted = lane.subscribe();
+            let mut output = output.clone();
+
+            async move { run_market_streams(exchange, lane_wanted, &mut output).await }
+        });
+
+        let split = async {
+            loop {
+                let partition = partition_lanes(&wanted.borrow_and_update());
+
+                for (lane, streams) in lanes.iter().zip(partition) {
+                    lane.send_if_modified(|current| {
+                        if *current == streams {
+                            false
+                        } else {
+                            *current = streams;
+                            true
+                        }
+                    });
+                }
+
+                if wanted.changed().await.is_err() {
+                    break;
+                }
+            }
+        };
+
+        future::join(split, future::join_all(connections)).await;
     })
 }
 
@@ -1148,67 +1496,91 @@ pub fn connect_kline_stream(
 
         let size_in_quote_currency = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
 
+        let kline_streams = streams
+            .iter()
+            .map(|(ticker_info, timeframe)| StreamKind::Kline {
+                ticker_info: *ticker_info,
+                timeframe: *timeframe,
+            })
+            .collect::<Vec<_>>();
+        let mut backoff = Backoff::default();
+
+        let url = ws_url();
+        let source = capture::Source::new(exchange, "klines");
+
         loop {
             match &mut state {
-                State::Disconnected => match connect_websocket(WS_DOMAIN, "/ws").await {
-                    Ok(mut websocket) => {
-                        for (ticker_info, timeframe) in &streams {
-                            let ticker = ticker_info.ticker;
-
-                            let interval = match timeframe {
-                                Timeframe::M1 => "1m",
-                                Timeframe::M5 => "5m",
-                                Timeframe::M15 => "15m",
-                                Timeframe::M30 => "30m",
-                                Timeframe::H1 => "1h",
-                                Timeframe::H4 => "4h",
-                                Timeframe::D1 => "1d",
-                                _ => continue,
-                            };
-
-                            let (symbol_str, _) = ticker.to_full_symbol_and_type();
-                            let subscribe_msg = json!({
-                                "method": "subscribe",
-                                "subscription": {
-                                    "type": "candle",
-                                    "coin": symbol_str,
-                                    "interval": interval
+                State::Disconnected => {
+                    connect::publish(&mut output, &kline_streams, ConnectionState::Connecting)
+                        .await;
+
+                    match connect_ws(&url).await {
+                        Ok(mut websocket) => {
+                            for (ticker_info, timeframe) in &streams {
+                                let ticker = ticker_info.ticker;
+
+                                let interval = match timeframe {
+                                    Timeframe::M1 => "1m",
+                                    Timeframe::M5 => "5m",
+                                    Timeframe::M15 => "15m",
+                                    Timeframe::M30 => "30m",
+                                    Timeframe::H1 => "1h",
+                                    Timeframe::H4 => "4h",
+                                    Timeframe::D1 => "1d",
+                                    _ => continue,
+                                };
+
+                                let (symbol_str, _) = ticker.to_full_symbol_and_type();
+                                let subscribe_msg = json!({
+                                    "method": "subscribe",
+                                    "subscription": {
+                                        "type": "candle",
+                                        "coin": symbol_str,
+                                        "interval": interval
+                                    }
+                                });
+
+                                log::debug!(
+                                    "Hyperliquid WS Kline Subscription: {}",
+                                    serde_json::to_string_pretty(&subscribe_msg)
+                                        .unwrap_or_else(|_| "Failed to serialize".to_string())
+                                );
+
+                                if (websocket
+                                    .write_frame(Frame::text(fastwebsockets::Payload::Borrowed(
+                                        subscribe_msg.to_string().as_bytes(),
+                                    )))
+                                    .await)
+                                    .is_err()
+                                {
+                                    break;
                                 }
-                            });
-
-                            log::debug!(
-                                "Hyperliquid WS Kline Subscription: {}",
-                                serde_json::to_string_pretty(&subscribe_msg)
-                                    .unwrap_or_else(|_| "Failed to serialize".to_string())
-                            );
-
-                            if (websocket
-                                .write_frame(Frame::text(fastwebsockets::Payload::Borrowed(
-                                    subscribe_msg.to_string().as_bytes(),
-                                )))
-                                .await)
-                                .is_err()
-                            {
-                                break;
                             }
+
+                            state = State::Connected(websocket);
+                            backoff.reset();
+
+                            let _ = output.send(Event::Connected(exchange)).await;
+                            connect::publish(&mut output, &kline_streams, ConnectionState::Live)
+                                .await;
                         }
+                        Err(_) => {
+                            let _ = output
+                                .send(Event::Disconnected(
+                                    exchange,
+                                    "Failed to connect to websocket".to_string(),
+                                ))
+                                .await;
 
-                        state = State::Connected(websocket);
-                        let _ = output.send(Event::Connected(exchange)).await;
-                    }
-                    Err(_) => {
-                        tokio::time::sleep(Duration::from_secs(1)).await;
-                        let _ = output
-                            .send(Event::Disconnected(
-                                exchange,
-                                "Failed to connect to websocket".to_string(),
-                            ))
-                            .await;
+                            backoff.wait(&kline_streams, &mut output).await;
+                        }
                     }
-                },
+                }
                 State::Connected(websocket) => match websocket.read_frame().await {
                     Ok(msg) => match msg.opcode {
                         OpCode::Text => {
+                            capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
                             if let Ok(StreamData::Kline(hl_kline)) =
                                 parse_websocket_message(&msg.payload)
                             {
@@ -1288,7 +1660,7 @@ async fn fetch_orderbook(
     cfg: Option<DepthFeedConfig>,
 ) -> Result<DepthPayload, AdapterError> {
     log::debug!("Fetching orderbook for symbol: '{}'", symbol);
-    let url = format!("{}/info", API_DOMAIN);
+    let url = format!("{}/info", endpoint::resolve(API_DOMAIN));
 
     let mut body = json!({
         "type": "l2Book",
@@ -1356,6 +1728,106 @@ async fn fetch_orderbook(
     })
 }
 
+pub struct Hyperliquid;
+
+impl ExchangeAdapter for Hyperliquid {
+    fn venue(&self) -> ExchangeInclusive {
+        ExchangeInclusive::Hyperliquid
+    }
+
+    fn name(&self) -> &'static str {
+        "Hyperliquid"
+    }
+
+    fn id(&self) -> &'static str {
+        "hyperliquid"
+    }
+
+    fn markets(&self) -> &'static [Exchange] {
+        &[Exchange::HyperliquidLinear, Exchange::HyperliquidSpot]
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["hyperliquid.xyz"]
+    }
+
+    fn supports_open_interest(&self, _market: MarketKind) -> bool {
+        false
+    }
+
+    fn is_depth_client_aggr(&self) -> bool {
+        false
+    }
+
+    fn supports_heatmap_timeframe(&self, _market: MarketKind, tf: Timeframe) -> bool {
+        tf != Timeframe::MS100 && tf != Timeframe::MS200 && tf != Timeframe::MS300
+    }
+
+    fn fetch_ticker_info(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, Option<TickerInfo>>> {
+        Box::pin(fetch_ticksize(market))
+    }
+
+    fn fetch_ticker_prices(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, TickerStats>> {
+        Box::pin(fetch_ticker_prices(market))
+    }
+
+    fn fetch_klines(
+        &self,
+        ticker_info: TickerInfo,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<Kline>> {
+        Box::pin(fetch_klines(ticker_info, timeframe, range))
+    }
+
+    fn fetch_funding_rates(
+        &self,
+        ticker: Ticker,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<FundingRate>> {
+        Box::pin(fetch_funding_rates(ticker, range))
+    }
+
+    fn fetch_predicted_funding(&self, ticker: Ticker) -> AdapterFuture<FundingRate> {
+        Box::pin(fetch_predicted_funding(ticker))
+    }
+
+    fn connect_market_stream(
+        &self,
+        ticker_info: TickerInfo,
+        tick_multiplier: Option<TickMultiplier>,
+        push_freq: PushFrequency,
+    ) -> EventStream {
+        Box::pin(connect_market_stream(
+            ticker_info,
+            tick_multiplier,
+            push_freq,
+        ))
+    }
+
+    fn multiplexes_market_streams(&self) -> bool {
+        true
+    }
+
+    fn connect_shared_market_stream(&self, market: MarketKind) -> EventStream {
+        Box::pin(connect_shared_market_stream(market))
+    }
+
+    fn connect_kline_stream(
+        &self,
+        streams: Vec<(TickerInfo, Timeframe)>,
+        market: MarketKind,
+    ) -> EventStream {
+        Box::pin(connect_kline_stream(streams, market))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1442,4 +1914,37 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn splits_tick_sizes_of_a_coin_across_lanes() {
+        let btc = TickerInfo::new(
+            Ticker::new("BTC", Exchange::HyperliquidLinear),
+            1.0,
+            0.00001,
+            None,
+        );
+        let eth = TickerInfo::new(
+            Ticker::new("ETH", Exchange::HyperliquidLinear),
+            0.1,
+            0.0001,
+            None,
+        );
+        let depth = |ticker_info, multiplier| StreamKind::DepthAndTrades {
+            ticker_info,
+            depth_aggr: StreamTicksize::ServerSide(TickMultiplier(multiplier)),
+            push_freq: PushFrequency::ServerDefault,
+        };
+
+        let wanted = [depth(btc, 10), depth(btc, 1), depth(eth, 1)]
+            .into_iter()
+            .collect();
+        let lanes = partition_lanes(&wanted);
+
+        assert_eq!(
+            lanes[0],
+            FxHashSet::from_iter([depth(btc, 1), depth(eth, 1)])
+        );
+        assert_eq!(lanes[1], FxHashSet::from_iter([depth(btc, 10)]));
+        assert!(lanes[2..].iter().all(FxHashSet::is_empty));
+    }
 }
@@ -0,0 +1,184 @@
+//! Checks that keep local order books consistent with the exchange's
+//!
+//! Adapters verify each diff against the venue's own guarantees, update ids
+//! following each other or a checksum over the top of the book, and look for
+//! a crossed book once it's applied. A failed check is [recorded](record)
+//! against the stream and the book is resynced from a fresh snapshot.
+
+use super::StreamKind;
+use crate::{Price, depth::Depth};
+
+use rustc_hash::FxHashMap;
+use std::sync::{LazyLock, PoisonError, RwLock};
+
+/// Why a local book can't be trusted anymore
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// An update id that doesn't follow the last applied one
+    SequenceGap { last: u64, got: u64 },
+    /// The exchange's checksum disagrees with the one of the local book
+    ChecksumMismatch { expected: i32, computed: i32 },
+    /// Best bid above best ask
+    CrossedBook { best_bid: Price, best_ask: Price },
+}
+
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::SequenceGap { last, got } => {
+                write!(f, "sequence gap, got {got} after {last}")
+            }
+            Fault::ChecksumMismatch { expected, computed } => {
+                write!(
+                    f,
+                    "checksum mismatch, expected {expected}, computed {computed}"
+                )
+            }
+            Fault::CrossedBook { best_bid, best_ask } => write!(
+                f,
+                "crossed book, best bid {} above best ask {}",
+                best_bid.to_f32(),
+                best_ask.to_f32()
+            ),
+        }
+    }
+}
+
+/// Faults seen on a stream since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub sequence_gaps: u64,
+    pub checksum_mismatches: u64,
+    pub crossed_books: u64,
+}
+
+impl Counters {
+    pub fn total(&self) -> u64 {
+        self.sequence_gaps + self.checksum_mismatches + self.crossed_books
+    }
+}
+
+static COUNTERS: LazyLock<RwLock<FxHashMap<StreamKind, Counters>>> =
+    LazyLock::new(Default::default);
+
+/// Counts `fault` against `stream`, the caller is expected to resync its book
+pub fn record(stream: StreamKind, fault: Fault) {
+    log::warn!("{stream:?}: {fault}, resyncing");
+
+    let mut counters = COUNTERS.write().unwrap_or_else(PoisonError::into_inner);
+    let counters = counters.entry(stream).or_default();
+
+    match fault {
+        Fault::SequenceGap { .. } => counters.sequence_gaps += 1,
+        Fault::ChecksumMismatch { .. } => counters.checksum_mismatches += 1,
+        Fault::CrossedBook { .. } => counters.crossed_books += 1,
+    }
+}
+
+pub fn counters(stream: &StreamKind) -> Counters {
+    COUNTERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(stream)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Update ids that must be consecutive, as Bybit's `u`
+pub fn check_next(last: u64, got: u64) -> Result<(), Fault> {
+    if got == last + 1 {
+        Ok(())
+    } else {
+        Err(Fault::SequenceGap { last, got })
+    }
+}
+
+/// Update ids that must carry the previous one along, as Binance's `pu` or OKX's `prevSeqId`
+pub fn check_prev(last: u64, prev: u64, got: u64) -> Result<(), Fault> {
+    if prev == last {
+        Ok(())
+    } else {
+        Err(Fault::SequenceGap { last, got })
+    }
+}
+
+/// Ids that only have to increase, as Bybit's cross sequence `seq`
+pub fn check_increasing(last: u64, got: u64) -> Result<(), Fault> {
+    if got > last {
+        Ok(())
+    } else {
+        Err(Fault::SequenceGap { last, got })
+    }
+}
+
+/// Timestamps that may repeat but never go back, as Bybit's `cts`
+pub fn check_monotonic(last: u64, got: u64) -> Result<(), Fault> {
+    if got >= last {
+        Ok(())
+    } else {
+        Err(Fault::SequenceGap { last, got })
+    }
+}
+
+pub fn check_crossed(depth: &Depth) -> Result<(), Fault> {
+    match depth.crossed() {
+        Some((best_bid, best_ask)) => Err(Fault::CrossedBook { best_bid, best_ask }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        PushFrequency, Ticker, TickerInfo,
+        adapter::{Exchange, StreamTicksize},
+    };
+
+    #[test]
+    fn detects_crossed_book() {
+        let mut depth = Depth::default();
+        depth.bids.insert(Price::from_f32(100.0), 1.0);
+        depth.asks.insert(Price::from_f32(100.5), 1.0);
+        assert_eq!(check_crossed(&depth), Ok(()));
+
+        depth.bids.insert(Price::from_f32(101.0), 1.0);
+        assert_eq!(
+            check_crossed(&depth),
+            Err(Fault::CrossedBook {
+                best_bid: Price::from_f32(101.0),
+                best_ask: Price::from_f32(100.5),
+            })
+        );
+    }
+
+    #[test]
+    fn counts_faults_per_stream() {
+        let ticker_info = TickerInfo::new(
+            Ticker::new("INTEGRITYUSDT", Exchange::BybitLinear),
+            0.1,
+            0.001,
+            None,
+        );
+        let stream = StreamKind::DepthAndTrades {
+            ticker_info,
+            depth_aggr: StreamTicksize::Client,
+            push_freq: PushFrequency::ServerDefault,
+        };
+
+        record(stream, Fault::SequenceGap { last: 1, got: 3 });
+        record(stream, check_next(3, 5).unwrap_err());
+        record(
+            stream,
+            Fault::ChecksumMismatch {
+                expected: 1,
+                computed: 2,
+            },
+        );
+
+        let counters = counters(&stream);
+        assert_eq!(counters.sequence_gaps, 2);
+        assert_eq!(counters.checksum_mismatches, 1);
+        assert_eq!(counters.total(), 3);
+    }
+}
@@ -0,0 +1,75 @@
+//! Market streams of many tickers over one connection per exchange
+//!
+//! The dashboard publishes the depth and trades streams it needs with
+//! [`set_wanted`]. Adapters that [multiplex](super::ExchangeAdapter::multiplexes_market_streams)
+//! keep a single socket per market, watch that set and subscribe or drop
+//! topics on the open connection as it changes, still emitting per-ticker events.
+
+use super::{Exchange, StreamKind};
+
+use enum_map::EnumMap;
+use rustc_hash::FxHashSet;
+use std::sync::LazyLock;
+use tokio::sync::watch;
+
+pub type WantedStreams = watch::Receiver<FxHashSet<StreamKind>>;
+
+static WANTED: LazyLock<EnumMap<Exchange, watch::Sender<FxHashSet<StreamKind>>>> =
+    LazyLock::new(|| EnumMap::from_fn(|_| watch::Sender::new(FxHashSet::default())));
+
+/// Replaces the depth streams wanted from `exchange`, a no-op if they didn't change
+pub fn set_wanted(exchange: Exchange, streams: impl IntoIterator<Item = StreamKind>) {
+    let streams = streams.into_iter().collect::<FxHashSet<_>>();
+
+    WANTED[exchange].send_if_modified(|wanted| {
+        if *wanted == streams {
+            false
+        } else {
+            *wanted = streams;
+            true
+        }
+    });
+}
+
+/// Streams the shared connection of `exchange` should carry, updated by [`set_wanted`]
+pub fn wanted(exchange: Exchange) -> WantedStreams {
+    WANTED[exchange].subscribe()
+}
+
+/// A set of streams that never changes, for a connection of its own
+///
+/// The sender is dropped right away, so `changed` errors once the set was seen.
+pub fn fixed(streams: impl IntoIterator<Item = StreamKind>) -> WantedStreams {
+    watch::channel(streams.into_iter().collect()).1
+}
+
+/// Topics to subscribe to and to unsubscribe from, going from `current` to `wanted`
+pub fn diff_topics(
+    current: &FxHashSet<String>,
+    wanted: &FxHashSet<String>,
+) -> (Vec<String>, Vec<String>) {
+    (
+        wanted.difference(current).cloned().collect(),
+        current.difference(wanted).cloned().collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topics(names: &[&str]) -> FxHashSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn diff_keeps_shared_topics_subscribed() {
+        let current = topics(&["btcusdt@aggTrade", "btcusdt@depth", "ethusdt@aggTrade"]);
+        let wanted = topics(&["btcusdt@aggTrade", "btcusdt@depth", "solusdt@aggTrade"]);
+
+        let (subscribe, unsubscribe) = diff_topics(&current, &wanted);
+
+        assert_eq!(subscribe, vec!["solusdt@aggTrade".to_string()]);
+        assert_eq!(unsubscribe, vec!["ethusdt@aggTrade".to_string()]);
+    }
+}
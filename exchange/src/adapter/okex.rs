@@ -1,35 +1,58 @@
 use crate::{
-    OpenInterest, Price, PushFrequency, SIZE_IN_QUOTE_CURRENCY,
+    FundingRate, Liquidation, OpenInterest, Price, PushFrequency, SIZE_IN_QUOTE_CURRENCY,
+    TickMultiplier,
     adapter::{StreamKind, StreamTicksize},
+    endpoint,
     limiter::{self, RateLimiter},
 };
 
 use super::{
     super::{
         Exchange, Kline, MarketKind, Ticker, TickerInfo, TickerStats, Timeframe, Trade,
-        connect::{State, connect_ws},
-        de_string_to_f32, de_string_to_u64, is_symbol_supported,
-        limiter::HTTP_CLIENT,
+        capture::{self, FrameKind},
+        connect::{self, Backoff, State, connect_ws},
+        de_string_to_f32, de_string_to_u64, is_symbol_supported, proxy,
     },
-    AdapterError, Event,
+    AdapterError, ConnectionState, Event, ExchangeInclusive, integrity, mux,
+    registry::{AdapterFuture, EventStream, ExchangeAdapter},
+    telemetry,
 };
 
 use super::super::depth::{DeOrder, DepthPayload, DepthUpdate, LocalDepthCache};
 
-use fastwebsockets::{Frame, OpCode};
+use fastwebsockets::{FragmentCollector, Frame, OpCode, WebSocketError};
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
 use iced_futures::{
     futures::{SinkExt, Stream, channel::mpsc},
     stream,
 };
+use rustc_hash::FxHashSet;
 use serde::Deserialize;
 use serde_json::Value;
-use std::{collections::HashMap, sync::LazyLock, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::LazyLock,
+    time::Duration,
+};
 use tokio::sync::Mutex;
 
-const WS_DOMAIN: &str = "ws.okx.com";
+const WS_DOMAIN: &str = "wss://ws.okx.com";
+const REST_DOMAIN: &str = "https://www.okx.com";
 
 const LIMIT: usize = 20;
 
+/// Levels per side the book checksum is computed over
+const CHECKSUM_DEPTH: usize = 25;
+
+/// Trades per page of the history endpoint, its maximum
+const HIST_TRADES_LIMIT: usize = 100;
+/// Span of the first window a trade backfill batch covers
+const HIST_TRADES_WINDOW: u64 = 60_000;
+/// Cap on the window span, which doubles while windows come back empty
+const HIST_TRADES_MAX_WINDOW: u64 = 3_600_000;
+
 const REFILL_RATE: Duration = Duration::from_secs(2);
 const LIMITER_BUFFER_PCT: f32 = 0.05;
 
@@ -58,7 +81,7 @@ impl RateLimiter for OkexLimiter {
         self.bucket.consume_tokens(weight);
     }
 
-    fn should_exit_on_response(&self, response: &reqwest::Response) -> bool {
+    fn is_rate_limited(&self, response: &reqwest::Response) -> bool {
         response.status() == 429
     }
 }
@@ -75,26 +98,176 @@ struct SonicTrade {
     pub is_sell: String,
 }
 
+/// A trade of the REST history, paged by its id
+#[derive(Deserialize, Debug)]
+struct DeHistTrade {
+    #[serde(rename = "tradeId")]
+    trade_id: String,
+    #[serde(flatten)]
+    trade: SonicTrade,
+}
+
+#[derive(Deserialize, Debug)]
+struct SonicLiquidationOrder {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    pub details: Vec<SonicLiquidationDetail>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SonicLiquidationDetail {
+    #[serde(rename = "ts", deserialize_with = "de_string_to_u64")]
+    pub time: u64,
+    #[serde(rename = "bkPx", deserialize_with = "de_string_to_f32")]
+    pub price: f32,
+    #[serde(rename = "sz", deserialize_with = "de_string_to_f32")]
+    pub qty: f32,
+    #[serde(rename = "side")]
+    pub is_sell: String,
+}
+
+/// Liquidations of `symbol` among the orders the channel pushes for every swap
+fn instrument_liquidations<'a>(
+    orders: &'a [SonicLiquidationOrder],
+    symbol: &'a str,
+    ticker_info: &'a TickerInfo,
+) -> impl Iterator<Item = Liquidation> + 'a {
+    orders
+        .iter()
+        .filter(move |order| order.inst_id == symbol)
+        .flat_map(|order| &order.details)
+        .map(|de_liq| Liquidation {
+            time: de_liq.time,
+            is_sell: de_liq.is_sell == "sell",
+            price: Price::from_f32(de_liq.price).round_to_min_tick(ticker_info.min_ticksize),
+            qty: calc_qty(
+                de_liq.qty,
+                de_liq.price,
+                SIZE_IN_QUOTE_CURRENCY.get() == Some(&true),
+                ticker_info.contract_size.map(f32::from),
+                ticker_info.market_type(),
+            ),
+        })
+}
+
 struct SonicDepth {
     pub update_id: u64,
+    /// Missing on snapshots
+    pub prev_update_id: Option<u64>,
     pub bids: Vec<DeOrder>,
     pub asks: Vec<DeOrder>,
+    pub raw_bids: Vec<RawLevel>,
+    pub raw_asks: Vec<RawLevel>,
+    pub checksum: Option<i32>,
+}
+
+/// Price and size strings of a level, as sent
+type RawLevel = (String, String);
+
+fn raw_levels(levels: Option<&Value>) -> Vec<RawLevel> {
+    levels
+        .and_then(Value::as_array)
+        .map(|levels| {
+            levels
+                .iter()
+                .filter_map(|level| {
+                    let price = level.get(0)?.as_str()?;
+                    let size = level.get(1)?.as_str()?;
+                    Some((price.to_string(), size.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The book as OKX prints it, its checksum is computed over the original strings
+#[derive(Default)]
+struct ChecksumBook {
+    bids: BTreeMap<Price, RawLevel>,
+    asks: BTreeMap<Price, RawLevel>,
+}
+
+impl ChecksumBook {
+    fn apply(&mut self, depth: &SonicDepth, is_snapshot: bool) {
+        if is_snapshot {
+            self.bids.clear();
+            self.asks.clear();
+        }
+
+        for (side, levels) in [
+            (&mut self.bids, &depth.raw_bids),
+            (&mut self.asks, &depth.raw_asks),
+        ] {
+            for (price, size) in levels {
+                let Ok(key) = price.parse::<f32>().map(Price::from_f32) else {
+                    continue;
+                };
+
+                if size.parse::<f32>().is_ok_and(|size| size == 0.0) {
+                    side.remove(&key);
+                } else {
+                    side.insert(key, (price.clone(), size.clone()));
+                }
+            }
+        }
+    }
+
+    /// `bid:size:ask:size:...` over the best levels, alternating while both sides have one
+    fn checksum_input(&self) -> String {
+        let mut bids = self.bids.values().rev().take(CHECKSUM_DEPTH);
+        let mut asks = self.asks.values().take(CHECKSUM_DEPTH);
+
+        let mut fields = Vec::with_capacity(CHECKSUM_DEPTH * 4);
+        loop {
+            let (bid, ask) = (bids.next(), asks.next());
+            if bid.is_none() && ask.is_none() {
+                break;
+            }
+
+            for (price, size) in bid.into_iter().chain(ask) {
+                fields.push(price.as_str());
+                fields.push(size.as_str());
+            }
+        }
+
+        fields.join(":")
+    }
+
+    fn verify(&self, expected: i32) -> Result<(), integrity::Fault> {
+        let computed = crc32fast::hash(self.checksum_input().as_bytes()) as i32;
+
+        if computed == expected {
+            Ok(())
+        } else {
+            Err(integrity::Fault::ChecksumMismatch { expected, computed })
+        }
+    }
 }
 
 enum StreamData {
     Trade(Vec<SonicTrade>),
     Depth(SonicDepth, String, u64),
+    Liquidation(Vec<SonicLiquidationOrder>),
 }
 
-fn feed_de(slice: &[u8], _ticker: Ticker) -> Result<StreamData, AdapterError> {
+/// Parses a public stream message, along with the topic it was published on, see [`topic_arg`]
+fn feed_de(slice: &[u8]) -> Result<(String, StreamData), AdapterError> {
     let v: Value =
         serde_json::from_slice(slice).map_err(|e| AdapterError::ParseError(e.to_string()))?;
 
     let mut channel = String::new();
+    let mut topic = String::new();
     if let Some(arg) = v.get("arg")
         && let Some(ch) = arg.get("channel").and_then(|c| c.as_str())
     {
         channel = ch.to_string();
+
+        let id = arg
+            .get("instId")
+            .or_else(|| arg.get("instType"))
+            .and_then(|id| id.as_str())
+            .unwrap_or_default();
+        topic = format!("{ch}:{id}");
     }
 
     if let Some(action) = v.get("action").and_then(|a| a.as_str())
@@ -115,6 +288,12 @@ fn feed_de(slice: &[u8], _ticker: Ticker) -> Result<StreamData, AdapterError> {
         };
 
         let seq_id = first.get("seqId").and_then(|s| s.as_u64()).unwrap_or(0);
+        // -1 on snapshots
+        let prev_seq_id = first.get("prevSeqId").and_then(|s| s.as_u64());
+        let checksum = first
+            .get("checksum")
+            .and_then(|c| c.as_i64())
+            .map(|c| c as i32);
 
         let time = first
             .get("ts")
@@ -124,8 +303,12 @@ fn feed_de(slice: &[u8], _ticker: Ticker) -> Result<StreamData, AdapterError> {
 
         let depth = SonicDepth {
             update_id: seq_id,
+            prev_update_id: prev_seq_id,
             bids,
             asks,
+            raw_bids: raw_levels(first.get("bids")),
+            raw_asks: raw_levels(first.get("asks")),
+            checksum,
         };
 
         match channel.as_str() {
@@ -135,7 +318,7 @@ fn feed_de(slice: &[u8], _ticker: Ticker) -> Result<StreamData, AdapterError> {
                 } else {
                     "snapshot"
                 };
-                return Ok(StreamData::Depth(depth, dtype.to_string(), time));
+                return Ok((topic, StreamData::Depth(depth, dtype.to_string(), time)));
             }
             _ => {
                 return Err(AdapterError::ParseError(
@@ -145,31 +328,48 @@ fn feed_de(slice: &[u8], _ticker: Ticker) -> Result<StreamData, AdapterError> {
         }
     }
 
+    if channel == "liquidation-orders"
+        && let Some(data_arr) = v.get("data")
+    {
+        let orders: Vec<SonicLiquidationOrder> = serde_json::from_value(data_arr.clone())
+            .map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+        return Ok((topic, StreamData::Liquidation(orders)));
+    }
+
     if let Some(data_arr) = v.get("data") {
         let trades: Vec<SonicTrade> = serde_json::from_value(data_arr.clone())
             .map_err(|e| AdapterError::ParseError(e.to_string()))?;
 
         if matches!(channel.as_str(), "trades" | "trade") {
-            return Ok(StreamData::Trade(trades));
+            return Ok((topic, StreamData::Trade(trades)));
         }
     }
 
     Err(AdapterError::ParseError("Unknown data".to_string()))
 }
 
+fn ws_url(topic: &str) -> String {
+    format!("{}/ws/v5/{topic}", endpoint::resolve(WS_DOMAIN))
+}
+
 async fn try_connect(
-    streams: &Value,
+    subscribe_message: &Value,
+    streams: &[StreamKind],
     exchange: Exchange,
+    backoff: &mut Backoff,
     output: &mut mpsc::Sender<Event>,
     topic: &str,
 ) -> State {
-    let url = format!("wss://{WS_DOMAIN}/ws/v5/{topic}");
+    let url = ws_url(topic);
 
-    match connect_ws(WS_DOMAIN, &url).await {
+    connect::publish(output, streams, ConnectionState::Connecting).await;
+
+    match connect_ws(&url).await {
         Ok(mut websocket) => {
             if let Err(e) = websocket
                 .write_frame(Frame::text(fastwebsockets::Payload::Borrowed(
-                    streams.to_string().as_bytes(),
+                    subscribe_message.to_string().as_bytes(),
                 )))
                 .await
             {
@@ -179,170 +379,485 @@ async fn try_connect(
                         format!("Failed subscribing: {e}"),
                     ))
                     .await;
+                backoff.wait(streams, output).await;
                 return State::Disconnected;
             }
+            backoff.reset();
 
             let _ = output.send(Event::Connected(exchange)).await;
+            connect::publish(output, streams, ConnectionState::Live).await;
+
             State::Connected(websocket)
         }
         Err(err) => {
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
             let _ = output
                 .send(Event::Disconnected(
                     exchange,
                     format!("Failed to connect: {err}"),
                 ))
                 .await;
+
+            backoff.wait(streams, output).await;
             State::Disconnected
         }
     }
 }
 
-pub fn connect_market_stream(
+/// Subscription argument of a topic, `channel:instId` or `liquidation-orders:instType`
+fn topic_arg(topic: &str) -> Value {
+    let (channel, id) = topic.split_once(':').unwrap_or((topic, ""));
+
+    if channel == "liquidation-orders" {
+        serde_json::json!({ "channel": channel, "instType": id })
+    } else {
+        serde_json::json!({ "channel": channel, "instId": id })
+    }
+}
+
+async fn send_subscription(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    op: &str,
+    topics: &[String],
+) -> Result<(), WebSocketError> {
+    if topics.is_empty() {
+        return Ok(());
+    }
+
+    let request = serde_json::json!({
+        "op": op,
+        "args": topics.iter().map(|topic| topic_arg(topic)).collect::<Vec<_>>(),
+    });
+
+    websocket
+        .write_frame(Frame::text(fastwebsockets::Payload::Owned(
+            request.to_string().into_bytes(),
+        )))
+        .await
+}
+
+/// Drops and requests a topic again, OKX pushes a new book snapshot on subscribing
+async fn resubscribe(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    topic: &str,
+) -> Result<(), WebSocketError> {
+    let topics = [topic.to_string()];
+
+    send_subscription(websocket, "unsubscribe", &topics).await?;
+    send_subscription(websocket, "subscribe", &topics).await
+}
+
+/// Depth and trades of one ticker carried on a market connection
+struct DepthFeed {
+    stream: StreamKind,
     ticker_info: TickerInfo,
-    push_freq: PushFrequency,
-) -> impl Stream<Item = Event> {
-    stream::channel(100, async move |mut output| {
-        let mut state: State = State::Disconnected;
+    symbol: String,
+    books_topic: String,
+    topics: Vec<String>,
+    orderbook: LocalDepthCache,
+    checksum_book: ChecksumBook,
+    trades_buffer: Vec<Trade>,
+    /// Waiting for the snapshot pushed on (re)subscribing to the book
+    resyncing: bool,
+}
 
-        let ticker = ticker_info.ticker;
+impl DepthFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::DepthAndTrades { ticker_info, .. } = stream else {
+            return None;
+        };
 
-        let (symbol_str, market_type) = ticker.to_full_symbol_and_type();
-        let exchange = ticker.exchange;
+        let (symbol, market_type) = ticker_info.ticker.to_full_symbol_and_type();
+        let books_topic = format!("books:{symbol}");
 
-        let subscribe_message = serde_json::json!({
-            "op": "subscribe",
-            "args": [
-                { "channel": "trades", "instId": symbol_str },
-                { "channel": "books",  "instId": symbol_str },
-            ],
-        });
+        let mut topics = vec![format!("trades:{symbol}"), books_topic.clone()];
+        if ticker_info.exchange().supports_liquidations() {
+            topics.push(format!("liquidation-orders:{}", okx_inst_type(market_type)));
+        }
 
-        let mut trades_buffer: Vec<Trade> = vec![];
-        let mut orderbook = LocalDepthCache::default();
+        Some(Self {
+            stream,
+            ticker_info,
+            symbol,
+            books_topic,
+            topics,
+            orderbook: LocalDepthCache::default(),
+            checksum_book: ChecksumBook::default(),
+            trades_buffer: Vec::new(),
+            resyncing: true,
+        })
+    }
 
-        let size_in_quote = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
-        let contract_size = ticker_info.contract_size.map(f32::from);
+    fn qty(&self, qty: f32, price: f32) -> f32 {
+        calc_qty(
+            qty,
+            price,
+            SIZE_IN_QUOTE_CURRENCY.get() == Some(&true),
+            self.ticker_info.contract_size.map(f32::from),
+            self.ticker_info.market_type(),
+        )
+    }
 
-        loop {
-            match &mut state {
-                State::Disconnected => {
-                    state = try_connect(&subscribe_message, exchange, &mut output, "public").await;
+    /// Applies the data, returns whether the book has to be resubscribed for a new snapshot
+    async fn handle(&mut self, data: &StreamData, output: &mut mpsc::Sender<Event>) -> bool {
+        let min_ticksize = self.ticker_info.min_ticksize;
+
+        match data {
+            StreamData::Trade(de_trade_vec) => {
+                for de_trade in de_trade_vec {
+                    let trade = Trade {
+                        time: de_trade.time,
+                        is_sell: de_trade.is_sell == "sell" || de_trade.is_sell == "SELL",
+                        price: Price::from_f32(de_trade.price).round_to_min_tick(min_ticksize),
+                        qty: self.qty(de_trade.qty, de_trade.price),
+                    };
+                    self.trades_buffer.push(trade);
                 }
-                State::Connected(ws) => match ws.read_frame().await {
-                    Ok(msg) => match msg.opcode {
-                        OpCode::Text => {
-                            if let Ok(data) = feed_de(&msg.payload[..], ticker) {
-                                match data {
-                                    StreamData::Trade(de_trade_vec) => {
-                                        for de_trade in &de_trade_vec {
-                                            let price = Price::from_f32(de_trade.price)
-                                                .round_to_min_tick(ticker_info.min_ticksize);
-                                            let qty = calc_qty(
-                                                de_trade.qty,
-                                                de_trade.price,
-                                                size_in_quote,
-                                                contract_size,
-                                                market_type,
-                                            );
-
-                                            let trade = Trade {
-                                                time: de_trade.time,
-                                                is_sell: de_trade.is_sell == "sell"
-                                                    || de_trade.is_sell == "SELL",
-                                                price,
-                                                qty,
-                                            };
-                                            trades_buffer.push(trade);
-                                        }
+            }
+            StreamData::Liquidation(orders) => {
+                for liquidation in instrument_liquidations(orders, &self.symbol, &self.ticker_info)
+                {
+                    let _ = output
+                        .send(Event::LiquidationReceived(self.stream, liquidation))
+                        .await;
+                }
+            }
+            StreamData::Depth(de_depth, data_type, time) => {
+                let is_snapshot = (data_type == "snapshot") || (de_depth.update_id == 1);
+
+                // waiting for the snapshot of a resync
+                if self.resyncing && !is_snapshot {
+                    return false;
+                }
+
+                let depth = DepthPayload {
+                    last_update_id: de_depth.update_id,
+                    time: *time,
+                    bids: de_depth
+                        .bids
+                        .iter()
+                        .map(|x| DeOrder {
+                            price: x.price,
+                            qty: self.qty(x.qty, x.price),
+                        })
+                        .collect(),
+                    asks: de_depth
+                        .asks
+                        .iter()
+                        .map(|x| DeOrder {
+                            price: x.price,
+                            qty: self.qty(x.qty, x.price),
+                        })
+                        .collect(),
+                };
+
+                let continuity = match de_depth.prev_update_id {
+                    Some(prev) if !is_snapshot => integrity::check_prev(
+                        self.orderbook.last_update_id,
+                        prev,
+                        de_depth.update_id,
+                    ),
+                    _ => Ok(()),
+                };
+
+                let checked = continuity
+                    .and_then(|()| {
+                        self.orderbook.update(
+                            if is_snapshot {
+                                DepthUpdate::Snapshot(depth)
+                            } else {
+                                DepthUpdate::Diff(depth)
+                            },
+                            min_ticksize,
+                        );
+                        self.checksum_book.apply(de_depth, is_snapshot);
+
+                        match de_depth.checksum {
+                            Some(expected) => self.checksum_book.verify(expected),
+                            None => Ok(()),
+                        }
+                    })
+                    .and_then(|()| integrity::check_crossed(&self.orderbook.depth));
+
+                match checked {
+                    Ok(()) if is_snapshot => {
+                        if self.resyncing {
+                            self.resyncing = false;
+                            connect::publish(output, &[self.stream], ConnectionState::Live).await;
+                        }
+                    }
+                    Ok(()) => {
+                        telemetry::observe(self.stream, *time);
+
+                        let _ = output
+                            .send(Event::DepthReceived(
+                                self.stream,
+                                *time,
+                                self.orderbook.depth.clone(),
+                                std::mem::take(&mut self.trades_buffer).into_boxed_slice(),
+                            ))
+                            .await;
+                    }
+                    Err(fault) => {
+                        integrity::record(self.stream, fault);
+
+                        self.resyncing = true;
+                        connect::publish(output, &[self.stream], ConnectionState::Resyncing).await;
+
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+}
+
+/// Liquidations of a ticker with no depth stream to carry them
+struct LiquidationFeed {
+    stream: StreamKind,
+    ticker_info: TickerInfo,
+    symbol: String,
+    topic: String,
+}
+
+impl LiquidationFeed {
+    fn new(stream: StreamKind) -> Option<Self> {
+        let StreamKind::Liquidations { ticker_info } = stream else {
+            return None;
+        };
+
+        let (symbol, market_type) = ticker_info.ticker.to_full_symbol_and_type();
+
+        Some(Self {
+            stream,
+            ticker_info,
+            symbol,
+            topic: format!("liquidation-orders:{}", okx_inst_type(market_type)),
+        })
+    }
+
+    async fn handle(&self, data: &StreamData, output: &mut mpsc::Sender<Event>) {
+        if let StreamData::Liquidation(orders) = data {
+            for liquidation in instrument_liquidations(orders, &self.symbol, &self.ticker_info) {
+                let _ = output
+                    .send(Event::LiquidationReceived(self.stream, liquidation))
+                    .await;
+            }
+        }
+    }
+}
+
+/// Subscribes and drops topics on the open socket so it carries exactly `wanted`
+async fn update_subscriptions(
+    websocket: &mut FragmentCollector<TokioIo<Upgraded>>,
+    feeds: &mut Vec<DepthFeed>,
+    liquidation_feeds: &mut Vec<LiquidationFeed>,
+    wanted: &FxHashSet<StreamKind>,
+    output: &mut mpsc::Sender<Event>,
+) -> Result<(), WebSocketError> {
+    let current_topics = feeds
+        .iter()
+        .flat_map(|feed| feed.topics.iter().cloned())
+        .chain(liquidation_feeds.iter().map(|feed| feed.topic.clone()))
+        .collect::<FxHashSet<_>>();
+
+    feeds.retain(|feed| wanted.contains(&feed.stream));
+    liquidation_feeds.retain(|feed| wanted.contains(&feed.stream));
+
+    let added = wanted
+        .iter()
+        .filter(|stream| !feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| DepthFeed::new(*stream))
+        .collect::<Vec<_>>();
+    let added_liquidations = wanted
+        .iter()
+        .filter(|stream| !liquidation_feeds.iter().any(|feed| feed.stream == **stream))
+        .filter_map(|stream| LiquidationFeed::new(*stream))
+        .collect::<Vec<_>>();
+
+    // tickers of an instrument type share its liquidation channel
+    let wanted_topics = feeds
+        .iter()
+        .chain(&added)
+        .flat_map(|feed| feed.topics.iter().cloned())
+        .chain(
+            liquidation_feeds
+                .iter()
+                .chain(&added_liquidations)
+                .map(|feed| feed.topic.clone()),
+        )
+        .collect::<FxHashSet<_>>();
+
+    let (subscribe, unsubscribe) = mux::diff_topics(&current_topics, &wanted_topics);
+
+    send_subscription(websocket, "unsubscribe", &unsubscribe).await?;
+    send_subscription(websocket, "subscribe", &subscribe).await?;
+
+    feeds.extend(added);
+
+    let streams = added_liquidations
+        .iter()
+        .map(|feed| feed.stream)
+        .collect::<Vec<_>>();
+    connect::publish(output, &streams, ConnectionState::Live).await;
+    liquidation_feeds.extend(added_liquidations);
+
+    Ok(())
+}
+
+/// Depth and trades of every stream in `wanted` over a single connection
+async fn run_market_streams(
+    market_type: MarketKind,
+    mut wanted: mux::WantedStreams,
+    output: &mut mpsc::Sender<Event>,
+) {
+    let exchange = exchange_from_market_type(market_type);
+    let url = ws_url("public");
+    let source = capture::Source::new(exchange, "market");
+
+    let mut state = State::Disconnected;
+    let mut backoff = Backoff::default();
+    let mut feeds: Vec<DepthFeed> = Vec::new();
+    let mut liquidation_feeds: Vec<LiquidationFeed> = Vec::new();
+
+    loop {
+        match &mut state {
+            State::Disconnected => {
+                feeds.clear();
+                liquidation_feeds.clear();
+
+                let streams = wanted.borrow().iter().copied().collect::<Vec<_>>();
+                connect::publish(output, &streams, ConnectionState::Connecting).await;
+
+                match connect_ws(&url).await {
+                    Ok(websocket) => {
+                        state = State::Connected(websocket);
+                        backoff.reset();
+                        // nothing is subscribed on a fresh socket, feeds go live on their snapshot
+                        wanted.mark_changed();
+
+                        let _ = output.send(Event::Connected(exchange)).await;
+                    }
+                    Err(err) => {
+                        let _ = output
+                            .send(Event::Disconnected(
+                                exchange,
+                                format!("Failed to connect: {err}"),
+                            ))
+                            .await;
+
+                        backoff.wait(&streams, output).await;
+                    }
+                }
+            }
+            State::Connected(websocket) => {
+                tokio::select! {
+                    Ok(()) = wanted.changed() => {
+                        let streams = wanted.borrow_and_update().clone();
+
+                        if let Err(e) = update_subscriptions(
+                            websocket,
+                            &mut feeds,
+                            &mut liquidation_feeds,
+                            &streams,
+                            output,
+                        )
+                        .await
+                        {
+                            state = State::Disconnected;
+                            let _ = output
+                                .send(Event::Disconnected(
+                                    exchange,
+                                    format!("Failed subscribing: {e}"),
+                                ))
+                                .await;
+                        }
+                    }
+                    frame = websocket.read_frame() => match frame {
+                        Ok(msg) => match msg.opcode {
+                            OpCode::Text => {
+                                capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
+                                if let Ok((topic, data)) = feed_de(&msg.payload[..]) {
+                                    let routed = feeds
+                                        .iter_mut()
+                                        .filter(|feed| feed.topics.contains(&topic));
+
+                                    let mut stale = false;
+                                    for feed in routed {
+                                        stale |= feed.handle(&data, output).await;
                                     }
-                                    StreamData::Depth(de_depth, data_type, time) => {
-                                        let depth = DepthPayload {
-                                            last_update_id: de_depth.update_id,
-                                            time,
-                                            bids: de_depth
-                                                .bids
-                                                .iter()
-                                                .map(|x| DeOrder {
-                                                    price: x.price,
-                                                    qty: calc_qty(
-                                                        x.qty,
-                                                        x.price,
-                                                        size_in_quote,
-                                                        contract_size,
-                                                        market_type,
-                                                    ),
-                                                })
-                                                .collect(),
-                                            asks: de_depth
-                                                .asks
-                                                .iter()
-                                                .map(|x| DeOrder {
-                                                    price: x.price,
-                                                    qty: calc_qty(
-                                                        x.qty,
-                                                        x.price,
-                                                        size_in_quote,
-                                                        contract_size,
-                                                        market_type,
-                                                    ),
-                                                })
-                                                .collect(),
-                                        };
 
-                                        if (data_type == "snapshot") || (depth.last_update_id == 1)
-                                        {
-                                            orderbook.update(
-                                                DepthUpdate::Snapshot(depth),
-                                                ticker_info.min_ticksize,
-                                            );
-                                        } else if data_type == "delta" {
-                                            orderbook.update(
-                                                DepthUpdate::Diff(depth),
-                                                ticker_info.min_ticksize,
-                                            );
-
-                                            let _ = output
-                                                .send(Event::DepthReceived(
-                                                    StreamKind::DepthAndTrades {
-                                                        ticker_info,
-                                                        depth_aggr: StreamTicksize::Client,
-                                                        push_freq,
-                                                    },
-                                                    time,
-                                                    orderbook.depth.clone(),
-                                                    std::mem::take(&mut trades_buffer)
-                                                        .into_boxed_slice(),
-                                                ))
-                                                .await;
-                                        }
+                                    let routed = liquidation_feeds
+                                        .iter()
+                                        .filter(|feed| feed.topic == topic);
+
+                                    for feed in routed {
+                                        feed.handle(&data, output).await;
+                                    }
+
+                                    if stale
+                                        && let Err(e) = resubscribe(websocket, &topic).await
+                                    {
+                                        state = State::Disconnected;
+                                        let _ = output
+                                            .send(Event::Disconnected(
+                                                exchange,
+                                                format!("Failed resubscribing: {e}"),
+                                            ))
+                                            .await;
                                     }
                                 }
                             }
-                        }
-                        OpCode::Close => {
+                            OpCode::Close => {
+                                state = State::Disconnected;
+                                let _ = output
+                                    .send(Event::Disconnected(
+                                        exchange,
+                                        "Connection closed".to_string(),
+                                    ))
+                                    .await;
+                            }
+                            _ => {}
+                        },
+                        Err(e) => {
                             state = State::Disconnected;
                             let _ = output
                                 .send(Event::Disconnected(
                                     exchange,
-                                    "Connection closed".to_string(),
+                                    "Error reading frame: ".to_string() + &e.to_string(),
                                 ))
                                 .await;
                         }
-                        _ => {}
                     },
-                    Err(e) => {
-                        state = State::Disconnected;
-                        let _ = output
-                            .send(Event::Disconnected(
-                                exchange,
-                                "Error reading frame: ".to_string() + &e.to_string(),
-                            ))
-                            .await;
-                    }
-                },
+                }
             }
         }
+    }
+}
+
+pub fn connect_market_stream(
+    ticker_info: TickerInfo,
+    push_freq: PushFrequency,
+) -> impl Stream<Item = Event> {
+    let stream = StreamKind::DepthAndTrades {
+        ticker_info,
+        depth_aggr: StreamTicksize::Client,
+        push_freq,
+    };
+
+    stream::channel(100, async move |mut output| {
+        let market_type = ticker_info.market_type();
+        run_market_streams(market_type, mux::fixed([stream]), &mut output).await;
+    })
+}
+
+/// Depth and trades of all the tickers wanted from a market, over one connection
+pub fn connect_shared_market_stream(market_type: MarketKind) -> impl Stream<Item = Event> {
+    stream::channel(100, async move |mut output| {
+        let wanted = mux::wanted(exchange_from_market_type(market_type));
+        run_market_streams(market_type, wanted, &mut output).await;
     })
 }
 
@@ -352,6 +867,15 @@ pub fn connect_kline_stream(
 ) -> impl Stream<Item = Event> {
     stream::channel(100, async move |mut output| {
         let mut state = State::Disconnected;
+        let mut backoff = Backoff::default();
+
+        let kline_streams = streams
+            .iter()
+            .map(|(ticker_info, timeframe)| StreamKind::Kline {
+                ticker_info: *ticker_info,
+                timeframe: *timeframe,
+            })
+            .collect::<Vec<_>>();
 
         let mut args = Vec::with_capacity(streams.len());
         let mut lookup = HashMap::new();
@@ -381,15 +905,27 @@ pub fn connect_kline_stream(
 
         let size_in_quote = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
 
+        let url = ws_url("business");
+        let source = capture::Source::new(exchange, "klines");
+
         loop {
             match &mut state {
                 State::Disconnected => {
-                    state =
-                        try_connect(&subscribe_message, exchange, &mut output, "business").await;
+                    state = try_connect(
+                        &subscribe_message,
+                        &kline_streams,
+                        exchange,
+                        &mut backoff,
+                        &mut output,
+                        "business",
+                    )
+                    .await;
                 }
                 State::Connected(ws) => match ws.read_frame().await {
                     Ok(msg) => match msg.opcode {
                         OpCode::Text => {
+                            capture::record(&source, FrameKind::WebSocket, &url, &msg.payload);
+
                             if let Ok(v) = serde_json::from_slice::<Value>(&msg.payload[..]) {
                                 let channel = v["arg"]["channel"].as_str().unwrap_or("");
                                 if !channel.starts_with("candle") {
@@ -540,6 +1076,14 @@ fn calc_qty(
     }
 }
 
+fn exchange_from_market_type(market: MarketKind) -> Exchange {
+    match market {
+        MarketKind::Spot => Exchange::OkexSpot,
+        MarketKind::LinearPerps => Exchange::OkexLinear,
+        MarketKind::InversePerps => Exchange::OkexInverse,
+    }
+}
+
 fn okx_inst_type(m: MarketKind) -> &'static str {
     match m {
         MarketKind::Spot => "SPOT",
@@ -569,11 +1113,12 @@ pub async fn fetch_ticksize(
 ) -> Result<std::collections::HashMap<Ticker, Option<TickerInfo>>, AdapterError> {
     let inst_type = okx_inst_type(market_type);
     let url = format!(
-        "https://www.okx.com/api/v5/public/instruments?instType={}",
+        "{}/api/v5/public/instruments?instType={}",
+        endpoint::resolve(REST_DOMAIN),
         inst_type
     );
 
-    let response_text = HTTP_CLIENT
+    let response_text = proxy::http_client(&url)?
         .get(&url)
         .send()
         .await
@@ -651,7 +1196,8 @@ pub async fn fetch_ticker_prices(
 ) -> Result<std::collections::HashMap<Ticker, TickerStats>, AdapterError> {
     let inst_type = okx_inst_type(market_type);
     let url = format!(
-        "https://www.okx.com/api/v5/market/tickers?instType={}",
+        "{}/api/v5/market/tickers?instType={}",
+        endpoint::resolve(REST_DOMAIN),
         inst_type
     );
 
@@ -740,7 +1286,8 @@ pub async fn fetch_klines(
     })?;
 
     let mut url = format!(
-        "https://www.okx.com/api/v5/market/history-candles?instId={}&bar={}&limit={}",
+        "{}/api/v5/market/history-candles?instId={}&bar={}&limit={}",
+        endpoint::resolve(REST_DOMAIN),
         symbol_str,
         bar,
         match range {
@@ -822,8 +1369,6 @@ pub async fn fetch_klines(
     Ok(klines)
 }
 
-const TRADING_STATS_DOMAIN: &str = "https://www.okx.com/api/v5/rubik/stat";
-
 pub async fn fetch_historical_oi(
     ticker: Ticker,
     range: Option<(u64, u64)>,
@@ -834,8 +1379,10 @@ pub async fn fetch_historical_oi(
     let bar = timeframe_to_okx_bar(period)
         .ok_or_else(|| AdapterError::InvalidRequest(format!("Unsupported timeframe: {period}")))?;
 
-    let mut url = TRADING_STATS_DOMAIN.to_string()
-        + format!("/contracts/open-interest-history?instId={ticker_str}&period={bar}").as_str();
+    let mut url = format!(
+        "{}/api/v5/rubik/stat/contracts/open-interest-history?instId={ticker_str}&period={bar}",
+        endpoint::resolve(REST_DOMAIN)
+    );
 
     if let Some((start, end)) = range {
         url.push_str(&format!("&begin={start}&end={end}"));
@@ -867,3 +1414,400 @@ pub async fn fetch_historical_oi(
 
     Ok(open_interest)
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeFundingRate {
+    #[serde(deserialize_with = "de_string_to_f32")]
+    funding_rate: f32,
+    #[serde(deserialize_with = "de_string_to_u64")]
+    funding_time: u64,
+}
+
+async fn fetch_funding_data(url: &str) -> Result<Vec<DeFundingRate>, AdapterError> {
+    let response_text =
+        limiter::http_request_with_limiter(url, &OKEX_LIMITER, 1, None, None).await?;
+
+    let doc: Value = serde_json::from_str(&response_text)
+        .map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+    serde_json::from_value(doc["data"].clone())
+        .map_err(|e| AdapterError::ParseError(format!("Failed to parse funding rates: {e}")))
+}
+
+pub async fn fetch_funding_rates(
+    ticker: Ticker,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<FundingRate>, AdapterError> {
+    let (symbol_str, market) = ticker.to_full_symbol_and_type();
+    if market == MarketKind::Spot {
+        return Err(AdapterError::InvalidRequest(
+            "Funding rates are only available for perpetuals".to_string(),
+        ));
+    }
+
+    let mut url = format!(
+        "{}/api/v5/public/funding-rate-history?instId={symbol_str}&limit=100",
+        endpoint::resolve(REST_DOMAIN)
+    );
+
+    // Both bounds are exclusive
+    if let Some((start, end)) = range {
+        url.push_str(&format!(
+            "&before={}&after={}",
+            start.saturating_sub(1),
+            end.saturating_add(1)
+        ));
+    }
+
+    let mut funding_rates: Vec<FundingRate> = fetch_funding_data(&url)
+        .await?
+        .into_iter()
+        .map(|x| FundingRate {
+            time: x.funding_time,
+            rate: x.funding_rate,
+        })
+        .collect();
+    funding_rates.sort_by_key(|f| f.time);
+
+    Ok(funding_rates)
+}
+
+pub async fn fetch_predicted_funding(ticker: Ticker) -> Result<FundingRate, AdapterError> {
+    let (symbol_str, market) = ticker.to_full_symbol_and_type();
+    if market == MarketKind::Spot {
+        return Err(AdapterError::InvalidRequest(
+            "Funding rates are only available for perpetuals".to_string(),
+        ));
+    }
+
+    let url = format!(
+        "{}/api/v5/public/funding-rate?instId={symbol_str}",
+        endpoint::resolve(REST_DOMAIN)
+    );
+
+    // The current rate settles at `fundingTime`
+    fetch_funding_data(&url)
+        .await?
+        .first()
+        .map(|x| FundingRate {
+            time: x.funding_time,
+            rate: x.funding_rate,
+        })
+        .ok_or_else(|| AdapterError::ParseError(format!("No funding rate for {symbol_str}")))
+}
+
+pub async fn fetch_server_time() -> Result<u64, AdapterError> {
+    let url = format!("{}/api/v5/public/time", endpoint::resolve(REST_DOMAIN));
+
+    let response_text =
+        limiter::http_request_with_limiter(&url, &OKEX_LIMITER, 1, None, None).await?;
+
+    let doc: Value = serde_json::from_str(&response_text)
+        .map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+    doc["data"][0]["ts"]
+        .as_str()
+        .and_then(|ts| ts.parse::<u64>().ok())
+        .ok_or_else(|| AdapterError::ParseError("Missing server time".to_string()))
+}
+
+/// A batch of trades after `from_time`, oldest first
+///
+/// The history endpoint only pages towards older trades, so each batch is a window
+/// past `from_time` paged backwards from its end. Empty windows are skipped with a
+/// growing span until the present is reached.
+pub async fn fetch_trades(
+    ticker_info: TickerInfo,
+    from_time: u64,
+) -> Result<Vec<Trade>, AdapterError> {
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+
+    let mut start = from_time;
+    let mut window = HIST_TRADES_WINDOW;
+
+    while start < now {
+        let end = start.saturating_add(window).min(now);
+
+        let trades = fetch_trades_between(ticker_info, start, end).await?;
+        if !trades.is_empty() {
+            return Ok(trades);
+        }
+
+        start = end;
+        window = (window * 2).min(HIST_TRADES_MAX_WINDOW);
+    }
+
+    Ok(vec![])
+}
+
+/// Trades in `(from_time, to_time]`, oldest first
+async fn fetch_trades_between(
+    ticker_info: TickerInfo,
+    from_time: u64,
+    to_time: u64,
+) -> Result<Vec<Trade>, AdapterError> {
+    let (symbol_str, _) = ticker_info.ticker.to_full_symbol_and_type();
+
+    let base_url = format!(
+        "{}/api/v5/market/history-trades?instId={symbol_str}&limit={HIST_TRADES_LIMIT}",
+        endpoint::resolve(REST_DOMAIN)
+    );
+
+    // the first page is found by time, the rest by trade id so that trades sharing
+    // a millisecond across a page boundary aren't skipped
+    let mut cursor = format!("&type=2&after={}", to_time + 1);
+    let mut newest_first = vec![];
+
+    loop {
+        let url = format!("{base_url}{cursor}");
+        let response_text =
+            limiter::http_request_with_limiter(&url, &OKEX_LIMITER, 1, None, None).await?;
+
+        let page = parse_hist_trades(&response_text)?;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+
+        let is_last = oldest.trade.time <= from_time || page.len() < HIST_TRADES_LIMIT;
+        cursor = format!("&type=1&after={}", oldest.trade_id);
+
+        newest_first.extend(page);
+
+        if is_last {
+            break;
+        }
+    }
+
+    Ok(hist_trades_after(newest_first, from_time, ticker_info))
+}
+
+fn parse_hist_trades(response_text: &str) -> Result<Vec<DeHistTrade>, AdapterError> {
+    let doc: Value =
+        serde_json::from_str(response_text).map_err(|e| AdapterError::ParseError(e.to_string()))?;
+
+    serde_json::from_value(doc["data"].clone())
+        .map_err(|e| AdapterError::ParseError(format!("Failed to parse trades: {e}")))
+}
+
+fn hist_trades_after(
+    newest_first: Vec<DeHistTrade>,
+    from_time: u64,
+    ticker_info: TickerInfo,
+) -> Vec<Trade> {
+    let market = ticker_info.market_type();
+    let size_in_quote = SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+    let contract_size = ticker_info.contract_size.map(f32::from);
+
+    newest_first
+        .into_iter()
+        .rev()
+        .filter(|de_trade| de_trade.trade.time > from_time)
+        .map(|DeHistTrade { trade, .. }| Trade {
+            time: trade.time,
+            is_sell: trade.is_sell == "sell",
+            price: Price::from_f32(trade.price).round_to_min_tick(ticker_info.min_ticksize),
+            qty: calc_qty(trade.qty, trade.price, size_in_quote, contract_size, market),
+        })
+        .collect()
+}
+
+pub struct Okex;
+
+impl ExchangeAdapter for Okex {
+    fn venue(&self) -> ExchangeInclusive {
+        ExchangeInclusive::Okex
+    }
+
+    fn name(&self) -> &'static str {
+        "OKX"
+    }
+
+    fn id(&self) -> &'static str {
+        "okex"
+    }
+
+    fn markets(&self) -> &'static [Exchange] {
+        &[
+            Exchange::OkexLinear,
+            Exchange::OkexInverse,
+            Exchange::OkexSpot,
+        ]
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["okx.com"]
+    }
+
+    fn supports_open_interest(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn supports_liquidations(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    fn supports_trade_backfill(&self, _market: MarketKind) -> bool {
+        true
+    }
+
+    fn fetch_ticker_info(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, Option<TickerInfo>>> {
+        Box::pin(fetch_ticksize(market))
+    }
+
+    fn fetch_ticker_prices(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, TickerStats>> {
+        Box::pin(fetch_ticker_prices(market))
+    }
+
+    fn fetch_klines(
+        &self,
+        ticker_info: TickerInfo,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<Kline>> {
+        Box::pin(fetch_klines(ticker_info, timeframe, range))
+    }
+
+    fn fetch_open_interest(
+        &self,
+        ticker: Ticker,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<OpenInterest>> {
+        Box::pin(fetch_historical_oi(ticker, range, timeframe))
+    }
+
+    fn fetch_funding_rates(
+        &self,
+        ticker: Ticker,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<FundingRate>> {
+        Box::pin(fetch_funding_rates(ticker, range))
+    }
+
+    fn fetch_predicted_funding(&self, ticker: Ticker) -> AdapterFuture<FundingRate> {
+        Box::pin(fetch_predicted_funding(ticker))
+    }
+
+    fn fetch_server_time(&self) -> AdapterFuture<u64> {
+        Box::pin(fetch_server_time())
+    }
+
+    fn fetch_trades(
+        &self,
+        ticker_info: TickerInfo,
+        from_time: u64,
+        _data_path: PathBuf,
+    ) -> AdapterFuture<Vec<Trade>> {
+        Box::pin(fetch_trades(ticker_info, from_time))
+    }
+
+    fn connect_market_stream(
+        &self,
+        ticker_info: TickerInfo,
+        _tick_multiplier: Option<TickMultiplier>,
+        push_freq: PushFrequency,
+    ) -> EventStream {
+        Box::pin(connect_market_stream(ticker_info, push_freq))
+    }
+
+    fn connect_kline_stream(
+        &self,
+        streams: Vec<(TickerInfo, Timeframe)>,
+        market: MarketKind,
+    ) -> EventStream {
+        Box::pin(connect_kline_stream(streams, market))
+    }
+
+    fn multiplexes_market_streams(&self) -> bool {
+        true
+    }
+
+    fn connect_shared_market_stream(&self, market: MarketKind) -> EventStream {
+        Box::pin(connect_shared_market_stream(market))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(levels: &[(&str, &str)]) -> Vec<RawLevel> {
+        levels
+            .iter()
+            .map(|(price, size)| (price.to_string(), size.to_string()))
+            .collect()
+    }
+
+    fn book_update(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> SonicDepth {
+        SonicDepth {
+            update_id: 0,
+            prev_update_id: None,
+            bids: vec![],
+            asks: vec![],
+            raw_bids: levels(bids),
+            raw_asks: levels(asks),
+            checksum: None,
+        }
+    }
+
+    #[test]
+    fn verifies_checksum_over_uneven_sides() {
+        let mut book = ChecksumBook::default();
+        book.apply(
+            &book_update(
+                &[("3366.1", "7"), ("3366", "6")],
+                &[("3366.8", "9"), ("3368", "8"), ("3372", "8")],
+            ),
+            true,
+        );
+        book.apply(&book_update(&[("3366", "0")], &[]), false);
+
+        assert_eq!(book.checksum_input(), "3366.1:7:3366.8:9:3368:8:3372:8");
+        assert_eq!(book.verify(831078360), Ok(()));
+        assert_eq!(
+            book.verify(1),
+            Err(integrity::Fault::ChecksumMismatch {
+                expected: 1,
+                computed: 831078360,
+            })
+        );
+    }
+
+    #[test]
+    fn orders_history_page_after_from_time() {
+        let ticker_info = TickerInfo::new(
+            Ticker::new("BTC-USDT", Exchange::OkexSpot),
+            0.1,
+            0.001,
+            None,
+        );
+
+        let page = parse_hist_trades(
+            r#"{"code":"0","msg":"","data":[
+                {"instId":"BTC-USDT","side":"sell","sz":"0.5","px":"60000.1","tradeId":"103","ts":"1700000000200"},
+                {"instId":"BTC-USDT","side":"buy","sz":"0.2","px":"60000.0","tradeId":"102","ts":"1700000000100"},
+                {"instId":"BTC-USDT","side":"buy","sz":"0.1","px":"59999.9","tradeId":"101","ts":"1700000000000"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(page.last().map(|t| t.trade_id.as_str()), Some("101"));
+
+        let trades = hist_trades_after(page, 1_700_000_000_000, ticker_info);
+
+        assert_eq!(
+            trades
+                .iter()
+                .map(|t| (t.time, t.is_sell))
+                .collect::<Vec<_>>(),
+            vec![(1_700_000_000_100, false), (1_700_000_000_200, true)]
+        );
+        assert_eq!(trades[1].qty, 0.5);
+    }
+}
@@ -0,0 +1,223 @@
+//! Exchange adapters behind a single trait
+//!
+//! Each venue implements [`ExchangeAdapter`] once and gets registered in
+//! [`adapter_for`], which dispatch and capability queries go through instead
+//! of matching over every [`Exchange`] variant.
+
+use super::{AdapterError, Event, Exchange, ExchangeInclusive, MarketKind};
+use super::{aster, binance, bybit, deribit, hyperliquid, okex};
+use crate::{
+    FundingRate, Kline, OpenInterest, PushFrequency, TickMultiplier, Ticker, TickerInfo,
+    TickerStats, Timeframe, Trade,
+};
+
+use iced_futures::futures::{
+    future::BoxFuture,
+    stream::{self, BoxStream},
+};
+use std::{collections::HashMap, path::PathBuf};
+
+pub type AdapterFuture<T> = BoxFuture<'static, Result<T, AdapterError>>;
+pub type EventStream = BoxStream<'static, Event>;
+
+pub trait ExchangeAdapter: Send + Sync {
+    fn venue(&self) -> ExchangeInclusive;
+
+    /// Human readable venue name, as shown in the capability table
+    fn name(&self) -> &'static str;
+
+    /// Lowercase identifier, used for on-disk paths
+    fn id(&self) -> &'static str;
+
+    /// Markets listed on this venue
+    fn markets(&self) -> &'static [Exchange];
+
+    /// Domains the venue's APIs are served from, subdomains included, see [`crate::proxy`]
+    fn hosts(&self) -> &'static [&'static str];
+
+    fn supports_open_interest(&self, market: MarketKind) -> bool;
+
+    /// Funding of perpetuals, see [`crate::Ticker::supports_funding_rates`] for dated futures
+    fn supports_funding_rates(&self, market: MarketKind) -> bool {
+        market != MarketKind::Spot
+    }
+
+    /// Settlement time in milliseconds of a dated future listed as `symbol`, `None` for perpetuals
+    fn expiry(&self, _symbol: &str) -> Option<u64> {
+        None
+    }
+
+    /// Whether historical trades can be backfilled over REST or bulk downloads
+    fn supports_trade_backfill(&self, _market: MarketKind) -> bool {
+        false
+    }
+
+    /// Whether the market stream also carries public liquidation orders
+    fn supports_liquidations(&self, _market: MarketKind) -> bool {
+        false
+    }
+
+    /// Depth is received at the native tick size and aggregated locally
+    fn is_depth_client_aggr(&self) -> bool {
+        true
+    }
+
+    fn allowed_push_freqs(&self, _market: MarketKind) -> &'static [PushFrequency] {
+        &[PushFrequency::ServerDefault]
+    }
+
+    fn supports_heatmap_timeframe(&self, _market: MarketKind, _tf: Timeframe) -> bool {
+        true
+    }
+
+    fn fetch_ticker_info(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, Option<TickerInfo>>>;
+
+    fn fetch_ticker_prices(
+        &self,
+        market: MarketKind,
+    ) -> AdapterFuture<HashMap<Ticker, TickerStats>>;
+
+    fn fetch_klines(
+        &self,
+        ticker_info: TickerInfo,
+        timeframe: Timeframe,
+        range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<Kline>>;
+
+    fn fetch_open_interest(
+        &self,
+        _ticker: Ticker,
+        _timeframe: Timeframe,
+        _range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<OpenInterest>> {
+        unsupported("Open interest not available for this exchange or market type")
+    }
+
+    fn fetch_funding_rates(
+        &self,
+        _ticker: Ticker,
+        _range: Option<(u64, u64)>,
+    ) -> AdapterFuture<Vec<FundingRate>> {
+        unsupported("Funding rates are only available for perpetuals")
+    }
+
+    fn fetch_predicted_funding(&self, _ticker: Ticker) -> AdapterFuture<FundingRate> {
+        unsupported("Funding rates are only available for perpetuals")
+    }
+
+    /// Server clock in milliseconds, for estimating the local clock's skew
+    fn fetch_server_time(&self) -> AdapterFuture<u64> {
+        unsupported("Server time not available for this exchange")
+    }
+
+    /// A batch of trades starting at `from_time`, caching bulk downloads under `data_path`
+    fn fetch_trades(
+        &self,
+        _ticker_info: TickerInfo,
+        _from_time: u64,
+        _data_path: PathBuf,
+    ) -> AdapterFuture<Vec<Trade>> {
+        unsupported("Trade backfill not available for this exchange")
+    }
+
+    fn connect_market_stream(
+        &self,
+        ticker_info: TickerInfo,
+        tick_multiplier: Option<TickMultiplier>,
+        push_freq: PushFrequency,
+    ) -> EventStream;
+
+    fn connect_kline_stream(
+        &self,
+        streams: Vec<(TickerInfo, Timeframe)>,
+        market: MarketKind,
+    ) -> EventStream;
+
+    /// Whether depth and trades of every ticker in a market share one connection, see [`super::mux`]
+    fn multiplexes_market_streams(&self) -> bool {
+        false
+    }
+
+    /// The shared connection of a market, carrying what's in [`super::mux::wanted`]
+    ///
+    /// Only used by adapters that [multiplex](Self::multiplexes_market_streams).
+    fn connect_shared_market_stream(&self, _market: MarketKind) -> EventStream {
+        Box::pin(stream::empty())
+    }
+}
+
+fn unsupported<T: Send + 'static>(reason: &'static str) -> AdapterFuture<T> {
+    Box::pin(async move { Err(AdapterError::InvalidRequest(reason.to_string())) })
+}
+
+pub fn adapter_for(venue: ExchangeInclusive) -> &'static dyn ExchangeAdapter {
+    match venue {
+        ExchangeInclusive::Aster => &aster::Aster,
+        ExchangeInclusive::Bybit => &bybit::Bybit,
+        ExchangeInclusive::Binance => &binance::Binance,
+        ExchangeInclusive::Deribit => &deribit::Deribit,
+        ExchangeInclusive::Hyperliquid => &hyperliquid::Hyperliquid,
+        ExchangeInclusive::Okex => &okex::Okex,
+    }
+}
+
+pub fn adapters() -> impl Iterator<Item = &'static dyn ExchangeAdapter> {
+    ExchangeInclusive::ALL.into_iter().map(adapter_for)
+}
+
+/// Markdown table of what each registered adapter supports, as in the README
+pub fn capability_table() -> String {
+    let mark = |supported: bool| if supported { "✅" } else { "❌" };
+
+    let mut table = String::from(
+        "| Exchange | Spot | Linear Perps | Inverse Perps | Open Interest | Funding Rates \
+         | Liquidations | Trade Backfill | Server-side Depth Aggregation |\n\
+         |---|---|---|---|---|---|---|---|---|\n",
+    );
+
+    for adapter in adapters() {
+        let markets = adapter.markets().iter().map(Exchange::market_type);
+        let any_market = |f: &dyn Fn(MarketKind) -> bool| markets.clone().any(f);
+
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            adapter.name(),
+            mark(any_market(&|m| m == MarketKind::Spot)),
+            mark(any_market(&|m| m == MarketKind::LinearPerps)),
+            mark(any_market(&|m| m == MarketKind::InversePerps)),
+            mark(any_market(&|m| adapter.supports_open_interest(m))),
+            mark(any_market(&|m| adapter.supports_funding_rates(m))),
+            mark(any_market(&|m| adapter.supports_liquidations(m))),
+            mark(any_market(&|m| adapter.supports_trade_backfill(m))),
+            mark(!adapter.is_depth_client_aggr()),
+        ));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_exchange_has_an_adapter() {
+        for exchange in Exchange::ALL {
+            let adapter = adapter_for(ExchangeInclusive::of(exchange));
+            assert!(adapter.markets().contains(&exchange), "{exchange}");
+        }
+    }
+
+    #[test]
+    fn readme_capability_table_is_up_to_date() {
+        let readme = include_str!("../../../README.md");
+        assert!(
+            readme.contains(&capability_table()),
+            "README table is stale, replace it with:\n{}",
+            capability_table()
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Feed latency and exchange clock skew, per stream
+//!
+//! Adapters [observe](observe) the exchange timestamp of every depth update as
+//! it arrives. The difference to the local clock mixes network latency with the
+//! skew between both clocks, which is [probed](probe_skew) separately against
+//! the venue's server time and taken out of the reported latency.
+
+use super::{AdapterError, ExchangeInclusive, StreamKind, adapter_for};
+use crate::Ticker;
+
+use rustc_hash::FxHashMap;
+use std::{
+    collections::VecDeque,
+    sync::{LazyLock, PoisonError, RwLock},
+};
+
+/// Samples the percentiles are computed over, per stream
+const WINDOW: usize = 1000;
+
+#[derive(Default)]
+struct Samples {
+    /// Local receive time minus exchange time, in milliseconds
+    delays: VecDeque<i64>,
+    last_received: u64,
+}
+
+static STREAMS: LazyLock<RwLock<FxHashMap<StreamKind, Samples>>> = LazyLock::new(Default::default);
+
+/// Exchange clock minus local clock, in milliseconds
+static SKEWS: LazyLock<RwLock<FxHashMap<ExchangeInclusive, i64>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: i64,
+    pub p90: i64,
+    pub p99: i64,
+}
+
+impl Percentiles {
+    fn of(samples: impl IntoIterator<Item = i64>) -> Option<Self> {
+        let mut sorted = samples.into_iter().collect::<Vec<_>>();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_unstable();
+
+        let at = |q: f32| sorted[((sorted.len() - 1) as f32 * q).round() as usize];
+
+        Some(Self {
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+        })
+    }
+}
+
+/// Receive latency of a stream, corrected for clock skew once it's known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Health {
+    pub latency: Percentiles,
+    /// Exchange clock minus local clock in milliseconds, `None` until probed
+    pub skew: Option<i64>,
+    pub samples: usize,
+    /// Local time of the last update, in milliseconds
+    pub last_received: u64,
+}
+
+/// Records an update of `stream` stamped `exchange_time` by the exchange, received just now
+pub fn observe(stream: StreamKind, exchange_time: u64) {
+    observe_at(stream, exchange_time, now_ms());
+}
+
+fn observe_at(stream: StreamKind, exchange_time: u64, received: u64) {
+    if exchange_time == 0 {
+        return;
+    }
+
+    let mut streams = STREAMS.write().unwrap_or_else(PoisonError::into_inner);
+    let samples = streams.entry(stream).or_default();
+
+    if samples.delays.len() == WINDOW {
+        samples.delays.pop_front();
+    }
+    samples
+        .delays
+        .push_back(received as i64 - exchange_time as i64);
+    samples.last_received = received;
+}
+
+/// Forgets the samples of a stream that's no longer used
+pub fn remove(stream: &StreamKind) {
+    STREAMS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(stream);
+}
+
+pub fn skew(venue: ExchangeInclusive) -> Option<i64> {
+    SKEWS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&venue)
+        .copied()
+}
+
+pub fn health(stream: &StreamKind) -> Option<Health> {
+    let streams = STREAMS.read().unwrap_or_else(PoisonError::into_inner);
+    let samples = streams.get(stream)?;
+
+    let skew = skew(ExchangeInclusive::of(stream.ticker_info().exchange()));
+    // exchange time is local time plus skew, so the event happened locally at `time - skew`
+    let latency = Percentiles::of(samples.delays.iter().map(|delay| delay + skew.unwrap_or(0)))?;
+
+    Some(Health {
+        latency,
+        skew,
+        samples: samples.delays.len(),
+        last_received: samples.last_received,
+    })
+}
+
+/// Every observed stream with its health, in no particular order
+pub fn streams() -> Vec<(StreamKind, Health)> {
+    let observed = STREAMS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .keys()
+        .copied()
+        .collect::<Vec<_>>();
+
+    observed
+        .into_iter()
+        .filter_map(|stream| health(&stream).map(|health| (stream, health)))
+        .collect()
+}
+
+/// Health of the busiest observed stream of `ticker`, if any is open
+pub fn ticker_health(ticker: &Ticker) -> Option<Health> {
+    streams()
+        .into_iter()
+        .filter(|(stream, _)| stream.ticker_info().ticker == *ticker)
+        .map(|(_, health)| health)
+        .max_by_key(|health| health.samples)
+}
+
+/// Estimates the venue's clock skew from its server time, assuming a symmetric round trip
+pub async fn probe_skew(venue: ExchangeInclusive) -> Result<i64, AdapterError> {
+    let sent = now_ms();
+    let server_time = adapter_for(venue).fetch_server_time().await?;
+    let received = now_ms();
+
+    let skew = server_time as i64 - (sent + received) as i64 / 2;
+
+    SKEWS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(venue, skew);
+
+    Ok(skew)
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        PushFrequency, Ticker, TickerInfo,
+        adapter::{Exchange, StreamTicksize},
+    };
+
+    fn stream(symbol: &str, exchange: Exchange) -> StreamKind {
+        StreamKind::DepthAndTrades {
+            ticker_info: TickerInfo::new(Ticker::new(symbol, exchange), 0.1, 0.001, None),
+            depth_aggr: StreamTicksize::Client,
+            push_freq: PushFrequency::ServerDefault,
+        }
+    }
+
+    #[test]
+    fn percentiles_of_rolling_window() {
+        // no skew is probed for this venue in tests
+        let stream = stream("LATENCYUSDT", Exchange::BybitSpot);
+
+        for delay in 0..WINDOW as u64 + 100 {
+            observe_at(stream, 1_000_000, 1_000_000 + delay);
+        }
+
+        let health = health(&stream).unwrap();
+        assert_eq!(health.samples, WINDOW);
+        // the oldest 100 samples were dropped
+        assert_eq!(health.latency.p50, 600);
+        assert_eq!(health.latency.p99, 1089);
+        assert_eq!(health.last_received, 1_000_000 + WINDOW as u64 + 99);
+    }
+
+    #[test]
+    fn corrects_latency_for_skew() {
+        let stream = stream("SKEW-USDT", Exchange::OkexSpot);
+
+        // the exchange clock runs 40ms behind, updates seem to take 40ms longer
+        observe_at(stream, 1_000_000, 1_000_050);
+        SKEWS.write().unwrap().insert(ExchangeInclusive::Okex, -40);
+
+        let health = health(&stream).unwrap();
+        assert_eq!(health.skew, Some(-40));
+        assert_eq!(health.latency.p50, 10);
+    }
+}
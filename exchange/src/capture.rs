@@ -0,0 +1,674 @@
+//! Raw exchange frames written to disk, for replaying and debugging streams
+//!
+//! While [enabled](enable), adapters hand every WebSocket text frame and REST
+//! depth snapshot to [`record`] before parsing it. Frames are compressed and
+//! written on a thread of their own, so recording never blocks an adapter on
+//! disk. Frames of a [`Source`] go to gzip files in a directory of their own,
+//! and a new file is started once one holds [`Settings::max_file_size`] bytes
+//! of frames. The oldest files are deleted once all of them take up more than
+//! [`Settings::max_total_size`] on disk.
+//!
+//! [`Reader`] gets the frames back in order, e.g. to serve them from
+//! `mock-exchange` to an unmodified adapter.
+
+use crate::adapter::{Exchange, ExchangeInclusive, adapter_for};
+
+use flate2::{Compression, read::MultiGzDecoder, write::GzEncoder};
+use rustc_hash::FxHashMap;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{LazyLock, PoisonError, RwLock, mpsc},
+    time::{Duration, Instant},
+};
+
+const EXTENSION: &str = "frames.gz";
+
+/// Buffered frames are flushed at least this often, so a crash loses little
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub dir: PathBuf,
+    /// Uncompressed bytes of frames per file before starting a new one
+    pub max_file_size: u64,
+    /// Compressed bytes of all capture files before the oldest get deleted
+    pub max_total_size: u64,
+}
+
+impl Settings {
+    pub const DEFAULT_MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
+    pub const DEFAULT_MAX_TOTAL_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            max_file_size: Self::DEFAULT_MAX_FILE_SIZE,
+            max_total_size: Self::DEFAULT_MAX_TOTAL_SIZE,
+        }
+    }
+}
+
+static SETTINGS: LazyLock<RwLock<Option<Settings>>> = LazyLock::new(Default::default);
+
+enum Command {
+    Frame {
+        source: Source,
+        kind: FrameKind,
+        received: u64,
+        url: String,
+        payload: Vec<u8>,
+    },
+    /// Finishes the open files and starts using the settings, acknowledged once done
+    Configure(Option<Settings>, mpsc::Sender<()>),
+}
+
+static WRITER_THREAD: LazyLock<mpsc::Sender<Command>> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::Builder::new()
+        .name("capture-writer".to_string())
+        .spawn(move || run(&receiver))
+        .expect("Failed to spawn the capture writer thread");
+
+    sender
+});
+
+fn send(command: Command) -> bool {
+    WRITER_THREAD.send(command).is_ok()
+}
+
+/// Starts capturing frames of every stream into `settings.dir`
+pub fn enable(settings: Settings) {
+    log::info!("Capturing raw frames into {}", settings.dir.display());
+
+    configure(Some(settings.clone()));
+    *SETTINGS.write().unwrap_or_else(PoisonError::into_inner) = Some(settings);
+}
+
+/// Stops capturing, returns once the frames recorded so far are on disk
+pub fn disable() {
+    *SETTINGS.write().unwrap_or_else(PoisonError::into_inner) = None;
+    configure(None);
+}
+
+pub fn settings() -> Option<Settings> {
+    SETTINGS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+pub fn is_enabled() -> bool {
+    SETTINGS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_some()
+}
+
+fn configure(settings: Option<Settings>) {
+    let (ack, done) = mpsc::channel();
+
+    if send(Command::Configure(settings, ack)) {
+        let _ = done.recv();
+    }
+}
+
+fn close_all(writers: &mut FxHashMap<Source, Writer>) {
+    for (source, writer) in writers.drain() {
+        if let Err(e) = writer.finish() {
+            log::warn!("Failed to finish capture of {source}: {e}");
+        }
+    }
+}
+
+/// Frames of a single stream, or of a connection shared by many streams
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Source(PathBuf);
+
+impl Source {
+    /// `name` tells apart the streams of a market, e.g. a ticker or `"klines"`
+    pub fn new(exchange: Exchange, name: &str) -> Self {
+        let venue = adapter_for(ExchangeInclusive::of(exchange)).id();
+        let market = exchange.market_type().to_string().to_lowercase();
+
+        let name = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '@') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+
+        Self(Path::new(venue).join(market).join(name))
+    }
+
+    /// Directory holding the capture files of this source
+    pub fn dir(&self, root: &Path) -> PathBuf {
+        root.join(&self.0)
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// Text frame received over a WebSocket
+    WebSocket,
+    /// Body of a REST response, e.g. a depth snapshot
+    Rest,
+}
+
+impl FrameKind {
+    fn tag(self) -> u8 {
+        match self {
+            FrameKind::WebSocket => 0,
+            FrameKind::Rest => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> io::Result<Self> {
+        match tag {
+            0 => Ok(FrameKind::WebSocket),
+            1 => Ok(FrameKind::Rest),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown frame kind {tag}"),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    /// Local receive time, in milliseconds
+    pub received: u64,
+    /// The WebSocket's URL, or the requested one for REST responses
+    pub url: String,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// The URL's path, which is what `mock-exchange` serves frames by
+    pub fn path(&self) -> &str {
+        let without_scheme = self
+            .url
+            .split_once("://")
+            .map_or(self.url.as_str(), |(_, rest)| rest);
+
+        let path = without_scheme
+            .find('/')
+            .map_or("/", |start| &without_scheme[start..]);
+
+        path.split(['?', '#']).next().unwrap_or(path)
+    }
+}
+
+/// Length prefixed, little endian: kind, receive time, URL and payload
+fn encode(
+    kind: FrameKind,
+    received: u64,
+    url: &str,
+    payload: &[u8],
+    out: &mut impl Write,
+) -> io::Result<usize> {
+    let url_len = u16::try_from(url.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "URL too long"))?;
+    let payload_len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Payload too large"))?;
+
+    out.write_all(&[kind.tag()])?;
+    out.write_all(&received.to_le_bytes())?;
+    out.write_all(&url_len.to_le_bytes())?;
+    out.write_all(url.as_bytes())?;
+    out.write_all(&payload_len.to_le_bytes())?;
+    out.write_all(payload)?;
+
+    Ok(1 + 8 + 2 + url.len() + 4 + payload.len())
+}
+
+/// The next frame, `None` at the end of the input
+fn decode(input: &mut impl Read) -> io::Result<Option<Frame>> {
+    let mut tag = [0; 1];
+    if input.read(&mut tag)? == 0 {
+        return Ok(None);
+    }
+
+    let mut received = [0; 8];
+    input.read_exact(&mut received)?;
+
+    let mut url_len = [0; 2];
+    input.read_exact(&mut url_len)?;
+    let mut url = vec![0; u16::from_le_bytes(url_len) as usize];
+    input.read_exact(&mut url)?;
+
+    let mut payload_len = [0; 4];
+    input.read_exact(&mut payload_len)?;
+    let mut payload = vec![0; u32::from_le_bytes(payload_len) as usize];
+    input.read_exact(&mut payload)?;
+
+    Ok(Some(Frame {
+        kind: FrameKind::from_tag(tag[0])?,
+        received: u64::from_le_bytes(received),
+        url: String::from_utf8(url).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        payload,
+    }))
+}
+
+/// Queues a frame of `source` received just now, if capturing is enabled
+pub fn record(source: &Source, kind: FrameKind, url: &str, payload: &[u8]) {
+    if !is_enabled() {
+        return;
+    }
+
+    let received = chrono::Utc::now().timestamp_millis() as u64;
+
+    send(Command::Frame {
+        source: source.clone(),
+        kind,
+        received,
+        url: url.to_string(),
+        payload: payload.to_vec(),
+    });
+}
+
+fn run(receiver: &mpsc::Receiver<Command>) {
+    let mut settings: Option<Settings> = None;
+    let mut writers: FxHashMap<Source, Writer> = FxHashMap::default();
+
+    loop {
+        match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(Command::Frame {
+                source,
+                kind,
+                received,
+                url,
+                payload,
+            }) => {
+                let Some(settings) = &settings else {
+                    continue;
+                };
+
+                if let Err(e) = write_frame(
+                    settings,
+                    &mut writers,
+                    &source,
+                    kind,
+                    received,
+                    &url,
+                    &payload,
+                ) {
+                    log::warn!("Failed to capture a frame of {source}: {e}");
+                }
+            }
+            Ok(Command::Configure(new_settings, ack)) => {
+                close_all(&mut writers);
+                settings = new_settings;
+                let _ = ack.send(());
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                close_all(&mut writers);
+                return;
+            }
+        }
+
+        for (source, writer) in writers.iter_mut() {
+            if let Err(e) = writer.flush_if_due() {
+                log::warn!("Failed to flush capture of {source}: {e}");
+            }
+        }
+    }
+}
+
+fn write_frame(
+    settings: &Settings,
+    writers: &mut FxHashMap<Source, Writer>,
+    source: &Source,
+    kind: FrameKind,
+    received: u64,
+    url: &str,
+    payload: &[u8],
+) -> io::Result<()> {
+    let mut writer = match writers.remove(source) {
+        Some(writer) if writer.written < settings.max_file_size => writer,
+        full => {
+            if let Some(full) = full {
+                full.finish()?;
+            }
+
+            let writer = Writer::create(&source.dir(&settings.dir))?;
+
+            let open = writers
+                .values()
+                .map(|writer| writer.path.as_path())
+                .chain([writer.path.as_path()])
+                .collect::<Vec<_>>();
+            if let Err(e) = prune(&settings.dir, settings.max_total_size, &open) {
+                log::warn!("Failed to delete old capture files: {e}");
+            }
+
+            writer
+        }
+    };
+
+    let result = writer.write(kind, received, url, payload);
+    writers.insert(source.clone(), writer);
+    result
+}
+
+/// Deletes the oldest capture files under `root` until they fit in `max_total_size`,
+/// files still being written to are kept
+fn prune(root: &Path, max_total_size: u64, open: &[&Path]) -> io::Result<()> {
+    let mut found = Vec::new();
+    collect_files(root, &mut found)?;
+
+    let mut total = found.iter().map(|(_, _, size)| size).sum::<u64>();
+    if total <= max_total_size {
+        return Ok(());
+    }
+
+    // names start with the time the file was created, whichever source it's of
+    found.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+
+    for (_, path, size) in found {
+        if total <= max_total_size {
+            break;
+        }
+        if open.contains(&path.as_path()) {
+            continue;
+        }
+
+        std::fs::remove_file(&path)?;
+        total -= size;
+    }
+
+    Ok(())
+}
+
+fn collect_files(dir: &Path, found: &mut Vec<(String, PathBuf, u64)>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            collect_files(&path, found)?;
+        } else if let Some(name) = path.file_name().and_then(|name| name.to_str())
+            && name.ends_with(EXTENSION)
+        {
+            found.push((name.to_string(), path.clone(), metadata.len()));
+        }
+    }
+
+    Ok(())
+}
+
+struct Writer {
+    encoder: GzEncoder<BufWriter<File>>,
+    path: PathBuf,
+    written: u64,
+    last_flush: Instant,
+}
+
+impl Writer {
+    fn create(dir: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let started = chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f");
+        let mut path = dir.join(format!("{started}.{EXTENSION}"));
+        let mut n = 1;
+        while path.exists() {
+            path = dir.join(format!("{started}-{n}.{EXTENSION}"));
+            n += 1;
+        }
+
+        Ok(Self {
+            encoder: GzEncoder::new(BufWriter::new(File::create(&path)?), Compression::fast()),
+            path,
+            written: 0,
+            last_flush: Instant::now(),
+        })
+    }
+
+    fn write(
+        &mut self,
+        kind: FrameKind,
+        received: u64,
+        url: &str,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        self.written += encode(kind, received, url, payload, &mut self.encoder)? as u64;
+        Ok(())
+    }
+
+    fn flush_if_due(&mut self) -> io::Result<()> {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.encoder.flush()?;
+            self.last_flush = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> io::Result<()> {
+        self.encoder.finish()?.flush()
+    }
+}
+
+/// Frames of a single capture file, in the order they were received
+///
+/// A file cut short, e.g. by a crash, ends after its last complete frame.
+pub struct Reader {
+    input: MultiGzDecoder<BufReader<File>>,
+    done: bool,
+}
+
+impl Reader {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            input: MultiGzDecoder::new(BufReader::new(File::open(path)?)),
+            done: false,
+        })
+    }
+}
+
+impl Iterator for Reader {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match decode(&mut self.input) {
+            Ok(Some(frame)) => Some(Ok(frame)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Capture files of a source directory, oldest first
+pub fn files(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let mut files = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(EXTENSION))
+        })
+        .collect::<Vec<_>>();
+
+    // names start with the time the file was created
+    files.sort();
+    Ok(files)
+}
+
+/// Every frame captured in a source directory, oldest first
+pub fn read_dir(dir: impl AsRef<Path>) -> io::Result<Vec<Frame>> {
+    let mut frames = Vec::new();
+
+    for file in files(dir)? {
+        for frame in Reader::open(file)? {
+            frames.push(frame?);
+        }
+    }
+
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Capturing is process-wide, tests enabling it must not overlap
+    static CAPTURING: Mutex<()> = Mutex::new(());
+
+    fn frame(kind: FrameKind, url: &str, payload: &str) -> Frame {
+        Frame {
+            kind,
+            received: 1_700_000_000_000,
+            url: url.to_string(),
+            payload: payload.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn frames_roundtrip_and_truncation_is_tolerated() {
+        let dir = std::env::temp_dir().join(format!("capture-roundtrip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("0.{EXTENSION}"));
+
+        let frames = [
+            frame(
+                FrameKind::WebSocket,
+                "wss://fstream.binance.com/stream",
+                r#"{"e":"depthUpdate"}"#,
+            ),
+            frame(
+                FrameKind::Rest,
+                "https://fapi.binance.com/fapi/v1/depth?symbol=BTCUSDT&limit=1000",
+                r#"{"lastUpdateId":1}"#,
+            ),
+        ];
+
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::fast());
+        for frame in &frames {
+            encode(
+                frame.kind,
+                frame.received,
+                &frame.url,
+                &frame.payload,
+                &mut encoder,
+            )
+            .unwrap();
+        }
+        // half of a third frame, as left behind by a crash
+        encoder.write_all(&[0, 1, 2, 3]).unwrap();
+        encoder.finish().unwrap();
+
+        let read = Reader::open(&path)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(read, frames);
+        assert_eq!(read[0].path(), "/stream");
+        assert_eq!(read[1].path(), "/fapi/v1/depth");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_files_by_size() {
+        let _guard = CAPTURING.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = std::env::temp_dir().join(format!("capture-rotation-{}", std::process::id()));
+
+        enable(Settings {
+            max_file_size: 150,
+            ..Settings::new(dir.clone())
+        });
+
+        let source = Source::new(Exchange::BybitSpot, "BTC/USDT");
+        for i in 0..3 {
+            record(
+                &source,
+                FrameKind::WebSocket,
+                "wss://stream.bybit.com/v5/public/spot",
+                &[i; 40],
+            );
+        }
+        disable();
+
+        let source_dir = source.dir(&dir);
+        assert!(source_dir.ends_with("bybit/spot/BTC_USDT"));
+        // the first two frames fill a file, the third starts the next one
+        assert_eq!(files(&source_dir).unwrap().len(), 2);
+
+        let frames = read_dir(&source_dir).unwrap();
+        assert_eq!(
+            frames
+                .iter()
+                .map(|frame| frame.payload[0])
+                .collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn deletes_oldest_files_over_total_size() {
+        let _guard = CAPTURING.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = std::env::temp_dir().join(format!("capture-total-{}", std::process::id()));
+
+        let old = Source::new(Exchange::BybitSpot, "ETH/USDT").dir(&dir);
+        std::fs::create_dir_all(&old).unwrap();
+        for name in ["20200101-000000.000", "20200102-000000.000"] {
+            std::fs::write(old.join(format!("{name}.{EXTENSION}")), [0; 100]).unwrap();
+        }
+
+        enable(Settings {
+            max_total_size: 150,
+            ..Settings::new(dir.clone())
+        });
+
+        let source = Source::new(Exchange::BybitSpot, "BTC/USDT");
+        record(
+            &source,
+            FrameKind::WebSocket,
+            "wss://stream.bybit.com/v5/public/spot",
+            &[0; 40],
+        );
+        disable();
+
+        // the older file of another source goes first, the newer one fits with the new file
+        assert_eq!(
+            files(&old).unwrap(),
+            [old.join(format!("20200102-000000.000.{EXTENSION}"))]
+        );
+        assert_eq!(files(source.dir(&dir)).unwrap().len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
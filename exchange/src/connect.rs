@@ -1,13 +1,21 @@
-use crate::adapter::AdapterError;
+use crate::adapter::{AdapterError, ConnectionState, Event, StreamKind};
+use crate::proxy;
 use bytes::Bytes;
 use fastwebsockets::FragmentCollector;
 use http_body_util::Empty;
 use hyper::{
-    Request,
+    Request, Uri,
     header::{CONNECTION, UPGRADE},
     upgrade::Upgraded,
 };
 use hyper_util::rt::TokioIo;
+use iced_futures::futures::{SinkExt, channel::mpsc};
+use std::{
+    hash::{BuildHasher, RandomState},
+    sync::{LazyLock, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio_rustls::{
     TlsConnector,
@@ -20,17 +28,134 @@ pub enum State {
     Connected(FragmentCollector<TokioIo<Upgraded>>),
 }
 
+/// How long a dropped stream waits between attempts to reconnect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Fraction of each wait that's randomized, so streams dropped together spread out
+    pub jitter: f32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Wait before the given attempt, doubling each time up to `max_backoff`
+    ///
+    /// `roll`, in `0.0..=1.0`, picks where the wait lands within the jitter.
+    pub fn backoff(&self, attempt: u32, roll: f32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_backoff);
+
+        let jitter = self.jitter.clamp(0.0, 1.0) * roll.clamp(0.0, 1.0);
+        exponential.mul_f32(1.0 - jitter)
+    }
+}
+
+static RECONNECT_POLICY: LazyLock<RwLock<ReconnectPolicy>> = LazyLock::new(Default::default);
+
+pub fn set_reconnect_policy(policy: ReconnectPolicy) {
+    *RECONNECT_POLICY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = policy;
+}
+
+fn reconnect_policy() -> ReconnectPolicy {
+    *RECONNECT_POLICY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Roughly uniform in `0.0..=1.0`, std's randomly keyed hasher is enough for jitter
+fn roll() -> f32 {
+    let bits = RandomState::new().hash_one(Instant::now());
+    (bits >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Reconnect attempts of a single connection
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    /// Starts over from the initial wait, once connected again
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    /// Sleeps before the next attempt, reporting the wait to the streams of the connection
+    pub async fn wait(&mut self, streams: &[StreamKind], output: &mut mpsc::Sender<Event>) {
+        let delay = reconnect_policy().backoff(self.attempt, roll());
+        self.attempt = self.attempt.saturating_add(1);
+
+        let until = Instant::now() + delay;
+        publish(output, streams, ConnectionState::Backoff { until }).await;
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Reports the connection state of every stream carried by a connection
+pub async fn publish(
+    output: &mut mpsc::Sender<Event>,
+    streams: &[StreamKind],
+    state: ConnectionState,
+) {
+    for stream in streams {
+        let _ = output.send(Event::StateChanged(*stream, state)).await;
+    }
+}
+
+/// Opens a WebSocket to `url`, over TLS for `wss://` and plain TCP for `ws://`
 pub async fn connect_ws(
-    domain: &str,
     url: &str,
 ) -> Result<
     fastwebsockets::FragmentCollector<hyper_util::rt::TokioIo<hyper::upgrade::Upgraded>>,
     AdapterError,
 > {
-    let tcp_stream = setup_tcp(domain).await?;
-    let tls_stream = upgrade_to_tls(domain, tcp_stream).await?;
+    let uri: Uri = url
+        .parse()
+        .map_err(|e| AdapterError::WebsocketError(format!("Invalid url {url}: {e}")))?;
+
+    let (Some(host), Some(authority)) = (uri.host(), uri.authority()) else {
+        return Err(AdapterError::WebsocketError(format!(
+            "Missing host in url {url}"
+        )));
+    };
+
+    let is_tls = match uri.scheme_str() {
+        Some("wss") => true,
+        Some("ws") => false,
+        _ => {
+            return Err(AdapterError::WebsocketError(format!(
+                "Unsupported scheme in url {url}"
+            )));
+        }
+    };
+    let port = uri.port_u16().unwrap_or(if is_tls { 443 } else { 80 });
+
+    let tcp_stream = match proxy::for_url(url) {
+        Some(proxy) => proxy::tunnel(&proxy, host, port).await?,
+        None => setup_tcp(host, port).await?,
+    };
 
-    upgrade_to_websocket(domain, tls_stream, url).await
+    if is_tls {
+        let tls_stream = upgrade_to_tls(host, tcp_stream).await?;
+        upgrade_to_websocket(authority.as_str(), tls_stream, url).await
+    } else {
+        upgrade_to_websocket(authority.as_str(), tcp_stream, url).await
+    }
 }
 
 struct SpawnExecutor;
@@ -45,8 +170,8 @@ where
     }
 }
 
-async fn setup_tcp(domain: &str) -> Result<TcpStream, AdapterError> {
-    let addr = format!("{domain}:443");
+async fn setup_tcp(domain: &str, port: u16) -> Result<TcpStream, AdapterError> {
+    let addr = format!("{domain}:{port}");
     TcpStream::connect(&addr)
         .await
         .map_err(|e| AdapterError::WebsocketError(e.to_string()))
@@ -85,11 +210,14 @@ async fn upgrade_to_tls(
         .map_err(|e| AdapterError::WebsocketError(e.to_string()))
 }
 
-async fn upgrade_to_websocket(
+async fn upgrade_to_websocket<S>(
     domain: &str,
-    tls_stream: tokio_rustls::client::TlsStream<TcpStream>,
+    stream: S,
     url: &str,
-) -> Result<FragmentCollector<TokioIo<Upgraded>>, AdapterError> {
+) -> Result<FragmentCollector<TokioIo<Upgraded>>, AdapterError>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
     let req: Request<Empty<Bytes>> = Request::builder()
         .method("GET")
         .uri(url)
@@ -104,9 +232,35 @@ async fn upgrade_to_websocket(
         .body(Empty::<Bytes>::new())
         .map_err(|e| AdapterError::WebsocketError(e.to_string()))?;
 
-    let (ws, _) = fastwebsockets::handshake::client(&SpawnExecutor, req, tls_stream)
+    let (ws, _) = fastwebsockets::handshake::client(&SpawnExecutor, req, stream)
         .await
         .map_err(|e| AdapterError::WebsocketError(e.to_string()))?;
 
     Ok(FragmentCollector::new(ws))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = ReconnectPolicy {
+            jitter: 0.0,
+            ..ReconnectPolicy::default()
+        };
+
+        assert_eq!(policy.backoff(0, 1.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3, 1.0), Duration::from_secs(8));
+        assert_eq!(policy.backoff(10, 1.0), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn jitter_shortens_wait_within_fraction() {
+        let policy = ReconnectPolicy::default();
+
+        assert_eq!(policy.backoff(1, 0.0), Duration::from_secs(2));
+        assert_eq!(policy.backoff(1, 1.0), Duration::from_secs(1));
+        assert!((0..100).all(|_| (0.0..=1.0).contains(&roll())));
+    }
+}
@@ -126,6 +126,14 @@ impl Depth {
             .collect::<BTreeMap<Price, f32>>();
     }
 
+    /// Best bid and ask when the bid is above the ask, which no consistent book has
+    pub fn crossed(&self) -> Option<(Price, Price)> {
+        match (self.bids.last_key_value(), self.asks.first_key_value()) {
+            (Some((bid, _)), Some((ask, _))) if bid > ask => Some((*bid, *ask)),
+            _ => None,
+        }
+    }
+
     pub fn mid_price(&self) -> Option<Price> {
         match (self.asks.first_key_value(), self.bids.last_key_value()) {
             (Some((ask_price, _)), Some((bid_price, _))) => Some((*ask_price + *bid_price) / 2),
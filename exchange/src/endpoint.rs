@@ -0,0 +1,53 @@
+//! Base URLs of the exchanges' REST and WebSocket APIs
+//!
+//! Adapters resolve their hardcoded production URLs through here, so tests can
+//! redirect them to a local mock server without touching adapter code.
+
+use rustc_hash::FxHashMap;
+use std::sync::{LazyLock, PoisonError, RwLock};
+
+static REDIRECTS: LazyLock<RwLock<FxHashMap<String, String>>> = LazyLock::new(Default::default);
+
+/// Sends everything addressed to `base`, e.g. `https://fapi.binance.com`, to `target` instead
+pub fn redirect(base: &str, target: impl Into<String>) {
+    REDIRECTS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(base.to_string(), target.into());
+}
+
+pub fn clear_redirects() {
+    REDIRECTS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+/// The URL to use in place of `base`, which is itself unless redirected
+pub fn resolve(base: &str) -> String {
+    REDIRECTS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(base)
+        .cloned()
+        .unwrap_or_else(|| base.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_redirected_base_only() {
+        redirect("https://redirected.example", "http://127.0.0.1:9000");
+
+        assert_eq!(
+            resolve("https://redirected.example"),
+            "http://127.0.0.1:9000"
+        );
+        assert_eq!(
+            resolve("https://untouched.example"),
+            "https://untouched.example"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Kline, OpenInterest, Trade};
+use crate::{FundingRate, Kline, OpenInterest, Trade};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use uuid::Uuid;
@@ -27,6 +27,11 @@ pub enum FetchedData {
         data: Vec<OpenInterest>,
         req_id: Option<uuid::Uuid>,
     },
+    Funding {
+        data: Vec<FundingRate>,
+        predicted: Option<FundingRate>,
+        req_id: Option<uuid::Uuid>,
+    },
 }
 
 #[derive(thiserror::Error, Debug, Clone)]
@@ -115,6 +120,7 @@ impl Default for RequestHandler {
 pub enum FetchRange {
     Kline(u64, u64),
     OpenInterest(u64, u64),
+    FundingRate(u64, u64),
     Trades(u64, u64),
 }
 
@@ -138,6 +144,9 @@ impl FetchRequest {
             (FetchRange::OpenInterest(s1, e1), FetchRange::OpenInterest(s2, e2)) => {
                 e1 == e2 && s1 == s2
             }
+            (FetchRange::FundingRate(s1, e1), FetchRange::FundingRate(s2, e2)) => {
+                e1 == e2 && s1 == s2
+            }
             _ => false,
         }
     }
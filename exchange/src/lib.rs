@@ -1,12 +1,16 @@
 pub mod adapter;
+pub mod capture;
 pub mod connect;
 pub mod depth;
+pub mod endpoint;
 pub mod fetcher;
 mod limiter;
+pub mod proxy;
 pub mod util;
 
 pub use adapter::Event;
 use adapter::{Exchange, MarketKind, StreamKind};
+pub use limiter::{RetryPolicy, retry_policy, set_retry_policy};
 
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
@@ -215,6 +219,8 @@ impl SerTicker {
             Exchange::BybitLinear => "BybitLinear",
             Exchange::BybitInverse => "BybitInverse",
             Exchange::BybitSpot => "BybitSpot",
+            Exchange::DeribitLinear => "DeribitLinear",
+            Exchange::DeribitInverse => "DeribitInverse",
             Exchange::HyperliquidLinear => "HyperliquidLinear",
             Exchange::HyperliquidSpot => "HyperliquidSpot",
             Exchange::OkexLinear => "OkexLinear",
@@ -232,6 +238,8 @@ impl SerTicker {
             "BybitLinear" => Ok(Exchange::BybitLinear),
             "BybitInverse" => Ok(Exchange::BybitInverse),
             "BybitSpot" => Ok(Exchange::BybitSpot),
+            "DeribitLinear" => Ok(Exchange::DeribitLinear),
+            "DeribitInverse" => Ok(Exchange::DeribitInverse),
             "HyperliquidLinear" => Ok(Exchange::HyperliquidLinear),
             "HyperliquidSpot" => Ok(Exchange::HyperliquidSpot),
             "OkexLinear" => Ok(Exchange::OkexLinear),
@@ -412,6 +420,21 @@ impl Ticker {
     pub fn market_type(&self) -> MarketKind {
         self.exchange.market_type()
     }
+
+    /// Settlement time in milliseconds of a dated future, `None` for spot and perpetuals
+    pub fn expiry(&self) -> Option<u64> {
+        self.exchange.adapter().expiry(self.as_str())
+    }
+
+    /// Perpetual swaps, dated futures of the same markets excluded
+    pub fn is_perps(&self) -> bool {
+        self.market_type() != MarketKind::Spot && self.expiry().is_none()
+    }
+
+    /// Only perpetuals pay funding, unlike dated futures listed on the same market
+    pub fn supports_funding_rates(&self) -> bool {
+        self.exchange.supports_funding_rates() && self.expiry().is_none()
+    }
 }
 
 impl fmt::Display for Ticker {
@@ -561,8 +584,7 @@ impl TickerInfo {
     }
 
     pub fn is_perps(&self) -> bool {
-        let market_type = self.ticker.market_type();
-        market_type == MarketKind::LinearPerps || market_type == MarketKind::InversePerps
+        self.ticker.is_perps()
     }
 
     pub fn exchange(&self) -> Exchange {
@@ -579,6 +601,16 @@ pub struct Trade {
     pub qty: f32,
 }
 
+/// A forced order closing out a position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Liquidation {
+    pub time: u64,
+    /// The forced order sold, meaning a long position got liquidated
+    pub is_sell: bool,
+    pub price: Price,
+    pub qty: f32,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Kline {
     pub time: u64,
@@ -664,6 +696,13 @@ pub struct OpenInterest {
     pub value: f32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FundingRate {
+    /// Settlement time of the funding interval
+    pub time: u64,
+    pub rate: f32,
+}
+
 fn str_f32_parse(s: &str) -> f32 {
     s.parse::<f32>().unwrap_or_else(|e| {
         log::error!("Failed to parse float: {}, error: {}", s, e);
@@ -1,8 +1,10 @@
 use crate::adapter::AdapterError;
+use crate::proxy;
 
 use reqwest::{Client, Method, Response};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::sync::LazyLock;
+use std::sync::{LazyLock, PoisonError, RwLock};
 use std::time::{Duration, Instant};
 
 pub static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
@@ -14,8 +16,74 @@ pub trait RateLimiter: Send + Sync {
     /// Update the limiter with response data (e.g., rate limit headers)
     fn update_from_response(&mut self, response: &Response, weight: usize);
 
-    /// Check if response indicates rate limiting and should exit
-    fn should_exit_on_response(&self, response: &Response) -> bool;
+    /// Check if response indicates rate limiting, or a temporary ban or geo-block
+    fn is_rate_limited(&self, response: &Response) -> bool;
+
+    /// How long the exchange asked us to back off for, if it said so
+    fn retry_after(&self, response: &Response) -> Option<Duration> {
+        retry_after_header(response)
+    }
+}
+
+/// Backoff applied to rate limited requests before giving up with [`AdapterError::RateLimited`]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries after the first rate limited response, `0` fails right away
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    /// Longer waits than this, including ones asked by the exchange, aren't retried
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before the given retry, doubling each time, or `None` to give up
+    pub fn backoff(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if retry >= self.max_retries {
+            return None;
+        }
+
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(1 << retry.min(16))
+            .min(self.max_backoff);
+
+        match retry_after {
+            Some(wait) if wait > self.max_backoff => None,
+            Some(wait) => Some(wait.max(exponential)),
+            None => Some(exponential),
+        }
+    }
+}
+
+static RETRY_POLICY: LazyLock<RwLock<RetryPolicy>> = LazyLock::new(Default::default);
+
+pub fn set_retry_policy(policy: RetryPolicy) {
+    *RETRY_POLICY.write().unwrap_or_else(PoisonError::into_inner) = policy;
+}
+
+pub fn retry_policy() -> RetryPolicy {
+    *RETRY_POLICY.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Parses the standard `Retry-After` header, given in seconds
+pub fn retry_after_header(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
 }
 
 pub async fn http_request_with_limiter<L: RateLimiter>(
@@ -26,36 +94,54 @@ pub async fn http_request_with_limiter<L: RateLimiter>(
     json_body: Option<&Value>,
 ) -> Result<String, AdapterError> {
     let method = method.unwrap_or(Method::GET);
+    let policy = retry_policy();
 
-    let mut limiter_guard = limiter.lock().await;
+    let mut retry = 0;
 
-    if let Some(wait_time) = limiter_guard.prepare_request(weight) {
-        log::warn!("Rate limit hit for: {url}. Waiting for {:?}", wait_time);
-        tokio::time::sleep(wait_time).await;
-    }
+    loop {
+        let mut limiter_guard = limiter.lock().await;
+
+        if let Some(wait_time) = limiter_guard.prepare_request(weight) {
+            log::warn!("Rate limit hit for: {url}. Waiting for {:?}", wait_time);
+            tokio::time::sleep(wait_time).await;
+        }
 
-    let mut request_builder = HTTP_CLIENT.request(method, url);
+        let mut request_builder = proxy::http_client(url)?.request(method.clone(), url);
 
-    if let Some(body) = json_body {
-        request_builder = request_builder.json(body);
-    }
+        if let Some(body) = json_body {
+            request_builder = request_builder.json(body);
+        }
+
+        let response = request_builder
+            .send()
+            .await
+            .map_err(AdapterError::FetchError)?;
 
-    let response = request_builder
-        .send()
-        .await
-        .map_err(AdapterError::FetchError)?;
+        limiter_guard.update_from_response(&response, weight);
+
+        if !limiter_guard.is_rate_limited(&response) {
+            return response.text().await.map_err(AdapterError::FetchError);
+        }
 
-    if limiter_guard.should_exit_on_response(&response) {
         let status = response.status();
-        eprintln!(
-            "HTTP error {status} for: {url}. Exiting. (This may be a rate limit, geo-block, or other access issue.)",
-        );
-        std::process::exit(1);
-    }
+        let retry_after = limiter_guard.retry_after(&response);
+
+        let Some(backoff) = policy.backoff(retry, retry_after) else {
+            log::error!(
+                "HTTP error {status} for: {url}. Giving up after {retry} retries. \
+                 (This may be a rate limit, geo-block, or other access issue.)",
+            );
+            return Err(AdapterError::RateLimited { retry_after });
+        };
+
+        // released while backing off, other requests to the exchange aren't held up by this one
+        drop(limiter_guard);
 
-    limiter_guard.update_from_response(&response, weight);
+        log::warn!("HTTP error {status} for: {url}. Retrying in {backoff:?}");
+        tokio::time::sleep(backoff).await;
 
-    response.text().await.map_err(AdapterError::FetchError)
+        retry += 1;
+    }
 }
 
 /// Limiter for a fixed window rate
@@ -170,16 +256,29 @@ impl DynamicBucket {
             return (None, None);
         }
 
+        (
+            Some(self.until_next_period()),
+            Some(DynamicLimitReason::HeaderRate),
+        )
+    }
+
+    /// Wait until the reported weight resets, if it's already used up
+    pub fn exhausted_for(&self) -> Option<Duration> {
+        let elapsed = Instant::now().duration_since(self.last_updated);
+
+        (elapsed <= self.refill_rate && self.current_used_weight >= self.max_weight)
+            .then(|| self.until_next_period())
+    }
+
+    fn until_next_period(&self) -> Duration {
         let current_time = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default();
 
         let period_seconds = self.refill_rate.as_secs();
         let seconds_in_period = current_time.as_secs() % period_seconds;
-        let wait_time = Duration::from_secs(period_seconds - seconds_in_period)
-            .saturating_add(Duration::from_millis(500));
-
-        (Some(wait_time), Some(DynamicLimitReason::HeaderRate))
+        Duration::from_secs(period_seconds - seconds_in_period)
+            .saturating_add(Duration::from_millis(500))
     }
 
     fn prepare_with_fallback(
@@ -192,3 +291,29 @@ impl DynamicBucket {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_retries_run_out() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.backoff(0, None), Some(Duration::from_secs(1)));
+        assert_eq!(policy.backoff(1, None), Some(Duration::from_secs(2)));
+        assert_eq!(policy.backoff(2, None), Some(Duration::from_secs(4)));
+        assert_eq!(policy.backoff(3, None), None);
+    }
+
+    #[test]
+    fn backoff_honors_retry_after_within_limit() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(
+            policy.backoff(0, Some(Duration::from_secs(30))),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(policy.backoff(0, Some(Duration::from_secs(3600))), None);
+    }
+}
@@ -0,0 +1,471 @@
+//! Outbound proxies for REST and WebSocket connections, configurable per venue
+//!
+//! A venue's proxy applies to every URL on the hosts its adapter
+//! [lists](crate::adapter::registry::ExchangeAdapter::hosts), so adapters keep
+//! passing plain URLs around. Redirected endpoints, e.g. a local mock, go direct.
+
+use crate::adapter::{AdapterError, ExchangeInclusive, registry::adapters};
+use crate::limiter::HTTP_CLIENT;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use reqwest::Client;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, PoisonError, RwLock};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ProxyKind {
+    /// Tunnels with `CONNECT`
+    #[default]
+    Http,
+    /// Host names are resolved by the proxy
+    Socks5,
+}
+
+impl ProxyKind {
+    pub const ALL: [ProxyKind; 2] = [ProxyKind::Http, ProxyKind::Socks5];
+}
+
+impl std::fmt::Display for ProxyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProxyKind::Http => write!(f, "HTTP"),
+            ProxyKind::Socks5 => write!(f, "SOCKS5"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Proxy {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Kept for the session only, so saved state never holds the credential
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+}
+
+impl Proxy {
+    /// Parses `[user[:password]@]host:port`
+    pub fn parse(kind: ProxyKind, address: &str) -> Result<Self, String> {
+        let address = address.trim();
+
+        let (credentials, host_port) = match address.rsplit_once('@') {
+            Some((credentials, host_port)) => (Some(credentials), host_port),
+            None => (None, address),
+        };
+
+        let (host, port) = host_port
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Expected host:port, got {host_port:?}"))?;
+        let port = port
+            .parse::<u16>()
+            .map_err(|_| format!("Invalid proxy port {port:?}"))?;
+
+        if host.is_empty() {
+            return Err("Missing proxy host".to_string());
+        }
+
+        let (username, password) = match credentials.map(|c| c.split_once(':')) {
+            Some(Some((user, password))) => (Some(user.to_string()), Some(password.to_string())),
+            Some(None) => (credentials.map(str::to_string), None),
+            None => (None, None),
+        };
+
+        Ok(Self {
+            kind,
+            host: host.to_string(),
+            port,
+            username,
+            password,
+        })
+    }
+
+    /// `[user@]host:port`, as accepted by [`Proxy::parse`]
+    ///
+    /// The password is left out, so the address can be shown.
+    pub fn address(&self) -> String {
+        match &self.username {
+            Some(user) => format!("{user}@{}:{}", self.host, self.port),
+            None => format!("{}:{}", self.host, self.port),
+        }
+    }
+
+    fn address_with_password(&self) -> String {
+        match (&self.username, &self.password) {
+            (Some(user), Some(password)) => {
+                format!("{user}:{password}@{}:{}", self.host, self.port)
+            }
+            _ => self.address(),
+        }
+    }
+
+    fn to_reqwest(&self) -> Result<reqwest::Proxy, reqwest::Error> {
+        match self.kind {
+            ProxyKind::Http => {
+                let proxy = reqwest::Proxy::all(format!("http://{}:{}", self.host, self.port))?;
+
+                Ok(match &self.username {
+                    Some(user) => proxy.basic_auth(user, self.password.as_deref().unwrap_or("")),
+                    None => proxy,
+                })
+            }
+            ProxyKind::Socks5 => {
+                reqwest::Proxy::all(format!("socks5h://{}", self.address_with_password()))
+            }
+        }
+    }
+}
+
+static PROXIES: LazyLock<RwLock<FxHashMap<ExchangeInclusive, Proxy>>> =
+    LazyLock::new(Default::default);
+
+static CLIENTS: LazyLock<RwLock<FxHashMap<Proxy, Client>>> = LazyLock::new(Default::default);
+
+/// Routes the venue's connections through `proxy`, or directly with `None`
+///
+/// Open sockets keep their route until they reconnect.
+pub fn set_proxy(venue: ExchangeInclusive, proxy: Option<Proxy>) {
+    let mut proxies = PROXIES.write().unwrap_or_else(PoisonError::into_inner);
+
+    match proxy {
+        Some(proxy) => proxies.insert(venue, proxy),
+        None => proxies.remove(&venue),
+    };
+}
+
+pub fn proxy(venue: ExchangeInclusive) -> Option<Proxy> {
+    PROXIES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&venue)
+        .cloned()
+}
+
+/// Every venue's proxy, e.g. for persisting them
+pub fn proxies() -> FxHashMap<ExchangeInclusive, Proxy> {
+    PROXIES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// The proxy of the venue serving `url`, if it has one
+pub fn for_url(url: &str) -> Option<Proxy> {
+    let host = url.split_once("://")?.1;
+    let host = host.split(['/', ':', '?']).next()?;
+
+    let venue = adapters()
+        .find(|adapter| {
+            adapter
+                .hosts()
+                .iter()
+                .any(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+        })?
+        .venue();
+
+    proxy(venue)
+}
+
+/// Client for REST requests to `url`, going through its venue's proxy
+pub fn http_client(url: &str) -> Result<Client, AdapterError> {
+    let Some(proxy) = for_url(url) else {
+        return Ok(HTTP_CLIENT.clone());
+    };
+
+    if let Some(client) = CLIENTS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&proxy)
+    {
+        return Ok(client.clone());
+    }
+
+    let client = Client::builder().proxy(proxy.to_reqwest()?).build()?;
+
+    CLIENTS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(proxy, client.clone());
+
+    Ok(client)
+}
+
+/// Opens a TCP stream to `host:port` through the proxy
+pub(crate) async fn tunnel(
+    proxy: &Proxy,
+    host: &str,
+    port: u16,
+) -> Result<TcpStream, AdapterError> {
+    let mut stream = TcpStream::connect((proxy.host.as_str(), proxy.port))
+        .await
+        .map_err(|e| proxy_error(proxy, e))?;
+
+    match proxy.kind {
+        ProxyKind::Http => http_connect(&mut stream, proxy, host, port).await?,
+        ProxyKind::Socks5 => socks5_connect(&mut stream, proxy, host, port).await?,
+    }
+
+    Ok(stream)
+}
+
+fn proxy_error(proxy: &Proxy, reason: impl std::fmt::Display) -> AdapterError {
+    AdapterError::WebsocketError(format!(
+        "{} proxy {}:{}: {reason}",
+        proxy.kind, proxy.host, proxy.port
+    ))
+}
+
+async fn http_connect(
+    stream: &mut TcpStream,
+    proxy: &Proxy,
+    host: &str,
+    port: u16,
+) -> Result<(), AdapterError> {
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some(user) = &proxy.username {
+        let credentials = format!("{user}:{}", proxy.password.as_deref().unwrap_or(""));
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            BASE64_STANDARD.encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| proxy_error(proxy, e))?;
+
+    // read byte by byte, anything after the empty line already belongs to the tunnel
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8192 {
+            return Err(proxy_error(proxy, "response head too long"));
+        }
+        stream
+            .read_exact(&mut byte)
+            .await
+            .map_err(|e| proxy_error(proxy, e))?;
+        head.push(byte[0]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.lines().next().unwrap_or_default();
+
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(proxy_error(
+            proxy,
+            format!("CONNECT refused with {status_line:?}"),
+        )),
+    }
+}
+
+async fn socks5_connect(
+    stream: &mut TcpStream,
+    proxy: &Proxy,
+    host: &str,
+    port: u16,
+) -> Result<(), AdapterError> {
+    const VERSION: u8 = 0x05;
+    const NO_AUTH: u8 = 0x00;
+    const USER_PASSWORD: u8 = 0x02;
+
+    let io_err = |e: std::io::Error| proxy_error(proxy, e);
+
+    let method = if proxy.username.is_some() {
+        USER_PASSWORD
+    } else {
+        NO_AUTH
+    };
+
+    stream
+        .write_all(&[VERSION, 1, method])
+        .await
+        .map_err(io_err)?;
+
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await.map_err(io_err)?;
+    if reply != [VERSION, method] {
+        return Err(proxy_error(proxy, "authentication method rejected"));
+    }
+
+    if method == USER_PASSWORD {
+        let user = proxy.username.as_deref().unwrap_or("").as_bytes();
+        let password = proxy.password.as_deref().unwrap_or("").as_bytes();
+
+        let (Ok(user_len), Ok(password_len)) =
+            (u8::try_from(user.len()), u8::try_from(password.len()))
+        else {
+            return Err(proxy_error(proxy, "credentials too long"));
+        };
+
+        let mut auth = vec![0x01, user_len];
+        auth.extend_from_slice(user);
+        auth.push(password_len);
+        auth.extend_from_slice(password);
+
+        stream.write_all(&auth).await.map_err(io_err)?;
+        stream.read_exact(&mut reply).await.map_err(io_err)?;
+        if reply[1] != 0 {
+            return Err(proxy_error(proxy, "authentication failed"));
+        }
+    }
+
+    let Ok(host_len) = u8::try_from(host.len()) else {
+        return Err(proxy_error(proxy, "host name too long"));
+    };
+
+    // CONNECT by domain name, so it's resolved on the proxy's side
+    let mut request = vec![VERSION, 0x01, 0x00, 0x03, host_len];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await.map_err(io_err)?;
+
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await.map_err(io_err)?;
+    if head[1] != 0 {
+        return Err(proxy_error(
+            proxy,
+            format!("CONNECT failed with reply code {}", head[1]),
+        ));
+    }
+
+    // skip the address the proxy bound for us
+    let addr_len = match head[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await.map_err(io_err)?;
+            usize::from(len[0])
+        }
+        kind => return Err(proxy_error(proxy, format!("unknown address type {kind}"))),
+    };
+    let mut bound = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound).await.map_err(io_err)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn parses_address_with_credentials() {
+        let proxy = Proxy::parse(ProxyKind::Socks5, "user:p@ss@10.0.0.1:1080").unwrap();
+
+        assert_eq!(proxy.username.as_deref(), Some("user"));
+        assert_eq!(proxy.password.as_deref(), Some("p@ss"));
+        assert_eq!((proxy.host.as_str(), proxy.port), ("10.0.0.1", 1080));
+        assert_eq!(proxy.address(), "user@10.0.0.1:1080");
+
+        assert!(Proxy::parse(ProxyKind::Http, "proxy.local").is_err());
+        assert!(Proxy::parse(ProxyKind::Http, "proxy.local:http").is_err());
+    }
+
+    #[test]
+    fn leaves_password_out_of_saved_state() {
+        let proxy = Proxy::parse(ProxyKind::Socks5, "user:secret@10.0.0.1:1080").unwrap();
+
+        let json = serde_json::to_string(&proxy).unwrap();
+        assert!(!json.contains("secret"));
+
+        let restored: Proxy = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.username.as_deref(), Some("user"));
+        assert_eq!(restored.password, None);
+    }
+
+    #[test]
+    fn applies_to_venue_hosts_only() {
+        let proxy = Proxy::parse(ProxyKind::Http, "127.0.0.1:3128").unwrap();
+        set_proxy(ExchangeInclusive::Okex, Some(proxy.clone()));
+
+        assert_eq!(for_url("wss://ws.okx.com/ws/v5/public"), Some(proxy));
+        assert_eq!(for_url("https://www.okx.com.evil.example/api"), None);
+        assert_eq!(for_url("http://127.0.0.1:9000/api"), None);
+
+        set_proxy(ExchangeInclusive::Okex, None);
+        assert_eq!(for_url("wss://ws.okx.com/ws/v5/public"), None);
+    }
+
+    #[tokio::test]
+    async fn tunnels_through_http_connect() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            let mut request = vec![0u8; 1024];
+            let read = socket.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..read]).into_owned();
+
+            socket
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\nhello")
+                .await
+                .unwrap();
+            request
+        });
+
+        let proxy = Proxy::parse(ProxyKind::Http, &format!("u:p@127.0.0.1:{port}")).unwrap();
+        let mut stream = tunnel(&proxy, "fstream.binance.com", 443).await.unwrap();
+
+        let mut tunneled = [0u8; 5];
+        stream.read_exact(&mut tunneled).await.unwrap();
+        assert_eq!(&tunneled, b"hello");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("CONNECT fstream.binance.com:443 HTTP/1.1\r\n"));
+        assert!(request.contains("Proxy-Authorization: Basic dTpw\r\n"));
+    }
+
+    #[tokio::test]
+    async fn tunnels_through_socks5() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            let mut greeting = [0u8; 3];
+            socket.read_exact(&mut greeting).await.unwrap();
+            socket.write_all(&[0x05, 0x00]).await.unwrap();
+
+            let mut request = [0u8; 5];
+            socket.read_exact(&mut request).await.unwrap();
+            let mut target = vec![0u8; usize::from(request[4]) + 2];
+            socket.read_exact(&mut target).await.unwrap();
+
+            socket
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 127, 0, 0, 1, 0, 0])
+                .await
+                .unwrap();
+            socket.write_all(b"hello").await.unwrap();
+
+            (greeting, target)
+        });
+
+        let proxy = Proxy::parse(ProxyKind::Socks5, &format!("127.0.0.1:{port}")).unwrap();
+        let mut stream = tunnel(&proxy, "stream.bybit.com", 443).await.unwrap();
+
+        let mut tunneled = [0u8; 5];
+        stream.read_exact(&mut tunneled).await.unwrap();
+        assert_eq!(&tunneled, b"hello");
+
+        let (greeting, target) = server.await.unwrap();
+        assert_eq!(greeting, [0x05, 1, 0x00]);
+        assert_eq!(&target[..16], b"stream.bybit.com");
+        assert_eq!(&target[16..], &443u16.to_be_bytes());
+    }
+}
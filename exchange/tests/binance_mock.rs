@@ -0,0 +1,298 @@
+//! Binance adapter against the recorded fixtures served by `mock-exchange`
+
+use exchange::adapter::{
+    ConnectionState, Exchange, MarketKind, StreamKind, StreamTicksize, binance, integrity, mux,
+};
+use exchange::util::Price;
+use exchange::{Event, Liquidation, PushFrequency, Ticker, TickerInfo, Timeframe, endpoint};
+use mock_exchange::{Fixture, MockExchange};
+
+use iced_futures::futures::{Stream, StreamExt};
+use std::{collections::BTreeMap, pin::pin, sync::LazyLock, time::Duration};
+use tokio::sync::{Mutex, MutexGuard};
+
+const LINEAR_PERP_REST: &str = "https://fapi.binance.com";
+const LINEAR_PERP_WS: &str = "wss://fstream.binance.com";
+
+/// Redirects are process-wide, tests holding different mocks must not overlap
+static REDIRECTS: LazyLock<Mutex<()>> = LazyLock::new(Default::default);
+
+async fn mock_linear_perps(fixture: &str) -> (MockExchange, MutexGuard<'static, ()>) {
+    let guard = REDIRECTS.lock().await;
+
+    let mock = MockExchange::start(Fixture::bundled(fixture).unwrap())
+        .await
+        .unwrap();
+
+    endpoint::redirect(LINEAR_PERP_REST, mock.http_url());
+    endpoint::redirect(LINEAR_PERP_WS, mock.ws_url());
+
+    (mock, guard)
+}
+
+fn ticker_info() -> TickerInfo {
+    linear_perp("BTCUSDT")
+}
+
+fn linear_perp(symbol: &str) -> TickerInfo {
+    let ticker = Ticker::new(symbol, Exchange::BinanceLinear);
+    TickerInfo::new(ticker, 0.1, 0.001, None)
+}
+
+fn depth_stream(ticker_info: TickerInfo) -> StreamKind {
+    StreamKind::DepthAndTrades {
+        ticker_info,
+        depth_aggr: StreamTicksize::Client,
+        push_freq: PushFrequency::ServerDefault,
+    }
+}
+
+fn price(value: f32) -> Price {
+    Price::from_f32(value).round_to_min_tick(ticker_info().min_ticksize)
+}
+
+/// Next event, connection states included
+async fn next_raw_event(stream: &mut (impl Stream<Item = Event> + Unpin)) -> Event {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("Timed out waiting for an event")
+        .expect("Market stream ended")
+}
+
+/// Next event that isn't a connection state change
+async fn next_event(stream: &mut (impl Stream<Item = Event> + Unpin)) -> Event {
+    loop {
+        match next_raw_event(stream).await {
+            Event::StateChanged(..) => continue,
+            event => return event,
+        }
+    }
+}
+
+/// Keeps the connection going until the mock got a request with `method`
+async fn next_request(
+    stream: &mut (impl Stream<Item = Event> + Unpin),
+    mock: &MockExchange,
+    method: &str,
+) -> Vec<String> {
+    for _ in 0..100 {
+        let request = mock.received().iter().rev().find_map(|frame| {
+            let request: serde_json::Value = serde_json::from_str(frame).ok()?;
+            (request["method"] == method).then(|| request["params"].clone())
+        });
+
+        if let Some(params) = request {
+            let mut topics: Vec<String> = serde_json::from_value(params).unwrap();
+            topics.sort();
+            return topics;
+        }
+
+        let _ = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+    }
+
+    panic!("Mock exchange never got a {method} request");
+}
+
+#[tokio::test]
+async fn syncs_depth_from_snapshot_and_diffs() {
+    let (_mock, _guard) = mock_linear_perps("binance/linear_btcusdt").await;
+
+    let mut stream = pin!(binance::connect_market_stream(
+        ticker_info(),
+        PushFrequency::ServerDefault
+    ));
+
+    assert!(matches!(
+        next_event(&mut stream).await,
+        Event::Connected(Exchange::BinanceLinear)
+    ));
+
+    let Event::DepthReceived(_, time, depth, trades) = next_event(&mut stream).await else {
+        panic!("Expected the first diff on top of the snapshot");
+    };
+    assert_eq!(time, 1700000000100);
+    assert_eq!(
+        depth.bids,
+        BTreeMap::from([(price(49999.9), 2.0), (price(50000.0), 1.2)])
+    );
+    assert_eq!(
+        depth.asks,
+        BTreeMap::from([(price(50000.1), 1.3), (price(50000.2), 3.0)])
+    );
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].time, 1700000000050);
+    assert_eq!(trades[0].price, price(50000.1));
+    assert_eq!(trades[0].qty, 0.2);
+    assert!(!trades[0].is_sell);
+
+    let Event::LiquidationReceived(_, liquidation) = next_event(&mut stream).await else {
+        panic!("Expected the forced order");
+    };
+    assert_eq!(
+        liquidation,
+        Liquidation {
+            time: 1700000000180,
+            is_sell: true,
+            price: price(49999.9),
+            qty: 0.1,
+        }
+    );
+
+    let Event::DepthReceived(_, time, depth, trades) = next_event(&mut stream).await else {
+        panic!("Expected the second diff");
+    };
+    assert_eq!(time, 1700000000200);
+    assert_eq!(depth.bids, BTreeMap::from([(price(50000.0), 1.2)]));
+    assert_eq!(
+        depth.asks,
+        BTreeMap::from([
+            (price(50000.1), 1.3),
+            (price(50000.2), 3.0),
+            (price(50000.3), 0.7)
+        ])
+    );
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].price, price(49999.9));
+    assert!(trades[0].is_sell);
+}
+
+#[tokio::test]
+async fn resyncs_on_sequence_gap() {
+    let (_mock, _guard) = mock_linear_perps("binance/linear_btcusdt_gap").await;
+
+    let mut stream = pin!(binance::connect_market_stream(
+        ticker_info(),
+        PushFrequency::ServerDefault
+    ));
+
+    assert!(matches!(
+        next_event(&mut stream).await,
+        Event::Connected(Exchange::BinanceLinear)
+    ));
+
+    let Event::DepthReceived(_, _, depth, _) = next_event(&mut stream).await else {
+        panic!("Expected the first diff on top of the snapshot");
+    };
+    assert_eq!(depth.bids[&price(50000.0)], 1.2);
+
+    // the skipped update gets the book replaced by a new snapshot, not patched
+    let Event::DepthReceived(_, time, depth, _) = next_event(&mut stream).await else {
+        panic!("Expected the diff following the resync");
+    };
+    assert_eq!(time, 1700000000400);
+    assert_eq!(
+        depth.bids,
+        BTreeMap::from([(price(49999.9), 2.0), (price(50000.0), 1.0)])
+    );
+    assert_eq!(
+        depth.asks,
+        BTreeMap::from([
+            (price(50000.1), 1.5),
+            (price(50000.2), 3.0),
+            (price(50000.3), 0.7)
+        ])
+    );
+
+    // other tests replay the same gap, the counters are process wide
+    let counters = integrity::counters(&depth_stream(ticker_info()));
+    assert!(counters.sequence_gaps >= 1);
+}
+
+#[tokio::test]
+async fn reports_resync_as_connection_states() {
+    let (_mock, _guard) = mock_linear_perps("binance/linear_btcusdt_gap").await;
+
+    let mut stream = pin!(binance::connect_market_stream(
+        ticker_info(),
+        PushFrequency::ServerDefault
+    ));
+
+    let mut states = vec![];
+    let mut depth_updates = 0;
+
+    while depth_updates < 2 {
+        match next_raw_event(&mut stream).await {
+            Event::StateChanged(stream, state) => {
+                assert_eq!(stream, depth_stream(ticker_info()));
+                states.push(state);
+            }
+            Event::DepthReceived(..) => depth_updates += 1,
+            _ => {}
+        }
+    }
+
+    assert_eq!(
+        states,
+        vec![
+            ConnectionState::Connecting,
+            ConnectionState::Resyncing,
+            ConnectionState::Live,
+            ConnectionState::Resyncing,
+            ConnectionState::Live,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn fetches_klines() {
+    let (_mock, _guard) = mock_linear_perps("binance/linear_btcusdt").await;
+
+    let klines = binance::fetch_klines(ticker_info(), Timeframe::M1, None)
+        .await
+        .unwrap();
+
+    assert_eq!(klines.len(), 2);
+    assert_eq!(klines[0].time, 1700000000000);
+    assert_eq!(klines[0].open, price(50000.0));
+    assert_eq!(klines[0].high, price(50010.0));
+    assert_eq!(klines[0].low, price(49990.0));
+    assert_eq!(klines[0].close, price(50005.0));
+    assert_eq!(klines[0].volume, (7.5, 5.0));
+    assert_eq!(klines[1].close, price(50015.0));
+}
+
+#[tokio::test]
+async fn shares_one_connection_across_tickers() {
+    let (mock, _guard) = mock_linear_perps("binance/linear_btcusdt").await;
+
+    let btc = depth_stream(ticker_info());
+    let eth = depth_stream(linear_perp("ETHUSDT"));
+    mux::set_wanted(Exchange::BinanceLinear, [btc, eth]);
+
+    let mut stream = pin!(binance::connect_shared_market_stream(
+        MarketKind::LinearPerps
+    ));
+
+    assert!(matches!(
+        next_event(&mut stream).await,
+        Event::Connected(Exchange::BinanceLinear)
+    ));
+    assert_eq!(
+        next_request(&mut stream, &mock, "SUBSCRIBE").await,
+        [
+            "btcusdt@aggTrade",
+            "btcusdt@depth@100ms",
+            "btcusdt@forceOrder",
+            "ethusdt@aggTrade",
+            "ethusdt@depth@100ms",
+            "ethusdt@forceOrder",
+        ]
+    );
+
+    // only btcusdt is in the recording
+    let Event::DepthReceived(received_for, ..) = next_event(&mut stream).await else {
+        panic!("Expected a diff routed to its own ticker");
+    };
+    assert_eq!(received_for, btc);
+
+    mux::set_wanted(Exchange::BinanceLinear, [eth]);
+
+    assert_eq!(
+        next_request(&mut stream, &mock, "UNSUBSCRIBE").await,
+        [
+            "btcusdt@aggTrade",
+            "btcusdt@depth@100ms",
+            "btcusdt@forceOrder"
+        ]
+    );
+}
@@ -0,0 +1,109 @@
+//! Frames captured from a stream, served back to the same adapter by `mock-exchange`
+
+use exchange::adapter::{Exchange, binance};
+use exchange::capture::{self, FrameKind, Settings, Source};
+use exchange::util::Price;
+use exchange::{Event, PushFrequency, Ticker, TickerInfo, endpoint};
+use mock_exchange::{Fixture, MockExchange};
+
+use iced_futures::futures::StreamExt;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    pin::pin,
+    time::Duration,
+};
+
+const LINEAR_PERP_REST: &str = "https://fapi.binance.com";
+const LINEAR_PERP_WS: &str = "wss://fstream.binance.com";
+
+async fn serve(fixture: Fixture) -> MockExchange {
+    let mock = MockExchange::start(fixture).await.unwrap();
+
+    endpoint::redirect(LINEAR_PERP_REST, mock.http_url());
+    endpoint::redirect(LINEAR_PERP_WS, mock.ws_url());
+
+    mock
+}
+
+type Update = (u64, BTreeMap<Price, f32>, BTreeMap<Price, f32>, usize);
+
+/// Time, book and trade count of the first `n` depth updates of BTCUSDT
+async fn depth_updates(n: usize) -> Vec<Update> {
+    let ticker_info = TickerInfo::new(
+        Ticker::new("BTCUSDT", Exchange::BinanceLinear),
+        0.1,
+        0.001,
+        None,
+    );
+    let mut stream = pin!(binance::connect_market_stream(
+        ticker_info,
+        PushFrequency::ServerDefault
+    ));
+
+    let mut updates = Vec::with_capacity(n);
+
+    while updates.len() < n {
+        let event = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("Timed out waiting for an event")
+            .expect("Market stream ended");
+
+        if let Event::DepthReceived(_, time, depth, trades) = event {
+            updates.push((time, depth.bids, depth.asks, trades.len()));
+        }
+    }
+
+    updates
+}
+
+/// Serves the captured REST responses and WebSocket frames by their URL's path
+fn fixture_from_capture(dir: &Path) -> Fixture {
+    // captured frames follow the subscribe request
+    let mut fixture = Fixture::default().waiting_for_request();
+    let mut ws: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for frame in capture::read_dir(dir).unwrap() {
+        let path = frame.path().to_string();
+
+        match frame.kind {
+            FrameKind::Rest => fixture = fixture.with_rest(path, frame.payload),
+            FrameKind::WebSocket => ws
+                .entry(path)
+                .or_default()
+                .push(String::from_utf8(frame.payload).unwrap()),
+        }
+    }
+
+    for (path, frames) in ws {
+        fixture = fixture.with_ws(path, frames);
+    }
+
+    fixture
+}
+
+fn capture_root() -> PathBuf {
+    std::env::temp_dir().join(format!("flowsurface-capture-{}", std::process::id()))
+}
+
+#[tokio::test]
+async fn replays_captured_frames_through_the_adapter() {
+    let root = capture_root();
+
+    let captured = {
+        let _mock = serve(Fixture::bundled("binance/linear_btcusdt").unwrap()).await;
+
+        capture::enable(Settings::new(root.clone()));
+        let updates = depth_updates(2).await;
+        capture::disable();
+
+        updates
+    };
+
+    let source = Source::new(Exchange::BinanceLinear, "market");
+    let _mock = serve(fixture_from_capture(&source.dir(&root))).await;
+
+    assert_eq!(depth_updates(2).await, captured);
+
+    std::fs::remove_dir_all(root).unwrap();
+}
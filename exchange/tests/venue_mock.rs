@@ -0,0 +1,285 @@
+//! Bybit, OKX, Hyperliquid and Aster adapters against the recorded fixtures served by
+//! `mock-exchange`, see `binance_mock.rs` for Binance
+
+use exchange::adapter::{Exchange, aster, bybit, hyperliquid, okex};
+use exchange::util::Price;
+use exchange::{Event, Liquidation, PushFrequency, Ticker, TickerInfo, Trade, endpoint};
+use mock_exchange::{Fixture, MockExchange};
+
+use iced_futures::futures::{Stream, StreamExt};
+use std::{collections::BTreeMap, pin::pin, sync::LazyLock, time::Duration};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Redirects are process-wide, tests holding different mocks must not overlap
+static REDIRECTS: LazyLock<Mutex<()>> = LazyLock::new(Default::default);
+
+/// Serves `fixture` in place of each of `domains`
+async fn mock(fixture: &str, domains: &[&str]) -> (MockExchange, MutexGuard<'static, ()>) {
+    let guard = REDIRECTS.lock().await;
+
+    let mock = MockExchange::start(Fixture::bundled(fixture).unwrap())
+        .await
+        .unwrap();
+
+    for domain in domains {
+        let url = if domain.starts_with("wss://") {
+            mock.ws_url()
+        } else {
+            mock.http_url()
+        };
+        endpoint::redirect(domain, url);
+    }
+
+    (mock, guard)
+}
+
+fn price(value: f32, ticker_info: &TickerInfo) -> Price {
+    Price::from_f32(value).round_to_min_tick(ticker_info.min_ticksize)
+}
+
+/// Compares levels with a tolerance, sizes converted from contracts aren't exact
+fn assert_levels(side: &BTreeMap<Price, f32>, expected: &[(f32, f32)], ticker_info: &TickerInfo) {
+    assert_eq!(
+        side.keys().copied().collect::<Vec<_>>(),
+        expected
+            .iter()
+            .map(|(px, _)| price(*px, ticker_info))
+            .collect::<Vec<_>>()
+    );
+
+    for ((_, qty), (_, expected_qty)) in side.iter().zip(expected) {
+        assert!(
+            (qty - expected_qty).abs() < 1e-6,
+            "Expected a size of {expected_qty}, got {qty}"
+        );
+    }
+}
+
+fn assert_trade(
+    trade: &Trade,
+    time: u64,
+    px: f32,
+    qty: f32,
+    is_sell: bool,
+    ticker_info: &TickerInfo,
+) {
+    assert_eq!(trade.time, time);
+    assert_eq!(
+        trade.price.round_to_min_tick(ticker_info.min_ticksize),
+        price(px, ticker_info)
+    );
+    assert!(
+        (trade.qty - qty).abs() < 1e-6,
+        "Expected a size of {qty}, got {}",
+        trade.qty
+    );
+    assert_eq!(trade.is_sell, is_sell);
+}
+
+/// Next event that isn't a connection state change
+async fn next_event(stream: &mut (impl Stream<Item = Event> + Unpin)) -> Event {
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("Timed out waiting for an event")
+            .expect("Market stream ended");
+
+        if !matches!(event, Event::StateChanged(..)) {
+            return event;
+        }
+    }
+}
+
+async fn next_depth(
+    stream: &mut (impl Stream<Item = Event> + Unpin),
+) -> (u64, exchange::depth::Depth, Box<[Trade]>) {
+    match next_event(stream).await {
+        Event::DepthReceived(_, time, depth, trades) => (time, depth, trades),
+        _ => panic!("Expected a depth update"),
+    }
+}
+
+#[tokio::test]
+async fn bybit_applies_snapshot_and_deltas() {
+    let (_mock, _guard) = mock("bybit/linear_btcusdt", &["wss://stream.bybit.com"]).await;
+
+    let ticker_info = TickerInfo::new(
+        Ticker::new("BTCUSDT", Exchange::BybitLinear),
+        0.1,
+        0.001,
+        None,
+    );
+    let mut stream = pin!(bybit::connect_market_stream(
+        ticker_info,
+        PushFrequency::ServerDefault
+    ));
+
+    assert!(matches!(
+        next_event(&mut stream).await,
+        Event::Connected(Exchange::BybitLinear)
+    ));
+
+    let (time, depth, trades) = next_depth(&mut stream).await;
+    assert_eq!(time, 1700000000100);
+    assert_levels(&depth.bids, &[(49999.9, 2.0), (50000.0, 1.2)], &ticker_info);
+    assert_levels(&depth.asks, &[(50000.1, 1.3), (50000.2, 3.0)], &ticker_info);
+    assert_eq!(trades.len(), 1);
+    assert_trade(&trades[0], 1700000000050, 50000.1, 0.2, false, &ticker_info);
+
+    // a liquidated long is a sell
+    let Event::LiquidationReceived(_, liquidation) = next_event(&mut stream).await else {
+        panic!("Expected the liquidation");
+    };
+    assert_eq!(
+        liquidation,
+        Liquidation {
+            time: 1700000000180,
+            is_sell: true,
+            price: price(49999.9, &ticker_info),
+            qty: 0.1,
+        }
+    );
+
+    let (time, depth, trades) = next_depth(&mut stream).await;
+    assert_eq!(time, 1700000000200);
+    assert_levels(&depth.bids, &[(50000.0, 1.2)], &ticker_info);
+    assert_levels(
+        &depth.asks,
+        &[(50000.1, 1.3), (50000.2, 3.0), (50000.3, 0.7)],
+        &ticker_info,
+    );
+    assert_eq!(trades.len(), 1);
+    assert_trade(&trades[0], 1700000000150, 49999.9, 0.5, true, &ticker_info);
+}
+
+#[tokio::test]
+async fn okx_applies_checksummed_books_in_contracts() {
+    let (_mock, _guard) = mock("okx/swap_btcusdt", &["wss://ws.okx.com"]).await;
+
+    let ticker_info = TickerInfo::new(
+        Ticker::new("BTC-USDT-SWAP", Exchange::OkexLinear),
+        0.1,
+        0.01,
+        Some(0.01),
+    );
+    let mut stream = pin!(okex::connect_market_stream(
+        ticker_info,
+        PushFrequency::ServerDefault
+    ));
+
+    assert!(matches!(
+        next_event(&mut stream).await,
+        Event::Connected(Exchange::OkexLinear)
+    ));
+
+    let (time, depth, trades) = next_depth(&mut stream).await;
+    assert_eq!(time, 1700000000100);
+    assert_levels(&depth.bids, &[(49999.9, 2.0), (50000.0, 1.2)], &ticker_info);
+    assert_levels(&depth.asks, &[(50000.1, 1.3), (50000.2, 3.0)], &ticker_info);
+    assert_eq!(trades.len(), 1);
+    assert_trade(&trades[0], 1700000000050, 50000.1, 0.2, false, &ticker_info);
+
+    // the channel carries every swap, only this instrument's gets through
+    let Event::LiquidationReceived(_, liquidation) = next_event(&mut stream).await else {
+        panic!("Expected the liquidation");
+    };
+    assert_eq!(liquidation.time, 1700000000180);
+    assert_eq!(liquidation.price, price(49999.9, &ticker_info));
+    assert!(liquidation.is_sell);
+    assert!((liquidation.qty - 0.1).abs() < 1e-6);
+
+    let (time, depth, trades) = next_depth(&mut stream).await;
+    assert_eq!(time, 1700000000200);
+    assert_levels(&depth.bids, &[(50000.0, 1.2)], &ticker_info);
+    assert_levels(
+        &depth.asks,
+        &[(50000.1, 1.3), (50000.2, 3.0), (50000.3, 0.7)],
+        &ticker_info,
+    );
+    assert_eq!(trades.len(), 1);
+    assert_trade(&trades[0], 1700000000150, 49999.9, 0.5, true, &ticker_info);
+}
+
+#[tokio::test]
+async fn hyperliquid_replaces_book_on_each_push() {
+    let (_mock, _guard) = mock(
+        "hyperliquid/perp_btc",
+        &["https://api.hyperliquid.xyz", "wss://api.hyperliquid.xyz"],
+    )
+    .await;
+
+    let ticker_info = TickerInfo::new(
+        Ticker::new("BTC", Exchange::HyperliquidLinear),
+        1.0,
+        0.00001,
+        None,
+    );
+    let mut stream = pin!(hyperliquid::connect_market_stream(
+        ticker_info,
+        None,
+        PushFrequency::ServerDefault
+    ));
+
+    assert!(matches!(
+        next_event(&mut stream).await,
+        Event::Connected(Exchange::HyperliquidLinear)
+    ));
+
+    let (time, depth, trades) = next_depth(&mut stream).await;
+    assert_eq!(time, 1700000000100);
+    assert_levels(&depth.bids, &[(49999.0, 2.0), (50000.0, 1.2)], &ticker_info);
+    assert_levels(&depth.asks, &[(50001.0, 1.3), (50002.0, 3.0)], &ticker_info);
+    assert_eq!(trades.len(), 1);
+    assert_trade(&trades[0], 1700000000050, 50001.0, 0.2, false, &ticker_info);
+
+    // levels missing from a push are gone, pushes aren't diffs
+    let (time, depth, trades) = next_depth(&mut stream).await;
+    assert_eq!(time, 1700000000200);
+    assert_levels(&depth.bids, &[(50000.0, 0.7)], &ticker_info);
+    assert_levels(&depth.asks, &[(50001.0, 1.3), (50003.0, 0.7)], &ticker_info);
+    assert_eq!(trades.len(), 1);
+    assert_trade(&trades[0], 1700000000150, 50000.0, 0.5, true, &ticker_info);
+}
+
+#[tokio::test]
+async fn aster_syncs_depth_from_snapshot_and_diffs() {
+    let (_mock, _guard) = mock(
+        "aster/linear_btcusdt",
+        &["https://fapi.asterdex.com", "wss://fstream.asterdex.com"],
+    )
+    .await;
+
+    let ticker_info = TickerInfo::new(
+        Ticker::new("BTCUSDT", Exchange::AsterLinear),
+        0.1,
+        0.001,
+        None,
+    );
+    let mut stream = pin!(aster::connect_market_stream(
+        ticker_info,
+        PushFrequency::ServerDefault
+    ));
+
+    assert!(matches!(
+        next_event(&mut stream).await,
+        Event::Connected(Exchange::AsterLinear)
+    ));
+
+    let (time, depth, trades) = next_depth(&mut stream).await;
+    assert_eq!(time, 1700000000100);
+    assert_levels(&depth.bids, &[(49999.9, 2.0), (50000.0, 1.2)], &ticker_info);
+    assert_levels(&depth.asks, &[(50000.1, 1.3), (50000.2, 3.0)], &ticker_info);
+    assert_eq!(trades.len(), 1);
+    assert_trade(&trades[0], 1700000000050, 50000.1, 0.2, false, &ticker_info);
+
+    let (time, depth, trades) = next_depth(&mut stream).await;
+    assert_eq!(time, 1700000000200);
+    assert_levels(&depth.bids, &[(50000.0, 1.2)], &ticker_info);
+    assert_levels(
+        &depth.asks,
+        &[(50000.1, 1.3), (50000.2, 3.0), (50000.3, 0.7)],
+        &ticker_info,
+    );
+    assert_eq!(trades.len(), 1);
+    assert_trade(&trades[0], 1700000000150, 49999.9, 0.5, true, &ticker_info);
+}
@@ -0,0 +1,286 @@
+//! Local REST and WebSocket server replaying recorded exchange traffic
+//!
+//! A fixture is a directory with a `manifest.json` mapping request paths to
+//! files next to it. REST responses are served as-is regardless of the query
+//! string, WebSocket scripts are `.jsonl` files whose lines are sent verbatim
+//! as text frames right after the upgrade. Venues that only push once
+//! subscribed set `wait_for_request`, their scripts start after the first
+//! text frame of the client.
+//!
+//! Fixtures can also be built in code, e.g. from frames captured with
+//! `exchange::capture`. Point the adapters at the server with
+//! `exchange::endpoint::redirect`.
+
+use bytes::Bytes;
+use fastwebsockets::{
+    Frame, OpCode, Payload, WebSocketError,
+    upgrade::{self, UpgradeFut},
+};
+use http_body_util::Full;
+use hyper::{
+    Request, Response, StatusCode, body::Incoming, server::conn::http1, service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use std::{
+    convert::Infallible,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    rest: FxHashMap<String, PathBuf>,
+    #[serde(default)]
+    ws: FxHashMap<String, PathBuf>,
+    #[serde(default)]
+    wait_for_request: bool,
+}
+
+/// Recorded responses of a single exchange, keyed by request path
+#[derive(Default)]
+pub struct Fixture {
+    rest: FxHashMap<String, Bytes>,
+    ws: FxHashMap<String, Arc<[String]>>,
+    wait_for_request: bool,
+}
+
+impl Fixture {
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+
+        let manifest = std::fs::read(dir.join("manifest.json"))?;
+        let manifest: Manifest = serde_json::from_slice(&manifest)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut fixture = Self {
+            wait_for_request: manifest.wait_for_request,
+            ..Self::default()
+        };
+
+        for (path, file) in manifest.rest {
+            fixture
+                .rest
+                .insert(path, Bytes::from(std::fs::read(dir.join(file))?));
+        }
+
+        for (path, file) in manifest.ws {
+            let script = std::fs::read_to_string(dir.join(file))?
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .collect();
+
+            fixture.ws.insert(path, script);
+        }
+
+        Ok(fixture)
+    }
+
+    /// Serves `body` to REST requests for `path`
+    pub fn with_rest(mut self, path: impl Into<String>, body: impl Into<Bytes>) -> Self {
+        self.rest.insert(path.into(), body.into());
+        self
+    }
+
+    /// Sends `frames` as text frames to WebSockets connecting to `path`
+    pub fn with_ws(mut self, path: impl Into<String>, frames: Vec<String>) -> Self {
+        self.ws.insert(path.into(), frames.into());
+        self
+    }
+
+    /// Holds WebSocket scripts back until the client sent a text frame, e.g. a subscribe request
+    pub fn waiting_for_request(mut self) -> Self {
+        self.wait_for_request = true;
+        self
+    }
+
+    /// One of the fixtures shipped in this crate, e.g. `"binance/linear_btcusdt"`
+    pub fn bundled(name: &str) -> io::Result<Self> {
+        Self::load(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("fixtures")
+                .join(name),
+        )
+    }
+}
+
+/// Serves a [`Fixture`] on a random local port until dropped
+pub struct MockExchange {
+    addr: SocketAddr,
+    server: JoinHandle<()>,
+    received: Received,
+}
+
+/// Text frames sent by clients, e.g. subscribe requests
+type Received = Arc<Mutex<Vec<String>>>;
+
+impl MockExchange {
+    pub async fn start(fixture: Fixture) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let fixture = Arc::new(fixture);
+        let received = Received::default();
+
+        let server = tokio::spawn({
+            let received = Arc::clone(&received);
+
+            async move {
+                loop {
+                    let stream = match listener.accept().await {
+                        Ok((stream, _)) => stream,
+                        Err(e) => {
+                            log::warn!("Mock exchange failed to accept a connection: {e}");
+                            continue;
+                        }
+                    };
+
+                    let fixture = Arc::clone(&fixture);
+                    let received = Arc::clone(&received);
+
+                    tokio::spawn(async move {
+                        let service = service_fn(move |req| {
+                            handle(Arc::clone(&fixture), Arc::clone(&received), req)
+                        });
+
+                        if let Err(e) = http1::Builder::new()
+                            .serve_connection(TokioIo::new(stream), service)
+                            .with_upgrades()
+                            .await
+                        {
+                            log::warn!("Mock exchange connection error: {e}");
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            server,
+            received,
+        })
+    }
+
+    /// Replacement for an `https://` REST base URL
+    pub fn http_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Replacement for a `wss://` WebSocket base URL
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    /// Text frames received over all WebSocket connections so far, oldest first
+    pub fn received(&self) -> Vec<String> {
+        self.received
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Drop for MockExchange {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn handle(
+    fixture: Arc<Fixture>,
+    received: Received,
+    mut req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let path = req.uri().path().to_string();
+
+    if upgrade::is_upgrade_request(&req) {
+        let Some(script) = fixture.ws.get(&path).cloned() else {
+            return Ok(respond(
+                StatusCode::NOT_FOUND,
+                format!("No script for {path}"),
+            ));
+        };
+
+        let wait_for_request = fixture.wait_for_request;
+
+        return match upgrade::upgrade(&mut req) {
+            Ok((response, websocket)) => {
+                tokio::spawn(async move {
+                    if let Err(e) = replay(websocket, script, wait_for_request, received).await {
+                        log::warn!("Mock exchange stopped replaying {path}: {e}");
+                    }
+                });
+
+                Ok(response.map(|_| Full::default()))
+            }
+            Err(e) => Ok(respond(StatusCode::BAD_REQUEST, e.to_string())),
+        };
+    }
+
+    match fixture.rest.get(&path) {
+        Some(body) => Ok(Response::new(Full::new(body.clone()))),
+        None => Ok(respond(
+            StatusCode::NOT_FOUND,
+            format!("No response for {path}"),
+        )),
+    }
+}
+
+async fn replay(
+    websocket: UpgradeFut,
+    script: Arc<[String]>,
+    wait_for_request: bool,
+    received: Received,
+) -> Result<(), WebSocketError> {
+    let mut ws = websocket.await?;
+
+    // frames sent before subscribing would be dropped by the client
+    if wait_for_request {
+        loop {
+            let frame = ws.read_frame().await?;
+
+            match frame.opcode {
+                OpCode::Text => {
+                    received
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(String::from_utf8_lossy(&frame.payload).into_owned());
+                    break;
+                }
+                OpCode::Close => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    for line in script.iter() {
+        ws.write_frame(Frame::text(Payload::Borrowed(line.as_bytes())))
+            .await?;
+    }
+
+    // keep the socket open, pings and closes are answered by fastwebsockets
+    loop {
+        let frame = ws.read_frame().await?;
+
+        match frame.opcode {
+            OpCode::Text => received
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(String::from_utf8_lossy(&frame.payload).into_owned()),
+            OpCode::Close => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn respond(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response
+}
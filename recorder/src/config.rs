@@ -0,0 +1,321 @@
+//! What to record, read from a TOML file
+
+use data::db::{RetainedData, RetentionPolicy, RetentionWindows};
+use exchange::adapter::{self, AdapterError, Exchange};
+use exchange::{PreferredCurrency, Ticker, TickerInfo, Timeframe};
+
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config: {0}")]
+    Read(#[from] std::io::Error),
+    #[error("Failed to parse config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+    #[error("Failed to fetch tickers of {0}: {1}")]
+    Tickers(Exchange, AdapterError),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Database file, the GUI's own database when not set
+    pub database: Option<PathBuf>,
+    /// Whether sizes get stored in quote currency, should match the GUI's setting
+    #[serde(default = "default_preferred_currency")]
+    pub preferred_currency: PreferredCurrency,
+    #[serde(default = "default_snapshot_interval")]
+    pub snapshot_interval_secs: u64,
+    #[serde(default = "default_health_interval")]
+    pub health_interval_secs: u64,
+    /// Windows of the GUI's retention policy, everything is kept when not set
+    pub retention: Option<RetentionConfig>,
+    #[serde(default)]
+    pub log: LogConfig,
+    #[serde(default, rename = "ticker")]
+    pub tickers: Vec<TickerConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    /// Where the recorder and health logs go, the `recorder` folder of the data directory by default
+    pub dir: Option<PathBuf>,
+    /// A log file gets rotated once it grows past this size
+    #[serde(default = "default_log_max_size")]
+    pub max_size_mb: u64,
+    /// Rotated files kept of each log
+    #[serde(default = "default_log_keep")]
+    pub keep: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            max_size_mb: default_log_max_size(),
+            keep: default_log_keep(),
+        }
+    }
+}
+
+/// Days of data kept per type, types not set are kept
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConfig {
+    pub trades: Option<u32>,
+    /// Days per kline timeframe, e.g. { "1m" = 30 }, footprints go with their klines
+    #[serde(default)]
+    pub klines: BTreeMap<String, u32>,
+    pub depth_snapshots: Option<u32>,
+    pub order_runs: Option<u32>,
+    pub open_interest: Option<u32>,
+    pub funding_rates: Option<u32>,
+    pub liquidations: Option<u32>,
+}
+
+impl RetentionConfig {
+    /// The policy enforced on every ticker, the recorder has no favorites
+    pub fn policy(&self) -> Result<RetentionPolicy, ConfigError> {
+        let klines = self
+            .klines
+            .iter()
+            .map(|(timeframe, days)| Ok((parse_timeframe(timeframe)?, *days)))
+            .collect::<Result<BTreeMap<_, _>, ConfigError>>()?;
+
+        let windows = RetentionWindows {
+            trades: self.trades,
+            klines,
+            depth_snapshots: self.depth_snapshots,
+            order_runs: self.order_runs,
+            open_interest: self.open_interest,
+            funding_rates: self.funding_rates,
+            liquidations: self.liquidations,
+        };
+
+        if RetainedData::all()
+            .into_iter()
+            .any(|kind| windows.days(kind) == Some(0))
+        {
+            return Err(ConfigError::Invalid(
+                "retention windows have to be at least a day".to_string(),
+            ));
+        }
+
+        Ok(RetentionPolicy {
+            all: windows.clone(),
+            favorites: windows,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TickerConfig {
+    /// As shown in the GUI, e.g. "Binance Linear"
+    pub exchange: String,
+    pub symbol: String,
+    #[serde(default = "default_true")]
+    pub trades: bool,
+    /// Order book snapshots, taken every `snapshot_interval_secs`
+    #[serde(default = "default_true")]
+    pub depth: bool,
+    /// Kline timeframes, e.g. ["1m", "1h"]
+    #[serde(default)]
+    pub klines: Vec<String>,
+    /// Open interest timeframe, polled at that pace
+    pub open_interest: Option<String>,
+}
+
+/// A configured ticker, looked up on its exchange
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub ticker_info: TickerInfo,
+    pub trades: bool,
+    pub depth: bool,
+    pub klines: Vec<Timeframe>,
+    pub open_interest: Option<Timeframe>,
+}
+
+impl Target {
+    pub fn needs_market_stream(&self) -> bool {
+        self.trades || self.depth
+    }
+}
+
+fn default_preferred_currency() -> PreferredCurrency {
+    PreferredCurrency::Base
+}
+
+fn default_snapshot_interval() -> u64 {
+    10
+}
+
+fn default_health_interval() -> u64 {
+    60
+}
+
+fn default_log_max_size() -> u64 {
+    50
+}
+
+fn default_log_keep() -> usize {
+    5
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+    }
+
+    fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(contents)?;
+
+        if config.tickers.is_empty() {
+            return Err(ConfigError::Invalid(
+                "no [[ticker]] to record was given".to_string(),
+            ));
+        }
+        if config.snapshot_interval_secs == 0 || config.health_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "intervals have to be at least a second".to_string(),
+            ));
+        }
+        if let Some(retention) = &config.retention {
+            retention.policy()?;
+        }
+
+        Ok(config)
+    }
+
+    /// Look up every configured ticker on its exchange
+    pub async fn resolve_targets(&self) -> Result<Vec<Target>, ConfigError> {
+        let mut listed: FxHashMap<Exchange, _> = FxHashMap::default();
+        let mut targets = Vec::with_capacity(self.tickers.len());
+
+        for ticker_cfg in &self.tickers {
+            let exchange =
+                Exchange::from_str(&ticker_cfg.exchange).map_err(ConfigError::Invalid)?;
+
+            let klines = ticker_cfg
+                .klines
+                .iter()
+                .map(|value| parse_timeframe(value))
+                .collect::<Result<Vec<_>, _>>()?;
+            let open_interest = ticker_cfg
+                .open_interest
+                .as_deref()
+                .map(parse_timeframe)
+                .transpose()?;
+
+            if open_interest.is_some() && !exchange.supports_open_interest() {
+                return Err(ConfigError::Invalid(format!(
+                    "{exchange} has no open interest to record"
+                )));
+            }
+
+            if !listed.contains_key(&exchange) {
+                let ticker_info = adapter::fetch_ticker_info(exchange)
+                    .await
+                    .map_err(|err| ConfigError::Tickers(exchange, err))?;
+                listed.insert(exchange, ticker_info);
+            }
+
+            let ticker = Ticker::new(&ticker_cfg.symbol, exchange);
+            let ticker_info = listed
+                .get(&exchange)
+                .and_then(|tickers| tickers.get(&ticker).copied().flatten())
+                .ok_or_else(|| {
+                    ConfigError::Invalid(format!(
+                        "{} isn't listed on {exchange}",
+                        ticker_cfg.symbol
+                    ))
+                })?;
+
+            targets.push(Target {
+                ticker_info,
+                trades: ticker_cfg.trades,
+                depth: ticker_cfg.depth,
+                klines,
+                open_interest,
+            });
+        }
+
+        Ok(targets)
+    }
+}
+
+fn parse_timeframe(value: &str) -> Result<Timeframe, ConfigError> {
+    Timeframe::KLINE
+        .into_iter()
+        .find(|timeframe| timeframe.to_string() == value)
+        .ok_or_else(|| {
+            let expected = Timeframe::KLINE.map(|timeframe| timeframe.to_string());
+            ConfigError::Invalid(format!(
+                "unknown timeframe {value:?}, expected one of {}",
+                expected.join(", ")
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tickers_with_defaults() {
+        let config = Config::parse(
+            r#"
+            [retention]
+            trades = 4
+            klines = { "1m" = 30 }
+
+            [[ticker]]
+            exchange = "Binance Linear"
+            symbol = "BTCUSDT"
+            klines = ["1m", "1h"]
+            open_interest = "5m"
+
+            [[ticker]]
+            exchange = "Bybit Spot"
+            symbol = "ETHUSDT"
+            depth = false
+            "#,
+        )
+        .expect("valid config");
+
+        let policy = config.retention.unwrap().policy().unwrap();
+        assert_eq!(policy.all.trades, Some(4));
+        assert_eq!(policy.all.klines.get(&Timeframe::M1), Some(&30));
+        assert_eq!(policy.all.liquidations, None);
+        assert_eq!(config.snapshot_interval_secs, 10);
+        assert_eq!(config.log.keep, 5);
+        assert_eq!(config.tickers.len(), 2);
+        assert!(config.tickers[0].depth && config.tickers[0].trades);
+        assert!(!config.tickers[1].depth);
+        assert_eq!(parse_timeframe("1h").unwrap(), Timeframe::H1);
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        assert!(matches!(Config::parse(""), Err(ConfigError::Invalid(_))));
+        assert!(matches!(
+            Config::parse(
+                "[[ticker]]\nexchange = \"OKX Linear\"\nsymbol = \"BTC-USDT-SWAP\"\nlevel = 2"
+            ),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!(parse_timeframe("100ms").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Logs to stdout and to files rotated by size, as the recorder runs for weeks at a time
+
+use crate::config::LogConfig;
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const LOG_FILE: &str = "recorder.log";
+const HEALTH_LOG_FILE: &str = "health.log";
+
+/// A log file that moves to `<name>.1` once full, shifting older ones up to `<name>.<keep>`
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    pub fn open(path: PathBuf, max_size: u64, keep: usize) -> io::Result<Self> {
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            keep,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = numbered(&self.path, self.keep);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+
+            for n in (1..self.keep).rev() {
+                let from = numbered(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, numbered(&self.path, n + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+        }
+
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        let bytes = self.file.write(buf)?;
+        self.size += bytes as u64;
+
+        Ok(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    fs::OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+pub fn log_dir(config: &LogConfig) -> PathBuf {
+    config
+        .dir
+        .clone()
+        .unwrap_or_else(|| data::data_path(Some("recorder")))
+}
+
+/// Route the logs of the recorder and the crates it drives to stdout and the rotating log
+pub fn setup(config: &LogConfig) -> Result<(), data::log::Error> {
+    let dir = log_dir(config);
+    fs::create_dir_all(&dir)?;
+
+    let level_filter = std::env::var("RUST_LOG")
+        .ok()
+        .as_deref()
+        .map(str::parse::<log::Level>)
+        .transpose()?
+        .unwrap_or(log::Level::Info)
+        .to_level_filter();
+
+    let file: Box<dyn Write + Send> = Box::new(RotatingFile::open(
+        dir.join(LOG_FILE),
+        config.max_size_mb * 1024 * 1024,
+        config.keep,
+    )?);
+
+    fern::Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{} {}:{} -- {}",
+                chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                message
+            ));
+        })
+        .level(log::LevelFilter::Off)
+        .level_for("data", level_filter)
+        .level_for("exchange", level_filter)
+        .level_for("flowsurface_recorder", level_filter)
+        .chain(io::stdout())
+        .chain(file)
+        .apply()?;
+
+    Ok(())
+}
+
+pub fn health_log(config: &LogConfig) -> io::Result<RotatingFile> {
+    RotatingFile::open(
+        log_dir(config).join(HEALTH_LOG_FILE),
+        config.max_size_mb * 1024 * 1024,
+        config.keep,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_and_drops_the_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.log");
+
+        let mut log = RotatingFile::open(path.clone(), 8, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+        log.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(numbered(&path, 1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(numbered(&path, 2)).unwrap(), "second\n");
+        assert!(!numbered(&path, 3).exists());
+    }
+}
@@ -0,0 +1,292 @@
+//! Records market data into the database without the GUI
+//!
+//! Usage: `flowsurface-recorder [config.toml]`, reading `recorder.toml` of the data
+//! directory when no path is given.
+
+mod config;
+mod logger;
+mod sink;
+
+use config::{Config, ConfigError, Target};
+use sink::Sink;
+
+use data::db::{DatabaseError, DatabaseManager, OpenInterestCRUD, RetentionPolicy, retention};
+use exchange::adapter::registry::EventStream;
+use exchange::adapter::{self, Exchange, StreamKind, StreamTicksize, mux};
+use exchange::{PushFrequency, TickMultiplier, TickerInfo, Timeframe};
+
+use iced_futures::futures::StreamExt;
+use iced_futures::futures::stream::select_all;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{Instant, MissedTickBehavior};
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("Failed to open database: {0}")]
+    Database(#[from] DatabaseError),
+    #[error("Failed to open health log: {0}")]
+    HealthLog(#[from] std::io::Error),
+}
+
+fn main() {
+    let config_path = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| data::data_path(Some("recorder.toml")));
+
+    let config = match Config::load(&config_path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}: {err}", config_path.display());
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = logger::setup(&config.log) {
+        eprintln!("Failed to set up logging: {err}");
+        std::process::exit(1);
+    }
+
+    exchange::set_size_in_quote_currency(config.preferred_currency);
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build the tokio runtime");
+
+    if let Err(err) = runtime.block_on(run(config)) {
+        log::error!("{err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(config: Config) -> Result<(), Error> {
+    let db_path = config
+        .database
+        .clone()
+        .unwrap_or_else(|| data::data_path(Some("flowsurface.duckdb")));
+    let db = Arc::new(DatabaseManager::new(&db_path)?);
+    log::info!("Recording into {}", db_path.display());
+
+    let targets = config.resolve_targets().await?;
+    for target in &targets {
+        log::info!(
+            "Recording {} on {}: trades {}, depth {}, klines {:?}, open interest {:?}",
+            target.ticker_info.ticker,
+            target.ticker_info.exchange(),
+            target.trades,
+            target.depth,
+            target.klines,
+            target.open_interest,
+        );
+    }
+
+    let mut sink = Sink::new(Arc::clone(&db), &targets, logger::health_log(&config.log)?);
+    let mut events = select_all(connect_streams(&targets));
+
+    for target in &targets {
+        if let Some(timeframe) = target.open_interest {
+            tokio::spawn(poll_open_interest(
+                Arc::clone(&db),
+                target.ticker_info,
+                timeframe,
+            ));
+        }
+    }
+    if let Some(retention) = &config.retention {
+        tokio::spawn(enforce_retention(Arc::clone(&db), retention.policy()?));
+    }
+
+    let mut snapshots = interval(Duration::from_secs(config.snapshot_interval_secs));
+    let mut health = interval(Duration::from_secs(config.health_interval_secs));
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            event = events.next() => match event {
+                Some(event) => sink.handle(event),
+                None => {
+                    log::error!("Every stream ended, nothing left to record");
+                    break;
+                }
+            },
+            _ = snapshots.tick() => sink.write_depth_snapshots(),
+            _ = health.tick() => sink.log_health().await,
+            () = &mut shutdown => {
+                log::info!("Shutting down");
+                break;
+            }
+        }
+    }
+
+    if let Err(err) = tokio::task::spawn_blocking(move || sink.flush()).await {
+        log::error!("Failed to flush buffered data: {err}");
+    }
+
+    Ok(())
+}
+
+/// Resolves on Ctrl+C, or on SIGTERM from a service manager
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("Failed to listen for SIGTERM");
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Starts a period from now, as the streams have nothing to report right after connecting
+fn interval(period: Duration) -> tokio::time::Interval {
+    let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+/// Market and kline streams of every target, sharing a connection where the exchange allows it
+fn connect_streams(targets: &[Target]) -> Vec<EventStream> {
+    let mut streams = vec![];
+
+    for exchange in Exchange::ALL {
+        let targets = targets
+            .iter()
+            .filter(|target| target.ticker_info.exchange() == exchange)
+            .collect::<Vec<_>>();
+        if targets.is_empty() {
+            continue;
+        }
+
+        let adapter = exchange.adapter();
+        let depth_aggr = if exchange.is_depth_client_aggr() {
+            StreamTicksize::Client
+        } else {
+            StreamTicksize::ServerSide(TickMultiplier(1))
+        };
+
+        let market = targets
+            .iter()
+            .filter(|target| target.needs_market_stream())
+            .map(|target| target.ticker_info)
+            .collect::<Vec<_>>();
+
+        if adapter.multiplexes_market_streams() {
+            if !market.is_empty() {
+                mux::set_wanted(
+                    exchange,
+                    market.iter().map(|ticker_info| StreamKind::DepthAndTrades {
+                        ticker_info: *ticker_info,
+                        depth_aggr,
+                        push_freq: PushFrequency::ServerDefault,
+                    }),
+                );
+                streams.push(adapter.connect_shared_market_stream(exchange.market_type()));
+            }
+        } else {
+            let tick_multiplier = match depth_aggr {
+                StreamTicksize::Client => None,
+                StreamTicksize::ServerSide(multiplier) => Some(multiplier),
+            };
+
+            streams.extend(market.into_iter().map(|ticker_info| {
+                adapter.connect_market_stream(
+                    ticker_info,
+                    tick_multiplier,
+                    PushFrequency::ServerDefault,
+                )
+            }));
+        }
+
+        let klines = targets
+            .iter()
+            .flat_map(|target| {
+                target
+                    .klines
+                    .iter()
+                    .map(|timeframe| (target.ticker_info, *timeframe))
+            })
+            .collect::<Vec<_>>();
+
+        if !klines.is_empty() {
+            streams.push(adapter.connect_kline_stream(klines, exchange.market_type()));
+        }
+    }
+
+    streams
+}
+
+/// Open interest has no stream, so it gets fetched once per timeframe
+async fn poll_open_interest(
+    db: Arc<DatabaseManager>,
+    ticker_info: TickerInfo,
+    timeframe: Timeframe,
+) {
+    let mut interval = tokio::time::interval(Duration::from_millis(timeframe.to_milliseconds()));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match adapter::fetch_open_interest(ticker_info.ticker, timeframe, None).await {
+            Ok(open_interest) => {
+                let db = Arc::clone(&db);
+                let inserted = tokio::task::spawn_blocking(move || {
+                    db.insert_open_interest(&ticker_info, &open_interest)
+                })
+                .await;
+
+                if let Ok(Err(err)) = inserted {
+                    log::error!(
+                        "Failed to persist open interest of {}: {err}",
+                        ticker_info.ticker
+                    );
+                }
+            }
+            Err(err) => {
+                log::warn!(
+                    "Failed to fetch open interest of {}: {err}",
+                    ticker_info.ticker
+                );
+            }
+        }
+    }
+}
+
+async fn enforce_retention(db: Arc<DatabaseManager>, policy: RetentionPolicy) {
+    let mut interval = tokio::time::interval(retention::ENFORCE_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        let db = Arc::clone(&db);
+        let policy = policy.clone();
+        let enforced = tokio::task::spawn_blocking(move || {
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            retention::enforce(&db, &policy, &[], now)
+        })
+        .await;
+
+        match enforced {
+            Ok(Ok(summary)) if summary.total_rows() > 0 => log::info!("{summary}"),
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => log::error!("Failed to enforce retention: {err}"),
+            Err(err) => log::error!("Retention task failed: {err}"),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Persists what the streams emit and keeps health counters of every recorded ticker
+
+use crate::config::Target;
+use crate::logger::RotatingFile;
+
+use data::db::{DatabaseManager, DbHealthMonitor, DepthCRUD, KlinesCRUD, TradeWriter};
+use exchange::adapter::{ConnectionState, StreamKind};
+use exchange::depth::Depth;
+use exchange::{Event, Kline, Ticker, TickerInfo, Timeframe};
+
+use rustc_hash::FxHashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Events of a ticker seen since the previous health line
+#[derive(Default)]
+struct Counters {
+    trades: u64,
+    depth_updates: u64,
+    klines_written: u64,
+    liquidations: u64,
+    snapshots_written: u64,
+}
+
+struct Recorded {
+    target: Target,
+    counters: Counters,
+    state: Option<ConnectionState>,
+    last_event: Option<u64>,
+    /// Latest book and when it was received, taken as a snapshot on the next interval
+    depth: Option<(u64, Depth)>,
+}
+
+pub struct Sink {
+    db: Arc<DatabaseManager>,
+    trade_writer: TradeWriter,
+    health_monitor: Arc<DbHealthMonitor>,
+    health_log: RotatingFile,
+    tickers: FxHashMap<Ticker, Recorded>,
+    /// Candle in progress of each kline stream, written once the next one opens
+    klines: FxHashMap<(TickerInfo, Timeframe), Kline>,
+}
+
+impl Sink {
+    pub fn new(db: Arc<DatabaseManager>, targets: &[Target], health_log: RotatingFile) -> Self {
+        let tickers = targets
+            .iter()
+            .map(|target| {
+                (
+                    target.ticker_info.ticker,
+                    Recorded {
+                        target: target.clone(),
+                        counters: Counters::default(),
+                        state: None,
+                        last_event: None,
+                        depth: None,
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            trade_writer: TradeWriter::spawn(Arc::clone(&db)),
+            health_monitor: Arc::new(DbHealthMonitor::new(Arc::clone(&db))),
+            db,
+            health_log,
+            tickers,
+            klines: FxHashMap::default(),
+        }
+    }
+
+    pub fn handle(&mut self, event: Event) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+
+        match event {
+            Event::Connected(exchange) => log::info!("Connected to {exchange}"),
+            Event::Disconnected(exchange, reason) => {
+                log::warn!("Disconnected from {exchange}: {reason}");
+            }
+            Event::DepthReceived(stream, time, depth, trades) => {
+                let ticker_info = stream.ticker_info();
+                let Some(recorded) = self.tickers.get_mut(&ticker_info.ticker) else {
+                    return;
+                };
+                recorded.last_event = Some(now);
+                recorded.counters.depth_updates += 1;
+
+                if recorded.target.trades {
+                    recorded.counters.trades += trades.len() as u64;
+                    self.trade_writer.push(ticker_info, &trades);
+                }
+                if recorded.target.depth {
+                    recorded.depth = Some((time, depth));
+                }
+            }
+            Event::KlineReceived(stream, kline) => {
+                let StreamKind::Kline {
+                    ticker_info,
+                    timeframe,
+                } = stream
+                else {
+                    return;
+                };
+                if let Some(recorded) = self.tickers.get_mut(&ticker_info.ticker) {
+                    recorded.last_event = Some(now);
+                }
+
+                if let Some(previous) = self.klines.insert((ticker_info, timeframe), kline)
+                    && previous.time < kline.time
+                {
+                    if let Some(recorded) = self.tickers.get_mut(&ticker_info.ticker) {
+                        recorded.counters.klines_written += 1;
+                    }
+                    self.persist("klines", move |db| {
+                        db.insert_klines(&ticker_info, timeframe, &[previous])
+                    });
+                }
+            }
+            Event::LiquidationReceived(stream, liquidation) => {
+                let ticker_info = stream.ticker_info();
+                if let Some(recorded) = self.tickers.get_mut(&ticker_info.ticker) {
+                    recorded.last_event = Some(now);
+                    recorded.counters.liquidations += 1;
+                }
+
+                self.trade_writer
+                    .push_liquidations(ticker_info, &[liquidation]);
+            }
+            Event::StateChanged(stream, state) => {
+                let ticker = stream.ticker_info().ticker;
+                if let Some(recorded) = self.tickers.get_mut(&ticker) {
+                    if recorded.state != Some(state) {
+                        log::info!("{ticker} stream is {state:?}");
+                    }
+                    recorded.state = Some(state);
+                }
+            }
+        }
+    }
+
+    /// Store the latest book of every ticker that got an update since the last snapshot
+    pub fn write_depth_snapshots(&mut self) {
+        let snapshots = self
+            .tickers
+            .values_mut()
+            .filter_map(|recorded| {
+                let (time, depth) = recorded.depth.take()?;
+                recorded.counters.snapshots_written += 1;
+                Some((recorded.target.ticker_info, time, depth))
+            })
+            .collect::<Vec<_>>();
+
+        if snapshots.is_empty() {
+            return;
+        }
+
+        self.persist("depth snapshots", move |db| {
+            snapshots
+                .iter()
+                .map(|(ticker_info, time, depth)| {
+                    db.insert_depth_snapshot(ticker_info, *time, depth)
+                })
+                .sum()
+        });
+    }
+
+    /// Append a line per ticker and one for the database to the health log, then reset the counters
+    pub async fn log_health(&mut self) {
+        let now = chrono::Utc::now();
+        let now_ms = now.timestamp_millis() as u64;
+        let time = now.to_rfc3339();
+
+        let mut lines = Vec::with_capacity(self.tickers.len() + 1);
+
+        for recorded in self.tickers.values_mut() {
+            let ticker_info = recorded.target.ticker_info;
+            let counters = std::mem::take(&mut recorded.counters);
+            let idle_ms = recorded.last_event.map(|last| now_ms.saturating_sub(last));
+
+            let is_live = matches!(recorded.state, Some(ConnectionState::Live) | None);
+            if !is_live || recorded.last_event.is_none() {
+                log::warn!(
+                    "{} on {} isn't receiving data ({:?})",
+                    ticker_info.ticker,
+                    ticker_info.exchange(),
+                    recorded.state
+                );
+            }
+
+            lines.push(serde_json::json!({
+                "time": time,
+                "exchange": ticker_info.exchange().to_string(),
+                "ticker": ticker_info.ticker.to_string(),
+                "state": recorded.state.map(|state| format!("{state:?}")),
+                "idle_ms": idle_ms,
+                "trades": counters.trades,
+                "depth_updates": counters.depth_updates,
+                "klines_written": counters.klines_written,
+                "liquidations": counters.liquidations,
+                "snapshots_written": counters.snapshots_written,
+            }));
+        }
+
+        // the check queries the database, which blocks
+        let health_monitor = Arc::clone(&self.health_monitor);
+        match tokio::task::spawn_blocking(move || health_monitor.run_health_check()).await {
+            Ok(report) => {
+                if !report.is_healthy() {
+                    log::error!("Database: {}", report.summary());
+                }
+                lines.push(serde_json::json!({
+                    "time": time,
+                    "database": report.summary(),
+                    "size_bytes": report.database_size_bytes,
+                    "query_latency_ms": report.query_latency_ms,
+                }));
+            }
+            Err(err) => log::error!("Database health check failed: {err}"),
+        }
+
+        for line in lines {
+            if let Err(err) = writeln!(self.health_log, "{line}") {
+                log::error!("Failed to write the health log: {err}");
+                break;
+            }
+        }
+        let _ = self.health_log.flush();
+    }
+
+    /// Write out everything still buffered, before shutting down
+    pub fn flush(self) {
+        for ((ticker_info, timeframe), kline) in &self.klines {
+            if let Err(err) = self.db.insert_klines(ticker_info, *timeframe, &[*kline]) {
+                log::error!("Failed to persist klines: {err}");
+            }
+        }
+
+        for recorded in self.tickers.values() {
+            if let Some((time, depth)) = &recorded.depth
+                && let Err(err) =
+                    self.db
+                        .insert_depth_snapshot(&recorded.target.ticker_info, *time, depth)
+            {
+                log::error!("Failed to persist depth snapshots: {err}");
+            }
+        }
+
+        // dropping the writer flushes its buffered trades and liquidations
+        drop(self.trade_writer);
+    }
+
+    /// Database calls block, so they run off the thread consuming the streams
+    fn persist<F>(&self, what: &'static str, write: F)
+    where
+        F: FnOnce(&DatabaseManager) -> data::db::Result<usize> + Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = write(&db) {
+                log::error!("Failed to persist {what}: {err}");
+            }
+        });
+    }
+}
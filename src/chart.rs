@@ -1206,3 +1206,74 @@ fn draw_volume_bar(
         }
     }
 }
+
+/// Shaded band over a span live data was missed for, e.g. while reconnecting
+fn draw_gap_marker(
+    frame: &mut canvas::Frame,
+    x_from: f32,
+    x_to: f32,
+    region: Rectangle,
+    palette: &Extended,
+) {
+    frame.fill_rectangle(
+        Point::new(x_from, region.y),
+        Size::new((x_to - x_from).max(1.0), region.height),
+        palette.warning.weak.color.scale_alpha(0.15),
+    );
+    frame.stroke(
+        &Path::line(
+            Point::new(x_from, region.y),
+            Point::new(x_from, region.y + region.height),
+        ),
+        Stroke::with_color(
+            Stroke {
+                width: 1.0,
+                line_dash: LineDash {
+                    segments: &[2.0, 2.0],
+                    offset: 0,
+                },
+                ..Default::default()
+            },
+            palette.warning.base.color.scale_alpha(0.6),
+        ),
+    );
+}
+
+/// Hollow ring sized by quantity, kept apart from the filled trade bubbles
+fn draw_liquidation_marker(
+    frame: &mut canvas::Frame,
+    center: Point,
+    qty: f32,
+    max_qty: f32,
+    is_sell: bool,
+    palette: &Extended,
+) {
+    const MIN_RADIUS: f32 = 3.0;
+    const MAX_RADIUS: f32 = 12.0;
+
+    let ratio = if max_qty > 0.0 {
+        (qty / max_qty).sqrt().min(1.0)
+    } else {
+        1.0
+    };
+    let radius = MIN_RADIUS + ratio * (MAX_RADIUS - MIN_RADIUS);
+
+    let color = if is_sell {
+        palette.danger.strong.color
+    } else {
+        palette.success.strong.color
+    };
+
+    let circle = Path::circle(center, radius);
+    frame.fill(&circle, color.scale_alpha(0.2));
+    frame.stroke(
+        &circle,
+        Stroke::with_color(
+            Stroke {
+                width: 1.5,
+                ..Default::default()
+            },
+            color,
+        ),
+    );
+}
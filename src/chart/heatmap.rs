@@ -8,10 +8,11 @@ use crate::{
 use data::chart::{
     Basis, ViewConfig,
     heatmap::{
-        CLEANUP_THRESHOLD, Config, HeatmapDataPoint, HeatmapStudy, HistoricalDepth, ProfileKind,
-        QtyScale,
+        CLEANUP_THRESHOLD, Config, HeatmapDataPoint, HeatmapStudy, HistoricalDepth, OrderRun,
+        ProfileKind, QtyScale,
     },
     indicator::HeatmapIndicator,
+    liquidation::LiquidationSeries,
 };
 use data::util::{abbr_large_numbers, count_decimals};
 use data::{
@@ -19,7 +20,7 @@ use data::{
     chart::Autoscale,
 };
 use exchange::util::{Price, PriceStep};
-use exchange::{SIZE_IN_QUOTE_CURRENCY, TickerInfo, Trade, depth::Depth};
+use exchange::{Liquidation, SIZE_IN_QUOTE_CURRENCY, TickerInfo, Trade, depth::Depth};
 
 use iced::widget::canvas::{self, Event, Geometry, Path};
 use iced::{
@@ -29,7 +30,7 @@ use iced::{
 
 use enum_map::EnumMap;
 use rustc_hash::FxHashMap;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 const MIN_SCALING: f32 = 0.6;
 const MAX_SCALING: f32 = 1.2;
@@ -48,6 +49,8 @@ const TOOLTIP_PADDING: f32 = 12.0;
 
 const MAX_CIRCLE_RADIUS: f32 = 16.0;
 
+const ORDER_RUNS_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
 impl Chart for HeatmapChart {
     type IndicatorKind = HeatmapIndicator;
 
@@ -144,9 +147,13 @@ pub struct HeatmapChart {
     indicators: EnumMap<HeatmapIndicator, Option<IndicatorData>>,
     pause_buffer: Vec<(u64, Box<[Trade]>, Depth)>,
     heatmap: HistoricalDepth,
+    liquidations: LiquidationSeries,
+    gaps: Vec<(u64, u64)>,
     visual_config: Config,
     study_configurator: study::Configurator<HeatmapStudy>,
     last_tick: Instant,
+    depth_history_requested: bool,
+    last_runs_flush: Instant,
     pub studies: Vec<HeatmapStudy>,
 }
 
@@ -189,11 +196,15 @@ impl HeatmapChart {
             indicators,
             pause_buffer: vec![],
             heatmap,
+            liquidations: LiquidationSeries::default(),
+            gaps: vec![],
             trades: TimeSeries::<HeatmapDataPoint>::new(basis, step),
             visual_config: config.unwrap_or_default(),
             study_configurator: study::Configurator::new(),
             studies,
             last_tick: Instant::now(),
+            depth_history_requested: false,
+            last_runs_flush: Instant::now(),
         }
     }
 
@@ -248,6 +259,8 @@ impl HeatmapChart {
 
             if let Some(oldest_time) = self.trades.datapoints.keys().next().copied() {
                 self.heatmap.cleanup_old_price_levels(oldest_time);
+                self.liquidations.remove_older_than(oldest_time);
+                self.gaps.retain(|&(_, to)| to >= oldest_time);
             }
         }
     }
@@ -306,6 +319,7 @@ impl HeatmapChart {
             self.chart.tick_size,
             basis,
         );
+        self.depth_history_requested = false;
 
         let chart = &mut self.chart;
         chart.translation = Vector::new(
@@ -368,6 +382,55 @@ impl HeatmapChart {
 
         self.trades.datapoints.clear();
         self.heatmap = HistoricalDepth::new(self.chart.ticker_info.min_qty.into(), step, basis);
+        self.depth_history_requested = false;
+    }
+
+    /// Time window to seed from persisted order runs, once per depth history
+    ///
+    /// Waits for the first depth update so the window ends at the live edge
+    pub fn request_depth_history(&mut self) -> Option<(u64, u64)> {
+        let latest = self.chart.latest_x;
+        if self.depth_history_requested || latest == 0 {
+            return None;
+        }
+
+        let interval = self.basis_interval()?;
+        self.depth_history_requested = true;
+
+        Some((
+            latest.saturating_sub(CLEANUP_THRESHOLD as u64 * interval),
+            latest,
+        ))
+    }
+
+    pub fn insert_depth_history(&mut self, runs: Vec<(Price, OrderRun)>) {
+        self.heatmap.insert_order_runs(runs);
+        self.invalidate(None);
+    }
+
+    /// Seeds the heatmap with stored full book snapshots, as imported from archives
+    pub fn insert_depth_snapshots(&mut self, snapshots: &[(u64, Depth)]) {
+        let runs = self.heatmap.snapshot_runs(snapshots);
+        self.insert_depth_history(runs);
+    }
+
+    pub fn insert_liquidations(&mut self, liquidations: &[Liquidation]) {
+        self.liquidations.extend(liquidations.iter().copied());
+    }
+
+    /// Marks a span the live streams missed data for, e.g. while reconnecting
+    pub fn insert_gap(&mut self, from: u64, to: u64) {
+        self.gaps.push((from, to));
+    }
+
+    /// Completed order runs to persist, collected at most once per flush interval
+    pub fn take_completed_runs(&mut self, now: Instant) -> Vec<(Price, OrderRun)> {
+        if now.duration_since(self.last_runs_flush) < ORDER_RUNS_FLUSH_INTERVAL {
+            return vec![];
+        }
+        self.last_runs_flush = now;
+
+        self.heatmap.take_completed_runs(self.chart.latest_x)
     }
 
     pub fn tick_size(&self) -> f32 {
@@ -669,6 +732,40 @@ impl canvas::Program<Message> for HeatmapChart {
                     }
                 });
 
+            if let Basis::Time(interval) = chart.basis {
+                let interval: u64 = interval.into();
+                let until = latest + interval;
+                let max_liq_qty = self.liquidations.max_qty_in_range(earliest, until);
+
+                for liquidation in self.liquidations.range(earliest, until) {
+                    let x_position =
+                        chart.interval_to_x(liquidation.time - liquidation.time % interval);
+                    let y_position = chart.price_to_y(liquidation.price);
+
+                    super::draw_liquidation_marker(
+                        frame,
+                        Point::new(x_position, y_position),
+                        liquidation.qty,
+                        max_liq_qty,
+                        liquidation.is_sell,
+                        palette,
+                    );
+                }
+
+                for &(from, to) in &self.gaps {
+                    if to < earliest || from > until {
+                        continue;
+                    }
+                    super::draw_gap_marker(
+                        frame,
+                        chart.interval_to_x(from),
+                        chart.interval_to_x(to),
+                        region,
+                        palette,
+                    );
+                }
+            }
+
             if volume_indicator && max_aggr_volume > 0.0 {
                 let text_size = 9.0 / chart.scaling;
                 let text_content = abbr_large_numbers(max_aggr_volume);
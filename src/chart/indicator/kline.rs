@@ -6,6 +6,7 @@ use data::chart::kline::KlineDataPoint;
 use exchange::fetcher::FetchRange;
 use exchange::{Kline, Timeframe, Trade};
 
+pub mod funding_rate;
 pub mod moving_average;
 pub mod open_interest;
 pub mod volume;
@@ -49,6 +50,13 @@ pub trait KlineIndicatorImpl {
 
     fn on_open_interest(&mut self, _pairs: &[exchange::OpenInterest]) {}
 
+    fn on_funding_rates(
+        &mut self,
+        _data: &[exchange::FundingRate],
+        _predicted: Option<exchange::FundingRate>,
+    ) {
+    }
+
     /// Draw indicator as overlay on main chart canvas
     /// Returns true if this indicator should be drawn as overlay
     fn draw_overlay(
@@ -84,5 +92,8 @@ pub fn make_empty(which: KlineIndicator) -> Box<dyn KlineIndicatorImpl> {
         KlineIndicator::MovingAverage => {
             Box::new(super::kline::moving_average::MovingAverageIndicator::new())
         }
+        KlineIndicator::FundingRate => {
+            Box::new(super::kline::funding_rate::FundingRateIndicator::new())
+        }
     }
 }
@@ -0,0 +1,201 @@
+use crate::chart::{
+    Basis, Caches, Message, ViewState,
+    indicator::{
+        indicator_row,
+        kline::{FetchCtx, KlineIndicatorImpl},
+        plot::{PlotTooltip, line::LinePlot},
+    },
+};
+
+use data::chart::{PlotData, kline::KlineDataPoint};
+use exchange::{FundingRate, Kline, Trade, fetcher::FetchRange};
+
+use iced::widget::{center, row, text};
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+/// Settlement times carry a few ms of jitter on some exchanges
+const SETTLEMENT_ALIGN_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FundingPoint {
+    rate: f32,
+    is_predicted: bool,
+}
+
+pub struct FundingRateIndicator {
+    cache: Caches,
+    pub data: BTreeMap<u64, FundingPoint>,
+}
+
+impl FundingRateIndicator {
+    pub fn new() -> Self {
+        Self {
+            cache: Caches::default(),
+            data: BTreeMap::new(),
+        }
+    }
+
+    fn indicator_elem<'a>(
+        &'a self,
+        main_chart: &'a ViewState,
+        visible_range: RangeInclusive<u64>,
+    ) -> iced::Element<'a, Message> {
+        match main_chart.basis {
+            Basis::Time(_) => {
+                let exchange = main_chart.ticker_info.exchange();
+                if !exchange.supports_funding_rates() {
+                    return center(text(format!(
+                        "Funding Rate is not available for {exchange}"
+                    )))
+                    .into();
+                }
+                if !main_chart.ticker_info.is_perps() {
+                    return center(text("Funding Rate is only paid on perpetuals")).into();
+                }
+
+                let (earliest, latest) = visible_range.clone().into_inner();
+                if latest < earliest {
+                    return row![].into();
+                }
+            }
+            Basis::Tick(_) => {
+                return center(text("WIP: Funding Rate is not available for tick charts.")).into();
+            }
+        }
+
+        let tooltip = |point: &FundingPoint, _next: Option<&FundingPoint>| {
+            let label = if point.is_predicted {
+                "Predicted Funding"
+            } else {
+                "Funding"
+            };
+            PlotTooltip::new(format!("{label}: {:.4}%", point.rate * 100.0))
+        };
+
+        // Plotted as a percentage
+        let value_fn = |point: &FundingPoint| point.rate * 100.0;
+
+        let plot = LinePlot::new(value_fn)
+            .stroke_width(1.0)
+            .show_points(true)
+            .point_radius_factor(0.2)
+            .padding(0.08)
+            .with_tooltip(tooltip);
+
+        indicator_row(main_chart, &self.cache, plot, &self.data, visible_range)
+    }
+
+    // helper to compute (earliest, latest) settled funding keys
+    fn settled_timerange(&self) -> Option<(u64, u64)> {
+        let mut settled = self
+            .data
+            .iter()
+            .filter(|(_, point)| !point.is_predicted)
+            .map(|(time, _)| *time);
+
+        let earliest = settled.next()?;
+        let latest = settled.next_back().unwrap_or(earliest);
+        Some((earliest, latest))
+    }
+
+    fn next_settlement(&self) -> Option<u64> {
+        self.data
+            .iter()
+            .rev()
+            .find(|(_, point)| point.is_predicted)
+            .map(|(time, _)| *time)
+    }
+}
+
+impl KlineIndicatorImpl for FundingRateIndicator {
+    fn clear_all_caches(&mut self) {
+        self.cache.clear_all();
+    }
+
+    fn clear_crosshair_caches(&mut self) {
+        self.cache.clear_crosshair();
+    }
+
+    fn element<'a>(
+        &'a self,
+        chart: &'a ViewState,
+        visible_range: RangeInclusive<u64>,
+    ) -> iced::Element<'a, Message> {
+        self.indicator_elem(chart, visible_range)
+    }
+
+    fn fetch_range(&mut self, ctx: &FetchCtx) -> Option<FetchRange> {
+        let ticker_info = &ctx.main_chart.ticker_info;
+        if !ticker_info.ticker.supports_funding_rates() {
+            return None;
+        }
+
+        let Some((earliest, latest)) = self.settled_timerange() else {
+            return Some(FetchRange::FundingRate(
+                ctx.prefetch_earliest,
+                ctx.kline_latest,
+            ));
+        };
+
+        if ctx.visible_earliest < earliest {
+            return Some(FetchRange::FundingRate(
+                ctx.prefetch_earliest,
+                earliest.saturating_sub(1),
+            ));
+        }
+
+        // refetch once the predicted funding got settled, or if it's unknown
+        let is_settlement_due = self
+            .next_settlement()
+            .is_none_or(|time| time <= ctx.kline_latest);
+
+        if is_settlement_due && latest < ctx.kline_latest {
+            return Some(FetchRange::FundingRate(latest + 1, ctx.kline_latest));
+        }
+
+        None
+    }
+
+    fn rebuild_from_source(&mut self, _source: &PlotData<KlineDataPoint>) {
+        // Funding comes from network via external fetches(open interest alike)
+        self.clear_all_caches();
+    }
+
+    fn on_insert_klines(&mut self, _klines: &[Kline]) {}
+
+    fn on_insert_trades(
+        &mut self,
+        _trades: &[Trade],
+        _old_dp_len: usize,
+        _source: &PlotData<KlineDataPoint>,
+    ) {
+    }
+
+    fn on_ticksize_change(&mut self, _source: &PlotData<KlineDataPoint>) {}
+
+    fn on_basis_change(&mut self, _source: &PlotData<KlineDataPoint>) {}
+
+    fn on_funding_rates(&mut self, data: &[FundingRate], predicted: Option<FundingRate>) {
+        self.data.extend(data.iter().map(|funding| {
+            (
+                funding.time - funding.time % SETTLEMENT_ALIGN_MS,
+                FundingPoint {
+                    rate: funding.rate,
+                    is_predicted: false,
+                },
+            )
+        }));
+
+        if let Some(funding) = predicted {
+            self.data.retain(|_, point| !point.is_predicted);
+            self.data
+                .entry(funding.time - funding.time % SETTLEMENT_ALIGN_MS)
+                .or_insert(FundingPoint {
+                    rate: funding.rate,
+                    is_predicted: true,
+                });
+        }
+
+        self.clear_all_caches();
+    }
+}
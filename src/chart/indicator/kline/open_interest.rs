@@ -97,7 +97,7 @@ impl OpenInterestIndicator {
     }
 
     pub fn is_supported_exchange(exchange: Exchange) -> bool {
-        exchange.is_perps() && exchange != Exchange::HyperliquidLinear
+        exchange.supports_open_interest()
     }
 
     pub fn is_supported_timeframe(timeframe: Timeframe) -> bool {
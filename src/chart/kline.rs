@@ -8,6 +8,7 @@ use data::aggr::ticks::TickAggr;
 use data::aggr::time::TimeSeries;
 use data::chart::Autoscale;
 use data::chart::kline::ClusterScaling;
+use data::chart::liquidation::LiquidationSeries;
 use data::chart::{
     KlineChartKind, ViewConfig,
     indicator::{Indicator, KlineIndicator},
@@ -16,7 +17,7 @@ use data::chart::{
 use data::util::{abbr_large_numbers, count_decimals};
 use exchange::util::{Price, PriceStep};
 use exchange::{
-    Kline, OpenInterest as OIData, TickerInfo, Trade,
+    FundingRate, Kline, Liquidation, OpenInterest as OIData, TickerInfo, Trade,
     fetcher::{FetchRange, RequestHandler},
 };
 
@@ -26,6 +27,7 @@ use iced::widget::canvas::{self, Event, Geometry, Path, Stroke};
 use iced::{Alignment, Element, Point, Rectangle, Renderer, Size, Theme, Vector, mouse};
 
 use enum_map::EnumMap;
+use std::collections::BTreeMap;
 use std::time::Instant;
 
 impl Chart for KlineChart {
@@ -172,6 +174,10 @@ pub struct KlineChart {
     raw_trades: Vec<Trade>,
     indicators: EnumMap<KlineIndicator, Option<Box<dyn KlineIndicatorImpl>>>,
     fetching_trades: (bool, Option<Handle>),
+    last_trade_fetch: Option<(u64, u64)>,
+    liquidations: LiquidationSeries,
+    liquidations_loaded_from: Option<u64>,
+    gaps: Vec<(u64, u64)>,
     pub(crate) kind: KlineChartKind,
     request_handler: RequestHandler,
     study_configurator: study::Configurator<FootprintStudy>,
@@ -264,6 +270,10 @@ impl KlineChart {
                     raw_trades,
                     indicators,
                     fetching_trades: (false, None),
+                    last_trade_fetch: None,
+                    liquidations: LiquidationSeries::default(),
+                    liquidations_loaded_from: None,
+                    gaps: vec![],
                     request_handler: RequestHandler::new(),
                     kind: kind.clone(),
                     study_configurator: study::Configurator::new(),
@@ -322,6 +332,10 @@ impl KlineChart {
                     raw_trades,
                     indicators,
                     fetching_trades: (false, None),
+                    last_trade_fetch: None,
+                    liquidations: LiquidationSeries::default(),
+                    liquidations_loaded_from: None,
+                    gaps: vec![],
                     request_handler: RequestHandler::new(),
                     kind: kind.clone(),
                     study_configurator: study::Configurator::new(),
@@ -375,15 +389,18 @@ impl KlineChart {
                     }
                 }
 
-                // priority 2, trades fetch
+                // priority 2, trades fetch, from the exchange or stored trades
                 if !self.fetching_trades.0
-                    && exchange::fetcher::is_trade_fetch_enabled()
+                    && (exchange::fetcher::is_trade_fetch_enabled()
+                        || data::db::is_persistence_enabled())
                     && let Some((fetch_from, fetch_to)) =
                         timeseries.suggest_trade_fetch_range(visible_earliest, visible_latest)
+                    && self.last_trade_fetch != Some((fetch_from, fetch_to))
                 {
                     let range = FetchRange::Trades(fetch_from, fetch_to);
                     if let Some(action) = request_fetch(&mut self.request_handler, range) {
                         self.fetching_trades = (true, None);
+                        self.last_trade_fetch = Some((fetch_from, fetch_to));
                         return Some(action);
                     }
                 }
@@ -430,6 +447,7 @@ impl KlineChart {
     pub fn reset_request_handler(&mut self) {
         self.request_handler = RequestHandler::new();
         self.fetching_trades = (false, None);
+        self.last_trade_fetch = None;
     }
 
     pub fn raw_trades(&self) -> Vec<Trade> {
@@ -661,6 +679,7 @@ impl KlineChart {
         let new_tick_aggr = TickAggr::new(tick_basis, self.chart.tick_size, &self.raw_trades);
 
         self.data_source = PlotData::TickBased(new_tick_aggr);
+        self.cleanup_old_data();
 
         self.indicators
             .values_mut()
@@ -735,6 +754,29 @@ impl KlineChart {
         }
     }
 
+    /// Fill klines that have no trades yet with footprints rebuilt from stored trades
+    pub fn insert_stored_footprints(
+        &mut self,
+        footprints: BTreeMap<u64, KlineTrades>,
+        is_batches_done: bool,
+    ) {
+        if let PlotData::TimeBased(ref mut timeseries) = self.data_source {
+            for (time, footprint) in footprints {
+                if let Some(dp) = timeseries.datapoints.get_mut(&time)
+                    && dp.footprint.trades.is_empty()
+                {
+                    dp.footprint = footprint;
+                }
+            }
+        }
+
+        if is_batches_done {
+            self.fetching_trades = (false, None);
+        }
+
+        self.invalidate(None);
+    }
+
     pub fn insert_new_klines(&mut self, req_id: uuid::Uuid, klines_raw: &[Kline]) {
         match self.data_source {
             PlotData::TimeBased(ref mut timeseries) => {
@@ -771,6 +813,81 @@ impl KlineChart {
         }
     }
 
+    pub fn insert_funding_rates(
+        &mut self,
+        req_id: Option<uuid::Uuid>,
+        data: &[FundingRate],
+        predicted: Option<FundingRate>,
+    ) {
+        if let Some(req_id) = req_id {
+            if data.is_empty() && predicted.is_none() {
+                self.request_handler
+                    .mark_failed(req_id, "No data received".to_string());
+            } else {
+                self.request_handler.mark_completed(req_id);
+            }
+        }
+
+        if let Some(indi) = self.indicators[KlineIndicator::FundingRate].as_mut() {
+            indi.on_funding_rates(data, predicted);
+        }
+    }
+
+    pub fn insert_liquidations(&mut self, liquidations: &[Liquidation]) {
+        self.liquidations.extend(liquidations.iter().copied());
+        self.cleanup_old_data();
+    }
+
+    /// Marks a span the live streams missed data for, e.g. while reconnecting
+    pub fn insert_gap(&mut self, from: u64, to: u64) {
+        self.gaps.push((from, to));
+        self.cleanup_old_data();
+    }
+
+    /// Drops markers before the first kline, they have nothing to be drawn on
+    fn cleanup_old_data(&mut self) {
+        let oldest_time = match &self.data_source {
+            PlotData::TimeBased(timeseries) => timeseries.datapoints.keys().next().copied(),
+            PlotData::TickBased(tick_aggr) => tick_aggr.datapoints.first().map(|dp| dp.kline.time),
+        };
+
+        if let Some(oldest_time) = oldest_time {
+            self.liquidations.remove_older_than(oldest_time);
+            self.gaps.retain(|&(_, to)| to >= oldest_time);
+        }
+    }
+
+    /// Time range of stored liquidations to load, covering klines not seeded yet
+    ///
+    /// Liquidations have no exchange history, so only the database can fill it
+    pub fn request_liquidation_history(&mut self) -> Option<(u64, u64)> {
+        if !self.chart.ticker_info.exchange().supports_liquidations() {
+            return None;
+        }
+
+        let PlotData::TimeBased(timeseries) = &self.data_source else {
+            return None;
+        };
+        let (kline_earliest, kline_latest) = timeseries.timerange();
+        if kline_latest == 0 {
+            return None;
+        }
+
+        let range = match self.liquidations_loaded_from {
+            None => (
+                kline_earliest,
+                kline_latest + timeseries.interval.to_milliseconds(),
+            ),
+            Some(loaded_from) if kline_earliest < loaded_from => {
+                (kline_earliest, loaded_from.saturating_sub(1))
+            }
+            Some(_) => return None,
+        };
+
+        self.liquidations_loaded_from = Some(kline_earliest);
+        Some(range)
+    }
+
     fn calc_qty_scales(
         &self,
         earliest: u64,
@@ -1109,6 +1226,39 @@ impl canvas::Program<Message> for KlineChart {
                 }
             }
 
+            if let PlotData::TimeBased(timeseries) = &self.data_source {
+                let interval = timeseries.interval.to_milliseconds();
+                let until = latest + interval;
+                let max_liq_qty = self.liquidations.max_qty_in_range(earliest, until);
+
+                for liquidation in self.liquidations.range(earliest, until) {
+                    super::draw_liquidation_marker(
+                        frame,
+                        Point::new(
+                            interval_to_x(liquidation.time - liquidation.time % interval),
+                            price_to_y(liquidation.price),
+                        ),
+                        liquidation.qty,
+                        max_liq_qty,
+                        liquidation.is_sell,
+                        palette,
+                    );
+                }
+
+                for &(from, to) in &self.gaps {
+                    if to < earliest || from > until {
+                        continue;
+                    }
+                    super::draw_gap_marker(
+                        frame,
+                        interval_to_x(from),
+                        interval_to_x(to),
+                        region,
+                        palette,
+                    );
+                }
+            }
+
             chart.draw_last_price_line(frame, palette, region);
 
             // Draw overlay indicators (e.g., Moving Average)
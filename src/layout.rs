@@ -25,6 +25,7 @@ pub struct SavedState {
     pub custom_theme: Option<data::Theme>,
     pub audio_cfg: data::AudioStream,
     pub preferred_currency: exchange::PreferredCurrency,
+    pub retention: data::db::RetentionPolicy,
 }
 
 impl SavedState {
@@ -53,6 +54,7 @@ impl Default for SavedState {
             custom_theme: None,
             audio_cfg: data::AudioStream::default(),
             preferred_currency: exchange::PreferredCurrency::Base,
+            retention: data::db::RetentionPolicy::default(),
         }
     }
 }
@@ -296,6 +298,17 @@ pub fn load_saved_state() -> SavedState {
 
             exchange::fetcher::toggle_trade_fetch(state.trade_fetch_enabled);
 
+            if state.frame_capture_enabled {
+                let dir = data::data_path(Some("captures"));
+                exchange::capture::enable(exchange::capture::Settings::new(dir));
+            }
+
+            for (venue, proxy) in state.proxies {
+                exchange::proxy::set_proxy(venue, Some(proxy));
+            }
+
+            exchange::set_retry_policy(state.retry_policy);
+
             exchange::SIZE_IN_QUOTE_CURRENCY
                 .set(state.size_in_quote_currency)
                 .expect("Failed to set SIZE_IN_QUOTE_CURRENCY");
@@ -314,6 +327,7 @@ pub fn load_saved_state() -> SavedState {
                 } else {
                     exchange::PreferredCurrency::Base
                 },
+                retention: state.retention,
             }
         }
         Err(e) => {
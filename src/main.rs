@@ -12,7 +12,7 @@ mod window;
 use data::config::theme::default_theme;
 use data::{layout::WindowSpec, sidebar};
 use layout::{Layout, configuration};
-use modal::{LayoutManager, ThemeEditor, audio};
+use modal::{LayoutManager, ThemeEditor, audio, connection_health, network, replay};
 use modal::{dashboard_modal, main_dialog_modal};
 use screen::dashboard::{self, Dashboard};
 use widget::{
@@ -59,6 +59,9 @@ struct Flowsurface {
     theme_editor: ThemeEditor,
     database_manager: modal::DbManager,
     audio_stream: audio::AudioStream,
+    network: network::Network,
+    connection_health: connection_health::ConnectionHealth,
+    replay: replay::Replay,
     confirm_dialog: Option<(String, Box<Message>)>,
     preferred_currency: exchange::PreferredCurrency,
     scale_factor: data::ScaleFactor,
@@ -66,6 +69,7 @@ struct Flowsurface {
     theme: data::Theme,
     notifications: Vec<Toast>,
     db_manager: Option<std::sync::Arc<data::db::DatabaseManager>>,
+    trade_writer: Option<data::db::TradeWriter>,
 }
 
 #[derive(Debug, Clone)]
@@ -82,6 +86,7 @@ enum Message {
     ScaleFactorChanged(data::ScaleFactor),
     SetTimezone(data::UserTimezone),
     ToggleTradeFetch(bool),
+    ToggleFrameCapture(bool),
     ToggleShowQuoteCurrency(bool),
     RemoveNotification(usize),
     ToggleDialogModal(Option<(String, Box<Message>)>),
@@ -89,6 +94,10 @@ enum Message {
     DatabaseManager(modal::database_manager::Message),
     Layouts(modal::layout_manager::Message),
     AudioStream(modal::audio::Message),
+    Network(modal::network::Message),
+    ConnectionHealth(modal::connection_health::Message),
+    Replay(modal::replay::Message),
+    EnforceRetention,
 }
 
 impl Flowsurface {
@@ -110,13 +119,17 @@ impl Flowsurface {
 
         // Initialize DatabaseManager if environment variable is set
         let db_manager = Self::initialize_database_manager();
+        let trade_writer = db_manager.clone().map(data::db::TradeWriter::spawn);
 
         let mut state = Self {
             main_window: window::Window::new(main_window_id),
             layout_manager: saved_state.layout_manager,
             theme_editor: ThemeEditor::new(saved_state.custom_theme),
-            database_manager: modal::DbManager::new(db_manager.clone()),
+            database_manager: modal::DbManager::new(db_manager.clone(), saved_state.retention),
             audio_stream: audio::AudioStream::new(saved_state.audio_cfg),
+            network: network::Network::new(),
+            connection_health: connection_health::ConnectionHealth::new(),
+            replay: replay::Replay::new(),
             sidebar,
             confirm_dialog: None,
             timezone: saved_state.timezone,
@@ -125,6 +138,7 @@ impl Flowsurface {
             theme: saved_state.theme,
             notifications: vec![],
             db_manager,
+            trade_writer,
         };
 
         // Update all dashboards with db_manager
@@ -136,25 +150,47 @@ impl Flowsurface {
 
         let last_active_layout = state.layout_manager.active_layout();
         let load_layout = state.load_layout(last_active_layout, main_window_id);
+        let probe_clocks = state.connection_health.probe_clocks();
 
         (
             state,
-            open_main_window
-                .discard()
-                .chain(load_layout)
-                .chain(launch_sidebar.map(Message::Sidebar)),
+            Task::batch([
+                open_main_window
+                    .discard()
+                    .chain(load_layout)
+                    .chain(launch_sidebar.map(Message::Sidebar)),
+                probe_clocks.map(Message::ConnectionHealth),
+                Task::done(Message::EnforceRetention),
+            ]),
         )
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::MarketWsEvent(event) => {
+                if let exchange::Event::DepthReceived(stream, _, _, trades_buffer) = &event
+                    && let Some(writer) = &self.trade_writer
+                {
+                    writer.push(stream.ticker_info(), trades_buffer);
+                }
+                if let exchange::Event::LiquidationReceived(stream, liquidation) = &event
+                    && let Some(writer) = &self.trade_writer
+                {
+                    writer.push_liquidations(stream.ticker_info(), &[*liquidation]);
+                }
+
                 let main_window_id = self.main_window.id;
                 let dashboard = self.active_dashboard_mut();
 
                 match event {
                     exchange::Event::Connected(exchange) => {
                         log::info!("a stream connected to {exchange} WS");
+
+                        // the skew may have drifted while disconnected
+                        return self
+                            .connection_health
+                            .probe_clock(exchange::adapter::ExchangeInclusive::of(exchange))
+                            .map(Message::ConnectionHealth);
                     }
                     exchange::Event::Disconnected(exchange, reason) => {
                         log::info!("a stream disconnected from {exchange} WS: {reason:?}");
@@ -187,6 +223,12 @@ impl Flowsurface {
                             .update_latest_klines(&stream, &kline, main_window_id)
                             .map(move |msg| Message::Dashboard(None, msg));
                     }
+                    exchange::Event::LiquidationReceived(stream, liquidation) => {
+                        dashboard.update_liquidation(&stream, liquidation, main_window_id);
+                    }
+                    exchange::Event::StateChanged(stream, state) => {
+                        dashboard.update_connection_state(&stream, state, main_window_id);
+                    }
                 }
             }
             Message::Tick(now) => {
@@ -219,6 +261,9 @@ impl Flowsurface {
                 }
             },
             Message::ExitRequested(windows) => {
+                // Dropping the writer flushes buffered live trades before exit
+                self.trade_writer.take();
+
                 self.active_dashboard_mut()
                     .popout
                     .iter_mut()
@@ -265,6 +310,7 @@ impl Flowsurface {
                     self.scale_factor,
                     audio_cfg,
                     self.preferred_currency,
+                    self.database_manager.retention_policy().clone(),
                 );
 
                 match serde_json::to_string(&layout) {
@@ -385,6 +431,14 @@ impl Flowsurface {
                     self.confirm_dialog = None;
                 }
             }
+            Message::ToggleFrameCapture(checked) => {
+                if checked {
+                    let dir = data::data_path(Some("captures"));
+                    exchange::capture::enable(exchange::capture::Settings::new(dir));
+                } else {
+                    exchange::capture::disable();
+                }
+            }
             Message::ToggleShowQuoteCurrency(checked) => {
                 self.preferred_currency = if checked {
                     exchange::PreferredCurrency::Quote
@@ -465,6 +519,17 @@ impl Flowsurface {
                 }
             }
             Message::AudioStream(message) => self.audio_stream.update(message),
+            Message::Network(message) => match self.network.update(message) {
+                Some(network::Action::Applied(venue)) => {
+                    self.notifications.push(Toast::warn(format!(
+                        "{venue}: the new route applies once open streams reconnect"
+                    )));
+                }
+                Some(network::Action::ErrorOccurred(err)) => {
+                    self.notifications.push(Toast::error(err));
+                }
+                None => {}
+            },
             Message::DataFolderRequested => {
                 if let Err(err) = data::open_data_folder() {
                     self.notifications
@@ -489,9 +554,67 @@ impl Flowsurface {
                     None => {}
                 }
             }
+            Message::ConnectionHealth(msg) => {
+                return self
+                    .connection_health
+                    .update(msg)
+                    .map(Message::ConnectionHealth);
+            }
+            Message::Replay(msg) => {
+                let main_window = self.main_window.id;
+
+                let task = match self.replay.update(msg) {
+                    Some(replay::Action::Start(range)) => {
+                        self.active_dashboard_mut().start_replay(main_window, range)
+                    }
+                    Some(replay::Action::Control(control)) => self
+                        .active_dashboard_mut()
+                        .control_replay(main_window, control),
+                    None => Task::none(),
+                };
+
+                return task.map(move |msg| Message::Dashboard(None, msg));
+            }
             Message::DatabaseManager(msg) => {
-                let (task, _action) = self.database_manager.update(msg);
-                return task.map(Message::DatabaseManager);
+                let (task, action) = self.database_manager.update(msg);
+
+                let action_task = match action {
+                    Some(modal::database_manager::Action::EnforceRetention) => {
+                        Task::done(Message::EnforceRetention)
+                    }
+                    Some(modal::database_manager::Action::RetentionEnforced(result)) => {
+                        self.notifications.push(match result {
+                            Ok(summary) => Toast::new(toast::Notification::Info(summary)),
+                            Err(err) => Toast::error(format!("Retention policy: {err}")),
+                        });
+                        Task::none()
+                    }
+                    Some(modal::database_manager::Action::Transfer(ticker)) => {
+                        let ticker_info = self.sidebar.listed_ticker_info(&ticker);
+
+                        self.database_manager
+                            .transfer(ticker_info)
+                            .map(Message::DatabaseManager)
+                    }
+                    Some(modal::database_manager::Action::Backfill(ticker)) => {
+                        let ticker_info = self.sidebar.listed_ticker_info(&ticker);
+
+                        self.database_manager
+                            .backfill(ticker_info)
+                            .map(Message::DatabaseManager)
+                    }
+                    None => Task::none(),
+                };
+
+                return Task::batch([task.map(Message::DatabaseManager), action_task]);
+            }
+            Message::EnforceRetention => {
+                let favorites = self.sidebar.favorited_tickers();
+
+                return self
+                    .database_manager
+                    .enforce_retention(favorites)
+                    .map(Message::DatabaseManager);
             }
             Message::Sidebar(message) => {
                 let (task, action) = self.sidebar.update(message);
@@ -628,12 +751,24 @@ impl Flowsurface {
             _ => None,
         });
 
+        let clock_probes = iced::time::every(connection_health::REPROBE_INTERVAL)
+            .map(|_| Message::ConnectionHealth(connection_health::Message::ProbeClocks));
+
+        let retention = if self.db_manager.is_some() {
+            iced::time::every(data::db::retention::ENFORCE_INTERVAL)
+                .map(|_| Message::EnforceRetention)
+        } else {
+            Subscription::none()
+        };
+
         Subscription::batch(vec![
             exchange_streams,
             sidebar,
             window_events,
             tick,
             hotkeys,
+            clock_probes,
+            retention,
         ])
     }
 
@@ -646,6 +781,7 @@ impl Flowsurface {
                 match data::db::DatabaseManager::new(&db_path) {
                     Ok(manager) => {
                         log::info!("Database initialized at {} for dual-write persistence", db_path.display());
+                        data::db::set_persistence_enabled(true);
                         Some(std::sync::Arc::new(manager))
                     }
                     Err(e) => {
@@ -784,7 +920,7 @@ impl Flowsurface {
                     let trade_fetch_checkbox = {
                         let is_active = exchange::fetcher::is_trade_fetch_enabled();
 
-                        let checkbox = iced::widget::checkbox("Fetch trades (Binance)", is_active)
+                        let checkbox = iced::widget::checkbox("Fetch historical trades", is_active)
                             .on_toggle(|checked| {
                                 if checked {
                                     Message::ToggleDialogModal(Some((
@@ -804,6 +940,22 @@ impl Flowsurface {
                         )
                     };
 
+                    let frame_capture_checkbox = {
+                        let checkbox = iced::widget::checkbox(
+                            "Capture raw frames",
+                            exchange::capture::is_enabled(),
+                        )
+                        .on_toggle(Message::ToggleFrameCapture);
+
+                        tooltip(
+                            checkbox,
+                            Some("Write received frames to the captures folder, for debugging"),
+                            TooltipPosition::Top,
+                        )
+                    };
+
+                    let network_settings = self.network.view().map(Message::Network);
+
                     let open_data_folder = {
                         let button =
                             button(text("Open data folder")).on_press(Message::DataFolderRequested);
@@ -822,9 +974,15 @@ impl Flowsurface {
                         column![text("Market data").size(14), size_in_quote_currency_checkbox,].spacing(12),
                         column![text("Theme").size(14), theme_picklist,].spacing(12),
                         column![text("Interface scale").size(14), scale_factor,].spacing(12),
+                        column![text("Network").size(14), network_settings,].spacing(12),
                         column![
                             text("Experimental").size(14),
-                            column![trade_fetch_checkbox, toggle_theme_editor,].spacing(8),
+                            column![
+                                trade_fetch_checkbox,
+                                frame_capture_checkbox,
+                                toggle_theme_editor,
+                            ]
+                            .spacing(8),
                         ]
                         .spacing(12),
                         ; spacing = 16, align_x = Alignment::Start
@@ -1033,6 +1191,40 @@ impl Flowsurface {
                     align_x,
                 )
             }
+            sidebar::Menu::ConnectionHealth => {
+                let (align_x, padding) = match sidebar_pos {
+                    sidebar::Position::Left => (Alignment::Start, padding::left(44).top(76)),
+                    sidebar::Position::Right => (Alignment::End, padding::right(44).top(76)),
+                };
+
+                let depth_streams_list = dashboard.streams.depth_streams(None);
+
+                dashboard_modal(
+                    base,
+                    self.connection_health
+                        .view(depth_streams_list)
+                        .map(Message::ConnectionHealth),
+                    Message::Sidebar(dashboard::sidebar::Message::ToggleSidebarMenu(None)),
+                    padding,
+                    Alignment::Start,
+                    align_x,
+                )
+            }
+            sidebar::Menu::Replay => {
+                let (align_x, padding) = match sidebar_pos {
+                    sidebar::Position::Left => (Alignment::Start, padding::left(44).top(76)),
+                    sidebar::Position::Right => (Alignment::End, padding::right(44).top(76)),
+                };
+
+                dashboard_modal(
+                    base,
+                    self.replay.view(dashboard.replay()).map(Message::Replay),
+                    Message::Sidebar(dashboard::sidebar::Message::ToggleSidebarMenu(None)),
+                    padding,
+                    Alignment::Start,
+                    align_x,
+                )
+            }
         }
     }
 }
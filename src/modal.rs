@@ -1,7 +1,10 @@
 pub mod audio;
+pub mod connection_health;
 pub mod database_manager;
 pub mod layout_manager;
+pub mod network;
 pub mod pane;
+pub mod replay;
 pub mod theme_editor;
 
 use iced::widget::{center, container, mouse_area, opaque, stack};
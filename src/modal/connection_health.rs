@@ -0,0 +1,204 @@
+use crate::style;
+use crate::widget::scrollable_content;
+use exchange::adapter::{ExchangeInclusive, StreamKind, StreamTicksize, integrity, telemetry};
+
+use exchange::{PushFrequency, TickerInfo};
+use iced::widget::{button, column, container, row, space, text};
+use iced::{Alignment, Element, Task};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::time::Duration;
+
+/// Without an update for this long, a stream is shown as stalled
+const STALE_AFTER_MS: u64 = 5_000;
+
+/// Clocks drift, skews are probed again this often
+pub const REPROBE_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ProbeClocks,
+    ClockProbed(ExchangeInclusive, Result<i64, String>),
+}
+
+/// Receive latency, clock skew and book faults of the active depth streams
+pub struct ConnectionHealth {
+    probe_errors: FxHashMap<ExchangeInclusive, String>,
+    pending_probes: FxHashSet<ExchangeInclusive>,
+}
+
+impl ConnectionHealth {
+    pub fn new() -> Self {
+        Self {
+            probe_errors: FxHashMap::default(),
+            pending_probes: FxHashSet::default(),
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ProbeClocks => {
+                if self.pending_probes.is_empty() {
+                    return self.probe_clocks();
+                }
+            }
+            Message::ClockProbed(venue, result) => {
+                self.pending_probes.remove(&venue);
+
+                match result {
+                    Ok(skew) => {
+                        log::info!("{venue} clock skew: {skew}ms");
+                        self.probe_errors.remove(&venue);
+                    }
+                    Err(err) => {
+                        self.probe_errors.insert(venue, err);
+                    }
+                }
+            }
+        }
+
+        Task::none()
+    }
+
+    /// Estimates the clock skew of every venue, latencies are corrected once it's known
+    pub fn probe_clocks(&mut self) -> Task<Message> {
+        Task::batch(
+            ExchangeInclusive::ALL
+                .into_iter()
+                .map(|venue| self.probe_clock(venue)),
+        )
+    }
+
+    /// Estimates the clock skew of a venue, e.g. after reconnecting to it,
+    /// unless a probe of it is already underway
+    pub fn probe_clock(&mut self, venue: ExchangeInclusive) -> Task<Message> {
+        if !self.pending_probes.insert(venue) {
+            return Task::none();
+        }
+
+        Task::perform(telemetry::probe_skew(venue), move |result| {
+            Message::ClockProbed(venue, result.map_err(|err| err.to_string()))
+        })
+    }
+
+    pub fn view(
+        &self,
+        active_streams: Vec<(TickerInfo, StreamTicksize, PushFrequency)>,
+    ) -> Element<'_, Message> {
+        let clocks = {
+            let probe_button = button(text("Probe clocks")).on_press_maybe(
+                self.pending_probes
+                    .is_empty()
+                    .then_some(Message::ProbeClocks),
+            );
+
+            let mut venues = column![].spacing(4);
+
+            for venue in ExchangeInclusive::ALL {
+                let skew = match (telemetry::skew(venue), self.probe_errors.get(&venue)) {
+                    (Some(skew), _) => format!("{skew:+}ms"),
+                    (None, Some(_)) => "unavailable".to_string(),
+                    (None, None) => "not probed".to_string(),
+                };
+
+                venues = venues.push(row![
+                    text(venue.to_string()),
+                    space::horizontal(),
+                    text(skew)
+                ]);
+            }
+
+            column![
+                row![
+                    text("Clock skew").size(14),
+                    space::horizontal(),
+                    probe_button
+                ]
+                .align_y(Alignment::Center),
+                venues,
+            ]
+            .spacing(8)
+        };
+
+        let streams = {
+            let mut streams = column![].spacing(4);
+
+            if active_streams.is_empty() {
+                streams = streams.push(text("No depth streams found"));
+            }
+
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+
+            for (ticker_info, depth_aggr, push_freq) in active_streams {
+                let stream = StreamKind::DepthAndTrades {
+                    ticker_info,
+                    depth_aggr,
+                    push_freq,
+                };
+
+                let faults = integrity::counters(&stream);
+
+                let mut card = column![text(format!(
+                    "{} - {}",
+                    ticker_info.exchange(),
+                    ticker_info.ticker
+                ))]
+                .spacing(2);
+
+                match telemetry::health(&stream) {
+                    Some(health) => {
+                        let latency = health.latency;
+                        let age = now.saturating_sub(health.last_received);
+
+                        card = card
+                            .push(stat(
+                                "Latency p50/p90/p99",
+                                format!("{}/{}/{}ms", latency.p50, latency.p90, latency.p99),
+                            ))
+                            .push(stat(
+                                "Last update",
+                                if age >= STALE_AFTER_MS {
+                                    format!("stalled, {}s ago", age / 1000)
+                                } else {
+                                    format!("{age}ms ago")
+                                },
+                            ));
+
+                        if health.skew.is_none() {
+                            card = card.push(text("Latency includes unprobed clock skew").size(10));
+                        }
+                    }
+                    None => card = card.push(text("Waiting for updates")),
+                }
+
+                card = card.push(stat("Book resyncs", faults.total().to_string()));
+
+                if faults.total() > 0 {
+                    card = card.push(
+                        text(format!(
+                            "{} sequence gaps, {} checksum mismatches, {} crossed books",
+                            faults.sequence_gaps, faults.checksum_mismatches, faults.crossed_books
+                        ))
+                        .size(10),
+                    );
+                }
+
+                streams = streams.push(container(card.padding(8)).style(style::modal_container));
+            }
+
+            column![text("Depth streams").size(14), scrollable_content(streams)].spacing(8)
+        };
+
+        container(column![clocks, streams].spacing(20))
+            .max_width(320)
+            .max_height(560)
+            .padding(24)
+            .style(style::dashboard_modal)
+            .into()
+    }
+}
+
+fn stat<'a>(label: &'a str, value: String) -> Element<'a, Message> {
+    row![text(label), space::horizontal(), text(value)]
+        .spacing(4)
+        .into()
+}
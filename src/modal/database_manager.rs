@@ -1,14 +1,132 @@
+use super::replay::{TIME_FORMAT, format_time, parse_time};
+use data::db::{
+    Coverage, DayRange, FileFormat, KlinesCRUD, RetainedData, RetentionPolicy, RetentionSummary,
+    StoredTable, TradesCRUD,
+};
+use exchange::adapter::{self, Exchange, fetch_ticker_info};
+use exchange::{Ticker, TickerInfo, Timeframe, Trade};
 use iced::{
-    widget::{column, container, row, rule, scrollable, space, text},
+    widget::{
+        button, column, container, pick_list, row, rule, scrollable, space, text, text_input,
+    },
     Alignment, Element, Length, Task,
 };
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
+
+/// Window preselected for exports
+const DEFAULT_EXPORT_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Candles requested at once when backfilling, few enough for every exchange to return all
+const KLINE_BACKFILL_BATCH: u64 = 100;
 
 #[derive(Debug, Clone)]
 pub struct DatabaseManager {
     db_manager: Option<Arc<data::db::DatabaseManager>>,
     stats: Option<DatabaseStats>,
     loading: bool,
+    /// Folder Hyperliquid's S3 archive files were downloaded to
+    archive_dir: String,
+    importing: bool,
+    import_result: Option<Result<String, String>>,
+    retention: RetentionPolicy,
+    enforcing: bool,
+    retention_result: Option<Result<String, String>>,
+    transfer: TransferForm,
+    coverage: Option<Vec<Coverage>>,
+    /// Backfill waiting for its ticker's info, see [`DatabaseManager::backfill`]
+    pending_backfill: Option<(RetainedData, DayRange)>,
+    /// Rows stored so far by the backfill in progress
+    backfilling: Option<usize>,
+    /// Batches of backfilled trades still being stored
+    storing_batches: usize,
+    /// Outcome of a finished fetch, reported once every batch is stored
+    fetched: Option<Result<usize, String>>,
+    /// Range whose delete button got pressed once, deleted on the second press
+    confirm_delete: Option<(Ticker, RetainedData, DayRange)>,
+    deleting: bool,
+    coverage_result: Option<Result<String, String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransferKind {
+    Export,
+    Import,
+}
+
+/// Export or import of a ticker's stored data, see [`data::db::transfer`]
+#[derive(Debug, Clone)]
+struct TransferForm {
+    exchange: Exchange,
+    symbol: String,
+    table: StoredTable,
+    format: FileFormat,
+    from: String,
+    to: String,
+    /// Folder exports get written to
+    export_dir: String,
+    import_file: String,
+    /// Waiting for the ticker to be looked up, see [`DatabaseManager::transfer`]
+    pending: Option<TransferKind>,
+    running: Option<TransferKind>,
+    result: Option<Result<String, String>>,
+}
+
+impl TransferForm {
+    fn new() -> Self {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+
+        Self {
+            exchange: Exchange::BinanceLinear,
+            symbol: String::new(),
+            table: StoredTable::Trades,
+            format: FileFormat::Parquet,
+            from: format_time(now - DEFAULT_EXPORT_WINDOW_MS),
+            to: format_time(now),
+            export_dir: data::data_path(Some("exports")).display().to_string(),
+            import_file: String::new(),
+            pending: None,
+            running: None,
+            result: None,
+        }
+    }
+
+    fn range(&self) -> Result<(u64, u64), String> {
+        match (parse_time(&self.from), parse_time(&self.to)) {
+            (Some(from), Some(to)) if from < to => Ok((from, to)),
+            (Some(_), Some(_)) => Err("The window has to end after it starts".to_string()),
+            _ => Err(format!("Times are expected as {TIME_FORMAT}, in UTC")),
+        }
+    }
+
+    /// Where an export of the current selection goes
+    fn export_path(&self, (from, to): (u64, u64)) -> PathBuf {
+        let name = format!(
+            "{}_{}_{}_{}_{}.{}",
+            self.exchange,
+            self.symbol.trim(),
+            self.table.name(),
+            file_time(from),
+            file_time(to),
+            self.format.extension(),
+        )
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+
+        PathBuf::from(&self.export_dir).join(name)
+    }
+}
+
+fn file_time(time: u64) -> String {
+    chrono::DateTime::from_timestamp_millis(time as i64)
+        .map(|dt| dt.format("%Y%m%d%H%M").to_string())
+        .unwrap_or_default()
 }
 
 #[derive(Debug, Clone)]
@@ -35,19 +153,218 @@ pub struct TickerStats {
 pub enum Message {
     Refresh,
     StatsLoaded(Result<DatabaseStats, String>),
+    ArchiveDirChanged(String),
+    ImportArchives,
+    ArchivesImported(Result<String, String>),
+    RetentionChanged {
+        kind: RetainedData,
+        favorites: bool,
+        value: String,
+    },
+    EnforceRetention,
+    RetentionEnforced(Result<RetentionSummary, String>),
+    TransferExchangeSelected(Exchange),
+    TransferSymbolChanged(String),
+    TransferTableSelected(StoredTable),
+    TransferFormatSelected(FileFormat),
+    TransferFromChanged(String),
+    TransferToChanged(String),
+    ExportDirChanged(String),
+    ImportFileChanged(String),
+    Export,
+    Import,
+    TransferDone(Result<String, String>),
+    CoverageLoaded(Result<Vec<Coverage>, String>),
+    Backfill {
+        ticker: Ticker,
+        kind: RetainedData,
+        gap: DayRange,
+    },
+    TradesBackfilled(TickerInfo, Vec<Trade>),
+    TradesStored(Result<usize, String>),
+    Backfilled(Result<usize, String>),
+    DeleteRange {
+        ticker: Ticker,
+        kind: RetainedData,
+        range: DayRange,
+    },
+    RangeDeleted(Result<String, String>),
 }
 
 pub enum Action {
-    // Future: actions that need to propagate up to main
+    /// Enforcing needs the favorited tickers, see [`DatabaseManager::enforce_retention`]
+    EnforceRetention,
+    /// Rows were deleted, or deleting them failed
+    RetentionEnforced(Result<String, String>),
+    /// Exporting or importing needs the ticker's info, see [`DatabaseManager::transfer`]
+    Transfer(Ticker),
+    /// Backfilling needs the ticker's info, see [`DatabaseManager::backfill`]
+    Backfill(Ticker),
 }
 
 impl DatabaseManager {
-    pub fn new(db_manager: Option<Arc<data::db::DatabaseManager>>) -> Self {
+    pub fn new(
+        db_manager: Option<Arc<data::db::DatabaseManager>>,
+        retention: RetentionPolicy,
+    ) -> Self {
         Self {
             db_manager,
             stats: None,
             loading: false,
+            archive_dir: data::data_path(Some("archives/hyperliquid"))
+                .display()
+                .to_string(),
+            importing: false,
+            import_result: None,
+            retention,
+            enforcing: false,
+            retention_result: None,
+            transfer: TransferForm::new(),
+            coverage: None,
+            pending_backfill: None,
+            backfilling: None,
+            storing_batches: 0,
+            fetched: None,
+            confirm_delete: None,
+            deleting: false,
+            coverage_result: None,
+        }
+    }
+
+    pub fn retention_policy(&self) -> &RetentionPolicy {
+        &self.retention
+    }
+
+    /// Delete what's past the retention policy in the background, `favorites` keeping theirs longer
+    pub fn enforce_retention(&mut self, favorites: Vec<Ticker>) -> Task<Message> {
+        let Some(db_manager) = self.db_manager.clone() else {
+            return Task::none();
+        };
+        if self.enforcing {
+            return Task::none();
         }
+        self.enforcing = true;
+
+        let policy = self.retention.clone();
+
+        Task::perform(
+            async move {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                data::db::retention::enforce(&db_manager, &policy, &favorites, now)
+                    .map_err(|e| e.to_string())
+            },
+            Message::RetentionEnforced,
+        )
+    }
+
+    /// Fetch the gap requested with [`Action::Backfill`] into the database, `None` if the ticker isn't listed
+    pub fn backfill(&mut self, ticker_info: Option<TickerInfo>) -> Task<Message> {
+        let Some((kind, gap)) = self.pending_backfill.take() else {
+            return Task::none();
+        };
+        let Some(db_manager) = self.db_manager.clone() else {
+            return Task::none();
+        };
+        let Some(ticker_info) = ticker_info else {
+            self.coverage_result = Some(Err(
+                "The ticker isn't listed anymore, so it can't be backfilled".to_string(),
+            ));
+            return Task::none();
+        };
+
+        // the gap up to today ends in the future
+        let (start, end) = gap.bounds();
+        let end = end.min(chrono::Utc::now().timestamp_millis() as u64);
+
+        let task = match kind {
+            RetainedData::Trades => {
+                let data_path = data::data_path(Some(&format!(
+                    "market_data/{}/",
+                    ticker_info.exchange().adapter().id()
+                )));
+
+                Task::sip(
+                    crate::screen::dashboard::fetch_trades_batched(
+                        ticker_info,
+                        start,
+                        end,
+                        data_path,
+                    ),
+                    move |mut batch: Vec<Trade>| {
+                        batch.retain(|trade| trade.time < end);
+                        Message::TradesBackfilled(ticker_info, batch)
+                    },
+                    |result| Message::Backfilled(result.map(|()| 0).map_err(|e| e.to_string())),
+                )
+            }
+            RetainedData::Klines(timeframe) => Task::perform(
+                Self::backfill_klines(db_manager, ticker_info, timeframe, (start, end)),
+                Message::Backfilled,
+            ),
+            _ => return Task::none(),
+        };
+
+        self.backfilling = Some(0);
+        self.coverage_result = None;
+
+        task
+    }
+
+    fn finish_backfill(&mut self, result: Result<usize, String>) -> Task<Message> {
+        let stored = self.backfilling.take().unwrap_or_default();
+
+        self.coverage_result = Some(match result {
+            Ok(count) => Ok(format!(
+                "Backfilled {} rows",
+                Self::format_number((stored + count) as i64)
+            )),
+            Err(e) => {
+                log::error!("Failed to backfill: {}", e);
+                Err(format!(
+                    "Backfill stopped after {} rows: {}",
+                    Self::format_number(stored as i64),
+                    e
+                ))
+            }
+        });
+
+        Task::done(Message::Refresh)
+    }
+
+    /// Gaps of these can be fetched from the exchange, others only get recorded live
+    fn can_backfill(ticker: &Ticker, kind: RetainedData) -> bool {
+        match kind {
+            RetainedData::Trades => ticker.exchange.supports_trade_backfill(),
+            RetainedData::Klines(_) => true,
+            _ => false,
+        }
+    }
+
+    async fn backfill_klines(
+        db_manager: Arc<data::db::DatabaseManager>,
+        ticker_info: TickerInfo,
+        timeframe: Timeframe,
+        (start, end): (u64, u64),
+    ) -> Result<usize, String> {
+        let batch = timeframe.to_milliseconds() * KLINE_BACKFILL_BATCH;
+        let mut stored = 0;
+
+        for from in (start..end).step_by(batch as usize) {
+            let to = (from + batch).min(end);
+
+            let klines = adapter::fetch_klines(ticker_info, timeframe, Some((from, to - 1)))
+                .await
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|kline| kline.time >= from && kline.time < to)
+                .collect::<Vec<_>>();
+
+            stored += db_manager
+                .insert_klines(&ticker_info, timeframe, &klines)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(stored)
     }
 
     fn format_number(n: i64) -> String {
@@ -69,18 +386,100 @@ impl DatabaseManager {
         self.db_manager = db_manager;
     }
 
+    /// Run the export or import requested with [`Action::Transfer`], `None` if the ticker isn't listed
+    pub fn transfer(&mut self, ticker_info: Option<TickerInfo>) -> Task<Message> {
+        let Some(kind) = self.transfer.pending.take() else {
+            return Task::none();
+        };
+        let Some(db_manager) = self.db_manager.clone() else {
+            return Task::none();
+        };
+        let Some(ticker_info) = ticker_info else {
+            self.transfer.result = Some(Err(format!(
+                "{} isn't listed on {}",
+                self.transfer.symbol.trim(),
+                self.transfer.exchange
+            )));
+            return Task::none();
+        };
+
+        let table = self.transfer.table;
+
+        let task = match kind {
+            TransferKind::Export => {
+                let range = match self.transfer.range() {
+                    Ok(range) => range,
+                    Err(e) => {
+                        self.transfer.result = Some(Err(e));
+                        return Task::none();
+                    }
+                };
+                let format = self.transfer.format;
+                let path = self.transfer.export_path(range);
+
+                Task::perform(
+                    async move {
+                        data::db::transfer::export(
+                            &db_manager,
+                            table,
+                            &ticker_info,
+                            range,
+                            format,
+                            &path,
+                        )
+                        .map(|rows| {
+                            format!(
+                                "Exported {} rows to {}",
+                                Self::format_number(rows as i64),
+                                path.display()
+                            )
+                        })
+                        .map_err(|e| e.to_string())
+                    },
+                    Message::TransferDone,
+                )
+            }
+            TransferKind::Import => {
+                let path = PathBuf::from(self.transfer.import_file.trim());
+
+                Task::perform(
+                    async move {
+                        data::db::transfer::import(&db_manager, table, &ticker_info, &path)
+                            .map(|rows| {
+                                format!("Imported {} rows", Self::format_number(rows as i64))
+                            })
+                            .map_err(|e| e.to_string())
+                    },
+                    Message::TransferDone,
+                )
+            }
+        };
+
+        self.transfer.running = Some(kind);
+        self.transfer.result = None;
+
+        task
+    }
+
     pub fn update(&mut self, message: Message) -> (Task<Message>, Option<Action>) {
         match message {
             Message::Refresh => {
                 if let Some(db_manager) = self.db_manager.clone() {
                     self.loading = true;
 
-                    let task = Task::perform(
-                        async move { Self::fetch_stats(db_manager).await },
+                    let stats = Task::perform(
+                        {
+                            let db_manager = Arc::clone(&db_manager);
+                            async move { Self::fetch_stats(db_manager).await }
+                        },
                         Message::StatsLoaded,
                     );
+                    let coverage = Task::perform(
+                        async move { Self::fetch_coverage(db_manager).await },
+                        Message::CoverageLoaded,
+                    );
 
-                    return (task, None);
+                    return (Task::batch([stats, coverage]), None);
                 }
             }
             Message::StatsLoaded(result) => {
@@ -95,11 +494,262 @@ impl DatabaseManager {
                     }
                 }
             }
+            Message::ArchiveDirChanged(dir) => {
+                self.archive_dir = dir;
+            }
+            Message::ImportArchives => {
+                if let Some(db_manager) = self.db_manager.clone() {
+                    self.importing = true;
+                    self.import_result = None;
+
+                    let dir = PathBuf::from(&self.archive_dir);
+
+                    return (
+                        Task::perform(
+                            Self::import_archives(db_manager, dir),
+                            Message::ArchivesImported,
+                        ),
+                        None,
+                    );
+                }
+            }
+            Message::ArchivesImported(result) => {
+                self.importing = false;
+
+                if let Err(e) = &result {
+                    log::error!("Failed to import Hyperliquid archives: {}", e);
+                }
+                self.import_result = Some(result);
+
+                return (Task::done(Message::Refresh), None);
+            }
+            Message::RetentionChanged {
+                kind,
+                favorites,
+                value,
+            } => {
+                let days = if value.trim().is_empty() {
+                    None
+                } else {
+                    match value.trim().parse::<u32>() {
+                        Ok(days) if days > 0 => Some(days),
+                        _ => return (Task::none(), None),
+                    }
+                };
+
+                let windows = if favorites {
+                    &mut self.retention.favorites
+                } else {
+                    &mut self.retention.all
+                };
+                windows.set_days(kind, days);
+            }
+            Message::EnforceRetention => {
+                return (Task::none(), Some(Action::EnforceRetention));
+            }
+            Message::RetentionEnforced(result) => {
+                self.enforcing = false;
+
+                match result {
+                    Ok(summary) => {
+                        log::info!("Retention policy enforced: {}", summary);
+
+                        let message = summary.to_string();
+                        self.retention_result = Some(Ok(message.clone()));
+
+                        if summary.total_rows() > 0 {
+                            return (
+                                Task::done(Message::Refresh),
+                                Some(Action::RetentionEnforced(Ok(message))),
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Failed to enforce retention policy: {}", e);
+                        self.retention_result = Some(Err(e.clone()));
+
+                        return (Task::none(), Some(Action::RetentionEnforced(Err(e))));
+                    }
+                }
+            }
+            Message::TransferExchangeSelected(exchange) => self.transfer.exchange = exchange,
+            Message::TransferSymbolChanged(symbol) => self.transfer.symbol = symbol,
+            Message::TransferTableSelected(table) => self.transfer.table = table,
+            Message::TransferFormatSelected(format) => self.transfer.format = format,
+            Message::TransferFromChanged(from) => self.transfer.from = from,
+            Message::TransferToChanged(to) => self.transfer.to = to,
+            Message::ExportDirChanged(dir) => self.transfer.export_dir = dir,
+            Message::ImportFileChanged(file) => self.transfer.import_file = file,
+            Message::Export | Message::Import => {
+                let symbol = self.transfer.symbol.trim();
+
+                if symbol.is_empty() {
+                    self.transfer.result = Some(Err("Enter the ticker's symbol".to_string()));
+                } else {
+                    self.transfer.pending = Some(if matches!(message, Message::Export) {
+                        TransferKind::Export
+                    } else {
+                        TransferKind::Import
+                    });
+
+                    let ticker = Ticker::new(symbol, self.transfer.exchange);
+                    return (Task::none(), Some(Action::Transfer(ticker)));
+                }
+            }
+            Message::TransferDone(result) => {
+                let kind = self.transfer.running.take();
+
+                match &result {
+                    Ok(summary) => log::info!("{}", summary),
+                    Err(e) => log::error!("Failed to transfer stored data: {}", e),
+                }
+                let imported = result.is_ok() && kind == Some(TransferKind::Import);
+                self.transfer.result = Some(result);
+
+                if imported {
+                    return (Task::done(Message::Refresh), None);
+                }
+            }
+            Message::CoverageLoaded(result) => match result {
+                Ok(coverage) => self.coverage = Some(coverage),
+                Err(e) => log::error!("Failed to load data coverage: {}", e),
+            },
+            Message::Backfill { ticker, kind, gap } => {
+                if self.backfilling.is_none() {
+                    self.pending_backfill = Some((kind, gap));
+                    return (Task::none(), Some(Action::Backfill(ticker)));
+                }
+            }
+            Message::TradesBackfilled(ticker_info, trades) => {
+                if let Some(db_manager) = self.db_manager.clone() {
+                    self.storing_batches += 1;
+
+                    let task = Task::perform(
+                        async move {
+                            db_manager
+                                .insert_trades(&ticker_info, &trades)
+                                .map_err(|e| e.to_string())
+                        },
+                        Message::TradesStored,
+                    );
+                    return (task, None);
+                }
+            }
+            Message::TradesStored(result) => {
+                self.storing_batches = self.storing_batches.saturating_sub(1);
+
+                match result {
+                    Ok(count) => {
+                        if let Some(stored) = &mut self.backfilling {
+                            *stored += count;
+                        }
+                    }
+                    Err(e) => log::error!("Failed to store backfilled trades: {}", e),
+                }
+
+                if self.storing_batches == 0
+                    && let Some(result) = self.fetched.take()
+                {
+                    return (self.finish_backfill(result), None);
+                }
+            }
+            Message::Backfilled(result) => {
+                // the count would miss batches still being stored
+                if self.storing_batches > 0 {
+                    self.fetched = Some(result);
+                } else {
+                    return (self.finish_backfill(result), None);
+                }
+            }
+            Message::DeleteRange {
+                ticker,
+                kind,
+                range,
+            } => {
+                if self.confirm_delete != Some((ticker, kind, range)) {
+                    self.confirm_delete = Some((ticker, kind, range));
+                } else if let Some(db_manager) = self.db_manager.clone() {
+                    self.confirm_delete = None;
+                    self.deleting = true;
+                    self.coverage_result = None;
+
+                    let task = Task::perform(
+                        async move {
+                            data::db::coverage::delete(&db_manager, &ticker, kind, range)
+                                .map(|count| {
+                                    format!(
+                                        "Deleted {} rows of {} {} from {}",
+                                        Self::format_number(count as i64),
+                                        ticker,
+                                        kind,
+                                        range
+                                    )
+                                })
+                                .map_err(|e| e.to_string())
+                        },
+                        Message::RangeDeleted,
+                    );
+
+                    return (task, None);
+                }
+            }
+            Message::RangeDeleted(result) => {
+                self.deleting = false;
+
+                if let Err(e) = &result {
+                    log::error!("Failed to delete range: {}", e);
+                }
+                self.coverage_result = Some(result);
+
+                return (Task::done(Message::Refresh), None);
+            }
         }
 
         (Task::none(), None)
     }
 
+    /// Reads Hyperliquid's trade and order book archives into the database
+    ///
+    /// Coins are matched against the currently listed markets, for their tick sizes.
+    async fn import_archives(
+        db_manager: Arc<data::db::DatabaseManager>,
+        dir: PathBuf,
+    ) -> Result<String, String> {
+        let mut tickers = vec![];
+
+        for exchange in [Exchange::HyperliquidLinear, Exchange::HyperliquidSpot] {
+            let infos = fetch_ticker_info(exchange)
+                .await
+                .map_err(|e| format!("Failed to fetch {exchange} markets: {e}"))?;
+
+            tickers.extend(infos.into_values().flatten());
+        }
+
+        let importer = data::db::HyperliquidArchiveImporter::new(
+            data::db::MigrationConfig::default(),
+            tickers,
+        );
+        let stats = importer
+            .import_dir(&db_manager, &dir)
+            .map_err(|e| e.to_string())?;
+
+        for error in &stats.errors {
+            log::warn!("Hyperliquid import: {}", error);
+        }
+
+        Ok(format!(
+            "Imported {} trades and {} depth snapshots from {} files{}",
+            Self::format_number(stats.trades_migrated as i64),
+            Self::format_number(stats.snapshots_migrated as i64),
+            stats.files_processed,
+            if stats.has_errors() {
+                format!(", {} warnings in the log", stats.errors.len())
+            } else {
+                String::new()
+            }
+        ))
+    }
+
     async fn fetch_stats(
         db_manager: Arc<data::db::DatabaseManager>,
     ) -> Result<DatabaseStats, String> {
@@ -141,7 +791,7 @@ impl DatabaseManager {
             let mut ticker_stmt = conn
                 .prepare(
                     "SELECT
-                        t.symbol,
+                        COALESCE(d.display_symbol, t.symbol),
                         e.name as exchange,
                         COALESCE((SELECT COUNT(*) FROM trades tr WHERE tr.ticker_id = t.ticker_id), 0) as trade_count,
                         COALESCE((SELECT COUNT(*) FROM klines k WHERE k.ticker_id = t.ticker_id), 0) as kline_count,
@@ -149,6 +799,7 @@ impl DatabaseManager {
                         (SELECT MAX(timestamp) FROM trades tr WHERE tr.ticker_id = t.ticker_id) as last_trade_ts
                      FROM tickers t
                      JOIN exchanges e ON t.exchange_id = e.exchange_id
+                     LEFT JOIN ticker_display_symbols d ON d.ticker_id = t.ticker_id
                      ORDER BY trade_count DESC",
                 )
                 .map_err(|e| DatabaseError::Query(format!("Failed to prepare query: {}", e)))?;
@@ -190,6 +841,12 @@ impl DatabaseManager {
         stats_result.map_err(|e: data::db::DatabaseError| e.to_string())
     }
 
+    async fn fetch_coverage(
+        db_manager: Arc<data::db::DatabaseManager>,
+    ) -> Result<Vec<Coverage>, String> {
+        data::db::coverage::query(&db_manager).map_err(|e| e.to_string())
+    }
+
     pub fn view(&self) -> Element<Message> {
         let title = text("Database Manager").size(20).width(Length::Fill);
 
@@ -199,6 +856,44 @@ impl DatabaseManager {
             .spacing(10)
             .align_y(Alignment::Center);
 
+        let import_section = {
+            let import_button = button(if self.importing {
+                "Importing..."
+            } else {
+                "Import"
+            })
+            .on_press_maybe(
+                (!self.importing && self.db_manager.is_some()).then_some(Message::ImportArchives),
+            );
+
+            let hint =
+                "node_trades and l2Book files from Hyperliquid's S3 buckets, .lz4 or unpacked";
+
+            let mut section = column![
+                text("Hyperliquid archives").size(16),
+                text(hint).size(12),
+                row![
+                    text_input("Archive folder", &self.archive_dir)
+                        .on_input(Message::ArchiveDirChanged),
+                    import_button,
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            ]
+            .spacing(6);
+
+            if let Some(result) = &self.import_result {
+                let (Ok(message) | Err(message)) = result;
+                section = section.push(text(message.as_str()).size(12));
+            }
+
+            section
+        };
+
+        let retention_section = self.retention_view();
+        let transfer_section = self.transfer_view();
+        let coverage_section = self.coverage_view();
+
         let content = if self.loading {
             column![text("Loading statistics...").size(16)]
                 .spacing(10)
@@ -217,7 +912,20 @@ impl DatabaseManager {
         let main_content = column![
             header,
             rule::horizontal(1.0),
-            scrollable(content),
+            import_section,
+            rule::horizontal(1.0),
+            scrollable(
+                column![
+                    retention_section,
+                    rule::horizontal(1.0),
+                    transfer_section,
+                    rule::horizontal(1.0),
+                    coverage_section,
+                    rule::horizontal(1.0),
+                    content,
+                ]
+                .spacing(10)
+            ),
         ]
         .spacing(10)
         .padding(20)
@@ -236,6 +944,246 @@ impl DatabaseManager {
             .into()
     }
 
+    fn retention_view(&self) -> iced::widget::Column<Message> {
+        let days_input = |kind: RetainedData, favorites: bool| {
+            let windows = if favorites {
+                &self.retention.favorites
+            } else {
+                &self.retention.all
+            };
+            let value = windows
+                .days(kind)
+                .map(|days| days.to_string())
+                .unwrap_or_default();
+
+            text_input("Keep", &value)
+                .on_input(move |value| Message::RetentionChanged {
+                    kind,
+                    favorites,
+                    value,
+                })
+                .width(Length::Fixed(100.0))
+        };
+
+        let mut section = column![
+            text("Retention").size(16),
+            text("Days of data kept, empty keeps everything. Favorited tickers keep the longer of both. Footprints go with the klines of their timeframe.")
+                .size(12),
+            row![
+                space::Space::new().width(Length::Fixed(150.0)),
+                text("All tickers").size(12).width(Length::Fixed(100.0)),
+                text("Favorites").size(12).width(Length::Fixed(100.0)),
+            ]
+            .spacing(10),
+        ]
+        .spacing(6);
+
+        for kind in RetainedData::all() {
+            section = section.push(
+                row![
+                    text(kind.to_string()).width(Length::Fixed(150.0)),
+                    days_input(kind, false),
+                    days_input(kind, true),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        let enforce_button = button(if self.enforcing {
+            "Deleting..."
+        } else {
+            "Apply now"
+        })
+        .on_press_maybe(
+            (!self.enforcing && self.db_manager.is_some()).then_some(Message::EnforceRetention),
+        );
+
+        let mut footer = row![enforce_button].spacing(10).align_y(Alignment::Center);
+        if let Some(result) = &self.retention_result {
+            let (Ok(message) | Err(message)) = result;
+            footer = footer.push(text(message.as_str()).size(12));
+        }
+
+        section.push(footer)
+    }
+
+    fn transfer_view(&self) -> iced::widget::Column<Message> {
+        let form = &self.transfer;
+        let idle = form.running.is_none() && self.db_manager.is_some();
+
+        let button_label = |kind: TransferKind, label: &'static str| {
+            if form.running == Some(kind) {
+                "Working..."
+            } else {
+                label
+            }
+        };
+
+        let export_button = button(button_label(TransferKind::Export, "Export"))
+            .on_press_maybe(idle.then_some(Message::Export));
+        let import_button = button(button_label(TransferKind::Import, "Import"))
+            .on_press_maybe(idle.then_some(Message::Import));
+
+        let mut section = column![
+            text("Export and import").size(16),
+            text(format!(
+                "Columns are documented in data::db::transfer, times as {TIME_FORMAT} in UTC"
+            ))
+            .size(12),
+            row![
+                pick_list(
+                    Exchange::ALL,
+                    Some(form.exchange),
+                    Message::TransferExchangeSelected
+                ),
+                text_input("Symbol, e.g. BTCUSDT", &form.symbol)
+                    .on_input(Message::TransferSymbolChanged),
+                pick_list(
+                    StoredTable::ALL,
+                    Some(form.table),
+                    Message::TransferTableSelected
+                ),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text_input("From", &form.from).on_input(Message::TransferFromChanged),
+                text_input("To", &form.to).on_input(Message::TransferToChanged),
+                pick_list(
+                    FileFormat::ALL,
+                    Some(form.format),
+                    Message::TransferFormatSelected
+                ),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text_input("Export folder", &form.export_dir).on_input(Message::ExportDirChanged),
+                export_button,
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text_input("File to import, .parquet or .csv", &form.import_file)
+                    .on_input(Message::ImportFileChanged),
+                import_button,
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(6);
+
+        if let Some(result) = &form.result {
+            let (Ok(message) | Err(message)) = result;
+            section = section.push(text(message.as_str()).size(12));
+        }
+
+        section
+    }
+
+    fn coverage_view(&self) -> iced::widget::Column<Message> {
+        let mut section = column![
+            text("Coverage").size(16),
+            text("Stored days per ticker and data type, in UTC. Sizes are estimates.").size(12),
+        ]
+        .spacing(6);
+
+        let Some(coverages) = &self.coverage else {
+            return section.push(text("Click Refresh to load coverage").size(12));
+        };
+        if coverages.is_empty() {
+            return section.push(text("Nothing stored yet").size(12));
+        }
+
+        let idle = self.backfilling.is_none() && !self.deleting;
+
+        if let Some(stored) = self.backfilling {
+            section = section.push(
+                text(format!(
+                    "Backfilling, {} rows stored...",
+                    Self::format_number(stored as i64)
+                ))
+                .size(12),
+            );
+        } else if let Some(result) = &self.coverage_result {
+            let (Ok(message) | Err(message)) = result;
+            section = section.push(text(message.as_str()).size(12));
+        }
+
+        for coverage in coverages {
+            let ticker = coverage.ticker;
+            let kind = coverage.kind;
+
+            let mut entries = column![
+                row![
+                    text(format!("{} ({}) {}", ticker, ticker.exchange, kind))
+                        .size(14)
+                        .width(Length::Fill),
+                    text(format!(
+                        "{} rows, {:.1} MB",
+                        Self::format_number(coverage.rows() as i64),
+                        coverage.bytes as f64 / 1024.0 / 1024.0
+                    ))
+                    .size(12),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            ]
+            .spacing(4);
+
+            let gaps = coverage.gaps();
+
+            for (i, covered) in coverage.ranges.iter().enumerate() {
+                let range = covered.days;
+                let confirming = self.confirm_delete == Some((ticker, kind, range));
+
+                entries = entries.push(
+                    row![
+                        text(format!(
+                            "{}, {} days, {} rows",
+                            range,
+                            range.days(),
+                            Self::format_number(covered.rows as i64)
+                        ))
+                        .size(12)
+                        .width(Length::Fill),
+                        button(text(if confirming { "Confirm" } else { "Delete" }).size(12))
+                            .on_press_maybe(idle.then_some(Message::DeleteRange {
+                                ticker,
+                                kind,
+                                range,
+                            })),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                );
+
+                if let Some(&gap) = gaps.get(i) {
+                    let mut gap_row = row![
+                        text(format!("Missing {}, {} days", gap, gap.days()))
+                            .size(12)
+                            .width(Length::Fill),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center);
+
+                    if Self::can_backfill(&ticker, kind) {
+                        gap_row = gap_row.push(button(text("Backfill").size(12)).on_press_maybe(
+                            idle.then_some(Message::Backfill { ticker, kind, gap }),
+                        ));
+                    }
+
+                    entries = entries.push(gap_row);
+                }
+            }
+
+            section = section.push(entries);
+        }
+
+        section
+    }
+
     fn stats_view(&self, stats: &DatabaseStats) -> iced::widget::Column<Message> {
         // Overview section
         let overview = column![
@@ -0,0 +1,164 @@
+use exchange::adapter::ExchangeInclusive;
+use exchange::proxy::{self, Proxy, ProxyKind};
+
+use iced::widget::{button, column, pick_list, row, text, text_input};
+use iced::{Alignment, Element};
+
+/// Connection route of a venue, as picked in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Direct,
+    Proxy(ProxyKind),
+}
+
+impl Route {
+    const ALL: [Route; 3] = [
+        Route::Direct,
+        Route::Proxy(ProxyKind::Http),
+        Route::Proxy(ProxyKind::Socks5),
+    ];
+}
+
+impl std::fmt::Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Route::Direct => write!(f, "Direct"),
+            Route::Proxy(kind) => write!(f, "{kind} proxy"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    VenueSelected(ExchangeInclusive),
+    RouteSelected(Route),
+    AddressChanged(String),
+    PasswordChanged(String),
+    Apply,
+}
+
+pub enum Action {
+    Applied(ExchangeInclusive),
+    ErrorOccurred(String),
+}
+
+/// Per venue proxy settings, editing one venue at a time
+pub struct Network {
+    venue: ExchangeInclusive,
+    route: Route,
+    /// Without the password, which has an input of its own
+    address: String,
+    password: String,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        let mut network = Self {
+            venue: ExchangeInclusive::Binance,
+            route: Route::Direct,
+            address: String::new(),
+            password: String::new(),
+        };
+        network.load(ExchangeInclusive::Binance);
+        network
+    }
+
+    fn load(&mut self, venue: ExchangeInclusive) {
+        self.venue = venue;
+
+        match proxy::proxy(venue) {
+            Some(proxy) => {
+                self.route = Route::Proxy(proxy.kind);
+                self.address = proxy.address();
+                self.password = proxy.password.unwrap_or_default();
+            }
+            None => {
+                self.route = Route::Direct;
+                self.address.clear();
+                self.password.clear();
+            }
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Option<Action> {
+        match message {
+            Message::VenueSelected(venue) => self.load(venue),
+            Message::RouteSelected(route) => self.route = route,
+            Message::AddressChanged(address) => self.address = address,
+            Message::PasswordChanged(password) => self.password = password,
+            Message::Apply => {
+                let proxy = match self.route {
+                    Route::Direct => None,
+                    Route::Proxy(kind) => match Proxy::parse(kind, &self.address) {
+                        Ok(mut proxy) => {
+                            if !self.password.is_empty() {
+                                if proxy.username.is_none() {
+                                    return Some(Action::ErrorOccurred(
+                                        "A proxy password needs a user".to_string(),
+                                    ));
+                                }
+                                proxy.password = Some(self.password.clone());
+                            }
+                            Some(proxy)
+                        }
+                        Err(err) => return Some(Action::ErrorOccurred(err)),
+                    },
+                };
+
+                proxy::set_proxy(self.venue, proxy);
+                self.load(self.venue);
+
+                return Some(Action::Applied(self.venue));
+            }
+        }
+
+        None
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let venue_picklist = pick_list(
+            ExchangeInclusive::ALL,
+            Some(self.venue),
+            Message::VenueSelected,
+        );
+
+        let route_picklist = pick_list(Route::ALL, Some(self.route), Message::RouteSelected);
+
+        let is_proxied = matches!(self.route, Route::Proxy(_));
+
+        let address_input = {
+            let input = text_input("[user@]host:port", &self.address);
+
+            if is_proxied {
+                input
+                    .on_input(Message::AddressChanged)
+                    .on_submit(Message::Apply)
+            } else {
+                input
+            }
+        };
+
+        let password_input = {
+            let input = text_input("Password, not saved", &self.password).secure(true);
+
+            if is_proxied {
+                input
+                    .on_input(Message::PasswordChanged)
+                    .on_submit(Message::Apply)
+            } else {
+                input
+            }
+        };
+
+        column![
+            row![venue_picklist, route_picklist]
+                .spacing(4)
+                .align_y(Alignment::Center),
+            address_input,
+            password_input,
+            button(text("Apply")).on_press(Message::Apply),
+        ]
+        .spacing(8)
+        .into()
+    }
+}
@@ -0,0 +1,179 @@
+use crate::screen::dashboard::ReplayControl;
+use crate::style;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use iced::widget::{button, column, container, pick_list, row, slider, space, text, text_input};
+use iced::{Alignment, Element};
+
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Window preselected when the modal opens
+const DEFAULT_WINDOW_MS: u64 = 60 * 60 * 1000;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    FromChanged(String),
+    ToChanged(String),
+    Start,
+    Play,
+    Pause,
+    Stop,
+    SeekChanged(f64),
+    SeekReleased,
+    SpeedSelected(Speed),
+}
+
+pub enum Action {
+    Start((u64, u64)),
+    Control(ReplayControl),
+}
+
+/// Playback speed as shown in the picker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Speed(f32);
+
+impl std::fmt::Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x", self.0)
+    }
+}
+
+/// Picks a stored window of the focused pane's ticker and controls its playback
+pub struct Replay {
+    from: String,
+    to: String,
+    error: Option<String>,
+    /// Position of the seek slider while it's being dragged
+    seeking: Option<f64>,
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        let now = Utc::now().timestamp_millis() as u64;
+
+        Self {
+            from: format_time(now - DEFAULT_WINDOW_MS),
+            to: format_time(now),
+            error: None,
+            seeking: None,
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Option<Action> {
+        match message {
+            Message::FromChanged(value) => self.from = value,
+            Message::ToChanged(value) => self.to = value,
+            Message::Start => match (parse_time(&self.from), parse_time(&self.to)) {
+                (Some(from), Some(to)) if from < to => {
+                    self.error = None;
+                    return Some(Action::Start((from, to)));
+                }
+                (Some(_), Some(_)) => {
+                    self.error = Some("The window has to end after it starts".to_string());
+                }
+                _ => {
+                    self.error = Some(format!("Times are expected as {TIME_FORMAT}, in UTC"));
+                }
+            },
+            Message::Play => return Some(Action::Control(ReplayControl::Play)),
+            Message::Pause => return Some(Action::Control(ReplayControl::Pause)),
+            Message::Stop => return Some(Action::Control(ReplayControl::Stop)),
+            Message::SeekChanged(position) => self.seeking = Some(position),
+            Message::SeekReleased => {
+                if let Some(position) = self.seeking.take() {
+                    return Some(Action::Control(ReplayControl::Seek(position as u64)));
+                }
+            }
+            Message::SpeedSelected(Speed(speed)) => {
+                return Some(Action::Control(ReplayControl::Speed(speed)));
+            }
+        }
+
+        None
+    }
+
+    pub fn view(&self, replay: Option<&data::replay::Replay>) -> Element<'_, Message> {
+        let window = {
+            let mut section = column![
+                text("Window (UTC)").size(14),
+                row![
+                    text_input("From", &self.from).on_input(Message::FromChanged),
+                    text_input("To", &self.to).on_input(Message::ToChanged),
+                ]
+                .spacing(8),
+                button(text("Load")).on_press(Message::Start),
+                text("Replays into the focused pane and its link group").size(10),
+            ]
+            .spacing(8);
+
+            if let Some(error) = &self.error {
+                section = section.push(text(error.as_str()).size(12));
+            }
+
+            section
+        };
+
+        let playback = match replay {
+            Some(replay) => {
+                let (start, end) = replay.range();
+                let position = self
+                    .seeking
+                    .unwrap_or(replay.position() as f64)
+                    .clamp(start as f64, end as f64);
+
+                let play_button = if replay.is_playing() {
+                    button(text("Pause")).on_press(Message::Pause)
+                } else {
+                    button(text("Play"))
+                        .on_press_maybe((!replay.is_finished()).then_some(Message::Play))
+                };
+
+                let speeds = data::replay::SPEEDS.map(Speed);
+
+                column![
+                    row![
+                        text(replay.ticker_info().ticker.to_string()).size(14),
+                        space::horizontal(),
+                        text(
+                            DateTime::<Utc>::from_timestamp_millis(position as i64)
+                                .map(|dt| dt.format("%H:%M:%S").to_string())
+                                .unwrap_or_default()
+                        ),
+                    ]
+                    .align_y(Alignment::Center),
+                    slider(start as f64..=end as f64, position, Message::SeekChanged)
+                        .step(1000.0)
+                        .on_release(Message::SeekReleased),
+                    row![
+                        play_button,
+                        pick_list(speeds, Some(Speed(replay.speed())), Message::SpeedSelected),
+                        space::horizontal(),
+                        button(text("Stop")).on_press(Message::Stop),
+                    ]
+                    .spacing(8)
+                    .align_y(Alignment::Center),
+                ]
+                .spacing(8)
+            }
+            None => column![text("No replay running").size(12)],
+        };
+
+        container(column![window, playback].spacing(20))
+            .max_width(320)
+            .padding(24)
+            .style(style::dashboard_modal)
+            .into()
+    }
+}
+
+pub fn format_time(time: u64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(time as i64)
+        .map(|dt| dt.format(TIME_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+pub fn parse_time(value: &str) -> Option<u64> {
+    NaiveDateTime::parse_from_str(value.trim(), TIME_FORMAT)
+        .ok()
+        .map(|dt| dt.and_utc().timestamp_millis() as u64)
+}
@@ -1,11 +1,24 @@
 pub mod dashboard;
 
+use exchange::adapter::AdapterError;
+
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum DashboardError {
     #[error("Fetch error: {0}")]
     Fetch(String),
+    #[error("{0}")]
+    RateLimited(String),
     #[error("Pane set error: {0}")]
     PaneSet(String),
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
+
+impl From<AdapterError> for DashboardError {
+    fn from(err: AdapterError) -> Self {
+        match err {
+            AdapterError::RateLimited { .. } => DashboardError::RateLimited(err.to_string()),
+            _ => DashboardError::Fetch(err.to_string()),
+        }
+    }
+}
@@ -13,15 +13,21 @@ use crate::{
     widget::toast::Toast,
     window::{self, Window},
 };
-use data::{UserTimezone, chart::Basis, layout::WindowSpec};
+use data::{
+    UserTimezone,
+    chart::{Basis, heatmap::OrderRun, kline::KlineTrades},
+    layout::WindowSpec,
+};
 use exchange::{
-    Kline, PushFrequency, TickMultiplier, TickerInfo, Timeframe, Trade,
+    FundingRate, Kline, Liquidation, OpenInterest, PushFrequency, TickMultiplier, Ticker,
+    TickerInfo, Timeframe, Trade,
     adapter::{
-        self, AdapterError, Exchange, PersistStreamKind, ResolvedStream, StreamConfig, StreamKind,
-        StreamTicksize, UniqueStreams, aster, binance, bybit, hyperliquid, okex,
+        self, AdapterError, ConnectionState, Exchange, PersistStreamKind, ResolvedStream,
+        StreamConfig, StreamKind, StreamTicksize, UniqueStreams, mux, telemetry,
     },
     depth::Depth,
     fetcher::{FetchRange, FetchedData},
+    util::{Price, PriceStep},
 };
 
 use iced::{
@@ -33,7 +39,14 @@ use iced::{
     },
 };
 use iced_futures::futures::TryFutureExt;
-use std::{collections::HashMap, path::PathBuf, time::Instant, vec};
+use rustc_hash::FxHashSet;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+    vec,
+};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -49,6 +62,49 @@ pub enum Message {
         data: FetchedData,
     },
     ResolveStreams(uuid::Uuid, Vec<PersistStreamKind>),
+    StoredFootprintsLoaded {
+        pane_id: uuid::Uuid,
+        stream: StreamKind,
+        footprints: BTreeMap<u64, KlineTrades>,
+        timeframe: Timeframe,
+        range: (u64, u64),
+    },
+    DepthHistoryLoaded {
+        pane_id: uuid::Uuid,
+        ticker_info: TickerInfo,
+        runs: Vec<(Price, OrderRun)>,
+    },
+    DepthSnapshotsLoaded {
+        pane_id: uuid::Uuid,
+        ticker_info: TickerInfo,
+        snapshots: Vec<(u64, Depth)>,
+    },
+    LiquidationsLoaded {
+        pane_id: uuid::Uuid,
+        ticker_info: TickerInfo,
+        liquidations: Vec<Liquidation>,
+    },
+    ReplayLoaded {
+        pane_ids: Vec<uuid::Uuid>,
+        recording: Arc<data::replay::Recording>,
+    },
+}
+
+/// Playback controls of a running replay
+#[derive(Debug, Clone, Copy)]
+pub enum ReplayControl {
+    Play,
+    Pause,
+    Seek(u64),
+    Speed(f32),
+    Stop,
+}
+
+/// A stored session played back into the panes it was started from
+struct ReplaySession {
+    replay: data::replay::Replay,
+    pane_ids: Vec<uuid::Uuid>,
+    last_tick: Option<Instant>,
 }
 
 pub struct Dashboard {
@@ -58,6 +114,7 @@ pub struct Dashboard {
     pub streams: UniqueStreams,
     layout_id: uuid::Uuid,
     db_manager: Option<std::sync::Arc<data::db::DatabaseManager>>,
+    replay: Option<ReplaySession>,
 }
 
 impl Default for Dashboard {
@@ -69,6 +126,7 @@ impl Default for Dashboard {
             popout: HashMap::new(),
             layout_id: uuid::Uuid::new_v4(),
             db_manager: None,
+            replay: None,
         }
     }
 }
@@ -137,6 +195,7 @@ impl Dashboard {
             popout,
             layout_id,
             db_manager,
+            replay: None,
         }
     }
 
@@ -187,20 +246,9 @@ impl Dashboard {
                     }
                 }
             }
-            Message::ErrorOccurred(pane_id, err) => match pane_id {
-                Some(id) => {
-                    if let Some(state) = self.get_mut_pane_state_by_uuid(main_window.id, id) {
-                        state.status = pane::Status::Ready;
-                        state.notifications.push(Toast::error(err.to_string()));
-                    }
-                }
-                _ => {
-                    return (
-                        Task::done(Message::Notification(Toast::error(err.to_string()))),
-                        None,
-                    );
-                }
-            },
+            Message::ErrorOccurred(pane_id, err) => {
+                return (self.handle_error(pane_id, &err, main_window.id), None);
+            }
             Message::Pane(window, message) => match message {
                 pane::Message::PaneClicked(pane) => {
                     self.focus = Some((window, pane));
@@ -391,7 +439,12 @@ impl Dashboard {
                                         if let StreamKind::Kline { .. } = stream {
                                             return (
                                                 kline_fetch_task(
-                                                    *layout_id, pane_id, *stream, None, None,
+                                                    *layout_id,
+                                                    pane_id,
+                                                    *stream,
+                                                    None,
+                                                    None,
+                                                    self.db_manager.clone(),
                                                 ),
                                                 None,
                                             );
@@ -639,6 +692,7 @@ impl Dashboard {
                                                     kline_stream,
                                                     None,
                                                     None,
+                                                    self.db_manager.clone(),
                                                 );
                                                 return (
                                                     self.refresh_streams(main_window.id)
@@ -785,6 +839,123 @@ impl Dashboard {
             Message::Notification(toast) => {
                 return (Task::none(), Some(Event::Notification(toast)));
             }
+            Message::StoredFootprintsLoaded {
+                pane_id,
+                stream,
+                footprints,
+                timeframe,
+                range: (from_time, to_time),
+            } => {
+                let gaps = stored_trade_gaps(&footprints, timeframe, from_time, to_time);
+
+                if let Some(state) = self.get_mut_pane_state_by_uuid(main_window.id, pane_id) {
+                    let should_backfill = exchange::fetcher::is_trade_fetch_enabled()
+                        && stream.ticker_info().exchange().supports_trade_backfill()
+                        && !gaps.is_empty();
+
+                    if let pane::Content::Kline { chart: Some(c), .. } = &mut state.content {
+                        c.insert_stored_footprints(footprints, !should_backfill);
+                    }
+
+                    if should_backfill
+                        && let StreamKind::DepthAndTrades { ticker_info, .. } = stream
+                    {
+                        let task = trades_fetch_task(
+                            state,
+                            *layout_id,
+                            pane_id,
+                            ticker_info,
+                            stream,
+                            gaps,
+                        );
+                        return (task, None);
+                    }
+
+                    state.status = pane::Status::Ready;
+                }
+            }
+            Message::DepthHistoryLoaded {
+                pane_id,
+                ticker_info,
+                runs,
+            } => {
+                if let Some(state) = self.get_mut_pane_state_by_uuid(main_window.id, pane_id)
+                    && state.stream_pair() == Some(ticker_info)
+                    && let pane::Content::Heatmap { chart: Some(c), .. } = &mut state.content
+                {
+                    c.insert_depth_history(runs);
+                }
+            }
+            Message::DepthSnapshotsLoaded {
+                pane_id,
+                ticker_info,
+                snapshots,
+            } => {
+                if let Some(state) = self.get_mut_pane_state_by_uuid(main_window.id, pane_id)
+                    && state.stream_pair() == Some(ticker_info)
+                    && let pane::Content::Heatmap { chart: Some(c), .. } = &mut state.content
+                {
+                    c.insert_depth_snapshots(&snapshots);
+                }
+            }
+            Message::LiquidationsLoaded {
+                pane_id,
+                ticker_info,
+                liquidations,
+            } => {
+                if let Some(state) = self.get_mut_pane_state_by_uuid(main_window.id, pane_id)
+                    && state.stream_pair() == Some(ticker_info)
+                {
+                    match &mut state.content {
+                        pane::Content::Kline { chart: Some(c), .. } => {
+                            c.insert_liquidations(&liquidations);
+                        }
+                        pane::Content::Heatmap { chart: Some(c), .. } => {
+                            c.insert_liquidations(&liquidations);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Message::ReplayLoaded {
+                pane_ids,
+                recording,
+            } => {
+                if recording.is_empty() {
+                    let toast = Toast::warn(format!(
+                        "Nothing was recorded for {} in that window",
+                        recording.ticker_info().ticker
+                    ));
+                    return (Task::none(), Some(Event::Notification(toast)));
+                }
+
+                let ticker_info = recording.ticker_info();
+                let mut streams = vec![];
+
+                for (_, _, state) in self.iter_all_panes(main_window.id) {
+                    if !pane_ids.contains(&state.unique_id())
+                        || state.stream_pair() != Some(ticker_info)
+                    {
+                        continue;
+                    }
+
+                    for stream in state.streams.ready_iter().into_iter().flatten() {
+                        if !streams.contains(stream) {
+                            streams.push(*stream);
+                        }
+                    }
+                }
+
+                let start = recording.range().0;
+
+                self.replay = Some(ReplaySession {
+                    replay: data::replay::Replay::new(recording, streams),
+                    pane_ids,
+                    last_tick: None,
+                });
+
+                return (self.reset_replay_panes(main_window.id, start), None);
+            }
         }
 
         (Task::none(), None)
@@ -1035,15 +1206,23 @@ impl Dashboard {
         err: &DashboardError,
         main_window: window::Id,
     ) -> Task<Message> {
+        // the pane keeps what it has, marked stale until a later fetch goes through
+        let (status, toast) = match err {
+            DashboardError::RateLimited(msg) => {
+                (pane::Status::Stale(msg.clone()), Toast::warn(msg.clone()))
+            }
+            _ => (pane::Status::Ready, Toast::error(err.to_string())),
+        };
+
         match pane_id {
             Some(id) => {
                 if let Some(state) = self.get_mut_pane_state_by_uuid(main_window, id) {
-                    state.status = pane::Status::Ready;
-                    state.notifications.push(Toast::error(err.to_string()));
+                    state.status = status;
+                    state.notifications.push(toast);
                 }
                 Task::none()
             }
-            _ => Task::done(Message::Notification(Toast::error(err.to_string()))),
+            _ => Task::done(Message::Notification(toast)),
         }
     }
 
@@ -1063,7 +1242,14 @@ impl Dashboard {
 
                     for stream in &streams {
                         if let StreamKind::Kline { .. } = stream {
-                            return kline_fetch_task(self.layout_id, pane_id, *stream, None, None);
+                            return kline_fetch_task(
+                                self.layout_id,
+                                pane_id,
+                                *stream,
+                                None,
+                                None,
+                                self.db_manager.clone(),
+                            );
                         }
                     }
                 }
@@ -1090,6 +1276,15 @@ impl Dashboard {
             self.focus = Some((main_window, *pane_id));
         }
 
+        // the replay panes go back to live data before taking the new ticker
+        if self.is_focus_replaying(main_window) {
+            let stop_replay = self.stop_replay(main_window);
+            return Task::batch([
+                stop_replay,
+                self.init_focused_pane(main_window, ticker_info, content),
+            ]);
+        }
+
         if let Some((window, selected_pane)) = self.focus
             && let Some(state) = self.get_mut_pane(main_window, window, selected_pane)
         {
@@ -1105,7 +1300,14 @@ impl Dashboard {
 
                     for stream in &streams {
                         if let StreamKind::Kline { .. } = stream {
-                            return kline_fetch_task(self.layout_id, pane_id, *stream, None, None);
+                            return kline_fetch_task(
+                                self.layout_id,
+                                pane_id,
+                                *stream,
+                                None,
+                                None,
+                                self.db_manager.clone(),
+                            );
                         }
                     }
                 }
@@ -1134,6 +1336,14 @@ impl Dashboard {
             self.focus = Some((main_window, *pane_id));
         }
 
+        if self.is_focus_replaying(main_window) {
+            let stop_replay = self.stop_replay(main_window);
+            return Task::batch([
+                stop_replay,
+                self.switch_tickers_in_group(main_window, ticker_info),
+            ]);
+        }
+
         let link_group = self.focus.and_then(|(window, pane)| {
             self.get_pane(main_window, window, pane)
                 .and_then(|state| state.link_group)
@@ -1204,6 +1414,7 @@ impl Dashboard {
         let ticker_info = match &stream_type {
             StreamKind::Kline { ticker_info, .. } => Some(ticker_info),
             StreamKind::DepthAndTrades { ticker_info, .. } => Some(ticker_info),
+            StreamKind::Liquidations { .. } => None,
         };
 
         if let Some(ticker_info) = ticker_info {
@@ -1252,6 +1463,19 @@ impl Dashboard {
                     }
                 }
             }
+            FetchedData::Funding {
+                data,
+                predicted,
+                req_id,
+            } => {
+                if let Some(pane_state) = self.get_mut_pane_state_by_uuid(main_window, pane_id) {
+                    pane_state.status = pane::Status::Ready;
+
+                    if let StreamKind::Kline { .. } = stream_type {
+                        pane_state.insert_funding_vec(req_id, &data, predicted);
+                    }
+                }
+            }
         }
 
         Task::none()
@@ -1293,9 +1517,34 @@ impl Dashboard {
                         Ok(())
                     }
                 }
-                FetchedData::OI { .. } => {
-                    // Open interest persistence can be added in the future
-                    Ok(())
+                FetchedData::OI { data: oi, .. } => {
+                    use data::db::OpenInterestCRUD;
+                    db_manager
+                        .insert_open_interest(ticker_info, oi)
+                        .map(|count| {
+                            if count > 0 {
+                                log::info!(
+                                    "✓ Persisted {} open interest points to database for {:?}",
+                                    count,
+                                    ticker_info.ticker
+                                );
+                            }
+                        })
+                }
+                // Predicted rates are not final until settlement, only store settled ones
+                FetchedData::Funding { data: funding, .. } => {
+                    use data::db::FundingRateCRUD;
+                    db_manager
+                        .insert_funding_rates(ticker_info, funding)
+                        .map(|count| {
+                            if count > 0 {
+                                log::info!(
+                                    "✓ Persisted {} funding rates to database for {:?}",
+                                    count,
+                                    ticker_info.ticker
+                                );
+                            }
+                        })
                 }
             };
 
@@ -1358,16 +1607,13 @@ impl Dashboard {
         main_window: window::Id,
     ) -> Task<Message> {
         let mut found_match = false;
+        let replaying = self.replaying_panes();
 
         self.iter_all_panes_mut(main_window)
             .for_each(|(_, _, pane_state)| {
-                if pane_state.matches_stream(stream) {
-                    if let pane::Content::Kline { chart, .. } = &mut pane_state.content
-                        && let Some(c) = chart
-                    {
-                        c.update_latest_kline(kline);
-                    }
-
+                if pane_state.matches_stream(stream) && !replaying.contains(&pane_state.unique_id())
+                {
+                    insert_kline(pane_state, kline);
                     found_match = true;
                 }
             });
@@ -1380,6 +1626,34 @@ impl Dashboard {
         }
     }
 
+    pub fn update_connection_state(
+        &mut self,
+        stream: &StreamKind,
+        state: ConnectionState,
+        main_window: window::Id,
+    ) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let replaying = self.replaying_panes();
+
+        self.iter_all_panes_mut(main_window)
+            .for_each(|(_, _, pane_state)| {
+                if !pane_state.matches_stream(stream) || replaying.contains(&pane_state.unique_id())
+                {
+                    return;
+                }
+
+                let Some((from, to)) = pane_state.set_connection_state(*stream, state, now) else {
+                    return;
+                };
+
+                match &mut pane_state.content {
+                    pane::Content::Kline { chart: Some(c), .. } => c.insert_gap(from, to),
+                    pane::Content::Heatmap { chart: Some(c), .. } => c.insert_gap(from, to),
+                    _ => {}
+                }
+            });
+    }
+
     pub fn update_depth_and_trades(
         &mut self,
         stream: &StreamKind,
@@ -1389,35 +1663,19 @@ impl Dashboard {
         main_window: window::Id,
     ) -> Task<Message> {
         let mut found_match = false;
+        let replaying = self.replaying_panes();
 
         self.iter_all_panes_mut(main_window)
             .for_each(|(_, _, pane_state)| {
-                if pane_state.matches_stream(stream) {
-                    match &mut pane_state.content {
-                        pane::Content::Heatmap { chart, .. } => {
-                            if let Some(c) = chart {
-                                c.insert_datapoint(trades_buffer, depth_update_t, depth);
-                            }
-                        }
-                        pane::Content::Kline { chart, .. } => {
-                            if let Some(c) = chart {
-                                c.insert_trades_buffer(trades_buffer);
-                            }
-                        }
-                        pane::Content::TimeAndSales(panel) => {
-                            if let Some(p) = panel {
-                                p.insert_buffer(trades_buffer);
-                            }
-                        }
-                        pane::Content::Ladder(panel) => {
-                            if let Some(panel) = panel {
-                                panel.insert_buffers(depth_update_t, depth, trades_buffer);
-                            }
-                        }
-                        _ => {
-                            log::error!("No chart found for the stream: {stream:?}");
-                        }
-                    }
+                if pane_state.matches_stream(stream) && !replaying.contains(&pane_state.unique_id())
+                {
+                    insert_depth_and_trades(
+                        pane_state,
+                        stream,
+                        depth_update_t,
+                        depth,
+                        trades_buffer,
+                    );
                     found_match = true;
                 }
             });
@@ -1430,6 +1688,238 @@ impl Dashboard {
         }
     }
 
+    /// Liquidations come from the depth & trades stream, or a stream of their own for
+    /// tickers with only candlestick charts, and get marked on every pane of the ticker
+    pub fn update_liquidation(
+        &mut self,
+        stream: &StreamKind,
+        liquidation: Liquidation,
+        main_window: window::Id,
+    ) {
+        let ticker_info = stream.ticker_info();
+        let replaying = self.replaying_panes();
+
+        self.iter_all_panes_mut(main_window)
+            .for_each(|(_, _, pane_state)| {
+                if pane_state.stream_pair() == Some(ticker_info)
+                    && !replaying.contains(&pane_state.unique_id())
+                {
+                    insert_liquidation(pane_state, &liquidation);
+                }
+            });
+    }
+
+    /// Load a stored window of the focused pane's ticker, to replay into its link group
+    pub fn start_replay(&mut self, main_window: window::Id, range: (u64, u64)) -> Task<Message> {
+        let Some(db_manager) = self.db_manager.clone() else {
+            return Task::done(Message::Notification(Toast::warn(
+                "Replay needs the database to be enabled".to_string(),
+            )));
+        };
+
+        let Some(focused) = self
+            .focus
+            .and_then(|(window, pane)| self.get_pane(main_window, window, pane))
+        else {
+            return Task::done(Message::Notification(Toast::warn(
+                "No focused pane found".to_string(),
+            )));
+        };
+
+        let Some(ticker_info) = focused.stream_pair() else {
+            return Task::done(Message::Notification(Toast::warn(
+                "Focused pane has no ticker to replay".to_string(),
+            )));
+        };
+
+        let focused_id = focused.unique_id();
+        let link_group = focused.link_group;
+
+        let pane_ids = self
+            .iter_all_panes(main_window)
+            .filter(|(_, _, state)| match link_group {
+                Some(group) => state.link_group == Some(group),
+                None => state.unique_id() == focused_id,
+            })
+            .filter(|(_, _, state)| state.stream_pair() == Some(ticker_info))
+            .map(|(_, _, state)| state.unique_id())
+            .collect::<Vec<_>>();
+
+        let stop_replay = self.stop_replay(main_window);
+
+        let load = Task::perform(
+            async move { data::replay::Recording::load(&db_manager, ticker_info, range) },
+            move |result| match result {
+                Ok(recording) => Message::ReplayLoaded {
+                    pane_ids,
+                    recording: Arc::new(recording),
+                },
+                Err(err) => Message::ErrorOccurred(None, DashboardError::Fetch(err.to_string())),
+            },
+        );
+
+        stop_replay.chain(load)
+    }
+
+    pub fn control_replay(
+        &mut self,
+        main_window: window::Id,
+        control: ReplayControl,
+    ) -> Task<Message> {
+        let Some(session) = &mut self.replay else {
+            return Task::none();
+        };
+
+        match control {
+            ReplayControl::Play => session.replay.play(),
+            ReplayControl::Pause => session.replay.pause(),
+            ReplayControl::Speed(speed) => session.replay.set_speed(speed),
+            ReplayControl::Seek(time) => {
+                session.replay.seek(time);
+                let position = session.replay.position();
+
+                // charts can't unsee what they got past the new position
+                return self.reset_replay_panes(main_window, position);
+            }
+            ReplayControl::Stop => return self.stop_replay(main_window),
+        }
+
+        Task::none()
+    }
+
+    pub fn replay(&self) -> Option<&data::replay::Replay> {
+        self.replay.as_ref().map(|session| &session.replay)
+    }
+
+    /// Hand the replayed panes back to their live streams
+    fn stop_replay(&mut self, main_window: window::Id) -> Task<Message> {
+        let Some(session) = self.replay.take() else {
+            return Task::none();
+        };
+
+        let tasks = session
+            .pane_ids
+            .iter()
+            .map(|pane_id| self.reset_pane(main_window, *pane_id, None))
+            .collect::<Vec<_>>();
+
+        Task::batch(tasks).chain(self.refresh_streams(main_window))
+    }
+
+    /// Clear the replayed panes and hydrate their klines up to `until`
+    fn reset_replay_panes(&mut self, main_window: window::Id, until: u64) -> Task<Message> {
+        let mut tasks = self
+            .replaying_panes()
+            .into_iter()
+            .map(|pane_id| self.reset_pane(main_window, pane_id, Some(until)))
+            .collect::<Vec<_>>();
+
+        tasks.push(self.refresh_streams(main_window));
+
+        Task::batch(tasks)
+    }
+
+    /// Give a pane a fresh chart of its ticker, with klines up to `until` or to now if not set
+    fn reset_pane(
+        &mut self,
+        main_window: window::Id,
+        pane_id: uuid::Uuid,
+        until: Option<u64>,
+    ) -> Task<Message> {
+        let layout_id = self.layout_id;
+        let db_manager = self.db_manager.clone();
+
+        let Some(state) = self.get_mut_pane_state_by_uuid(main_window, pane_id) else {
+            return Task::none();
+        };
+        let Some(ticker_info) = state.stream_pair() else {
+            return Task::none();
+        };
+
+        match state.set_content_and_streams(ticker_info, &state.content.identifier_str()) {
+            Ok(streams) => Task::batch(streams.into_iter().filter_map(|stream| {
+                let StreamKind::Kline { timeframe, .. } = stream else {
+                    return None;
+                };
+                let range = until.map(|until| {
+                    let interval = timeframe.to_milliseconds();
+                    (
+                        until.saturating_sub(interval * DEFAULT_KLINE_HYDRATION_COUNT),
+                        until,
+                    )
+                });
+
+                Some(kline_fetch_task(
+                    layout_id,
+                    pane_id,
+                    stream,
+                    None,
+                    range,
+                    db_manager.clone(),
+                ))
+            })),
+            Err(err) => {
+                state.status = pane::Status::Ready;
+                state.notifications.push(Toast::error(err.to_string()));
+                Task::none()
+            }
+        }
+    }
+
+    fn replaying_panes(&self) -> Vec<uuid::Uuid> {
+        self.replay
+            .as_ref()
+            .map(|session| session.pane_ids.clone())
+            .unwrap_or_default()
+    }
+
+    fn is_focus_replaying(&self, main_window: window::Id) -> bool {
+        self.focus
+            .and_then(|(window, pane)| self.get_pane(main_window, window, pane))
+            .is_some_and(|state| self.replaying_panes().contains(&state.unique_id()))
+    }
+
+    /// Feed the replayed panes what the replay emitted since the last tick
+    fn advance_replay(&mut self, now: Instant, main_window: window::Id) {
+        let Some(session) = &mut self.replay else {
+            return;
+        };
+
+        // a layout that was in the background resumes where it left off
+        let elapsed = session.last_tick.map_or(Duration::ZERO, |last| {
+            now.duration_since(last).min(Duration::from_secs(1))
+        });
+        let events = session.replay.advance(elapsed);
+        session.last_tick = session.replay.is_playing().then_some(now);
+
+        if events.is_empty() {
+            return;
+        }
+
+        let pane_ids = session.pane_ids.clone();
+
+        for event in events {
+            self.iter_all_panes_mut(main_window)
+                .filter(|(_, _, state)| pane_ids.contains(&state.unique_id()))
+                .for_each(|(_, _, state)| match &event {
+                    exchange::Event::DepthReceived(stream, depth_update_t, depth, trades) => {
+                        if state.matches_stream(stream) {
+                            insert_depth_and_trades(state, stream, *depth_update_t, depth, trades);
+                        }
+                    }
+                    exchange::Event::KlineReceived(stream, kline) => {
+                        if state.matches_stream(stream) {
+                            insert_kline(state, kline);
+                        }
+                    }
+                    exchange::Event::LiquidationReceived(_, liquidation) => {
+                        insert_liquidation(state, liquidation);
+                    }
+                    _ => {}
+                });
+        }
+    }
+
     pub fn invalidate_all_panes(&mut self, main_window: window::Id) {
         self.iter_all_panes_mut(main_window)
             .for_each(|(_, _, state)| {
@@ -1438,8 +1928,12 @@ impl Dashboard {
     }
 
     pub fn tick(&mut self, now: Instant, main_window: window::Id) -> Task<Message> {
+        self.advance_replay(now, main_window);
+
         let mut tasks = vec![];
         let layout_id = self.layout_id;
+        let db_manager = self.db_manager.clone();
+        let replaying = self.replaying_panes();
 
         self.iter_all_panes_mut(main_window)
             .for_each(|(_window_id, _pane, state)| match state.tick(now) {
@@ -1449,7 +1943,13 @@ impl Dashboard {
                         state.notifications.push(Toast::error(err.to_string()));
                     }
                     chart::Action::FetchRequested(req_id, fetch) => {
-                        tasks.push(request_fetch(state, layout_id, req_id, fetch));
+                        tasks.push(request_fetch(
+                            state,
+                            layout_id,
+                            req_id,
+                            fetch,
+                            db_manager.clone(),
+                        ));
                     }
                 },
                 Some(pane::Action::Panel(_action)) => {}
@@ -1469,6 +1969,16 @@ impl Dashboard {
                 None => {}
             });
 
+        // replayed panes get their history from the replay, and must not store it again
+        if let Some(db_manager) = &db_manager {
+            self.iter_all_panes_mut(main_window)
+                .filter(|(_, _, state)| !replaying.contains(&state.unique_id()))
+                .for_each(|(_window_id, _pane, state)| {
+                    tasks.push(order_runs_task(state, db_manager, now));
+                    tasks.push(liquidations_task(state, db_manager));
+                });
+        }
+
         Task::batch(tasks)
     }
 
@@ -1491,7 +2001,11 @@ impl Dashboard {
             .flat_map(|(exchange, specs)| {
                 let mut subs = vec![];
 
-                if !specs.depth.is_empty() {
+                if exchange.adapter().multiplexes_market_streams() {
+                    if !specs.depth.is_empty() || !specs.liquidations.is_empty() {
+                        subs.push(shared_market_subscription(exchange));
+                    }
+                } else if !specs.depth.is_empty() {
                     let depth_subs = specs
                         .depth
                         .iter()
@@ -1527,24 +2041,144 @@ impl Dashboard {
     }
 
     fn refresh_streams(&mut self, main_window: window::Id) -> Task<Message> {
+        let replaying = self.replaying_panes();
+
         let all_pane_streams = self
             .iter_all_panes(main_window)
+            .filter(|(_, _, pane_state)| !replaying.contains(&pane_state.unique_id()))
             .flat_map(|(_, _, pane_state)| pane_state.streams.ready_iter().into_iter().flatten());
         self.streams = UniqueStreams::from(all_pane_streams);
 
-        Task::none()
-    }
-}
-
-fn request_fetch(
-    state: &mut pane::State,
-    layout_id: uuid::Uuid,
-    req_id: uuid::Uuid,
-    fetch: FetchRange,
-) -> Task<Message> {
-    let pane_id = state.unique_id();
+        // candlestick charts have no depth stream carrying liquidations, they get them alone
+        let depth_tickers = self
+            .streams
+            .depth_streams(None)
+            .into_iter()
+            .map(|(ticker_info, _, _)| ticker_info)
+            .collect::<FxHashSet<_>>();
+        let kline_only = self
+            .streams
+            .kline_streams(None)
+            .into_iter()
+            .map(|(ticker_info, _)| ticker_info)
+            .filter(|ticker_info| {
+                ticker_info.exchange().supports_liquidations()
+                    && !depth_tickers.contains(ticker_info)
+            })
+            .collect::<FxHashSet<_>>();
+        for ticker_info in kline_only {
+            self.streams.add(StreamKind::Liquidations { ticker_info });
+        }
 
-    match fetch {
+        // only the active layout is subscribed, streams none of its panes use have ended
+        let subscribed = self
+            .streams
+            .depth_streams(None)
+            .into_iter()
+            .map(
+                |(ticker_info, depth_aggr, push_freq)| StreamKind::DepthAndTrades {
+                    ticker_info,
+                    depth_aggr,
+                    push_freq,
+                },
+            )
+            .collect::<FxHashSet<_>>();
+        for (stream, _) in telemetry::streams() {
+            if !subscribed.contains(&stream) {
+                telemetry::remove(&stream);
+            }
+        }
+
+        for (exchange, specs) in self.streams.combined() {
+            if !exchange.adapter().multiplexes_market_streams() {
+                continue;
+            }
+
+            let depth_streams = specs.iter().flat_map(|specs| &specs.depth).map(
+                |(ticker_info, depth_aggr, push_freq)| StreamKind::DepthAndTrades {
+                    ticker_info: *ticker_info,
+                    depth_aggr: *depth_aggr,
+                    push_freq: *push_freq,
+                },
+            );
+            let liquidation_streams = specs
+                .iter()
+                .flat_map(|specs| &specs.liquidations)
+                .map(|&ticker_info| StreamKind::Liquidations { ticker_info });
+            mux::set_wanted(exchange, depth_streams.chain(liquidation_streams));
+        }
+
+        Task::none()
+    }
+}
+
+fn insert_depth_and_trades(
+    pane_state: &mut pane::State,
+    stream: &StreamKind,
+    depth_update_t: u64,
+    depth: &Depth,
+    trades_buffer: &[Trade],
+) {
+    match &mut pane_state.content {
+        pane::Content::Heatmap { chart, .. } => {
+            if let Some(c) = chart {
+                c.insert_datapoint(trades_buffer, depth_update_t, depth);
+            }
+        }
+        pane::Content::Kline { chart, .. } => {
+            if let Some(c) = chart {
+                c.insert_trades_buffer(trades_buffer);
+            }
+        }
+        pane::Content::TimeAndSales(panel) => {
+            if let Some(p) = panel {
+                p.insert_buffer(trades_buffer);
+            }
+        }
+        pane::Content::Ladder(panel) => {
+            if let Some(panel) = panel {
+                panel.insert_buffers(depth_update_t, depth, trades_buffer);
+            }
+        }
+        _ => {
+            log::error!("No chart found for the stream: {stream:?}");
+        }
+    }
+}
+
+fn insert_kline(pane_state: &mut pane::State, kline: &Kline) {
+    if let pane::Content::Kline { chart, .. } = &mut pane_state.content
+        && let Some(c) = chart
+    {
+        c.update_latest_kline(kline);
+    }
+}
+
+fn insert_liquidation(pane_state: &mut pane::State, liquidation: &Liquidation) {
+    match &mut pane_state.content {
+        pane::Content::Kline { chart: Some(c), .. } => {
+            c.insert_liquidations(&[*liquidation]);
+        }
+        pane::Content::Heatmap { chart: Some(c), .. } => {
+            c.insert_liquidations(&[*liquidation]);
+        }
+        pane::Content::TimeAndSales(Some(panel)) => {
+            panel.insert_liquidation(liquidation);
+        }
+        _ => {}
+    }
+}
+
+fn request_fetch(
+    state: &mut pane::State,
+    layout_id: uuid::Uuid,
+    req_id: uuid::Uuid,
+    fetch: FetchRange,
+    db_manager: Option<Arc<data::db::DatabaseManager>>,
+) -> Task<Message> {
+    let pane_id = state.unique_id();
+
+    match fetch {
         FetchRange::Kline(from, to) => {
             let kline_stream = {
                 state.streams.find_ready_map(|stream| {
@@ -1563,6 +2197,7 @@ fn request_fetch(
                     stream,
                     Some(req_id),
                     Some((from, to)),
+                    db_manager,
                 );
             }
         }
@@ -1578,7 +2213,36 @@ fn request_fetch(
             };
 
             if let Some((stream, pane_uid)) = kline_stream {
-                return oi_fetch_task(layout_id, pane_uid, stream, Some(req_id), Some((from, to)));
+                return oi_fetch_task(
+                    layout_id,
+                    pane_uid,
+                    stream,
+                    Some(req_id),
+                    Some((from, to)),
+                    db_manager,
+                );
+            }
+        }
+        FetchRange::FundingRate(from, to) => {
+            let kline_stream = {
+                state.streams.find_ready_map(|stream| {
+                    if let StreamKind::Kline { .. } = stream {
+                        Some((*stream, pane_id))
+                    } else {
+                        None
+                    }
+                })
+            };
+
+            if let Some((stream, pane_uid)) = kline_stream {
+                return funding_fetch_task(
+                    layout_id,
+                    pane_uid,
+                    stream,
+                    Some(req_id),
+                    (from, to),
+                    db_manager,
+                );
             }
         }
         FetchRange::Trades(from_time, to_time) => {
@@ -1591,51 +2255,303 @@ fn request_fetch(
             });
 
             if let Some((ticker_info, pane_id, stream)) = trade_info {
-                let is_binance = matches!(
-                    ticker_info.exchange(),
-                    Exchange::BinanceSpot | Exchange::BinanceLinear | Exchange::BinanceInverse
+                if let Some(db_manager) = db_manager
+                    && let pane::Content::Kline { chart: Some(c), .. } = &state.content
+                    && let Basis::Time(timeframe) = c.basis()
+                {
+                    let step = PriceStep::from_f32(c.tick_size());
+
+                    return stored_footprints_task(
+                        db_manager,
+                        pane_id,
+                        stream,
+                        timeframe,
+                        step,
+                        (from_time, to_time),
+                    );
+                }
+
+                return trades_fetch_task(
+                    state,
+                    layout_id,
+                    pane_id,
+                    ticker_info,
+                    stream,
+                    vec![(from_time, to_time)],
                 );
+            }
+        }
+    }
 
-                if is_binance {
-                    let data_path = data::data_path(Some("market_data/binance/"));
+    Task::none()
+}
 
-                    let (task, handle) = Task::sip(
-                        fetch_trades_batched(ticker_info, from_time, to_time, data_path),
-                        move |batch| {
-                            let data = FetchedData::Trades {
-                                batch,
-                                until_time: to_time,
-                            };
-                            Message::DistributeFetchedData {
-                                layout_id,
-                                pane_id,
-                                data,
-                                stream,
-                            }
-                        },
-                        move |result| match result {
-                            Ok(()) => Message::ChangePaneStatus(pane_id, pane::Status::Ready),
-                            Err(err) => Message::ErrorOccurred(
-                                Some(pane_id),
-                                DashboardError::Fetch(err.to_string()),
-                            ),
-                        },
-                    )
-                    .abortable();
+fn trades_fetch_task(
+    state: &mut pane::State,
+    layout_id: uuid::Uuid,
+    pane_id: uuid::Uuid,
+    ticker_info: TickerInfo,
+    stream: StreamKind,
+    gaps: Vec<(u64, u64)>,
+) -> Task<Message> {
+    let exchange = ticker_info.exchange();
+    if !exchange.supports_trade_backfill() {
+        return Task::none();
+    }
 
-                    if let pane::Content::Kline { chart, .. } = &mut state.content
-                        && let Some(c) = chart
-                    {
-                        c.set_handle(handle.abort_on_drop());
-                    }
+    let data_path = data::data_path(Some(&format!("market_data/{}/", exchange.adapter().id())));
+    let to_time = gaps.iter().map(|&(_, to)| to).max().unwrap_or_default();
 
-                    return task;
-                }
+    let (task, handle) = Task::sip(
+        fetch_trade_gaps_batched(ticker_info, gaps, data_path),
+        move |batch| {
+            let data = FetchedData::Trades {
+                batch,
+                until_time: to_time,
+            };
+            Message::DistributeFetchedData {
+                layout_id,
+                pane_id,
+                data,
+                stream,
             }
+        },
+        move |result| match result {
+            Ok(()) => Message::ChangePaneStatus(pane_id, pane::Status::Ready),
+            Err(err) => Message::ErrorOccurred(Some(pane_id), err.into()),
+        },
+    )
+    .abortable();
+
+    if let pane::Content::Kline { chart, .. } = &mut state.content
+        && let Some(c) = chart
+    {
+        c.set_handle(handle.abort_on_drop());
+    }
+
+    task
+}
+
+/// Parts of `[from_time, to_time]` without stored trades: klines none were stored for,
+/// and the rest of the last stored kline after its last trade
+fn stored_trade_gaps(
+    footprints: &BTreeMap<u64, KlineTrades>,
+    timeframe: Timeframe,
+    from_time: u64,
+    to_time: u64,
+) -> Vec<(u64, u64)> {
+    use data::db::crud::klines::missing_kline_ranges;
+
+    let interval = timeframe.to_milliseconds();
+    if from_time >= to_time || interval == 0 {
+        return vec![];
+    }
+
+    let stored_times = footprints
+        .iter()
+        .filter(|(_, footprint)| footprint.first_trade_t().is_some())
+        .map(|(kline_t, _)| *kline_t)
+        .collect::<Vec<_>>();
+
+    // the kline `from_time` falls in counts too
+    let first_kline_t = (from_time / interval) * interval;
+    let mut gaps = missing_kline_ranges(&stored_times, first_kline_t, to_time, timeframe)
+        .into_iter()
+        .map(|(from, to)| (from.max(from_time), (to + interval - 1).min(to_time)))
+        .collect::<Vec<_>>();
+
+    // the latest stored kline was most likely still open when its trades were stored
+    let last_trade_t = footprints
+        .values()
+        .filter_map(KlineTrades::last_trade_t)
+        .max();
+    if let Some(last_trade_t) = last_trade_t
+        && last_trade_t + 1 < to_time
+        && gaps.last().is_none_or(|&(_, to)| to < to_time)
+    {
+        gaps.push((last_trade_t + 1, to_time));
+    }
+
+    gaps
+}
+
+/// Rebuild footprint clusters from trades stored in the database
+fn stored_footprints_task(
+    db_manager: Arc<data::db::DatabaseManager>,
+    pane_id: uuid::Uuid,
+    stream: StreamKind,
+    timeframe: Timeframe,
+    step: PriceStep,
+    range: (u64, u64),
+) -> Task<Message> {
+    let StreamKind::DepthAndTrades { ticker_info, .. } = stream else {
+        return Task::none();
+    };
+
+    Task::perform(
+        async move {
+            use data::db::FootprintCRUD;
+
+            db_manager.rebuild_footprints_from_trades(
+                &ticker_info,
+                timeframe,
+                step,
+                range.0,
+                range.1,
+            )
+        },
+        move |result| match result {
+            Ok(footprints) => Message::StoredFootprintsLoaded {
+                pane_id,
+                stream,
+                footprints,
+                timeframe,
+                range,
+            },
+            Err(err) => {
+                Message::ErrorOccurred(Some(pane_id), DashboardError::Fetch(err.to_string()))
+            }
+        },
+    )
+}
+
+/// Seed heatmap panes from persisted order runs, depth snapshots and
+/// liquidations, and flush their completed runs
+fn order_runs_task(
+    state: &mut pane::State,
+    db_manager: &Arc<data::db::DatabaseManager>,
+    now: Instant,
+) -> Task<Message> {
+    let pane_id = state.unique_id();
+
+    let Some(ticker_info) = state.stream_pair() else {
+        return Task::none();
+    };
+    let pane::Content::Heatmap {
+        chart: Some(chart), ..
+    } = &mut state.content
+    else {
+        return Task::none();
+    };
+
+    let mut tasks = vec![];
+
+    let completed = chart.take_completed_runs(now);
+    if !completed.is_empty() {
+        let db_manager = db_manager.clone();
+
+        tasks.push(
+            Task::future(async move {
+                match db_manager.insert_order_runs(&ticker_info, &completed) {
+                    Ok(count) => log::debug!(
+                        "Persisted {} order runs for {:?}",
+                        count,
+                        ticker_info.ticker
+                    ),
+                    Err(e) => log::error!("Failed to persist order runs: {}", e),
+                }
+            })
+            .discard(),
+        );
+    }
+
+    if let Some((earliest, latest)) = chart.request_depth_history() {
+        if ticker_info.exchange().supports_liquidations() {
+            tasks.push(query_liquidations_task(
+                pane_id,
+                ticker_info,
+                earliest,
+                latest,
+                db_manager.clone(),
+            ));
         }
+
+        let snapshots_db = db_manager.clone();
+
+        tasks.push(Task::perform(
+            async move {
+                use data::db::DepthCRUD;
+
+                snapshots_db.query_depth_snapshots_range(&ticker_info, earliest, latest)
+            },
+            move |result| match result {
+                Ok(snapshots) => Message::DepthSnapshotsLoaded {
+                    pane_id,
+                    ticker_info,
+                    snapshots,
+                },
+                Err(err) => {
+                    Message::ErrorOccurred(Some(pane_id), DashboardError::Fetch(err.to_string()))
+                }
+            },
+        ));
+
+        let db_manager = db_manager.clone();
+
+        tasks.push(Task::perform(
+            async move { db_manager.query_order_runs_in_window(&ticker_info, earliest, latest) },
+            move |result| match result {
+                Ok(runs) => Message::DepthHistoryLoaded {
+                    pane_id,
+                    ticker_info,
+                    runs,
+                },
+                Err(err) => {
+                    Message::ErrorOccurred(Some(pane_id), DashboardError::Fetch(err.to_string()))
+                }
+            },
+        ));
     }
 
-    Task::none()
+    Task::batch(tasks)
+}
+
+/// Seed kline chart panes with stored liquidations as older klines get loaded
+fn liquidations_task(
+    state: &mut pane::State,
+    db_manager: &Arc<data::db::DatabaseManager>,
+) -> Task<Message> {
+    let pane_id = state.unique_id();
+
+    let Some(ticker_info) = state.stream_pair() else {
+        return Task::none();
+    };
+    let pane::Content::Kline {
+        chart: Some(chart), ..
+    } = &mut state.content
+    else {
+        return Task::none();
+    };
+
+    let Some((earliest, latest)) = chart.request_liquidation_history() else {
+        return Task::none();
+    };
+
+    query_liquidations_task(pane_id, ticker_info, earliest, latest, db_manager.clone())
+}
+
+fn query_liquidations_task(
+    pane_id: uuid::Uuid,
+    ticker_info: TickerInfo,
+    earliest: u64,
+    latest: u64,
+    db_manager: Arc<data::db::DatabaseManager>,
+) -> Task<Message> {
+    use data::db::LiquidationsCRUD;
+
+    Task::perform(
+        async move { db_manager.query_liquidations(&ticker_info, earliest, latest) },
+        move |result| match result {
+            Ok(liquidations) => Message::LiquidationsLoaded {
+                pane_id,
+                ticker_info,
+                liquidations,
+            },
+            Err(err) => {
+                Message::ErrorOccurred(Some(pane_id), DashboardError::Fetch(err.to_string()))
+            }
+        },
+    )
 }
 
 fn oi_fetch_task(
@@ -1644,6 +2560,7 @@ fn oi_fetch_task(
     stream: StreamKind,
     req_id: Option<uuid::Uuid>,
     range: Option<(u64, u64)>,
+    db_manager: Option<Arc<data::db::DatabaseManager>>,
 ) -> Task<Message> {
     let update_status = Task::done(Message::ChangePaneStatus(
         pane_id,
@@ -1655,8 +2572,8 @@ fn oi_fetch_task(
             ticker_info,
             timeframe,
         } => Task::perform(
-            adapter::fetch_open_interest(ticker_info.ticker, timeframe, range)
-                .map_err(|err| format!("{err}")),
+            fetch_open_interest_with_db(db_manager, ticker_info, timeframe, range)
+                .map_err(DashboardError::from),
             move |result| match result {
                 Ok(oi) => {
                     let data = FetchedData::OI { data: oi, req_id };
@@ -1667,7 +2584,63 @@ fn oi_fetch_task(
                         stream,
                     }
                 }
-                Err(err) => Message::ErrorOccurred(Some(pane_id), DashboardError::Fetch(err)),
+                Err(err) => Message::ErrorOccurred(Some(pane_id), err),
+            },
+        ),
+        _ => Task::none(),
+    };
+
+    update_status.chain(fetch_task)
+}
+
+fn funding_fetch_task(
+    layout_id: uuid::Uuid,
+    pane_id: uuid::Uuid,
+    stream: StreamKind,
+    req_id: Option<uuid::Uuid>,
+    range: (u64, u64),
+    db_manager: Option<Arc<data::db::DatabaseManager>>,
+) -> Task<Message> {
+    let update_status = Task::done(Message::ChangePaneStatus(
+        pane_id,
+        pane::Status::Loading(pane::InfoType::FetchingFunding),
+    ));
+
+    let fetch_task = match stream {
+        StreamKind::Kline { ticker_info, .. } => Task::perform(
+            async move {
+                let data = fetch_funding_rates_with_db(db_manager, ticker_info, range).await?;
+
+                // Historical rates are still useful without the upcoming one
+                let predicted = adapter::fetch_predicted_funding(ticker_info.ticker)
+                    .await
+                    .inspect_err(|e| {
+                        log::warn!(
+                            "Failed to fetch predicted funding for {:?}: {}",
+                            ticker_info.ticker,
+                            e
+                        );
+                    })
+                    .ok();
+
+                Ok::<_, AdapterError>((data, predicted))
+            }
+            .map_err(DashboardError::from),
+            move |result| match result {
+                Ok((data, predicted)) => {
+                    let data = FetchedData::Funding {
+                        data,
+                        predicted,
+                        req_id,
+                    };
+                    Message::DistributeFetchedData {
+                        layout_id,
+                        pane_id,
+                        data,
+                        stream,
+                    }
+                }
+                Err(err) => Message::ErrorOccurred(Some(pane_id), err),
             },
         ),
         _ => Task::none(),
@@ -1682,6 +2655,7 @@ fn kline_fetch_task(
     stream: StreamKind,
     req_id: Option<uuid::Uuid>,
     range: Option<(u64, u64)>,
+    db_manager: Option<Arc<data::db::DatabaseManager>>,
 ) -> Task<Message> {
     let update_status = Task::done(Message::ChangePaneStatus(
         pane_id,
@@ -1693,7 +2667,8 @@ fn kline_fetch_task(
             ticker_info,
             timeframe,
         } => Task::perform(
-            adapter::fetch_klines(ticker_info, timeframe, range).map_err(|err| format!("{err}")),
+            fetch_klines_with_db(db_manager, ticker_info, timeframe, range)
+                .map_err(DashboardError::from),
             move |result| match result {
                 Ok(klines) => {
                     let data = FetchedData::Klines {
@@ -1707,7 +2682,7 @@ fn kline_fetch_task(
                         stream,
                     }
                 }
-                Err(err) => Message::ErrorOccurred(Some(pane_id), DashboardError::Fetch(err)),
+                Err(err) => Message::ErrorOccurred(Some(pane_id), err),
             },
         ),
         _ => Task::none(),
@@ -1716,27 +2691,301 @@ fn kline_fetch_task(
     update_status.chain(fetch_task)
 }
 
+/// Candles loaded from the database when a chart requests its initial history
+const DEFAULT_KLINE_HYDRATION_COUNT: u64 = 400;
+
+/// Above this many gaps a single ranged request is cheaper than one per gap
+const MAX_KLINE_GAP_REQUESTS: usize = 8;
+
+/// Load klines from the database first and only fetch the missing gaps from the exchange
+async fn fetch_klines_with_db(
+    db_manager: Option<Arc<data::db::DatabaseManager>>,
+    ticker_info: TickerInfo,
+    timeframe: Timeframe,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<Kline>, AdapterError> {
+    use data::db::KlinesCRUD;
+    use data::db::crud::klines::missing_kline_ranges;
+
+    let Some(db_manager) = db_manager else {
+        return adapter::fetch_klines(ticker_info, timeframe, range).await;
+    };
+
+    let interval = timeframe.to_milliseconds();
+    let (start, end) = range.unwrap_or_else(|| {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        (
+            now.saturating_sub(interval * DEFAULT_KLINE_HYDRATION_COUNT),
+            now,
+        )
+    });
+
+    let mut stored: Vec<Kline> =
+        match db_manager.load_timeseries(&ticker_info, timeframe, start, end) {
+            Ok(timeseries) => timeseries.datapoints.values().map(|dp| dp.kline).collect(),
+            Err(e) => {
+                log::warn!("Failed to load klines from database: {}", e);
+                vec![]
+            }
+        };
+
+    // The latest stored candle was most likely persisted before it closed
+    if range.is_none() {
+        stored.pop();
+    }
+
+    if stored.is_empty() {
+        return adapter::fetch_klines(ticker_info, timeframe, range).await;
+    }
+
+    let stored_times = stored.iter().map(|kline| kline.time).collect::<Vec<_>>();
+    let gaps = missing_kline_ranges(&stored_times, start, end, timeframe);
+
+    if gaps.len() > MAX_KLINE_GAP_REQUESTS {
+        return adapter::fetch_klines(ticker_info, timeframe, range).await;
+    }
+
+    log::debug!(
+        "Hydrated {} klines from database for {:?} {}, fetching {} gap(s)",
+        stored.len(),
+        ticker_info.ticker,
+        timeframe,
+        gaps.len()
+    );
+
+    let mut klines: BTreeMap<u64, Kline> = stored.into_iter().map(|k| (k.time, k)).collect();
+
+    for (from, to) in gaps {
+        // end bound is exclusive for the limit calculation of most adapters
+        let fetched =
+            adapter::fetch_klines(ticker_info, timeframe, Some((from, to + interval))).await?;
+        klines.extend(fetched.into_iter().map(|k| (k.time, k)));
+    }
+
+    Ok(klines.into_values().collect())
+}
+
+/// Load open interest from the database first and only fetch what lies outside of it
+///
+/// Exchanges serve a limited window of open interest history, so anything older
+/// than that is only available from what was stored earlier
+async fn fetch_open_interest_with_db(
+    db_manager: Option<Arc<data::db::DatabaseManager>>,
+    ticker_info: TickerInfo,
+    timeframe: Timeframe,
+    range: Option<(u64, u64)>,
+) -> Result<Vec<OpenInterest>, AdapterError> {
+    use data::db::OpenInterestCRUD;
+
+    let Some(db_manager) = db_manager else {
+        return adapter::fetch_open_interest(ticker_info.ticker, timeframe, range).await;
+    };
+
+    let interval = timeframe.to_milliseconds();
+    // Drawn under the klines, so the initial load covers as many points as candles
+    let (start, end) = range.unwrap_or_else(|| {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        (
+            now.saturating_sub(interval * DEFAULT_KLINE_HYDRATION_COUNT),
+            now,
+        )
+    });
+
+    // Points of other timeframes share the table, keep the ones on this timeframe's grid
+    let stored: Vec<OpenInterest> = match db_manager.query_open_interest(&ticker_info, start, end) {
+        Ok(data) => data
+            .into_iter()
+            .filter(|oi| oi.time % interval == 0)
+            .collect(),
+        Err(e) => {
+            log::warn!("Failed to load open interest from database: {}", e);
+            vec![]
+        }
+    };
+
+    let (Some(first), Some(last)) = (stored.first(), stored.last()) else {
+        return adapter::fetch_open_interest(ticker_info.ticker, timeframe, range).await;
+    };
+    let (stored_first, stored_last) = (first.time, last.time);
+
+    let mut data: BTreeMap<u64, OpenInterest> =
+        stored.into_iter().map(|oi| (oi.time, oi)).collect();
+
+    let mut gaps = vec![];
+    if stored_first > start.saturating_add(interval) {
+        gaps.push((start, stored_first));
+    }
+    if stored_last.saturating_add(interval) < end {
+        gaps.push((stored_last, end));
+    }
+
+    for gap in gaps {
+        // Older gaps are often past what the exchange serves, the stored points still show
+        match adapter::fetch_open_interest(ticker_info.ticker, timeframe, Some(gap)).await {
+            Ok(fetched) => data.extend(fetched.into_iter().map(|oi| (oi.time, oi))),
+            Err(e) => log::warn!(
+                "Failed to fetch open interest of {:?} for {:?}, showing stored data: {}",
+                ticker_info.ticker,
+                gap,
+                e
+            ),
+        }
+    }
+
+    Ok(data.into_values().collect())
+}
+
+/// Shortest funding interval among supported exchanges
+const MIN_FUNDING_INTERVAL: u64 = 60 * 60 * 1000;
+
+/// Pages requested at most for one window of funding rates
+const MAX_FUNDING_REQUESTS: usize = 32;
+
+/// Load settled funding rates from the database first and only fetch what lies outside of it
+async fn fetch_funding_rates_with_db(
+    db_manager: Option<Arc<data::db::DatabaseManager>>,
+    ticker_info: TickerInfo,
+    (start, end): (u64, u64),
+) -> Result<Vec<FundingRate>, AdapterError> {
+    use data::db::FundingRateCRUD;
+
+    let Some(db_manager) = db_manager else {
+        return fetch_funding_window(ticker_info.ticker, (start, end)).await;
+    };
+
+    let stored = match db_manager.query_funding_rates(&ticker_info, start, end) {
+        Ok(data) => data,
+        Err(e) => {
+            log::warn!("Failed to load funding rates from database: {}", e);
+            vec![]
+        }
+    };
+
+    let (Some(first), Some(last)) = (stored.first(), stored.last()) else {
+        return fetch_funding_window(ticker_info.ticker, (start, end)).await;
+    };
+    let (stored_first, stored_last) = (first.time, last.time);
+
+    let mut data: BTreeMap<u64, FundingRate> = stored.into_iter().map(|f| (f.time, f)).collect();
+
+    let mut gaps = vec![];
+    if stored_first > start.saturating_add(MIN_FUNDING_INTERVAL) {
+        gaps.push((start, stored_first - 1));
+    }
+    if stored_last.saturating_add(MIN_FUNDING_INTERVAL) <= end {
+        gaps.push((stored_last + 1, end));
+    }
+
+    for gap in gaps {
+        match fetch_funding_window(ticker_info.ticker, gap).await {
+            Ok(fetched) => data.extend(fetched.into_iter().map(|f| (f.time, f))),
+            Err(e) => log::warn!(
+                "Failed to fetch funding rates of {:?} for {:?}, showing stored data: {}",
+                ticker_info.ticker,
+                gap,
+                e
+            ),
+        }
+    }
+
+    Ok(data.into_values().collect())
+}
+
+/// Funding rates of the whole window, paging as exchanges cap how many a response holds
+///
+/// Some exchanges fill a capped response from the start of the window and others from
+/// its end, so whatever is left on either side of a page gets requested again.
+async fn fetch_funding_window(
+    ticker: Ticker,
+    (start, end): (u64, u64),
+) -> Result<Vec<FundingRate>, AdapterError> {
+    let mut data: BTreeMap<u64, FundingRate> = BTreeMap::new();
+    let mut windows = vec![(start, end)];
+
+    for _ in 0..MAX_FUNDING_REQUESTS {
+        let Some((from, to)) = windows.pop() else {
+            break;
+        };
+
+        let fetched = match adapter::fetch_funding_rates(ticker, Some((from, to))).await {
+            Ok(fetched) => fetched,
+            Err(e) if data.is_empty() => return Err(e),
+            Err(e) => {
+                log::warn!("Stopped paging funding rates of {:?}: {}", ticker, e);
+                break;
+            }
+        };
+
+        let page = fetched
+            .into_iter()
+            .filter(|f| f.time >= from && f.time <= to)
+            .collect::<Vec<_>>();
+
+        let (Some(first), Some(last)) = (
+            page.iter().map(|f| f.time).min(),
+            page.iter().map(|f| f.time).max(),
+        ) else {
+            continue;
+        };
+
+        if first > from.saturating_add(MIN_FUNDING_INTERVAL) {
+            windows.push((from, first - 1));
+        }
+        if last.saturating_add(MIN_FUNDING_INTERVAL) <= to {
+            windows.push((last + 1, to));
+        }
+
+        data.extend(page.into_iter().map(|f| (f.time, f)));
+    }
+
+    Ok(data.into_values().collect())
+}
+
 pub fn fetch_trades_batched(
     ticker_info: TickerInfo,
     from_time: u64,
     to_time: u64,
     data_path: PathBuf,
+) -> impl Straw<(), Vec<Trade>, AdapterError> {
+    fetch_trade_gaps_batched(ticker_info, vec![(from_time, to_time)], data_path)
+}
+
+/// Fills each gap in turn, batches of the last one may run past its end as with
+/// [`fetch_trades_batched`]
+pub fn fetch_trade_gaps_batched(
+    ticker_info: TickerInfo,
+    gaps: Vec<(u64, u64)>,
+    data_path: PathBuf,
 ) -> impl Straw<(), Vec<Trade>, AdapterError> {
     sipper(async move |mut progress| {
-        let mut latest_trade_t = from_time;
+        let until_time = gaps.iter().map(|&(_, to)| to).max().unwrap_or_default();
 
-        while latest_trade_t < to_time {
-            match binance::fetch_trades(ticker_info, latest_trade_t, data_path.clone()).await {
-                Ok(batch) => {
-                    if batch.is_empty() {
-                        break;
-                    }
+        for (from_time, to_time) in gaps {
+            let mut latest_trade_t = from_time;
+
+            while latest_trade_t < to_time {
+                match ticker_info
+                    .exchange()
+                    .adapter()
+                    .fetch_trades(ticker_info, latest_trade_t, data_path.clone())
+                    .await
+                {
+                    Ok(mut batch) => {
+                        if batch.is_empty() {
+                            break;
+                        }
 
-                    latest_trade_t = batch.last().map_or(latest_trade_t, |trade| trade.time);
+                        latest_trade_t = batch.last().map_or(latest_trade_t, |trade| trade.time);
 
-                    let () = progress.send(batch).await;
+                        // trades past an earlier gap are already stored
+                        if to_time < until_time {
+                            batch.retain(|trade| trade.time <= to_time);
+                        }
+
+                        let () = progress.send(batch).await;
+                    }
+                    Err(err) => return Err(err),
                 }
-                Err(err) => return Err(err),
             }
         }
 
@@ -1753,37 +3002,23 @@ pub fn depth_subscription(
 
     let config = StreamConfig::new(ticker_info, exchange, tick_mlpt, push_freq);
 
-    match exchange {
-        Exchange::AsterLinear => {
-            let builder = |cfg: &StreamConfig<TickerInfo>| {
-                aster::connect_market_stream(cfg.id, cfg.push_freq)
-            };
-            Subscription::run_with(config, builder)
-        }
-        Exchange::BinanceSpot | Exchange::BinanceInverse | Exchange::BinanceLinear => {
-            let builder = |cfg: &StreamConfig<TickerInfo>| {
-                binance::connect_market_stream(cfg.id, cfg.push_freq)
-            };
-            Subscription::run_with(config, builder)
-        }
-        Exchange::BybitSpot | Exchange::BybitLinear | Exchange::BybitInverse => {
-            let builder = |cfg: &StreamConfig<TickerInfo>| {
-                bybit::connect_market_stream(cfg.id, cfg.push_freq)
-            };
-            Subscription::run_with(config, builder)
-        }
-        Exchange::HyperliquidSpot | Exchange::HyperliquidLinear => {
-            let builder = |cfg: &StreamConfig<TickerInfo>| {
-                hyperliquid::connect_market_stream(cfg.id, cfg.tick_mltp, cfg.push_freq)
-            };
-            Subscription::run_with(config, builder)
-        }
-        Exchange::OkexLinear | Exchange::OkexInverse | Exchange::OkexSpot => {
-            let builder =
-                |cfg: &StreamConfig<TickerInfo>| okex::connect_market_stream(cfg.id, cfg.push_freq);
-            Subscription::run_with(config, builder)
-        }
-    }
+    let builder = |cfg: &StreamConfig<TickerInfo>| {
+        cfg.id
+            .exchange()
+            .adapter()
+            .connect_market_stream(cfg.id, cfg.tick_mltp, cfg.push_freq)
+    };
+    Subscription::run_with(config, builder)
+}
+
+/// One connection for all the depth streams of `exchange`, see [`mux::set_wanted`]
+pub fn shared_market_subscription(exchange: Exchange) -> Subscription<exchange::Event> {
+    let builder = |exchange: &Exchange| {
+        exchange
+            .adapter()
+            .connect_shared_market_stream(exchange.market_type())
+    };
+    Subscription::run_with(exchange, builder)
 }
 
 pub fn kline_subscription(
@@ -1791,36 +3026,11 @@ pub fn kline_subscription(
     kline_subs: Vec<(TickerInfo, Timeframe)>,
 ) -> Subscription<exchange::Event> {
     let config = StreamConfig::new(kline_subs, exchange, None, PushFrequency::ServerDefault);
-    match exchange {
-        Exchange::AsterLinear => {
-            let builder = |cfg: &StreamConfig<Vec<(TickerInfo, Timeframe)>>| {
-                aster::connect_kline_stream(cfg.id.clone(), cfg.market_type)
-            };
-            Subscription::run_with(config, builder)
-        }
-        Exchange::BinanceSpot | Exchange::BinanceInverse | Exchange::BinanceLinear => {
-            let builder = |cfg: &StreamConfig<Vec<(TickerInfo, Timeframe)>>| {
-                binance::connect_kline_stream(cfg.id.clone(), cfg.market_type)
-            };
-            Subscription::run_with(config, builder)
-        }
-        Exchange::BybitSpot | Exchange::BybitInverse | Exchange::BybitLinear => {
-            let builder = |cfg: &StreamConfig<Vec<(TickerInfo, Timeframe)>>| {
-                bybit::connect_kline_stream(cfg.id.clone(), cfg.market_type)
-            };
-            Subscription::run_with(config, builder)
-        }
-        Exchange::HyperliquidSpot | Exchange::HyperliquidLinear => {
-            let builder = |cfg: &StreamConfig<Vec<(TickerInfo, Timeframe)>>| {
-                hyperliquid::connect_kline_stream(cfg.id.clone(), cfg.market_type)
-            };
-            Subscription::run_with(config, builder)
-        }
-        Exchange::OkexLinear | Exchange::OkexInverse | Exchange::OkexSpot => {
-            let builder = |cfg: &StreamConfig<Vec<(TickerInfo, Timeframe)>>| {
-                okex::connect_kline_stream(cfg.id.clone(), cfg.market_type)
-            };
-            Subscription::run_with(config, builder)
-        }
-    }
+
+    let builder = |(exchange, cfg): &(Exchange, StreamConfig<Vec<(TickerInfo, Timeframe)>>)| {
+        exchange
+            .adapter()
+            .connect_kline_stream(cfg.id.clone(), cfg.market_type)
+    };
+    Subscription::run_with((exchange, config), builder)
 }
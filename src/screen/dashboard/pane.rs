@@ -25,8 +25,10 @@ use data::{
     layout::pane::{LinkGroup, Settings, VisualConfig},
 };
 use exchange::{
-    Kline, OpenInterest, TickMultiplier, TickerInfo, Timeframe,
-    adapter::{MarketKind, PersistStreamKind, ResolvedStream, StreamKind, StreamTicksize},
+    FundingRate, Kline, OpenInterest, TickMultiplier, TickerInfo, Timeframe,
+    adapter::{
+        ConnectionState, MarketKind, PersistStreamKind, ResolvedStream, StreamKind, StreamTicksize,
+    },
 };
 use iced::{
     Alignment, Element, Length, Renderer, Theme,
@@ -42,6 +44,7 @@ pub enum InfoType {
     FetchingKlines,
     FetchingTrades(usize),
     FetchingOI,
+    FetchingFunding,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -52,6 +55,15 @@ pub enum Status {
     Stale(String),
 }
 
+/// Connection of one of the pane's streams
+#[derive(Debug, Clone, Copy)]
+struct StreamConnection {
+    stream: StreamKind,
+    state: ConnectionState,
+    /// When the stream stopped being live, to mark the missed span once it's back
+    interrupted_at: Option<u64>,
+}
+
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
 pub enum Modal {
     StreamModifier(modal::stream::Modifier),
@@ -112,6 +124,7 @@ pub struct State {
     pub streams: ResolvedStream,
     pub status: Status,
     pub link_group: Option<LinkGroup>,
+    connections: Vec<StreamConnection>,
 }
 
 impl State {
@@ -138,6 +151,7 @@ impl State {
         self.streams.find_ready_map(|stream| match stream {
             StreamKind::DepthAndTrades { ticker_info, .. }
             | StreamKind::Kline { ticker_info, .. } => Some(*ticker_info),
+            StreamKind::Liquidations { .. } => None,
         })
     }
 
@@ -380,6 +394,25 @@ impl State {
         }
     }
 
+    pub fn insert_funding_vec(
+        &mut self,
+        req_id: Option<uuid::Uuid>,
+        data: &[FundingRate],
+        predicted: Option<FundingRate>,
+    ) {
+        match &mut self.content {
+            Content::Kline { chart, .. } => {
+                let Some(chart) = chart else {
+                    panic!("Kline chart wasn't initialized when inserting funding rates");
+                };
+                chart.insert_funding_rates(req_id, data, predicted);
+            }
+            _ => {
+                log::error!("pane content not candlestick");
+            }
+        }
+    }
+
     pub fn insert_klines_vec(
         &mut self,
         req_id: Option<uuid::Uuid>,
@@ -679,12 +712,23 @@ impl State {
             Status::Loading(InfoType::FetchingOI) => {
                 stream_info_element = stream_info_element.push(text("Fetching Open Interest..."));
             }
+            Status::Loading(InfoType::FetchingFunding) => {
+                stream_info_element = stream_info_element.push(text("Fetching Funding Rates..."));
+            }
             Status::Stale(msg) => {
                 stream_info_element = stream_info_element.push(text(msg));
             }
             Status::Ready => {}
         }
 
+        if let Some(badge) = self.connection_badge() {
+            stream_info_element = stream_info_element.push(
+                container(text(badge).size(11))
+                    .padding([2, 6])
+                    .style(style::connection_badge),
+            );
+        }
+
         let content = pane_grid::Content::new(body)
             .style(move |theme| style::pane_background(theme, is_focused));
 
@@ -983,6 +1027,51 @@ impl State {
         self.streams.matches_stream(stream)
     }
 
+    /// Tracks the connection of a stream, returning the span it missed data for once live again
+    pub fn set_connection_state(
+        &mut self,
+        stream: StreamKind,
+        state: ConnectionState,
+        now: u64,
+    ) -> Option<(u64, u64)> {
+        self.connections
+            .retain(|conn| self.streams.matches_stream(&conn.stream));
+
+        let Some(conn) = self.connections.iter_mut().find(|c| c.stream == stream) else {
+            self.connections.push(StreamConnection {
+                stream,
+                state,
+                interrupted_at: None,
+            });
+            return None;
+        };
+
+        let was_live = conn.state == ConnectionState::Live;
+        conn.state = state;
+
+        if state == ConnectionState::Live {
+            conn.interrupted_at.take().map(|from| (from, now))
+        } else {
+            if was_live {
+                conn.interrupted_at = Some(now);
+            }
+            None
+        }
+    }
+
+    /// Label for the first of the pane's streams that isn't live
+    fn connection_badge(&self) -> Option<String> {
+        self.connections.iter().find_map(|conn| match conn.state {
+            ConnectionState::Live => None,
+            ConnectionState::Connecting => Some("Connecting...".to_string()),
+            ConnectionState::Resyncing => Some("Resyncing...".to_string()),
+            ConnectionState::Backoff { until } => {
+                let secs = until.saturating_duration_since(Instant::now()).as_secs() + 1;
+                Some(format!("Reconnecting in {secs}s"))
+            }
+        })
+    }
+
     pub fn invalidate(&mut self, now: Instant) -> Option<Action> {
         match &mut self.content {
             Content::Heatmap { chart, .. } => chart
@@ -1071,6 +1160,7 @@ impl Default for State {
             notifications: vec![],
             status: Status::Ready,
             link_group: None,
+            connections: vec![],
         }
     }
 }
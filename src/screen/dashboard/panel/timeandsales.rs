@@ -3,7 +3,7 @@ use crate::style;
 use data::config::theme::{darken, lighten};
 pub use data::panel::timeandsales::Config;
 use data::panel::timeandsales::{HistAgg, StackedBar, StackedBarRatio, TradeDisplay, TradeEntry};
-use exchange::{TickerInfo, Trade};
+use exchange::{Liquidation, TickerInfo, Trade, util::Price};
 
 use iced::widget::canvas::{self, Text};
 use iced::{Alignment, Event, Point, Rectangle, Renderer, Size, Theme, mouse};
@@ -99,58 +99,74 @@ impl TimeAndSales {
     }
 
     pub fn insert_buffer(&mut self, trades_buffer: &[Trade]) {
-        let size_filter = self.config.trade_size_filter;
+        for trade in trades_buffer {
+            self.push_entry(trade.time, trade.price, trade.qty, trade.is_sell, false);
+        }
 
-        let target_trades = if self.is_paused {
-            &mut self.paused_trades_buffer
-        } else {
-            &mut self.recent_trades
-        };
+        if !self.is_paused {
+            self.prune_by_time(None);
+        }
+        self.prune_paused_by_time(None);
+    }
 
-        let market_type = self.ticker_info.market_type();
-        let size_in_quote_currency = exchange::SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
+    /// Liquidations are shown as highlighted rows among the trades
+    pub fn insert_liquidation(&mut self, liquidation: &Liquidation) {
+        self.push_entry(
+            liquidation.time,
+            liquidation.price,
+            liquidation.qty,
+            liquidation.is_sell,
+            true,
+        );
+    }
 
-        for trade in trades_buffer {
-            let trade_time_ms = trade.time;
-
-            if let Some(trade_time) = chrono::DateTime::from_timestamp(
-                trade_time_ms as i64 / 1000,
-                (trade_time_ms % 1000) as u32 * 1_000_000,
-            ) {
-                let trade_display = TradeDisplay {
-                    time_str: trade_time.format("%M:%S.%3f").to_string(),
-                    price: trade.price,
-                    qty: trade.qty,
-                    is_sell: trade.is_sell,
-                };
+    fn push_entry(
+        &mut self,
+        time_ms: u64,
+        price: Price,
+        qty: f32,
+        is_sell: bool,
+        is_liquidation: bool,
+    ) {
+        let Some(trade_time) = chrono::DateTime::from_timestamp(
+            time_ms as i64 / 1000,
+            (time_ms % 1000) as u32 * 1_000_000,
+        ) else {
+            return;
+        };
 
-                let trade_size_value = market_type.qty_in_quote_value(
-                    trade_display.qty,
-                    trade.price,
-                    size_in_quote_currency,
-                );
+        let trade_display = TradeDisplay {
+            time_str: trade_time.format("%M:%S.%3f").to_string(),
+            price,
+            qty,
+            is_sell,
+            is_liquidation,
+        };
 
-                if trade_size_value >= size_filter {
-                    self.max_filtered_qty = self.max_filtered_qty.max(trade_display.qty);
-                }
+        let market_type = self.ticker_info.market_type();
+        let size_in_quote_currency = exchange::SIZE_IN_QUOTE_CURRENCY.get() == Some(&true);
 
-                target_trades.push_back(TradeEntry {
-                    ts_ms: trade_time_ms,
-                    display: trade_display,
-                });
+        let trade_size_value =
+            market_type.qty_in_quote_value(trade_display.qty, price, size_in_quote_currency);
 
-                if !self.is_paused
-                    && let Some(last) = target_trades.back()
-                {
-                    self.hist_agg.add(&last.display);
-                }
-            }
+        if trade_size_value >= self.config.trade_size_filter {
+            self.max_filtered_qty = self.max_filtered_qty.max(trade_display.qty);
         }
 
         if !self.is_paused {
-            self.prune_by_time(None);
+            self.hist_agg.add(&trade_display);
         }
-        self.prune_paused_by_time(None);
+
+        let target_trades = if self.is_paused {
+            &mut self.paused_trades_buffer
+        } else {
+            &mut self.recent_trades
+        };
+
+        target_trades.push_back(TradeEntry {
+            ts_ms: time_ms,
+            display: trade_display,
+        });
     }
 
     pub fn last_update(&self) -> Instant {
@@ -482,16 +498,21 @@ impl canvas::Program<Message> for TimeAndSales {
                     continue;
                 }
 
-                let bg_color = if trade.is_sell {
+                let side_color = if trade.is_sell {
                     palette.danger.weak.color
                 } else {
                     palette.success.weak.color
                 };
 
-                let bg_color_alpha = if self.max_filtered_qty > 0.0 {
-                    (trade.qty / self.max_filtered_qty).clamp(0.02, 1.0)
+                let (bg_color, bg_color_alpha) = if trade.is_liquidation {
+                    (palette.warning.weak.color, 0.9)
+                } else if self.max_filtered_qty > 0.0 {
+                    (
+                        side_color,
+                        (trade.qty / self.max_filtered_qty).clamp(0.02, 1.0),
+                    )
                 } else {
-                    0.02
+                    (side_color, 0.02)
                 };
 
                 let mut text_color = if palette.is_dark {
@@ -519,6 +540,21 @@ impl canvas::Program<Message> for TimeAndSales {
                     bg_color.scale_alpha(bg_color_alpha.min(0.9)),
                 );
 
+                // Side of the forced order, a sell liquidates a long
+                if trade.is_liquidation {
+                    frame.fill_rectangle(
+                        Point {
+                            x: 0.0,
+                            y: y_position,
+                        },
+                        Size {
+                            width: 4.0,
+                            height: row_height,
+                        },
+                        side_color,
+                    );
+                }
+
                 let trade_time = create_text(
                     trade.time_str.clone(),
                     Point {
@@ -197,11 +197,41 @@ impl Sidebar {
             )
         };
 
+        let connection_health_btn = {
+            let is_active = self.is_menu_active(sidebar::Menu::ConnectionHealth);
+
+            button_with_tooltip(
+                icon_text(Icon::ChartOutline, 14)
+                    .width(24)
+                    .align_x(Alignment::Center),
+                Message::ToggleSidebarMenu(Some(sidebar::Menu::ConnectionHealth)),
+                None,
+                tooltip_position,
+                move |theme, status| crate::style::button::transparent(theme, status, is_active),
+            )
+        };
+
+        let replay_btn = {
+            let is_active = self.is_menu_active(sidebar::Menu::Replay);
+
+            button_with_tooltip(
+                icon_text(Icon::Return, 14)
+                    .width(24)
+                    .align_x(Alignment::Center),
+                Message::ToggleSidebarMenu(Some(sidebar::Menu::Replay)),
+                None,
+                tooltip_position,
+                move |theme, status| crate::style::button::transparent(theme, status, is_active),
+            )
+        };
+
         column![
             ticker_search_button,
             layout_modal_button,
             audio_btn,
             database_btn,
+            connection_health_btn,
+            replay_btn,
             space::vertical(),
             settings_modal_button,
         ]
@@ -244,7 +274,28 @@ impl Sidebar {
         self.state.tickers_table = Some(settings.clone());
     }
 
+    pub fn favorited_tickers(&self) -> Vec<exchange::Ticker> {
+        self.tickers_table
+            .favorited_tickers
+            .iter()
+            .copied()
+            .collect()
+    }
+
     pub fn tickers_info(&self) -> &FxHashMap<exchange::Ticker, Option<exchange::TickerInfo>> {
         &self.tickers_table.tickers_info
     }
+
+    /// Info of a listed ticker, matched by symbol as ones read from the database lack display symbols
+    pub fn listed_ticker_info(&self, ticker: &exchange::Ticker) -> Option<exchange::TickerInfo> {
+        let (symbol, _) = ticker.to_full_symbol_and_type();
+
+        self.tickers_table
+            .tickers_info
+            .iter()
+            .find(|(listed, _)| {
+                listed.exchange == ticker.exchange && listed.to_full_symbol_and_type().0 == symbol
+            })
+            .and_then(|(_, ticker_info)| *ticker_info)
+    }
 }
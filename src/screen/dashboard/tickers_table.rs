@@ -11,7 +11,9 @@ use data::{
 };
 use exchange::{
     Ticker, TickerInfo, TickerStats,
-    adapter::{Exchange, ExchangeInclusive, MarketKind, fetch_ticker_info, fetch_ticker_prices},
+    adapter::{
+        Exchange, ExchangeInclusive, MarketKind, fetch_ticker_info, fetch_ticker_prices, telemetry,
+    },
 };
 use iced::{
     Alignment, Element, Length, Renderer, Size, Subscription, Task, Theme,
@@ -69,6 +71,7 @@ pub enum Message {
     FavoriteTicker(Ticker),
     Scrolled(scrollable::Viewport),
     ToggleMarketFilter(MarketKind),
+    ToggleDatedFutures,
     ToggleExchangeFilter(ExchangeInclusive),
     ToggleTable,
     ToggleFavorites,
@@ -91,6 +94,7 @@ pub struct TickersTable {
     pub tickers_info: FxHashMap<Ticker, Option<TickerInfo>>,
     selected_exchanges: FxHashSet<ExchangeInclusive>,
     selected_markets: FxHashSet<MarketKind>,
+    show_dated_futures: bool,
     show_favorites: bool,
 }
 
@@ -114,6 +118,7 @@ impl TickersTable {
                 tickers_info: FxHashMap::default(),
                 selected_exchanges: settings.selected_exchanges.iter().cloned().collect(),
                 selected_markets: settings.selected_markets.iter().cloned().collect(),
+                show_dated_futures: settings.show_dated_futures,
                 show_favorites: settings.show_favorites,
             },
             fetch_tickers_info(),
@@ -127,6 +132,7 @@ impl TickersTable {
             selected_sort_option: self.selected_sort_option,
             selected_exchanges: self.selected_exchanges.iter().cloned().collect(),
             selected_markets: self.selected_markets.iter().cloned().collect(),
+            show_dated_futures: self.show_dated_futures,
         }
     }
 
@@ -351,6 +357,9 @@ impl TickersTable {
                     self.selected_exchanges.insert(exch);
                 }
             }
+            Message::ToggleDatedFutures => {
+                self.show_dated_futures = !self.show_dated_futures;
+            }
             Message::ToggleFavorites => {
                 self.show_favorites = !self.show_favorites;
             }
@@ -441,8 +450,13 @@ impl TickersTable {
             let (raw_str, _) = row.ticker.to_full_symbol_and_type();
             display_str.contains(&self.search_query) || raw_str.contains(&self.search_query)
         };
-        let matches_market =
-            |row: &TickerRowData| self.selected_markets.contains(&row.ticker.market_type());
+        let matches_market = |row: &TickerRowData| {
+            if row.ticker.expiry().is_some() {
+                self.show_dated_futures
+            } else {
+                self.selected_markets.contains(&row.ticker.market_type())
+            }
+        };
         let matches_exchange = |row: &TickerRowData| {
             self.selected_exchanges
                 .contains(&ExchangeInclusive::of(row.exchange))
@@ -542,6 +556,11 @@ impl TickersTable {
             let spot_market_button = self.market_filter_btn("Spot", MarketKind::Spot);
             let linear_markets_btn = self.market_filter_btn("Linear", MarketKind::LinearPerps);
             let inverse_markets_btn = self.market_filter_btn("Inverse", MarketKind::InversePerps);
+            let dated_futures_btn = button(text("Futures").align_x(Alignment::Center))
+                .on_press(Message::ToggleDatedFutures)
+                .style(move |theme, status| {
+                    style::button::transparent(theme, status, self.show_dated_futures)
+                });
 
             let exchange_filters = column![
                 self.exchange_filter_btn(ExchangeInclusive::Aster, Exchange::AsterLinear, "Aster"),
@@ -551,6 +570,11 @@ impl TickersTable {
                     Exchange::BinanceLinear,
                     "Binance"
                 ),
+                self.exchange_filter_btn(
+                    ExchangeInclusive::Deribit,
+                    Exchange::DeribitInverse,
+                    "Deribit"
+                ),
                 self.exchange_filter_btn(
                     ExchangeInclusive::Hyperliquid,
                     Exchange::HyperliquidLinear,
@@ -581,6 +605,7 @@ impl TickersTable {
                     spot_market_button.width(Length::Fill),
                     linear_markets_btn.width(Length::Fill),
                     inverse_markets_btn.width(Length::Fill),
+                    dated_futures_btn.width(Length::Fill),
                 ]
                 .spacing(4),
                 rule::horizontal(1.0).style(style::split_ruler),
@@ -838,6 +863,23 @@ fn create_expanded_ticker_card<'a>(
     let (ticker_str, market) = ticker.display_symbol_and_type();
     let exchange_icon = style::exchange_icon(ticker.exchange);
 
+    let mut feed_stats = column![].spacing(2);
+    if let Some(health) = telemetry::ticker_health(ticker) {
+        feed_stats = feed_stats.push(row![
+            text("Feed Latency (p50/p99): ").size(11),
+            Space::new().width(Length::Fill).height(Length::Shrink),
+            text(format!("{}/{}ms", health.latency.p50, health.latency.p99)),
+        ]);
+
+        if let Some(skew) = health.skew {
+            feed_stats = feed_stats.push(row![
+                text("Clock Skew: ").size(11),
+                Space::new().width(Length::Fill).height(Length::Shrink),
+                text(format!("{skew:+}ms")),
+            ]);
+        }
+    }
+
     column![
         row![
             button(icon_text(Icon::Return, 11))
@@ -868,7 +910,13 @@ fn create_expanded_ticker_card<'a>(
                     + &market.to_string()
                     + match market {
                         MarketKind::Spot => "",
-                        MarketKind::LinearPerps | MarketKind::InversePerps => " Perp",
+                        MarketKind::LinearPerps | MarketKind::InversePerps => {
+                            if ticker.expiry().is_some() {
+                                " Future"
+                            } else {
+                                " Perp"
+                            }
+                        }
                     }
             ),
         ]
@@ -890,6 +938,7 @@ fn create_expanded_ticker_card<'a>(
                     Space::new().width(Length::Fill).height(Length::Shrink),
                     text(&display_data.volume_display),
                 ],
+                feed_stats,
             ]
             .spacing(2)
         )
@@ -110,6 +110,8 @@ pub fn exchange_icon(exchange: Exchange) -> Icon {
         Exchange::BinanceInverse | Exchange::BinanceLinear | Exchange::BinanceSpot => {
             Icon::BinanceLogo
         }
+        // no logo in the icon font yet
+        Exchange::DeribitLinear | Exchange::DeribitInverse => Icon::ChartOutline,
         Exchange::HyperliquidLinear | Exchange::HyperliquidSpot => Icon::HyperliquidLogo,
         Exchange::OkexLinear | Exchange::OkexInverse | Exchange::OkexSpot => Icon::OkexLogo,
     }
@@ -436,6 +438,20 @@ pub fn pane_title_bar(theme: &Theme) -> Style {
     }
 }
 
+pub fn connection_badge(theme: &Theme) -> Style {
+    let palette = theme.extended_palette();
+
+    Style {
+        text_color: Some(palette.warning.base.text),
+        background: Some(palette.warning.base.color.scale_alpha(0.8).into()),
+        border: Border {
+            radius: 4.0.into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
 pub fn pane_background(theme: &Theme, is_focused: bool) -> Style {
     let palette = theme.extended_palette();
 